@@ -0,0 +1,551 @@
+//## PBR: una rejilla de esferas en la que la metalicidad crece hacia arriba y la rugosidad hacia la derecha, y un cubo
+//## con las 5 texturas de PbrMaterial (baldosas de oro y de ceramica con juntas, oclusion y una luz en las de
+//## ceramica). Ademas del sol y una luz puntual, todo recibe la luz del cielo (Environment), que se calcula a partir
+//## del mismo cubemap que dibuja el Skybox. El panel permite cambiar la intensidad del cielo y de las luces.
+
+use std::f32::consts::PI;
+use std::iter;
+
+use cgmath::{Matrix4, Point3, Rad, Vector3};
+use wgpu::util::DeviceExt;
+use winit::{
+    event::*,
+    event_loop::{ControlFlow, EventLoop},
+    window::{Window, WindowBuilder},
+};
+
+use crate::examples::{
+    cube_mesh, generate_tangents, msaa_sample_count, normal_map_from_height, procedural_sky, sphere_mesh, Camera,
+    CameraUniform, Capture, DepthBuffer, DirectionalLight, Environment, GlobalsUniform, GpuMesh, InstanceData,
+    LightGizmo, Lights, LightsUniform, MeshVertex, Multisample, PbrDefaults, PbrMaterial, PbrParams, PbrTextures,
+    PipelineBuilder, PointLight, Skybox, SpecularModel, TangentVertex, Texture, Ui, DEPTH_FORMAT,
+};
+
+const GRID_SIZE: usize = 5;
+const GRID_SPACING: f32 = 1.2;
+const SKY_FACE_SIZE: u32 = 256;
+const TILE_TEXTURE_SIZE: u32 = 256;
+const TILES: u32 = 4;
+const GROUT_WIDTH: f32 = 5.0;
+const CAMERA_DISTANCE: f32 = 9.0;
+// Hacia donde va la luz del sol: la contraria a la direccion del sol de procedural_sky
+const SUN_DIRECTION: [f32; 3] = [-0.6, -0.35, 0.72];
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+// Las imagenes de las 5 texturas del cubo: baldosas en damero, las de oro metalicas y las de ceramica con un circulo
+// que brilla en el centro
+struct TileImages {
+    base_color: image::RgbaImage,
+    metallic_roughness: image::RgbaImage,
+    height: image::GrayImage,
+    occlusion: image::RgbaImage,
+    emissive: image::RgbaImage,
+}
+
+fn tile_images(size: u32) -> TileImages {
+    let tile_size = size / TILES;
+    let mut images = TileImages {
+        base_color: image::RgbaImage::new(size, size),
+        metallic_roughness: image::RgbaImage::new(size, size),
+        height: image::GrayImage::new(size, size),
+        occlusion: image::RgbaImage::new(size, size),
+        emissive: image::RgbaImage::new(size, size),
+    };
+    let byte = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    for y in 0..size {
+        for x in 0..size {
+            let (tx, ty) = (x % tile_size, y % tile_size);
+            let edge = tx.min(tile_size - 1 - tx).min(ty).min(tile_size - 1 - ty) as f32;
+            let inside = smoothstep(GROUT_WIDTH * 0.5, GROUT_WIDTH * 0.5 + 4.0, edge);
+            let gold = (x / tile_size) % 2 == (y / tile_size) % 2;
+
+            let (color, roughness, metallic) = if gold {
+                ([255, 199, 87], 0.3, 1.0)
+            } else {
+                ([25, 40, 90], 0.1, 0.0)
+            };
+            let grout = [128, 124, 118];
+            let mut base_color = [255; 4];
+            for i in 0..3 {
+                base_color[i] = byte((grout[i] as f32 + (color[i] as f32 - grout[i] as f32) * inside) / 255.0);
+            }
+            images.base_color.put_pixel(x, y, image::Rgba(base_color));
+
+            let roughness = 0.9 + (roughness - 0.9) * inside;
+            images
+                .metallic_roughness
+                .put_pixel(x, y, image::Rgba([0, byte(roughness), byte(metallic * inside), 255]));
+            images.height.put_pixel(x, y, image::Luma([byte(inside)]));
+            let occlusion = byte(0.4 + 0.6 * inside);
+            images
+                .occlusion
+                .put_pixel(x, y, image::Rgba([occlusion, occlusion, occlusion, 255]));
+
+            let center = tile_size as f32 * 0.5;
+            let distance = ((tx as f32 - center).powi(2) + (ty as f32 - center).powi(2)).sqrt();
+            let glow = if gold { 0.0 } else { 1.0 - smoothstep(0.12, 0.16, distance / tile_size as f32) };
+            images
+                .emissive
+                .put_pixel(x, y, image::Rgba([byte(0.2 * glow), byte(0.9 * glow), byte(glow), 255]));
+        }
+    }
+    images
+}
+
+// Las esferas de la rejilla, en el plano z = 0 centradas en el origen
+fn sphere_instances() -> Vec<InstanceData> {
+    let offset = (GRID_SIZE - 1) as f32 * GRID_SPACING * 0.5;
+    (0..GRID_SIZE * GRID_SIZE)
+        .map(|i| {
+            let (column, row) = (i % GRID_SIZE, i / GRID_SIZE);
+            InstanceData::new(Matrix4::from_translation(Vector3::new(
+                column as f32 * GRID_SPACING - offset,
+                row as f32 * GRID_SPACING - offset,
+                0.0,
+            )))
+        })
+        .collect()
+}
+
+fn sphere_params(index: usize) -> PbrParams {
+    let (column, row) = (index % GRID_SIZE, index / GRID_SIZE);
+    PbrParams {
+        base_color: [0.95, 0.45, 0.3, 1.0],
+        metallic: row as f32 / (GRID_SIZE - 1) as f32,
+        roughness: (column as f32 / (GRID_SIZE - 1) as f32).max(0.05),
+        ..Default::default()
+    }
+}
+
+// La rugosidad y la metalicidad del cubo salen enteras de la textura
+fn cube_params(emissive_strength: f32) -> PbrParams {
+    PbrParams {
+        metallic: 1.0,
+        roughness: 1.0,
+        emissive: [emissive_strength, emissive_strength, emissive_strength, 0.0],
+        ..Default::default()
+    }
+}
+
+// El cubo gira a la derecha de la rejilla
+fn cube_model(time: f32) -> Matrix4<f32> {
+    Matrix4::from_translation(Vector3::new(4.8, 0.0, 0.0))
+        * Matrix4::from_angle_y(Rad(time * 0.4))
+        * Matrix4::from_angle_x(Rad(0.4))
+}
+
+fn tangent_mesh(device: &wgpu::Device, (vertices, indices): (Vec<MeshVertex>, Vec<u16>), label: &str) -> GpuMesh {
+    let vertices: Vec<TangentVertex> = generate_tangents(&vertices, &indices);
+    GpuMesh::new(device, &vertices, &indices, label)
+}
+
+struct State {
+    surface: wgpu::Surface,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    sc_desc: wgpu::SwapChainDescriptor,
+    swap_chain: wgpu::SwapChain,
+    size: winit::dpi::PhysicalSize<u32>,
+    render_pipeline: wgpu::RenderPipeline,
+    sphere_mesh: GpuMesh,
+    sphere_instances: wgpu::Buffer,
+    sphere_materials: Vec<PbrMaterial>,
+    cube_mesh: GpuMesh,
+    cube_instance: wgpu::Buffer,
+    cube_material: PbrMaterial,
+    emissive_strength: f32,
+    camera: Camera,
+    camera_uniform: CameraUniform,
+    orbit_speed: f32,
+    orbit_angle: f32,
+    lights: Lights,
+    sun_intensity: f32,
+    point_light: bool,
+    lights_uniform: LightsUniform,
+    light_gizmo: LightGizmo,
+    environment: Environment,
+    skybox: Skybox,
+    depth_buffer: DepthBuffer,
+    globals: GlobalsUniform,
+    capture: Capture,
+    multisample: Multisample,
+    ui: Ui,
+}
+
+impl State {
+    async fn new(window: &Window, sample_count: u32) -> Self {
+        let size = window.inner_size();
+
+        let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+        let surface = unsafe { instance.create_surface(window) };
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::Default,
+                compatible_surface: Some(&surface),
+            })
+            .await
+            .unwrap();
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    features: wgpu::Features::empty(),
+                    limits: wgpu::Limits::default(),
+                    shader_validation: true,
+                },
+                None, // Trace path
+            )
+            .await
+            .unwrap();
+
+        let sc_desc = wgpu::SwapChainDescriptor {
+            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+            format: wgpu::TextureFormat::Bgra8UnormSrgb,
+            width: size.width,
+            height: size.height,
+            present_mode: wgpu::PresentMode::Fifo,
+        };
+        let swap_chain = device.create_swap_chain(&surface, &sc_desc);
+        let capture = Capture::new(&device, &sc_desc);
+        let multisample = Multisample::new(&device, &sc_desc, msaa_sample_count(&adapter, sample_count));
+        let depth_buffer = DepthBuffer::new(&device, &sc_desc, multisample.sample_count);
+
+        let globals = GlobalsUniform::new(&device, size);
+        let camera = Camera::new(Point3::new(0.0, 0.0, CAMERA_DISTANCE), Point3::new(1.0, 0.0, 0.0), size);
+        let camera_uniform = CameraUniform::new(&device, &camera);
+
+        let sun_intensity = 3.0;
+        let lights = Lights {
+            ambient: [0.0; 3],
+            specular_model: SpecularModel::BlinnPhong,
+            point_lights: vec![PointLight {
+                position: [0.0, 0.0, 2.0],
+                color: [1.0, 0.9, 0.8],
+                intensity: 20.0,
+                range: 15.0,
+            }],
+            directional_lights: vec![DirectionalLight {
+                direction: SUN_DIRECTION,
+                color: [1.0, 0.95, 0.85],
+                intensity: sun_intensity,
+            }],
+            spot_lights: Vec::new(),
+        };
+        let lights_uniform = LightsUniform::new(&device, &lights);
+
+        let sky = Texture::cube_from_equirectangular(
+            &device,
+            &queue,
+            &image::DynamicImage::ImageRgba8(procedural_sky(SKY_FACE_SIZE * 4, SKY_FACE_SIZE * 2)),
+            SKY_FACE_SIZE,
+            Some("Sky"),
+        )
+        .unwrap();
+        let skybox = Skybox::new(&device, &sky, sc_desc.format, multisample.sample_count);
+        let environment = Environment::new(&device, &queue, &sky, &lights_uniform);
+
+        let material_layout = PbrMaterial::create_bind_group_layout(&device);
+        let defaults = PbrDefaults::new(&device, &queue);
+        let sphere_materials = (0..GRID_SIZE * GRID_SIZE)
+            .map(|i| PbrMaterial::new(&device, &material_layout, &defaults, &PbrTextures::default(), sphere_params(i)))
+            .collect();
+
+        let images = tile_images(TILE_TEXTURE_SIZE);
+        let color = |img: image::RgbaImage, label| {
+            Texture::from_image(&device, &queue, &image::DynamicImage::ImageRgba8(img), Some(label)).unwrap()
+        };
+        let data = |img: image::RgbaImage, label| {
+            Texture::from_image_linear(&device, &queue, &image::DynamicImage::ImageRgba8(img), Some(label)).unwrap()
+        };
+        let base_color = color(images.base_color, "Tiles Base Color");
+        let emissive = color(images.emissive, "Tiles Emissive");
+        let metallic_roughness = data(images.metallic_roughness, "Tiles Metallic Roughness");
+        let normal = data(normal_map_from_height(&images.height, 4.0), "Tiles Normal");
+        let occlusion = data(images.occlusion, "Tiles Occlusion");
+        let cube_material = PbrMaterial::new(
+            &device,
+            &material_layout,
+            &defaults,
+            &PbrTextures {
+                base_color: Some(&base_color),
+                metallic_roughness: Some(&metallic_roughness),
+                normal: Some(&normal),
+                occlusion: Some(&occlusion),
+                emissive: Some(&emissive),
+            },
+            cube_params(2.0),
+        );
+
+        let sphere_mesh = tangent_mesh(&device, sphere_mesh(0.5, 48, 24), "Sphere");
+        let sphere_instances = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Sphere Instances"),
+            contents: bytemuck::cast_slice(&sphere_instances()),
+            usage: wgpu::BufferUsage::VERTEX,
+        });
+        let cube_mesh = tangent_mesh(&device, cube_mesh(0.9), "Cube");
+        let cube_instance = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Cube Instance"),
+            contents: bytemuck::cast_slice(&[InstanceData::new(cube_model(0.0))]),
+            usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+        });
+
+        let vs_module = device.create_shader_module(wgpu::include_spirv!("shaders/normal_map.vert.spv"));
+        let fs_module = device.create_shader_module(wgpu::include_spirv!("shaders/pbr.frag.spv"));
+
+        // Set 0 Globals, 1 material, 2 camara, 3 luces y cielo
+        let render_pipeline = PipelineBuilder::new(&vs_module, sc_desc.format)
+            .label("PBR Pipeline")
+            .fragment_shader(&fs_module)
+            .bind_group_layouts(&[
+                &globals.bind_group_layout,
+                &material_layout,
+                &camera_uniform.bind_group_layout,
+                &environment.bind_group_layout,
+            ])
+            .vertex_buffer(TangentVertex::desc())
+            .vertex_buffer(InstanceData::desc())
+            .depth(DEPTH_FORMAT, wgpu::CompareFunction::Less)
+            .sample_count(multisample.sample_count)
+            .build(&device);
+
+        let light_gizmo = LightGizmo::new(
+            &device,
+            &globals.bind_group_layout,
+            &camera_uniform.bind_group_layout,
+            &lights_uniform.bind_group_layout,
+            sc_desc.format,
+            multisample.sample_count,
+        );
+
+        let ui = Ui::new(&device, &queue, &globals.bind_group_layout, sc_desc.format, multisample.sample_count);
+
+        Self {
+            surface,
+            device,
+            queue,
+            sc_desc,
+            swap_chain,
+            size,
+            render_pipeline,
+            sphere_mesh,
+            sphere_instances,
+            sphere_materials,
+            cube_mesh,
+            cube_instance,
+            cube_material,
+            emissive_strength: 2.0,
+            camera,
+            camera_uniform,
+            orbit_speed: 0.0,
+            orbit_angle: 0.0,
+            lights,
+            sun_intensity,
+            point_light: true,
+            lights_uniform,
+            light_gizmo,
+            environment,
+            skybox,
+            depth_buffer,
+            globals,
+            capture,
+            multisample,
+            ui,
+        }
+    }
+
+    fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.size = new_size;
+        self.sc_desc.width = new_size.width;
+        self.sc_desc.height = new_size.height;
+        self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+        self.capture.resize(&self.device, &self.sc_desc);
+        self.multisample.resize(&self.device, &self.sc_desc);
+        self.depth_buffer.resize(&self.device, &self.sc_desc);
+        self.globals.resize(new_size);
+        self.camera.resize(new_size);
+    }
+
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        self.globals.input(event);
+        self.capture.input(event)
+    }
+
+    fn update(&mut self) {
+        self.globals.set_fixed_time_step(self.capture.time_step());
+        self.globals.update(&self.queue);
+
+        // F1 oculta el panel
+        self.ui.label("1_10 PBR");
+        self.ui.label("Metallic: bottom to top, roughness: left to right");
+        self.ui.slider("Sky intensity", &mut self.environment.intensity, 0.0..=2.0);
+        self.ui.slider("Sun intensity", &mut self.sun_intensity, 0.0..=10.0);
+        self.ui.checkbox("Point light", &mut self.point_light);
+        self.ui.slider("Emissive", &mut self.emissive_strength, 0.0..=5.0);
+        self.ui.slider("Orbit speed", &mut self.orbit_speed, -1.0..=1.0);
+        self.ui.prepare(&self.device, &self.queue);
+
+        self.cube_material.update(&self.queue, cube_params(self.emissive_strength));
+
+        let time = self.globals.data.time;
+        self.queue.write_buffer(
+            &self.cube_instance,
+            0,
+            bytemuck::cast_slice(&[InstanceData::new(cube_model(time))]),
+        );
+
+        // La luz puntual da vueltas delante de la rejilla
+        let angle = time * 0.7 % (2.0 * PI);
+        self.lights.point_lights.clear();
+        if self.point_light {
+            self.lights.point_lights.push(PointLight {
+                position: [2.5 * angle.cos(), 2.5 * angle.sin(), 1.5],
+                color: [1.0, 0.9, 0.8],
+                intensity: 20.0,
+                range: 15.0,
+            });
+        }
+        self.lights.directional_lights[0].intensity = self.sun_intensity;
+
+        self.orbit_angle += self.orbit_speed * self.globals.data.time_delta;
+        self.camera.eye = Point3::new(
+            1.0 + CAMERA_DISTANCE * self.orbit_angle.sin(),
+            0.0,
+            CAMERA_DISTANCE * self.orbit_angle.cos(),
+        );
+        self.camera_uniform.update(&self.queue, &self.camera);
+        self.lights_uniform.update(&self.queue, &self.lights);
+        self.environment.update(&self.queue);
+        self.skybox.update(&self.queue, &self.camera);
+    }
+
+    fn render(&mut self) {
+        let frame = self
+            .swap_chain
+            .get_current_frame()
+            .expect("Timeout getting texture")
+            .output;
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        self.draw(&mut encoder, &frame.view);
+        // Si hay una captura pendiente volvemos a dibujar el frame en la textura de captura
+        if let Some(view) = self.capture.target() {
+            self.draw(&mut encoder, view);
+        }
+
+        self.queue.submit(iter::once(encoder.finish()));
+        self.capture.finish_frame(&self.device, &self.queue);
+    }
+
+    fn draw(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let (attachment, resolve_target) = self.multisample.color_attachment(view);
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                    attachment,
+                    resolve_target,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: Some(self.depth_buffer.attachment()),
+            });
+
+            render_pass.set_pipeline(&self.render_pipeline);
+            render_pass.set_bind_group(0, &self.globals.bind_group, &[]);
+            render_pass.set_bind_group(2, &self.camera_uniform.bind_group, &[]);
+            render_pass.set_bind_group(3, &self.environment.bind_group, &[]);
+            // Cada esfera tiene su material, asi que va en su propio draw con su instancia
+            for (i, material) in self.sphere_materials.iter().enumerate() {
+                render_pass.set_bind_group(1, &material.bind_group, &[]);
+                let i = i as u32;
+                self.sphere_mesh
+                    .draw_instanced(&mut render_pass, &self.sphere_instances, i..i + 1);
+            }
+            render_pass.set_bind_group(1, &self.cube_material.bind_group, &[]);
+            self.cube_mesh.draw_instanced(&mut render_pass, &self.cube_instance, 0..1);
+
+            self.light_gizmo.draw(
+                &mut render_pass,
+                &self.globals.bind_group,
+                &self.camera_uniform.bind_group,
+                &self.lights_uniform,
+            );
+            self.skybox.draw(&mut render_pass);
+        }
+
+        // La UI va en un pass sin depth, como en el 1_6
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                attachment,
+                resolve_target,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        });
+        self.ui.draw(&mut render_pass, &self.globals.bind_group);
+    }
+}
+
+pub fn main_1_10(sample_count: u32) {
+    env_logger::init();
+    let event_loop = EventLoop::new();
+    let window = WindowBuilder::new().build(&event_loop).unwrap();
+
+    use futures::executor::block_on;
+
+    // Since main can't be async, we're going to need to block
+    let mut state = block_on(State::new(&window, sample_count));
+
+    event_loop.run(move |event, _, control_flow| {
+        match event {
+            Event::WindowEvent {
+                ref event,
+                window_id,
+            } if window_id == window.id() => {
+                if !state.ui.input(event) && !state.input(event) {
+                    match event {
+                        WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+                        WindowEvent::KeyboardInput { input, .. } => match input {
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Escape),
+                                ..
+                            } => *control_flow = ControlFlow::Exit,
+                            _ => {}
+                        },
+                        WindowEvent::Resized(physical_size) => {
+                            state.resize(*physical_size);
+                        }
+                        WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                            // new_inner_size is &mut so w have to dereference it twice
+                            state.resize(**new_inner_size);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Event::RedrawRequested(_) => {
+                state.update();
+                state.render();
+            }
+            Event::MainEventsCleared => {
+                // RedrawRequested will only trigger once, unless we manually
+                // request it.
+                window.request_redraw();
+            }
+            _ => {}
+        }
+    });
+}
@@ -0,0 +1,528 @@
+//## Post-procesado: la escena (materiales PBR, cielo y unos cubos de neon muy brillantes) se dibuja en una textura HDR
+//## y la cadena de efectos de PostProcess la lleva al frame. Las teclas 1-8 activan cada efecto en el orden de la
+//## cadena: bloom, tonemapping, FXAA, aberracion cromatica, viñeta, grano, pixelado y CRT. Sin tonemapping los colores
+//## que pasan de 1.0 se quedan cortados, y sin bloom los cubos de neon no brillan.
+//##
+//## El frame se monta con un RenderGraph: el pass de la escena, uno por cada efecto del post-procesado y el de la UI
+//## declaran que texturas usan y el grafo crea la escena, el depth buffer, la textura del MSAA y las de los efectos
+//## y decide el orden.
+//##
+//## La exposicion es automatica: se calcula con el histograma de luminancia de la escena y se adapta poco a poco si
+//## cambia la luz (prueba a subir o bajar el sol). La H muestra el histograma, y el panel deja elegir el operador del
+//## tonemapping (Reinhard, ACES o filmico).
+
+use std::f32::consts::PI;
+
+use cgmath::{Matrix4, Point3, Rad, Vector3};
+use wgpu::util::DeviceExt;
+use winit::{
+    event::*,
+    event_loop::{ControlFlow, EventLoop},
+    window::{Window, WindowBuilder},
+};
+
+use crate::examples::{
+    cube_mesh, generate_tangents, msaa_sample_count, plane_mesh, procedural_sky, sphere_mesh, BufferHandle, Camera,
+    CameraUniform, Capture, DirectionalLight, Effect, Environment, GlobalsUniform, GpuMesh, InstanceData, Lights,
+    LightsUniform, MeshVertex, PbrDefaults, PbrMaterial, PbrParams, PbrTextures, PipelineBuilder, PointLight,
+    PostProcess, RenderGraph, Skybox, SpecularModel, TangentVertex, Texture, TextureHandle, TransientDesc,
+    TransientTextures, Ui, DEPTH_FORMAT, SCENE_FORMAT,
+};
+
+const SKY_FACE_SIZE: u32 = 256;
+const NUM_SPHERES: usize = 6;
+const SPHERE_RING_RADIUS: f32 = 2.5;
+const TONEMAP_OPERATORS: [&str; 3] = ["Reinhard", "ACES", "Filmic"];
+const NEON_COLORS: [[f32; 3]; 3] = [[1.0, 0.2, 0.6], [0.2, 0.8, 1.0], [1.0, 0.7, 0.1]];
+const NEON_ORBIT_RADIUS: f32 = 1.2;
+const CAMERA_DISTANCE: f32 = 7.5;
+
+// Los materiales de las esferas: oro, plastico rojo, cromo, goma, cobre rugoso y ceramica blanca
+fn sphere_params(index: usize) -> PbrParams {
+    let (base_color, metallic, roughness) = match index % NUM_SPHERES {
+        0 => ([1.0, 0.78, 0.34, 1.0], 1.0, 0.2),
+        1 => ([0.8, 0.05, 0.05, 1.0], 0.0, 0.35),
+        2 => ([0.95, 0.95, 0.95, 1.0], 1.0, 0.05),
+        3 => ([0.05, 0.05, 0.05, 1.0], 0.0, 0.9),
+        4 => ([0.95, 0.64, 0.54, 1.0], 1.0, 0.6),
+        _ => ([0.9, 0.9, 0.85, 1.0], 0.0, 0.1),
+    };
+    PbrParams {
+        base_color,
+        metallic,
+        roughness,
+        ..Default::default()
+    }
+}
+
+fn neon_position(index: usize, time: f32) -> Vector3<f32> {
+    let angle = time * 0.6 + index as f32 * 2.0 * PI / NEON_COLORS.len() as f32;
+    Vector3::new(
+        NEON_ORBIT_RADIUS * angle.cos(),
+        1.0 + 0.3 * (time * 1.3 + index as f32).sin(),
+        NEON_ORBIT_RADIUS * angle.sin(),
+    )
+}
+
+fn neon_model(index: usize, time: f32) -> Matrix4<f32> {
+    Matrix4::from_translation(neon_position(index, time)) * Matrix4::from_angle_y(Rad(time * (1.0 + index as f32)))
+}
+
+// Con tangentes, una instancia y su material
+struct Object {
+    mesh: GpuMesh,
+    instance_buffer: wgpu::Buffer,
+    material: PbrMaterial,
+}
+
+impl Object {
+    fn new(
+        device: &wgpu::Device,
+        (vertices, indices): (Vec<MeshVertex>, Vec<u16>),
+        model: Matrix4<f32>,
+        material: PbrMaterial,
+        label: &str,
+    ) -> Self {
+        let vertices: Vec<TangentVertex> = generate_tangents(&vertices, &indices);
+        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents: bytemuck::cast_slice(&[InstanceData::new(model)]),
+            usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+        });
+        Self {
+            mesh: GpuMesh::new(device, &vertices, &indices, label),
+            instance_buffer,
+            material,
+        }
+    }
+}
+
+struct State {
+    surface: wgpu::Surface,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    sc_desc: wgpu::SwapChainDescriptor,
+    swap_chain: wgpu::SwapChain,
+    size: winit::dpi::PhysicalSize<u32>,
+    render_pipeline: wgpu::RenderPipeline,
+    objects: Vec<Object>,
+    // Los cubos de neon son los ultimos de objects
+    first_neon: usize,
+    camera: Camera,
+    camera_uniform: CameraUniform,
+    lights: Lights,
+    lights_uniform: LightsUniform,
+    environment: Environment,
+    skybox: Skybox,
+    post: PostProcess,
+    transients: TransientTextures,
+    sample_count: u32,
+    globals: GlobalsUniform,
+    capture: Capture,
+    ui: Ui,
+}
+
+impl State {
+    async fn new(window: &Window, sample_count: u32) -> Self {
+        let size = window.inner_size();
+
+        let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+        let surface = unsafe { instance.create_surface(window) };
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::Default,
+                compatible_surface: Some(&surface),
+            })
+            .await
+            .unwrap();
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    features: wgpu::Features::empty(),
+                    limits: wgpu::Limits::default(),
+                    shader_validation: true,
+                },
+                None, // Trace path
+            )
+            .await
+            .unwrap();
+
+        let sc_desc = wgpu::SwapChainDescriptor {
+            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+            format: wgpu::TextureFormat::Bgra8UnormSrgb,
+            width: size.width,
+            height: size.height,
+            present_mode: wgpu::PresentMode::Fifo,
+        };
+        let swap_chain = device.create_swap_chain(&surface, &sc_desc);
+        let capture = Capture::new(&device, &sc_desc);
+        // El depth buffer y la textura del MSAA los crea el grafo
+        let transients = TransientTextures::new(&sc_desc);
+        let sample_count = msaa_sample_count(&adapter, sample_count);
+
+        let globals = GlobalsUniform::new(&device, size);
+        let camera = Camera::new(Point3::new(0.0, 2.5, CAMERA_DISTANCE), Point3::new(0.0, 0.6, 0.0), size);
+        let camera_uniform = CameraUniform::new(&device, &camera);
+
+        // Una luz puntual de cada color junto a su cubo de neon
+        let lights = Lights {
+            ambient: [0.0; 3],
+            specular_model: SpecularModel::BlinnPhong,
+            point_lights: NEON_COLORS
+                .iter()
+                .enumerate()
+                .map(|(i, &color)| PointLight {
+                    position: neon_position(i, 0.0).into(),
+                    color,
+                    intensity: 4.0,
+                    range: 6.0,
+                })
+                .collect(),
+            directional_lights: vec![DirectionalLight {
+                direction: [-0.6, -0.35, 0.72],
+                color: [1.0, 0.95, 0.85],
+                intensity: 1.5,
+            }],
+            spot_lights: Vec::new(),
+        };
+        let lights_uniform = LightsUniform::new(&device, &lights);
+
+        let sky = Texture::cube_from_equirectangular(
+            &device,
+            &queue,
+            &image::DynamicImage::ImageRgba8(procedural_sky(SKY_FACE_SIZE * 4, SKY_FACE_SIZE * 2)),
+            SKY_FACE_SIZE,
+            Some("Sky"),
+        )
+        .unwrap();
+        let skybox = Skybox::new(&device, &sky, SCENE_FORMAT, sample_count);
+        let environment = Environment::new(&device, &queue, &sky, &lights_uniform);
+
+        let material_layout = PbrMaterial::create_bind_group_layout(&device);
+        let defaults = PbrDefaults::new(&device, &queue);
+        let material = |params| PbrMaterial::new(&device, &material_layout, &defaults, &PbrTextures::default(), params);
+
+        let mut objects = vec![Object::new(
+            &device,
+            plane_mesh(6.0),
+            Matrix4::from_scale(1.0),
+            material(PbrParams {
+                base_color: [0.3, 0.3, 0.32, 1.0],
+                roughness: 0.6,
+                ..Default::default()
+            }),
+            "Floor",
+        )];
+        for i in 0..NUM_SPHERES {
+            let angle = i as f32 * 2.0 * PI / NUM_SPHERES as f32;
+            let position = Vector3::new(SPHERE_RING_RADIUS * angle.cos(), 0.5, SPHERE_RING_RADIUS * angle.sin());
+            objects.push(Object::new(
+                &device,
+                sphere_mesh(0.5, 48, 24),
+                Matrix4::from_translation(position),
+                material(sphere_params(i)),
+                "Sphere",
+            ));
+        }
+        // Mucho mas brillantes que 1.0: el bloom se queda con ellos
+        let first_neon = objects.len();
+        for (i, color) in NEON_COLORS.iter().enumerate() {
+            objects.push(Object::new(
+                &device,
+                cube_mesh(0.15),
+                neon_model(i, 0.0),
+                material(PbrParams {
+                    base_color: [0.0, 0.0, 0.0, 1.0],
+                    emissive: [color[0] * 8.0, color[1] * 8.0, color[2] * 8.0, 0.0],
+                    ..Default::default()
+                }),
+                "Neon",
+            ));
+        }
+
+        let vs_module = device.create_shader_module(wgpu::include_spirv!("shaders/normal_map.vert.spv"));
+        let fs_module = device.create_shader_module(wgpu::include_spirv!("shaders/pbr.frag.spv"));
+
+        // Set 0 Globals, 1 material, 2 camara, 3 luces y cielo
+        let render_pipeline = PipelineBuilder::new(&vs_module, SCENE_FORMAT)
+            .label("PBR Pipeline")
+            .fragment_shader(&fs_module)
+            .bind_group_layouts(&[
+                &globals.bind_group_layout,
+                &material_layout,
+                &camera_uniform.bind_group_layout,
+                &environment.bind_group_layout,
+            ])
+            .vertex_buffer(TangentVertex::desc())
+            .vertex_buffer(InstanceData::desc())
+            .depth(DEPTH_FORMAT, wgpu::CompareFunction::Less)
+            .sample_count(sample_count)
+            .build(&device);
+
+        let post = PostProcess::new(&device, &globals.bind_group_layout, &sc_desc, &Effect::ALL);
+
+        // La UI se dibuja despues del post-procesado, directamente en el frame y sin MSAA
+        let ui = Ui::new(&device, &queue, &globals.bind_group_layout, sc_desc.format, 1);
+
+        Self {
+            surface,
+            device,
+            queue,
+            sc_desc,
+            swap_chain,
+            size,
+            render_pipeline,
+            objects,
+            first_neon,
+            camera,
+            camera_uniform,
+            lights,
+            lights_uniform,
+            environment,
+            skybox,
+            post,
+            transients,
+            sample_count,
+            globals,
+            capture,
+            ui,
+        }
+    }
+
+    fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.size = new_size;
+        self.sc_desc.width = new_size.width;
+        self.sc_desc.height = new_size.height;
+        self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+        self.capture.resize(&self.device, &self.sc_desc);
+        self.transients.resize(&self.sc_desc);
+        self.post.resize(&self.sc_desc);
+        self.globals.resize(new_size);
+        self.camera.resize(new_size);
+    }
+
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        self.globals.input(event);
+        self.capture.input(event) || self.post.input(event)
+    }
+
+    fn update(&mut self) {
+        self.globals.set_fixed_time_step(self.capture.time_step());
+        self.globals.update(&self.queue);
+
+        // F1 oculta el panel
+        self.ui.label("1_11 Post-processing");
+        for (i, post_effect) in self.post.chain.iter_mut().enumerate() {
+            let label = format!("{} ({})", post_effect.effect.name(), i + 1);
+            self.ui.checkbox(&label, &mut post_effect.enabled);
+        }
+        if let Some(tonemap) = self.post.effect_mut(Effect::Tonemap) {
+            // La etiqueta del slider es su id, asi que el nombre del operador va aparte
+            let mut operator = tonemap.params[1] as u32;
+            if self.ui.slider_u32("Tonemap operator", &mut operator, 0..=2) {
+                tonemap.params[1] = operator as f32;
+            }
+            self.ui.label(&format!("Operator: {}", TONEMAP_OPERATORS[operator as usize]));
+            let mut auto_exposure = tonemap.params[2] > 0.5;
+            if self.ui.checkbox("Auto exposure", &mut auto_exposure) {
+                tonemap.params[2] = if auto_exposure { 1.0 } else { 0.0 };
+            }
+            self.ui.slider("Exposure", &mut tonemap.params[0], 0.1..=4.0);
+        }
+        self.ui.checkbox("Histogram (H)", &mut self.post.show_histogram);
+        let sun = &mut self.lights.directional_lights[0];
+        self.ui.slider("Sun intensity", &mut sun.intensity, 0.0..=20.0);
+        if let Some(bloom) = self.post.effect_mut(Effect::Bloom) {
+            self.ui.slider("Bloom threshold", &mut bloom.params[0], 0.0..=4.0);
+            self.ui.slider("Bloom intensity", &mut bloom.params[1], 0.0..=2.0);
+        }
+        self.ui
+            .label(&format!("Render graph textures: {}", self.transients.texture_count()));
+        self.ui.prepare(&self.device, &self.queue);
+        self.post.update(&self.queue, self.globals.data.time_delta);
+
+        let time = self.globals.data.time;
+        for i in 0..NEON_COLORS.len() {
+            self.queue.write_buffer(
+                &self.objects[self.first_neon + i].instance_buffer,
+                0,
+                bytemuck::cast_slice(&[InstanceData::new(neon_model(i, time))]),
+            );
+            self.lights.point_lights[i].position = neon_position(i, time).into();
+        }
+
+        let angle = time * 0.1;
+        self.camera.eye = Point3::new(CAMERA_DISTANCE * angle.sin(), 2.5, CAMERA_DISTANCE * angle.cos());
+        self.camera_uniform.update(&self.queue, &self.camera);
+        self.lights_uniform.update(&self.queue, &self.lights);
+        self.skybox.update(&self.queue, &self.camera);
+    }
+
+    fn render(&mut self) {
+        let frame = self
+            .swap_chain
+            .get_current_frame()
+            .expect("Timeout getting texture")
+            .output;
+
+        // Las closures de los passes toman prestado self, asi que las texturas del grafo salen de self mientras tanto
+        let mut transients = std::mem::take(&mut self.transients);
+        let mut graph = RenderGraph::new();
+        let scene = self.add_scene_pass(&mut graph);
+        let exposure = self.post.add_exposure_pass(&mut graph, scene);
+        let output = graph.import_texture("Frame", &frame.view);
+        self.add_output_passes(&mut graph, scene, exposure, output);
+        // Si hay una captura pendiente el post-procesado y la UI se repiten en la textura de captura, la escena no
+        if let Some(view) = self.capture.target() {
+            let capture = graph.import_texture("Capture", view);
+            self.add_output_passes(&mut graph, scene, exposure, capture);
+        }
+        graph
+            .execute(&self.device, &self.queue, &mut transients)
+            .expect("Invalid render graph");
+        self.transients = transients;
+
+        self.capture.finish_frame(&self.device, &self.queue);
+    }
+
+    // Dibuja la escena en una textura HDR temporal, que devuelve. Con MSAA se dibuja en una textura multisampled
+    // temporal que se resuelve en ella
+    fn add_scene_pass<'a>(&'a self, graph: &mut RenderGraph<'a>) -> TextureHandle {
+        let scene = graph.add_texture("Scene", TransientDesc::new(SCENE_FORMAT));
+        let depth = graph.add_texture(
+            "Depth",
+            TransientDesc::new(DEPTH_FORMAT)
+                .sample_count(self.sample_count)
+                .usage(wgpu::TextureUsage::OUTPUT_ATTACHMENT),
+        );
+        let multisampled = if self.sample_count > 1 {
+            Some(graph.add_texture(
+                "Multisampled Scene",
+                TransientDesc::new(SCENE_FORMAT)
+                    .sample_count(self.sample_count)
+                    .usage(wgpu::TextureUsage::OUTPUT_ATTACHMENT),
+            ))
+        } else {
+            None
+        };
+
+        let mut pass = graph.add_pass("Scene").write(scene).write(depth);
+        if let Some(multisampled) = multisampled {
+            pass = pass.write(multisampled);
+        }
+        pass.execute(move |ctx| {
+            let (attachment, resolve_target) = match multisampled {
+                Some(multisampled) => (ctx.texture(multisampled), Some(ctx.texture(scene))),
+                None => (ctx.texture(scene), None),
+            };
+            let mut render_pass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                    attachment,
+                    resolve_target,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachmentDescriptor {
+                    attachment: ctx.texture(depth),
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+
+            render_pass.set_pipeline(&self.render_pipeline);
+            render_pass.set_bind_group(0, &self.globals.bind_group, &[]);
+            render_pass.set_bind_group(2, &self.camera_uniform.bind_group, &[]);
+            render_pass.set_bind_group(3, &self.environment.bind_group, &[]);
+            for object in &self.objects {
+                render_pass.set_bind_group(1, &object.material.bind_group, &[]);
+                object.mesh.draw_instanced(&mut render_pass, &object.instance_buffer, 0..1);
+            }
+            self.skybox.draw(&mut render_pass);
+        });
+        scene
+    }
+
+    // El post-procesado de scene a output y la UI encima
+    fn add_output_passes<'a>(
+        &'a self,
+        graph: &mut RenderGraph<'a>,
+        scene: TextureHandle,
+        exposure: BufferHandle,
+        output: TextureHandle,
+    ) {
+        self.post
+            .add_passes(graph, &self.globals.bind_group, scene, exposure, output);
+
+        graph.add_pass("UI").write(output).execute(move |ctx| {
+            let mut render_pass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                    attachment: ctx.texture(output),
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: None,
+            });
+            self.ui.draw(&mut render_pass, &self.globals.bind_group);
+        });
+    }
+}
+
+pub fn main_1_11(sample_count: u32) {
+    env_logger::init();
+    let event_loop = EventLoop::new();
+    let window = WindowBuilder::new().build(&event_loop).unwrap();
+
+    use futures::executor::block_on;
+
+    // Since main can't be async, we're going to need to block
+    let mut state = block_on(State::new(&window, sample_count));
+
+    event_loop.run(move |event, _, control_flow| {
+        match event {
+            Event::WindowEvent {
+                ref event,
+                window_id,
+            } if window_id == window.id() => {
+                if !state.ui.input(event) && !state.input(event) {
+                    match event {
+                        WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+                        WindowEvent::KeyboardInput { input, .. } => match input {
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Escape),
+                                ..
+                            } => *control_flow = ControlFlow::Exit,
+                            _ => {}
+                        },
+                        WindowEvent::Resized(physical_size) => {
+                            state.resize(*physical_size);
+                        }
+                        WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                            // new_inner_size is &mut so w have to dereference it twice
+                            state.resize(**new_inner_size);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Event::RedrawRequested(_) => {
+                state.update();
+                state.render();
+            }
+            Event::MainEventsCleared => {
+                // RedrawRequested will only trigger once, unless we manually
+                // request it.
+                window.request_redraw();
+            }
+            _ => {}
+        }
+    });
+}
@@ -0,0 +1,693 @@
+//## Deferred shading: una plaza con columnas y esferas iluminada por cientos de luces puntuales de colores que dan
+//## vueltas a ras de suelo. Con el forward de los ejemplos anteriores cada fragmento recorreria todas las luces; aqui
+//## la geometria se dibuja primero en el G-buffer, un compute shader reparte las luces entre los tiles de la pantalla
+//## y el pass de luz solo calcula, en cada pixel visible, las luces de su tile. El resultado es HDR y pasa por el
+//## post-procesado (bloom, tonemapping y FXAA). Como en el 1_11, el frame se monta con un RenderGraph.
+//##
+//## Mientras se mantiene pulsado el espacio se ven las texturas del G-buffer (color base, normales, material,
+//## emision, profundidad y luces por tile). En el panel se elige el numero de luces y se pueden pintar los tiles segun
+//## cuantas luces les tocan.
+//##
+//## Antes de grabar el pass del G-buffer se descartan en la CPU los objetos cuya caja o esfera envolvente queda fuera
+//## del frustum de la camara; el panel muestra cuantos se dibujan y cuantos no. Acercando la camara se ve como baja
+//## el numero de objetos visibles.
+//##
+//## Las esferas tienen cuatro niveles de detalle, simplificados al arrancar, y cada una usa el que le toca por lo
+//## grande que se ve; con el cross-fade activado el cambio de nivel se funde con dithering en vez de saltar.
+
+use std::iter;
+use std::mem;
+
+use cgmath::{Matrix4, Point3, Vector3};
+use wgpu::util::DeviceExt;
+use winit::{
+    event::*,
+    event_loop::{ControlFlow, EventLoop},
+    window::{Window, WindowBuilder},
+};
+
+use crate::examples::{
+    cube_mesh, generate_lods, generate_tangents, plane_mesh, projected_size, sphere_mesh, Bounds, BufferHandle, Camera,
+    CameraUniform, Capture, CullingStats, DeferredLighting, DirectionalLight, Effect, Frustum, GBuffer, GlobalsUniform,
+    GpuMesh, InstanceData, Lights, LightsUniform, LodFade, LodSelection, LodSelector, MeshVertex, PbrDefaults,
+    PbrMaterial, PbrParams, PbrTextures, PipelineBuilder, PointLight, PostProcess, RenderGraph, SpecularModel,
+    TangentVertex, TextureHandle, TransientDesc, TransientTextures, Ui, DEPTH_FORMAT, GBUFFER_FORMATS,
+    MAX_DEFERRED_LIGHTS, SCENE_FORMAT,
+};
+
+const FLOOR_SIZE: f32 = 12.0;
+const GRID_SIZE: usize = 6;
+const GRID_SPACING: f32 = 3.0;
+const NUM_MATERIALS: usize = 6;
+// Las luces se reparten en un disco de este radio
+const LIGHTS_RADIUS: f32 = 11.0;
+const CAMERA_DISTANCE: f32 = 16.0;
+// Las esferas tienen LOD_LEVELS mallas; por debajo de cada tamaño en pixeles se pasa a la siguiente
+const LOD_LEVELS: usize = 4;
+const LOD_THRESHOLDS: [f32; LOD_LEVELS - 1] = [160.0, 90.0, 50.0];
+// Cielo de noche, en la textura HDR donde el pass de luz no dibuja
+const SKY_COLOR: wgpu::Color = wgpu::Color {
+    r: 0.005,
+    g: 0.006,
+    b: 0.015,
+    a: 1.0,
+};
+
+// La parte fraccionaria de i por la razon aurea reparte los valores por [0, 1) sin que se repitan
+fn golden(index: usize) -> f32 {
+    (index as f32 * 0.618_034) % 1.0
+}
+
+// Color saturado con el tono hue en [0, 1)
+fn hue_color(hue: f32) -> [f32; 3] {
+    let channel = |offset: f32| (((hue + offset) * 6.0 % 6.0 - 3.0).abs() - 1.0).clamp(0.0, 1.0);
+    [channel(0.0), channel(2.0 / 3.0), channel(1.0 / 3.0)]
+}
+
+// Cada luz da vueltas a su propia velocidad alrededor del centro, en un radio que reparte las luces por el disco
+// (como las semillas de un girasol) y subiendo y bajando un poco
+fn light_position(index: usize, num_lights: usize, time: f32) -> [f32; 3] {
+    let radius = LIGHTS_RADIUS * ((index as f32 + 0.5) / num_lights as f32).sqrt();
+    // Las pares en un sentido y las impares en el otro
+    let speed = [1.0, -1.0][index % 2] * (0.1 + 0.3 * golden(index * 7 + 3));
+    let angle = index as f32 * 2.4 + time * speed;
+    let height = 0.6 + 0.4 * (time * 1.3 + index as f32).sin();
+    [radius * angle.cos(), height, radius * angle.sin()]
+}
+
+fn point_lights(num_lights: usize, time: f32) -> Vec<PointLight> {
+    (0..num_lights)
+        .map(|i| PointLight {
+            position: light_position(i, num_lights, time),
+            color: hue_color(golden(i)),
+            intensity: 3.0,
+            range: 2.5,
+        })
+        .collect()
+}
+
+fn material_params(index: usize) -> PbrParams {
+    let (base_color, metallic, roughness) = match index % NUM_MATERIALS {
+        0 => ([0.9, 0.9, 0.88, 1.0], 0.0, 0.5),
+        1 => ([1.0, 0.78, 0.34, 1.0], 1.0, 0.25),
+        2 => ([0.2, 0.35, 0.8, 1.0], 0.0, 0.2),
+        3 => ([0.95, 0.95, 0.95, 1.0], 1.0, 0.1),
+        4 => ([0.6, 0.15, 0.1, 1.0], 0.0, 0.8),
+        _ => ([0.95, 0.64, 0.54, 1.0], 1.0, 0.5),
+    };
+    PbrParams {
+        base_color,
+        metallic,
+        roughness,
+        ..Default::default()
+    }
+}
+
+// Columnas y esferas alternadas en una rejilla sobre el suelo
+fn grid_models(column_height: f32) -> (Vec<Matrix4<f32>>, Vec<Matrix4<f32>>) {
+    let offset = (GRID_SIZE - 1) as f32 * GRID_SPACING * 0.5;
+    let mut columns = Vec::new();
+    let mut spheres = Vec::new();
+    for i in 0..GRID_SIZE * GRID_SIZE {
+        let (x, z) = ((i % GRID_SIZE) as f32, (i / GRID_SIZE) as f32);
+        let position = Vector3::new(x * GRID_SPACING - offset, 0.0, z * GRID_SPACING - offset);
+        if (i % GRID_SIZE) % 2 == (i / GRID_SIZE) % 2 {
+            let model = Matrix4::from_translation(position + Vector3::new(0.0, column_height * 0.5, 0.0))
+                * Matrix4::from_nonuniform_scale(0.5, column_height, 0.5);
+            columns.push(model);
+        } else {
+            spheres.push(Matrix4::from_translation(position + Vector3::new(0.0, 0.7, 0.0)));
+        }
+    }
+    (columns, spheres)
+}
+
+// Lo que se ha dibujado en el frame
+#[derive(Copy, Clone, Debug, Default)]
+struct FrameStats {
+    culling: CullingStats,
+    lods: [u32; LOD_LEVELS],
+    triangles: u32,
+}
+
+// Una malla con muchas instancias; cada instancia usa el material de su indice. Las que quedan fuera de la camara
+// no se dibujan, y las demas usan el nivel de detalle que les toca por tamaño en pantalla: visible se calcula en
+// select() antes de grabar el render pass
+struct Batch {
+    lods: Vec<GpuMesh>,
+    bounds: Bounds,
+    models: Vec<Matrix4<f32>>,
+    instance_buffer: wgpu::Buffer,
+    // Un LodFade por instancia para su nivel y detras otro para el siguiente, que solo se usa durante el cross-fade
+    fade_buffer: wgpu::Buffer,
+    visible: Vec<(u32, LodSelection)>,
+}
+
+impl Batch {
+    // Las mallas de lods van de mas detalle a menos; la primera es la que da las cajas envolventes
+    fn new(
+        device: &wgpu::Device,
+        lods: Vec<(Vec<MeshVertex>, Vec<u16>)>,
+        models: Vec<Matrix4<f32>>,
+        label: &str,
+    ) -> Self {
+        let bounds = Bounds::from_points(lods[0].0.iter().map(|vertex| vertex.position)).unwrap();
+        let lods = lods
+            .iter()
+            .map(|(vertices, indices)| {
+                let vertices: Vec<TangentVertex> = generate_tangents(vertices, indices);
+                GpuMesh::new(device, &vertices, indices, label)
+            })
+            .collect();
+        let instances = models.iter().map(|&model| InstanceData::new(model)).collect::<Vec<_>>();
+        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents: bytemuck::cast_slice(&instances),
+            usage: wgpu::BufferUsage::VERTEX,
+        });
+        let fades = vec![LodFade::OPAQUE; models.len() * 2];
+        let fade_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents: bytemuck::cast_slice(&fades),
+            usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+        });
+        Self {
+            lods,
+            bounds,
+            visible: Vec::new(),
+            models,
+            instance_buffer,
+            fade_buffer,
+        }
+    }
+
+    fn select(
+        &mut self,
+        queue: &wgpu::Queue,
+        frustum: &Frustum,
+        camera: &Camera,
+        screen_height: f32,
+        selector: &LodSelector,
+        stats: &mut FrameStats,
+    ) {
+        let num_instances = self.models.len();
+        let mut fades = vec![LodFade::OPAQUE; num_instances * 2];
+        self.visible.clear();
+        for (i, model) in self.models.iter().enumerate() {
+            if !stats.culling.record(self.bounds.is_visible(frustum, model)) {
+                continue;
+            }
+            let size = projected_size(&self.bounds.sphere.transform(model), camera, screen_height);
+            let selection = selector.select(size, self.lods.len());
+            let (fade, next_fade) = selection.fades();
+            fades[i] = fade;
+            fades[num_instances + i] = next_fade;
+
+            stats.lods[selection.level] += 1;
+            stats.triangles += self.lods[selection.level].num_indices / 3;
+            if selection.is_fading() {
+                stats.triangles += self.lods[selection.level + 1].num_indices / 3;
+            }
+            self.visible.push((i as u32, selection));
+        }
+        queue.write_buffer(&self.fade_buffer, 0, bytemuck::cast_slice(&fades));
+    }
+
+    // El pipeline y los bind groups ya tienen que estar puestos
+    fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, instance: u32, selection: LodSelection) {
+        let instances = instance..instance + 1;
+        render_pass.set_vertex_buffer(2, self.fade_buffer.slice(..));
+        self.lods[selection.level].draw_instanced(render_pass, &self.instance_buffer, instances.clone());
+        if selection.is_fading() {
+            // La misma instancia, pero leyendo los LodFade de la segunda mitad del buffer
+            let offset = (self.models.len() * mem::size_of::<LodFade>()) as wgpu::BufferAddress;
+            render_pass.set_vertex_buffer(2, self.fade_buffer.slice(offset..));
+            self.lods[selection.level + 1].draw_instanced(render_pass, &self.instance_buffer, instances);
+        }
+    }
+}
+
+struct State {
+    surface: wgpu::Surface,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    sc_desc: wgpu::SwapChainDescriptor,
+    swap_chain: wgpu::SwapChain,
+    size: winit::dpi::PhysicalSize<u32>,
+    geometry_pipeline: wgpu::RenderPipeline,
+    floor: Batch,
+    floor_material: PbrMaterial,
+    batches: Vec<Batch>,
+    materials: Vec<PbrMaterial>,
+    camera: Camera,
+    camera_uniform: CameraUniform,
+    camera_distance: f32,
+    orbit_speed: f32,
+    orbit_angle: f32,
+    lod_selector: LodSelector,
+    stats: FrameStats,
+    lights: Lights,
+    num_lights: u32,
+    lights_uniform: LightsUniform,
+    gbuffer: GBuffer,
+    deferred: DeferredLighting,
+    show_gbuffer: bool,
+    post: PostProcess,
+    transients: TransientTextures,
+    globals: GlobalsUniform,
+    capture: Capture,
+    ui: Ui,
+}
+
+impl State {
+    async fn new(window: &Window) -> Self {
+        let size = window.inner_size();
+
+        let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+        let surface = unsafe { instance.create_surface(window) };
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::Default,
+                compatible_surface: Some(&surface),
+            })
+            .await
+            .unwrap();
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    features: wgpu::Features::empty(),
+                    limits: wgpu::Limits::default(),
+                    shader_validation: true,
+                },
+                None, // Trace path
+            )
+            .await
+            .unwrap();
+
+        let sc_desc = wgpu::SwapChainDescriptor {
+            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+            format: wgpu::TextureFormat::Bgra8UnormSrgb,
+            width: size.width,
+            height: size.height,
+            present_mode: wgpu::PresentMode::Fifo,
+        };
+        let swap_chain = device.create_swap_chain(&surface, &sc_desc);
+        let capture = Capture::new(&device, &sc_desc);
+
+        let globals = GlobalsUniform::new(&device, size);
+        let camera = Camera::new(Point3::new(0.0, 7.0, CAMERA_DISTANCE), Point3::new(0.0, 0.0, 0.0), size);
+        let camera_uniform = CameraUniform::new(&device, &camera);
+
+        // La luz de la luna es la unica que no es puntual
+        let num_lights = 512;
+        let lights = Lights {
+            ambient: [0.01, 0.01, 0.015],
+            specular_model: SpecularModel::BlinnPhong,
+            point_lights: point_lights(num_lights, 0.0),
+            directional_lights: vec![DirectionalLight {
+                direction: [0.4, -0.8, -0.45],
+                color: [0.6, 0.7, 1.0],
+                intensity: 0.15,
+            }],
+            spot_lights: Vec::new(),
+        };
+        let lights_uniform = LightsUniform::new(&device, &lights);
+
+        let material_layout = PbrMaterial::create_bind_group_layout(&device);
+        let defaults = PbrDefaults::new(&device, &queue);
+        let material = |params| PbrMaterial::new(&device, &material_layout, &defaults, &PbrTextures::default(), params);
+        let floor_material = material(PbrParams {
+            base_color: [0.45, 0.45, 0.47, 1.0],
+            roughness: 0.4,
+            ..Default::default()
+        });
+        let materials = (0..NUM_MATERIALS).map(|i| material(material_params(i))).collect();
+
+        let floor = Batch::new(&device, vec![plane_mesh(FLOOR_SIZE)], vec![Matrix4::from_scale(1.0)], "Floor");
+        let (columns, spheres) = grid_models(2.5);
+        // Los cubos ya tienen los triangulos justos, solo las esferas tienen niveles de detalle
+        let (sphere_vertices, sphere_indices) = sphere_mesh(0.7, 48, 24);
+        let batches = vec![
+            Batch::new(&device, vec![cube_mesh(0.5)], columns, "Column"),
+            Batch::new(
+                &device,
+                generate_lods(&sphere_vertices, &sphere_indices, LOD_LEVELS),
+                spheres,
+                "Sphere",
+            ),
+        ];
+
+        let gbuffer = GBuffer::new(&device, &sc_desc);
+        let vs_module = device.create_shader_module(wgpu::include_spirv!("shaders/deferred_geometry.vert.spv"));
+        let fs_module = device.create_shader_module(wgpu::include_spirv!("shaders/deferred_geometry.frag.spv"));
+
+        // Set 0 Globals, 1 material, 2 camara. Escribe en las cuatro texturas del G-buffer
+        let geometry_pipeline = PipelineBuilder::new(&vs_module, GBUFFER_FORMATS[0])
+            .label("GBuffer Pipeline")
+            .fragment_shader(&fs_module)
+            .bind_group_layouts(&[
+                &globals.bind_group_layout,
+                &material_layout,
+                &camera_uniform.bind_group_layout,
+            ])
+            .vertex_buffer(TangentVertex::desc())
+            .vertex_buffer(InstanceData::desc())
+            .vertex_buffer(LodFade::desc())
+            .color_formats(&GBUFFER_FORMATS)
+            .depth(DEPTH_FORMAT, wgpu::CompareFunction::Less)
+            .build(&device);
+
+        let deferred = DeferredLighting::new(
+            &device,
+            &gbuffer,
+            &lights_uniform.bind_group_layout,
+            SCENE_FORMAT,
+            sc_desc.format,
+        );
+        let transients = TransientTextures::new(&sc_desc);
+        let post = PostProcess::new(
+            &device,
+            &globals.bind_group_layout,
+            &sc_desc,
+            &[Effect::Bloom, Effect::Tonemap, Effect::Fxaa],
+        );
+
+        // La UI se dibuja despues del post-procesado, directamente en el frame y sin MSAA
+        let ui = Ui::new(&device, &queue, &globals.bind_group_layout, sc_desc.format, 1);
+
+        Self {
+            surface,
+            device,
+            queue,
+            sc_desc,
+            swap_chain,
+            size,
+            geometry_pipeline,
+            floor,
+            floor_material,
+            batches,
+            materials,
+            camera,
+            camera_uniform,
+            camera_distance: CAMERA_DISTANCE,
+            orbit_speed: 0.1,
+            orbit_angle: 0.0,
+            lod_selector: LodSelector::new(&LOD_THRESHOLDS),
+            stats: FrameStats::default(),
+            lights,
+            num_lights: num_lights as u32,
+            lights_uniform,
+            gbuffer,
+            deferred,
+            show_gbuffer: false,
+            post,
+            transients,
+            globals,
+            capture,
+            ui,
+        }
+    }
+
+    fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.size = new_size;
+        self.sc_desc.width = new_size.width;
+        self.sc_desc.height = new_size.height;
+        self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+        self.capture.resize(&self.device, &self.sc_desc);
+        self.gbuffer.resize(&self.device, &self.sc_desc);
+        self.deferred.resize(&self.device, &self.gbuffer);
+        self.post.resize(&self.sc_desc);
+        self.transients.resize(&self.sc_desc);
+        self.globals.resize(new_size);
+        self.camera.resize(new_size);
+    }
+
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        self.globals.input(event);
+        if self.capture.input(event) || self.post.input(event) {
+            return true;
+        }
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state,
+                        virtual_keycode: Some(VirtualKeyCode::Space),
+                        ..
+                    },
+                ..
+            } => {
+                self.show_gbuffer = *state == ElementState::Pressed;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn update(&mut self) {
+        self.globals.set_fixed_time_step(self.capture.time_step());
+        self.globals.update(&self.queue);
+
+        // F1 oculta el panel
+        self.ui.label("1_12 Deferred shading");
+        self.ui.checkbox("G-buffer (Space)", &mut self.show_gbuffer);
+        self.ui.checkbox("Light tiles", &mut self.deferred.show_tiles);
+        self.ui
+            .slider_u32("Lights", &mut self.num_lights, 0..=MAX_DEFERRED_LIGHTS as u32);
+        self.ui.slider("Orbit speed", &mut self.orbit_speed, -1.0..=1.0);
+        self.ui.slider("Camera distance", &mut self.camera_distance, 2.0..=CAMERA_DISTANCE);
+        self.ui.checkbox("LOD cross-fade", &mut self.lod_selector.cross_fade);
+        self.ui.slider("LOD bias", &mut self.lod_selector.bias, 0.25..=4.0);
+        // Los del frame anterior, el culling de este se hace despues de mover la camara
+        let stats = self.stats;
+        self.ui.label(&format!(
+            "Objects: {}/{} visible, {} culled",
+            stats.culling.visible,
+            stats.culling.total(),
+            stats.culling.culled
+        ));
+        self.ui.label(&format!(
+            "LODs: {} / {} / {} / {}, {} triangles",
+            stats.lods[0], stats.lods[1], stats.lods[2], stats.lods[3], stats.triangles
+        ));
+        self.ui.prepare(&self.device, &self.queue);
+
+        let time = self.globals.data.time;
+        self.lights.point_lights = point_lights(self.num_lights as usize, time);
+
+        self.orbit_angle += self.orbit_speed * self.globals.data.time_delta;
+        // Desde cerca la camara baja y mira hacia el horizonte, asi que la mayoria de los objetos quedan fuera
+        let distance = self.camera_distance;
+        self.camera.eye = Point3::new(
+            distance * self.orbit_angle.sin(),
+            distance * 7.0 / CAMERA_DISTANCE,
+            distance * self.orbit_angle.cos(),
+        );
+        self.camera_uniform.update(&self.queue, &self.camera);
+
+        let frustum = Frustum::from_matrix(&self.camera.view_projection());
+        let screen_height = self.size.height as f32;
+        let mut stats = FrameStats::default();
+        for batch in iter::once(&mut self.floor).chain(&mut self.batches) {
+            batch.select(
+                &self.queue,
+                &frustum,
+                &self.camera,
+                screen_height,
+                &self.lod_selector,
+                &mut stats,
+            );
+        }
+        self.stats = stats;
+        self.lights_uniform.update(&self.queue, &self.lights);
+        self.deferred.update(&self.queue, &self.camera, &self.lights);
+        self.post.update(&self.queue, self.globals.data.time_delta);
+    }
+
+    fn render(&mut self) {
+        let frame = self
+            .swap_chain
+            .get_current_frame()
+            .expect("Timeout getting texture")
+            .output;
+
+        // Las closures de los passes toman prestado self, asi que las texturas del grafo salen de self mientras tanto
+        let mut transients = mem::take(&mut self.transients);
+        let mut graph = RenderGraph::new();
+        let gbuffer: Vec<TextureHandle> = self
+            .gbuffer
+            .views()
+            .map(|view| graph.import_texture("G-buffer", view))
+            .collect();
+        let scene = self.add_scene_passes(&mut graph, &gbuffer);
+        // Una sola vez por frame: si se midiera en cada salida la captura veria otra exposicion
+        let exposure = self.post.add_exposure_pass(&mut graph, scene);
+        let output = graph.import_texture("Frame", &frame.view);
+        self.add_output_passes(&mut graph, &gbuffer, scene, exposure, output);
+        // Si hay una captura pendiente el post-procesado y la UI se repiten en la textura de captura, la escena no
+        if let Some(view) = self.capture.target() {
+            let capture = graph.import_texture("Capture", view);
+            self.add_output_passes(&mut graph, &gbuffer, scene, exposure, capture);
+        }
+        graph
+            .execute(&self.device, &self.queue, &mut transients)
+            .expect("Invalid render graph");
+        self.transients = transients;
+
+        self.capture.finish_frame(&self.device, &self.queue);
+    }
+
+    // El G-buffer y la luz, que dejan la escena HDR en una textura temporal que devuelve
+    fn add_scene_passes<'a>(&'a self, graph: &mut RenderGraph<'a>, gbuffer: &[TextureHandle]) -> TextureHandle {
+        let mut pass = graph.add_pass("G-buffer");
+        for &texture in gbuffer {
+            pass = pass.write(texture);
+        }
+        pass.execute(move |ctx| {
+            let color_attachments = self.gbuffer.color_attachments();
+            let mut render_pass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &color_attachments,
+                depth_stencil_attachment: Some(self.gbuffer.depth_attachment()),
+            });
+            render_pass.set_pipeline(&self.geometry_pipeline);
+            render_pass.set_bind_group(0, &self.globals.bind_group, &[]);
+            render_pass.set_bind_group(2, &self.camera_uniform.bind_group, &[]);
+            render_pass.set_bind_group(1, &self.floor_material.bind_group, &[]);
+            for &(i, selection) in &self.floor.visible {
+                self.floor.draw(&mut render_pass, i, selection);
+            }
+            for batch in &self.batches {
+                for &(i, selection) in &batch.visible {
+                    let material = &self.materials[i as usize % NUM_MATERIALS];
+                    render_pass.set_bind_group(1, &material.bind_group, &[]);
+                    batch.draw(&mut render_pass, i, selection);
+                }
+            }
+        });
+
+        let scene = graph.add_texture("Scene", TransientDesc::new(SCENE_FORMAT));
+        let mut pass = graph.add_pass("Lighting").write(scene);
+        for &texture in gbuffer {
+            pass = pass.read(texture);
+        }
+        pass.execute(move |ctx| {
+            self.deferred.cull_lights(ctx.encoder);
+
+            let mut render_pass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                    attachment: ctx.texture(scene),
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(SKY_COLOR),
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: None,
+            });
+            self.deferred
+                .draw_lighting(&mut render_pass, &self.gbuffer, &self.lights_uniform.bind_group);
+        });
+        scene
+    }
+
+    // El post-procesado de scene (o las texturas del G-buffer) a output y la UI encima
+    fn add_output_passes<'a>(
+        &'a self,
+        graph: &mut RenderGraph<'a>,
+        gbuffer: &[TextureHandle],
+        scene: TextureHandle,
+        exposure: BufferHandle,
+        output: TextureHandle,
+    ) {
+        if self.show_gbuffer {
+            let mut pass = graph.add_pass("G-buffer debug").write(output);
+            for &texture in gbuffer {
+                pass = pass.read(texture);
+            }
+            pass.execute(move |ctx| {
+                let mut render_pass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                        attachment: ctx.texture(output),
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                            store: true,
+                        },
+                    }],
+                    depth_stencil_attachment: None,
+                });
+                self.deferred.draw_debug(&mut render_pass, &self.gbuffer);
+            });
+        } else {
+            self.post
+                .add_passes(graph, &self.globals.bind_group, scene, exposure, output);
+        }
+
+        graph.add_pass("UI").write(output).execute(move |ctx| {
+            let mut render_pass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                    attachment: ctx.texture(output),
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: None,
+            });
+            self.ui.draw(&mut render_pass, &self.globals.bind_group);
+        });
+    }
+}
+
+pub fn main_1_12() {
+    env_logger::init();
+    let event_loop = EventLoop::new();
+    let window = WindowBuilder::new().build(&event_loop).unwrap();
+
+    use futures::executor::block_on;
+
+    // Since main can't be async, we're going to need to block
+    let mut state = block_on(State::new(&window));
+
+    event_loop.run(move |event, _, control_flow| {
+        match event {
+            Event::WindowEvent {
+                ref event,
+                window_id,
+            } if window_id == window.id() => {
+                if !state.ui.input(event) && !state.input(event) {
+                    match event {
+                        WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+                        WindowEvent::KeyboardInput { input, .. } => match input {
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Escape),
+                                ..
+                            } => *control_flow = ControlFlow::Exit,
+                            _ => {}
+                        },
+                        WindowEvent::Resized(physical_size) => {
+                            state.resize(*physical_size);
+                        }
+                        WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                            // new_inner_size is &mut so w have to dereference it twice
+                            state.resize(**new_inner_size);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Event::RedrawRequested(_) => {
+                state.update();
+                state.render();
+            }
+            Event::MainEventsCleared => {
+                // RedrawRequested will only trigger once, unless we manually
+                // request it.
+                window.request_redraw();
+            }
+            _ => {}
+        }
+    });
+}
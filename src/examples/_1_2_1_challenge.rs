@@ -6,6 +6,8 @@ use winit::{
     window::{Window, WindowBuilder},
 };
 
+use crate::examples::Capture;
+
 struct State {
     surface: wgpu::Surface,
     device: wgpu::Device,
@@ -14,6 +16,7 @@ struct State {
     swap_chain: wgpu::SwapChain,
     size: winit::dpi::PhysicalSize<u32>,
     clear_color: wgpu::Color,               //## Nuevo miembro del struct
+    capture: Capture,
 }
 
 impl State {
@@ -52,6 +55,7 @@ impl State {
             present_mode: wgpu::PresentMode::Fifo,
         };
         let swap_chain = device.create_swap_chain(&surface, &sc_desc);
+        let capture = Capture::new(&device, &sc_desc);
 
         let clear_color = wgpu::Color::BLACK;
 
@@ -65,6 +69,7 @@ impl State {
             clear_color,        //## Nuevo miembro del struct
 
             size,
+            capture,
         }
     }
 
@@ -73,9 +78,13 @@ impl State {
         self.sc_desc.width = new_size.width;
         self.sc_desc.height = new_size.height;
         self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+        self.capture.resize(&self.device, &self.sc_desc);
     }
 
     fn input(&mut self, event: &WindowEvent) -> bool {
+        if self.capture.input(event) {
+            return true;
+        }
         match event {
             WindowEvent::CursorMoved { position, .. } => {
                 self.clear_color = wgpu::Color {
@@ -105,21 +114,28 @@ impl State {
                 label: Some("Render Encoder"),
             });
 
-        {
-            let _render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
-                    attachment: &frame.view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(self.clear_color),
-                        store: true,
-                    },
-                }],
-                depth_stencil_attachment: None,
-            });
+        self.draw(&mut encoder, &frame.view);
+        // Si hay una captura pendiente volvemos a dibujar el frame en la textura de captura
+        if let Some(view) = self.capture.target() {
+            self.draw(&mut encoder, view);
         }
 
         self.queue.submit(iter::once(encoder.finish()));
+        self.capture.finish_frame(&self.device, &self.queue);
+    }
+
+    fn draw(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let _render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                attachment: view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(self.clear_color),
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        });
     }
 }
 
@@ -1,4 +1,5 @@
 use std::iter;
+use std::rc::Rc;
 
 use winit::{
     event::*,
@@ -6,6 +7,10 @@ use winit::{
     window::{Window, WindowBuilder},
 };
 
+use crate::examples::{
+    msaa_sample_count, Capture, GlobalsUniform, Multisample, PipelineBuilder, PipelineCache,
+};
+
 struct State {
     surface: wgpu::Surface,
     device: wgpu::Device,
@@ -13,13 +18,18 @@ struct State {
     sc_desc: wgpu::SwapChainDescriptor,
     swap_chain: wgpu::SwapChain,
     size: winit::dpi::PhysicalSize<u32>,
-    render_pipeline: wgpu::RenderPipeline,
-    challenge_render_pipeline: wgpu::RenderPipeline,
+    pipeline_cache: PipelineCache,
+    // Los pipelines se piden al cache cada frame, solo se crean la primera vez que se usan
+    builder: PipelineBuilder<'static>,
+    challenge_builder: PipelineBuilder<'static>,
     use_color: bool,
+    globals: GlobalsUniform,
+    capture: Capture,
+    multisample: Multisample,
 }
 
 impl State {
-    async fn new(window: &Window) -> Self {
+    async fn new(window: &Window, sample_count: u32) -> Self {
         let size = window.inner_size();
 
         // The instance is a handle to our GPU
@@ -54,93 +64,33 @@ impl State {
             present_mode: wgpu::PresentMode::Fifo,
         };
         let swap_chain = device.create_swap_chain(&surface, &sc_desc);
+        let capture = Capture::new(&device, &sc_desc);
+        let multisample = Multisample::new(&device, &sc_desc, msaa_sample_count(&adapter, sample_count));
 
-        let vs_module = device.create_shader_module(wgpu::include_spirv!("shaders/shader_1_3.vert.spv"));
-        let fs_module = device.create_shader_module(wgpu::include_spirv!("shaders/shader_1_3.frag.spv"));
+        let vs_module = Rc::new(device.create_shader_module(wgpu::include_spirv!("shaders/shader_1_3.vert.spv")));
+        let fs_module = Rc::new(device.create_shader_module(wgpu::include_spirv!("shaders/shader_1_3.frag.spv")));
 
-        let render_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[],
-                push_constant_ranges: &[],
-            });
+        let globals = GlobalsUniform::new(&device, size);
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex_stage: wgpu::ProgrammableStageDescriptor {
-                module: &vs_module,
-                entry_point: "main",
-            },
-            fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
-                module: &fs_module,
-                entry_point: "main",
-            }),
-            rasterization_state: Some(wgpu::RasterizationStateDescriptor {
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: wgpu::CullMode::Back,
-                depth_bias: 0,
-                depth_bias_slope_scale: 0.0,
-                depth_bias_clamp: 0.0,
-                clamp_depth: false,
-            }),
-            primitive_topology: wgpu::PrimitiveTopology::TriangleList,
-            color_states: &[wgpu::ColorStateDescriptor {
-                format: sc_desc.format,
-                color_blend: wgpu::BlendDescriptor::REPLACE,
-                alpha_blend: wgpu::BlendDescriptor::REPLACE,
-                write_mask: wgpu::ColorWrite::ALL,
-            }],
-            depth_stencil_state: None,
-            vertex_state: wgpu::VertexStateDescriptor {
-                index_format: wgpu::IndexFormat::Uint16,
-                vertex_buffers: &[],
-            },
-            sample_count: 1,
-            sample_mask: !0,
-            alpha_to_coverage_enabled: false,
-        });
+        let challenge_vs_module =
+            Rc::new(device.create_shader_module(wgpu::include_spirv!("shaders/shader_1_3_1.vert.spv")));
+        let challenge_fs_module =
+            Rc::new(device.create_shader_module(wgpu::include_spirv!("shaders/shader_1_3_1.frag.spv")));
 
-        let vs_module = device.create_shader_module(wgpu::include_spirv!("shaders/shader_1_3_1.vert.spv"));
-        let fs_module = device.create_shader_module(wgpu::include_spirv!("shaders/shader_1_3_1.frag.spv"));
+        // Los dos pipelines solo se diferencian en los shaders, asi que partimos del mismo builder. El cache necesita
+        // que el builder tenga su propio Rc de cada shader y layout
+        let globals_layout = globals.bind_group_layout.clone();
+        let builder = PipelineBuilder::new(vs_module, sc_desc.format)
+            .label("Render Pipeline")
+            .fragment_shader(fs_module)
+            .bind_group_layouts(&[globals_layout])
+            .sample_count(multisample.sample_count);
 
         // Nuevo Pipeline
-        let challenge_render_pipeline =
-            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                label: Some("Render Pipeline"),
-                layout: Some(&render_pipeline_layout),
-                vertex_stage: wgpu::ProgrammableStageDescriptor {
-                    module: &vs_module,
-                    entry_point: "main",
-                },
-                fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
-                    module: &fs_module,
-                    entry_point: "main",
-                }),
-                rasterization_state: Some(wgpu::RasterizationStateDescriptor {
-                    front_face: wgpu::FrontFace::Ccw,
-                    cull_mode: wgpu::CullMode::Back,
-                    depth_bias: 0,
-                    depth_bias_slope_scale: 0.0,
-                    depth_bias_clamp: 0.0,
-                    clamp_depth: false,
-                }),
-                primitive_topology: wgpu::PrimitiveTopology::TriangleList,
-                color_states: &[wgpu::ColorStateDescriptor {
-                    format: sc_desc.format,
-                    color_blend: wgpu::BlendDescriptor::REPLACE,
-                    alpha_blend: wgpu::BlendDescriptor::REPLACE,
-                    write_mask: wgpu::ColorWrite::ALL,
-                }],
-                depth_stencil_state: None,
-                vertex_state: wgpu::VertexStateDescriptor {
-                    index_format: wgpu::IndexFormat::Uint16,
-                    vertex_buffers: &[],
-                },
-                sample_count: 1,
-                sample_mask: !0,
-                alpha_to_coverage_enabled: false,
-            });
+        let challenge_builder = builder
+            .clone()
+            .vertex_shader(challenge_vs_module)
+            .fragment_shader(challenge_fs_module);
 
         let use_color = true;
 
@@ -150,10 +100,14 @@ impl State {
             queue,
             sc_desc,
             swap_chain,
-            render_pipeline,
-            challenge_render_pipeline,
+            pipeline_cache: PipelineCache::new(),
+            builder,
+            challenge_builder,
             use_color,
+            globals,
             size,
+            capture,
+            multisample,
         }
     }
 
@@ -162,9 +116,16 @@ impl State {
         self.sc_desc.width = new_size.width;
         self.sc_desc.height = new_size.height;
         self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+        self.capture.resize(&self.device, &self.sc_desc);
+        self.multisample.resize(&self.device, &self.sc_desc);
+        self.globals.resize(new_size);
     }
 
     fn input(&mut self, event: &WindowEvent) -> bool {
+        self.globals.input(event);
+        if self.capture.input(event) {
+            return true;
+        }
         match event {
             WindowEvent::KeyboardInput {
                 input:
@@ -182,7 +143,10 @@ impl State {
         }
     }
 
-    fn update(&mut self) {}
+    fn update(&mut self) {
+        self.globals.set_fixed_time_step(self.capture.time_step());
+        self.globals.update(&self.queue);
+    }
 
     fn render(&mut self) {
         let frame = self
@@ -197,37 +161,49 @@ impl State {
                 label: Some("Render Encoder"),
             });
 
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
-                    attachment: &frame.view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.1,
-                            g: 0.2,
-                            b: 0.3,
-                            a: 1.0,
-                        }),
-                        store: true,
-                    },
-                }],
-                depth_stencil_attachment: None,
-            });
+        let builder = if self.use_color {
+            &self.builder
+        } else {
+            &self.challenge_builder
+        };
+        let pipeline = self.pipeline_cache.get_or_create(&self.device, builder);
 
-            render_pass.set_pipeline(if self.use_color {
-                &self.render_pipeline
-            } else {
-                &self.challenge_render_pipeline
-            });
-            render_pass.draw(0..3, 0..1);
+        self.draw(&mut encoder, &frame.view, &pipeline);
+        // Si hay una captura pendiente volvemos a dibujar el frame en la textura de captura
+        if let Some(view) = self.capture.target() {
+            self.draw(&mut encoder, view, &pipeline);
         }
 
         self.queue.submit(iter::once(encoder.finish()));
+        self.capture.finish_frame(&self.device, &self.queue);
+    }
+
+    fn draw(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView, pipeline: &wgpu::RenderPipeline) {
+        let (attachment, resolve_target) = self.multisample.color_attachment(view);
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                attachment,
+                resolve_target,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color {
+                        r: 0.1,
+                        g: 0.2,
+                        b: 0.3,
+                        a: 1.0,
+                    }),
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        });
+
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, &self.globals.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
     }
 }
 
-pub fn main_1_3_1() {
+pub fn main_1_3_1(sample_count: u32) {
     env_logger::init();
     let event_loop = EventLoop::new();
     let window = WindowBuilder::new().build(&event_loop).unwrap();
@@ -235,7 +211,7 @@ pub fn main_1_3_1() {
     use futures::executor::block_on;
 
     // Since main can't be async, we're going to need to block
-    let mut state = block_on(State::new(&window));
+    let mut state = block_on(State::new(&window, sample_count));
 
     event_loop.run(move |event, _, control_flow| {
         match event {
@@ -8,6 +8,8 @@ use winit::{
     window::{Window, WindowBuilder},
 };
 
+use crate::examples::{msaa_sample_count, Capture, GlobalsUniform, Multisample};
+
 struct State {
     surface: wgpu::Surface,
     device: wgpu::Device,
@@ -16,11 +18,14 @@ struct State {
     swap_chain: wgpu::SwapChain,
     size: winit::dpi::PhysicalSize<u32>,
     render_pipeline: wgpu::RenderPipeline,  // Nuevo atributo para usar shaders
+    globals: GlobalsUniform,                // Uniforms por frame (tiempo, resolucion, raton, frame)
+    capture: Capture,
+    multisample: Multisample,
 }
 
 impl State {
     // Creating some of the wgpu types requires async code
-    async fn new(window: &Window) -> Self {
+    async fn new(window: &Window, sample_count: u32) -> Self {
         let size = window.inner_size();
 
         // The instance is a handle to our GPU
@@ -52,6 +57,8 @@ impl State {
         };
 
         let swap_chain = device.create_swap_chain(&surface, &sc_desc);
+        let capture = Capture::new(&device, &sc_desc);
+        let multisample = Multisample::new(&device, &sc_desc, msaa_sample_count(&adapter, sample_count));
 
         //## Esta seccion es para compilar los shaders a SPIRV en tiempo de ejecucion, hace falta la dependencia shaderc y es lento en runtime
         //let vs_src = include_str!("shaders/shader_1_3.vert");
@@ -66,11 +73,14 @@ impl State {
         let vs_module = device.create_shader_module(wgpu::include_spirv!("shaders/shader_1_3.vert.spv"));     
         let fs_module = device.create_shader_module(wgpu::include_spirv!("shaders/shader_1_3.frag.spv"));
 
+        // Los Globals van siempre en el set 0 de todos los pipelines
+        let globals = GlobalsUniform::new(&device, size);
+
         // Helper para ayudar a construir el render_pipeline
         let render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[],
+                bind_group_layouts: &[&globals.bind_group_layout],
                 push_constant_ranges: &[],
             });
 
@@ -126,7 +136,7 @@ impl State {
                 index_format: wgpu::IndexFormat::Uint16,
                 vertex_buffers: &[],
             },
-            sample_count: 1,
+            sample_count: multisample.sample_count,
             sample_mask: !0,
             alpha_to_coverage_enabled: false,
         });
@@ -139,6 +149,9 @@ impl State {
             swap_chain,
             size,
             render_pipeline,
+            globals,
+            capture,
+            multisample,
         }
     }
 
@@ -148,14 +161,19 @@ impl State {
         self.sc_desc.width = new_size.width;
         self.sc_desc.height = new_size.height;
         self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+        self.capture.resize(&self.device, &self.sc_desc);
+        self.multisample.resize(&self.device, &self.sc_desc);
+        self.globals.resize(new_size);
     }
 
-    fn input(&mut self, _event: &WindowEvent) -> bool {
-        false
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        self.globals.input(event);
+        self.capture.input(event)
     }
 
     fn update(&mut self) {
-        
+        self.globals.set_fixed_time_step(self.capture.time_step());
+        self.globals.update(&self.queue);
     }
 
     fn render(&mut self) {
@@ -171,35 +189,44 @@ impl State {
                 label: Some("Render Encoder"),
             });
 
-        {
-            // 1.
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {   // Lo hacemos mutable respecto al apartado anterior
-                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
-                    attachment: &frame.view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.1,
-                            g: 0.2,
-                            b: 0.3,
-                            a: 1.0,
-                        }),
-                        store: true,
-                    },
-                }],
-                depth_stencil_attachment: None,
-            });
-
-            // NEW!
-            render_pass.set_pipeline(&self.render_pipeline);    // 2.
-            render_pass.draw(0..3, 0..1);            // 3. Le decimos que renderice algo con 3 vertices y 1 instancia, aqui es donde se usa gl_VertexIndex
+        self.draw(&mut encoder, &frame.view);
+        // Si hay una captura pendiente volvemos a dibujar el frame en la textura de captura
+        if let Some(view) = self.capture.target() {
+            self.draw(&mut encoder, view);
         }
 
         self.queue.submit(iter::once(encoder.finish()));
+        self.capture.finish_frame(&self.device, &self.queue);
+    }
+
+    fn draw(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        // 1.
+        let (attachment, resolve_target) = self.multisample.color_attachment(view);
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {   // Lo hacemos mutable respecto al apartado anterior
+            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                attachment,
+                resolve_target,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color {
+                        r: 0.1,
+                        g: 0.2,
+                        b: 0.3,
+                        a: 1.0,
+                    }),
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        });
+
+        // NEW!
+        render_pass.set_pipeline(&self.render_pipeline);    // 2.
+        render_pass.set_bind_group(0, &self.globals.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);            // 3. Le decimos que renderice algo con 3 vertices y 1 instancia, aqui es donde se usa gl_VertexIndex
     }
 }
 
-pub fn main_1_3() {
+pub fn main_1_3(sample_count: u32) {
     env_logger::init();
     let event_loop = EventLoop::new();
     let window = WindowBuilder::new()
@@ -208,7 +235,7 @@ pub fn main_1_3() {
 
     use futures::executor::block_on;
     // Since main can't be async, we're going to need to block
-    let mut state = block_on(State::new(&window));
+    let mut state = block_on(State::new(&window, sample_count));
 
     event_loop.run(move |event, _, control_flow| {
         match event {
@@ -7,6 +7,10 @@ use winit::{
     window::{Window, WindowBuilder},
 };
 
+use crate::examples::{
+    msaa_sample_count, Capture, DebugMesh, DebugView, GlobalsUniform, Multisample, PipelineBuilder, Ui,
+};
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug)]
 struct Vertex {
@@ -63,6 +67,26 @@ const VERTICES: &[Vertex] = &[
 
 const INDICES: &[u16] = &[0, 1, 4, 1, 2, 4, 2, 3, 4];
 
+// Poligono regular de num_vertices vertices, triangulado en abanico desde el vertice 0
+fn challenge_polygon(num_vertices: u16, radius: f32) -> (Vec<Vertex>, Vec<u16>) {
+    let angle = std::f32::consts::PI * 2.0 / num_vertices as f32;
+    let challenge_verts = (0..num_vertices)
+        .map(|i| {
+            let theta = angle * i as f32;
+            Vertex {
+                position: [radius * theta.cos(), -radius * theta.sin(), 0.0],
+                color: [(1.0 + theta.cos()) / 2.0, (1.0 + theta.sin()) / 2.0, 1.0],
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let num_triangles = num_vertices - 2;
+    let challenge_indices = (1u16..num_triangles + 1)
+        .flat_map(|i| vec![i + 1, i, 0])
+        .collect::<Vec<_>>();
+    (challenge_verts, challenge_indices)
+}
+
 struct State {
     surface: wgpu::Surface,
     device: wgpu::Device,
@@ -75,17 +99,28 @@ struct State {
     vertex_buffer: wgpu::Buffer,
     index_buffer: wgpu::Buffer,
     num_indices: u32,
+    globals: GlobalsUniform,
 
     challenge_vertex_buffer: wgpu::Buffer,
     challenge_index_buffer: wgpu::Buffer,
     num_challenge_indices: u32,
     use_complex: bool,
+    // Se cambian desde la UI
+    num_vertices: u32,
+    radius: f32,
+    clear_color: [f32; 3],
 
     size: winit::dpi::PhysicalSize<u32>,
+    capture: Capture,
+    multisample: Multisample,
+    debug_view: DebugView,
+    debug_mesh: DebugMesh,
+    challenge_debug_mesh: DebugMesh,
+    ui: Ui,
 }
 
 impl State {
-    async fn new(window: &Window) -> Self {
+    async fn new(window: &Window, sample_count: u32) -> Self {
         let size = window.inner_size();
 
         // The instance is a handle to our GPU
@@ -120,52 +155,22 @@ impl State {
             present_mode: wgpu::PresentMode::Fifo,
         };
         let swap_chain = device.create_swap_chain(&surface, &sc_desc);
+        let capture = Capture::new(&device, &sc_desc);
+        let multisample = Multisample::new(&device, &sc_desc, msaa_sample_count(&adapter, sample_count));
 
         let vs_module = device.create_shader_module(wgpu::include_spirv!("shaders/shader_1_4.vert.spv"));
         let fs_module = device.create_shader_module(wgpu::include_spirv!("shaders/shader_1_4.frag.spv"));
 
-        let render_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[],
-                push_constant_ranges: &[],
-            });
+        let globals = GlobalsUniform::new(&device, size);
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex_stage: wgpu::ProgrammableStageDescriptor {
-                module: &vs_module,
-                entry_point: "main",
-            },
-            fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
-                module: &fs_module,
-                entry_point: "main",
-            }),
-            rasterization_state: Some(wgpu::RasterizationStateDescriptor {
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: wgpu::CullMode::Back,
-                depth_bias: 0,
-                depth_bias_slope_scale: 0.0,
-                depth_bias_clamp: 0.0,
-                clamp_depth: false,
-            }),
-            primitive_topology: wgpu::PrimitiveTopology::TriangleList,
-            color_states: &[wgpu::ColorStateDescriptor {
-                format: sc_desc.format,
-                color_blend: wgpu::BlendDescriptor::REPLACE,
-                alpha_blend: wgpu::BlendDescriptor::REPLACE,
-                write_mask: wgpu::ColorWrite::ALL,
-            }],
-            depth_stencil_state: None,
-            vertex_state: wgpu::VertexStateDescriptor {
-                index_format: wgpu::IndexFormat::Uint16,
-                vertex_buffers: &[Vertex::desc()],
-            },
-            sample_count: 1,
-            sample_mask: !0,
-            alpha_to_coverage_enabled: false,
-        });
+        let pipeline_builder = PipelineBuilder::new(&vs_module, sc_desc.format)
+            .label("Render Pipeline")
+            .fragment_shader(&fs_module)
+            .bind_group_layouts(&[&globals.bind_group_layout])
+            .vertex_buffer(Vertex::desc())
+            .sample_count(multisample.sample_count);
+        let render_pipeline = pipeline_builder.build(&device);
+        let debug_view = DebugView::new(&device, &pipeline_builder);
 
         let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Vertex Buffer"),
@@ -178,39 +183,15 @@ impl State {
             usage: wgpu::BufferUsage::INDEX,
         });
         let num_indices = INDICES.len() as u32;
+        let debug_mesh = DebugMesh::new(&device, INDICES, VERTICES.len() as u32);
 
         let num_vertices = 16;
-        let angle = std::f32::consts::PI * 2.0 / num_vertices as f32;
-        let challenge_verts = (0..num_vertices)
-            .map(|i| {
-                let theta = angle * i as f32;
-                Vertex {
-                    position: [0.5 * theta.cos(), -0.5 * theta.sin(), 0.0],
-                    color: [(1.0 + theta.cos()) / 2.0, (1.0 + theta.sin()) / 2.0, 1.0],
-                }
-            })
-            .collect::<Vec<_>>();
-
-        let num_triangles = num_vertices - 2;
-        let challenge_indices = (1u16..num_triangles + 1)
-            .into_iter()
-            .flat_map(|i| vec![i + 1, i, 0])
-            .collect::<Vec<_>>();
-        let num_challenge_indices = challenge_indices.len() as u32;
-
-        let challenge_vertex_buffer =
-            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Challenge Vertex Buffer"),
-                contents: bytemuck::cast_slice(&challenge_verts),
-                usage: wgpu::BufferUsage::VERTEX,
-            });
-        let challenge_index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Challenge Index Buffer"),
-            contents: bytemuck::cast_slice(&challenge_indices),
-            usage: wgpu::BufferUsage::INDEX,
-        });
+        let radius = 0.5;
+        let (challenge_vertex_buffer, challenge_index_buffer, num_challenge_indices, challenge_debug_mesh) =
+            Self::create_challenge_buffers(&device, num_vertices, radius);
 
         let use_complex = false;
+        let ui = Ui::new(&device, &queue, &globals.bind_group_layout, sc_desc.format, multisample.sample_count);
 
         Self {
             surface,
@@ -222,22 +203,66 @@ impl State {
             vertex_buffer,
             index_buffer,
             num_indices,
+            globals,
             challenge_vertex_buffer,
             challenge_index_buffer,
             num_challenge_indices,
             use_complex,
+            num_vertices,
+            radius,
+            clear_color: [0.1, 0.2, 0.3],
             size,
+            capture,
+            multisample,
+            debug_view,
+            debug_mesh,
+            challenge_debug_mesh,
+            ui,
         }
     }
 
+    fn create_challenge_buffers(
+        device: &wgpu::Device,
+        num_vertices: u32,
+        radius: f32,
+    ) -> (wgpu::Buffer, wgpu::Buffer, u32, DebugMesh) {
+        let (challenge_verts, challenge_indices) = challenge_polygon(num_vertices as u16, radius);
+        let challenge_vertex_buffer =
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Challenge Vertex Buffer"),
+                contents: bytemuck::cast_slice(&challenge_verts),
+                usage: wgpu::BufferUsage::VERTEX,
+            });
+        let challenge_index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Challenge Index Buffer"),
+            contents: bytemuck::cast_slice(&challenge_indices),
+            usage: wgpu::BufferUsage::INDEX,
+        });
+        let challenge_debug_mesh =
+            DebugMesh::new(device, &challenge_indices, challenge_verts.len() as u32);
+        (
+            challenge_vertex_buffer,
+            challenge_index_buffer,
+            challenge_indices.len() as u32,
+            challenge_debug_mesh,
+        )
+    }
+
     fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
         self.size = new_size;
         self.sc_desc.width = new_size.width;
         self.sc_desc.height = new_size.height;
         self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+        self.capture.resize(&self.device, &self.sc_desc);
+        self.multisample.resize(&self.device, &self.sc_desc);
+        self.globals.resize(new_size);
     }
 
     fn input(&mut self, event: &WindowEvent) -> bool {
+        self.globals.input(event);
+        if self.capture.input(event) || self.debug_view.input(event) {
+            return true;
+        }
         match event {
             WindowEvent::KeyboardInput {
                 input:
@@ -255,7 +280,26 @@ impl State {
         }
     }
 
-    fn update(&mut self) {}
+    fn update(&mut self) {
+        self.globals.set_fixed_time_step(self.capture.time_step());
+        self.globals.update(&self.queue);
+
+        // F1 oculta el panel
+        self.ui.label("1_4_1 Buffers challenge");
+        self.ui.checkbox("Complex polygon (Space)", &mut self.use_complex);
+        let vertices_changed = self.ui.slider_u32("Vertices", &mut self.num_vertices, 3..=64);
+        let radius_changed = self.ui.slider("Radius", &mut self.radius, 0.1..=1.0);
+        if vertices_changed || radius_changed {
+            let (vertex_buffer, index_buffer, num_indices, debug_mesh) =
+                Self::create_challenge_buffers(&self.device, self.num_vertices, self.radius);
+            self.challenge_vertex_buffer = vertex_buffer;
+            self.challenge_index_buffer = index_buffer;
+            self.num_challenge_indices = num_indices;
+            self.challenge_debug_mesh = debug_mesh;
+        }
+        self.ui.color_edit("Clear color", &mut self.clear_color);
+        self.ui.prepare(&self.device, &self.queue);
+    }
 
     fn render(&mut self) {
         let frame = self
@@ -270,46 +314,59 @@ impl State {
                 label: Some("Render Encoder"),
             });
 
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
-                    attachment: &frame.view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.1,
-                            g: 0.2,
-                            b: 0.3,
-                            a: 1.0,
-                        }),
-                        store: true,
-                    },
-                }],
-                depth_stencil_attachment: None,
-            });
-
-            render_pass.set_pipeline(&self.render_pipeline);
-
-            let data = if self.use_complex {
-                (
-                    &self.challenge_vertex_buffer,
-                    &self.challenge_index_buffer,
-                    self.num_challenge_indices,
-                )
-            } else {
-                (&self.vertex_buffer, &self.index_buffer, self.num_indices)
-            };
-            render_pass.set_vertex_buffer(0, data.0.slice(..));
-            render_pass.set_index_buffer(data.1.slice(..));
-
-            render_pass.draw_indexed(0..data.2, 0, 0..1);
+        self.draw(&mut encoder, &frame.view);
+        // Si hay una captura pendiente volvemos a dibujar el frame en la textura de captura
+        if let Some(view) = self.capture.target() {
+            self.draw(&mut encoder, view);
         }
 
         self.queue.submit(iter::once(encoder.finish()));
+        self.capture.finish_frame(&self.device, &self.queue);
+    }
+
+    fn draw(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let (attachment, resolve_target) = self.multisample.color_attachment(view);
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                attachment,
+                resolve_target,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color {
+                        r: self.clear_color[0] as f64,
+                        g: self.clear_color[1] as f64,
+                        b: self.clear_color[2] as f64,
+                        a: 1.0,
+                    }),
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        });
+
+        render_pass.set_bind_group(0, &self.globals.bind_group, &[]);
+
+        let data = if self.use_complex {
+            (
+                &self.challenge_vertex_buffer,
+                &self.challenge_index_buffer,
+                self.num_challenge_indices,
+                &self.challenge_debug_mesh,
+            )
+        } else {
+            (&self.vertex_buffer, &self.index_buffer, self.num_indices, &self.debug_mesh)
+        };
+        render_pass.set_vertex_buffer(0, data.0.slice(..));
+
+        // F3 cambia entre relleno, wireframe y puntos, util para ver como queda el abanico de 16 vertices
+        self.debug_view
+            .draw_indexed(&mut render_pass, &self.render_pipeline, data.1, data.2, data.3);
+
+        // La UI al final, encima de todo
+        self.ui.draw(&mut render_pass, &self.globals.bind_group);
     }
 }
 
-pub fn main_1_4_1() {
+pub fn main_1_4_1(sample_count: u32) {
     env_logger::init();
     let event_loop = EventLoop::new();
     let window = WindowBuilder::new().build(&event_loop).unwrap();
@@ -317,7 +374,7 @@ pub fn main_1_4_1() {
     use futures::executor::block_on;
 
     // Since main can't be async, we're going to need to block
-    let mut state = block_on(State::new(&window));
+    let mut state = block_on(State::new(&window, sample_count));
 
     event_loop.run(move |event, _, control_flow| {
         match event {
@@ -325,7 +382,8 @@ pub fn main_1_4_1() {
                 ref event,
                 window_id,
             } if window_id == window.id() => {
-                if !state.input(event) {
+                // La UI ve los eventos antes que el State, que solo recibe los que ella no usa
+                if !state.ui.input(event) && !state.input(event) {
                     match event {
                         WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
                         WindowEvent::KeyboardInput { input, .. } => match input {
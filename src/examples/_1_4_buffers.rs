@@ -7,6 +7,8 @@ use winit::{
     window::{Window, WindowBuilder},
 };
 
+use crate::examples::{msaa_sample_count, Capture, GlobalsUniform, Multisample, PipelineBuilder};
+
 // Ejemplo de una estructura de un vertex para un buffer
 #[repr(C)]
 #[derive(Copy, Clone, Debug)]
@@ -103,10 +105,13 @@ struct State {
     vertex_buffer: wgpu::Buffer,
     index_buffer: wgpu::Buffer,
     num_indices: u32,
+    globals: GlobalsUniform,
+    capture: Capture,
+    multisample: Multisample,
 }
 
 impl State {
-    async fn new(window: &Window) -> Self {
+    async fn new(window: &Window, sample_count: u32) -> Self {
         let size = window.inner_size();
 
         // The instance is a handle to our GPU
@@ -141,53 +146,22 @@ impl State {
             present_mode: wgpu::PresentMode::Fifo,
         };
         let swap_chain = device.create_swap_chain(&surface, &sc_desc);
+        let capture = Capture::new(&device, &sc_desc);
+        let multisample = Multisample::new(&device, &sc_desc, msaa_sample_count(&adapter, sample_count));
 
         let vs_module = device.create_shader_module(wgpu::include_spirv!("shaders/shader_1_4.vert.spv"));
         let fs_module = device.create_shader_module(wgpu::include_spirv!("shaders/shader_1_4.frag.spv"));
 
-        let render_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[],
-                push_constant_ranges: &[],
-            });
+        let globals = GlobalsUniform::new(&device, size);
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex_stage: wgpu::ProgrammableStageDescriptor {
-                module: &vs_module,
-                entry_point: "main",
-            },
-            fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
-                module: &fs_module,
-                entry_point: "main",
-            }),
-            rasterization_state: Some(wgpu::RasterizationStateDescriptor {
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: wgpu::CullMode::Back,
-                depth_bias: 0,
-                depth_bias_slope_scale: 0.0,
-                depth_bias_clamp: 0.0,
-                clamp_depth: false,
-            }),
-            primitive_topology: wgpu::PrimitiveTopology::TriangleList,
-            color_states: &[wgpu::ColorStateDescriptor {
-                format: sc_desc.format,
-                color_blend: wgpu::BlendDescriptor::REPLACE,
-                alpha_blend: wgpu::BlendDescriptor::REPLACE,
-                write_mask: wgpu::ColorWrite::ALL,
-            }],
-            depth_stencil_state: None,
-            // NUEVO, es diferente del anterior vertex_stage: wgpu::ProgrammableStageDescriptor
-            vertex_state: wgpu::VertexStateDescriptor {
-                index_format: wgpu::IndexFormat::Uint16,
-                vertex_buffers: &[Vertex::desc()],
-            },
-            sample_count: 1,
-            sample_mask: !0,
-            alpha_to_coverage_enabled: false,
-        });
+        let render_pipeline = PipelineBuilder::new(&vs_module, sc_desc.format)
+            .label("Render Pipeline")
+            .fragment_shader(&fs_module)
+            .bind_group_layouts(&[&globals.bind_group_layout])
+            // NUEVO, el layout de los vertices que le llegan al vertex shader
+            .vertex_buffer(Vertex::desc())
+            .sample_count(multisample.sample_count)
+            .build(&device);
 
         // Creacion del buffer de vertices
         let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -214,7 +188,10 @@ impl State {
             vertex_buffer,
             index_buffer,
             num_indices,
+            globals,
             size,
+            capture,
+            multisample,
         }
     }
 
@@ -223,14 +200,20 @@ impl State {
         self.sc_desc.width = new_size.width;
         self.sc_desc.height = new_size.height;
         self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+        self.capture.resize(&self.device, &self.sc_desc);
+        self.multisample.resize(&self.device, &self.sc_desc);
+        self.globals.resize(new_size);
     }
 
-    #[allow(unused_variables)]
     fn input(&mut self, event: &WindowEvent) -> bool {
-        false
+        self.globals.input(event);
+        self.capture.input(event)
     }
 
-    fn update(&mut self) {}
+    fn update(&mut self) {
+        self.globals.set_fixed_time_step(self.capture.time_step());
+        self.globals.update(&self.queue);
+    }
 
     fn render(&mut self) {
         let frame = self
@@ -245,41 +228,50 @@ impl State {
                 label: Some("Render Encoder"),
             });
 
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
-                    attachment: &frame.view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.1,
-                            g: 0.2,
-                            b: 0.3,
-                            a: 1.0,
-                        }),
-                        store: true,
-                    },
-                }],
-                depth_stencil_attachment: None,
-            });
-
-            // 1. The method name is set_index_buffer not set_index_buffers. You can only have one index buffer set at a time.
-            // 2. When using an index buffer, you need to use draw_indexed. The draw method ignores the index buffer. Also make sure you use the number of 
-            //    indices (num_indices), not vertices as you model will either draw wrong, or the method will panic because there are not enough indices.
-            render_pass.set_pipeline(&self.render_pipeline);
-            // NUEVO, pasamos el indice del Vertex buffer
-            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            // NUEVO, pasamos el indice del Index Buffer
-            render_pass.set_index_buffer(self.index_buffer.slice(..));
-            // render_pass.draw(0..self.num_vertices, 0..1);        // Cuando no usamos el Index Buffer
-            render_pass.draw_indexed(0..self.num_indices, 0, 0..1);
+        self.draw(&mut encoder, &frame.view);
+        // Si hay una captura pendiente volvemos a dibujar el frame en la textura de captura
+        if let Some(view) = self.capture.target() {
+            self.draw(&mut encoder, view);
         }
 
         self.queue.submit(iter::once(encoder.finish()));
+        self.capture.finish_frame(&self.device, &self.queue);
+    }
+
+    fn draw(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let (attachment, resolve_target) = self.multisample.color_attachment(view);
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                attachment,
+                resolve_target,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color {
+                        r: 0.1,
+                        g: 0.2,
+                        b: 0.3,
+                        a: 1.0,
+                    }),
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        });
+
+        // 1. The method name is set_index_buffer not set_index_buffers. You can only have one index buffer set at a time.
+        // 2. When using an index buffer, you need to use draw_indexed. The draw method ignores the index buffer. Also make sure you use the number of 
+        //    indices (num_indices), not vertices as you model will either draw wrong, or the method will panic because there are not enough indices.
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, &self.globals.bind_group, &[]);
+        // NUEVO, pasamos el indice del Vertex buffer
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        // NUEVO, pasamos el indice del Index Buffer
+        render_pass.set_index_buffer(self.index_buffer.slice(..));
+        // render_pass.draw(0..self.num_vertices, 0..1);        // Cuando no usamos el Index Buffer
+        render_pass.draw_indexed(0..self.num_indices, 0, 0..1);
     }
 }
 
-pub fn main_1_4() {
+pub fn main_1_4(sample_count: u32) {
     env_logger::init();
     let event_loop = EventLoop::new();
     let window = WindowBuilder::new().build(&event_loop).unwrap();
@@ -287,7 +279,7 @@ pub fn main_1_4() {
     use futures::executor::block_on;
 
     // Since main can't be async, we're going to need to block
-    let mut state = block_on(State::new(&window));
+    let mut state = block_on(State::new(&window, sample_count));
 
     event_loop.run(move |event, _, control_flow| {
         match event {
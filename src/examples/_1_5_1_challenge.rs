@@ -7,7 +7,10 @@ use winit::{
     window::{Window, WindowBuilder},
 };
 
-use crate::examples::Texture as texture;
+use crate::examples::{
+    load_material, msaa_sample_count, Capture, DebugMesh, DebugView, GlobalsUniform, Handle, Material, MaterialTarget,
+    Multisample, PipelineBuilder, ResourceManager,
+};
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug)]
@@ -66,6 +69,14 @@ const VERTICES: &[Vertex] = &[
 
 const INDICES: &[u16] = &[0, 1, 4, 1, 2, 4, 2, 3, 4];
 
+// La malla solo sabe con que material se dibuja, cambiar su aspecto es cambiar el handle
+struct Mesh {
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    num_indices: u32,
+    material: Handle<Material>,
+}
+
 struct State {
     surface: wgpu::Surface,
     device: wgpu::Device,
@@ -73,21 +84,20 @@ struct State {
     sc_desc: wgpu::SwapChainDescriptor,
     swap_chain: wgpu::SwapChain,
     size: winit::dpi::PhysicalSize<u32>,
-    render_pipeline: wgpu::RenderPipeline,
-    vertex_buffer: wgpu::Buffer,
-    index_buffer: wgpu::Buffer,
-    num_indices: u32,
-    #[allow(dead_code)]
-    diffuse_texture: texture,
-    diffuse_bind_group: wgpu::BindGroup,
-    #[allow(dead_code)]
-    cartoon_texture: texture,
-    cartoon_bind_group: wgpu::BindGroup,
-    is_space_pressed: bool,
+    mesh: Mesh,
+    globals: GlobalsUniform,
+    diffuse_material: Handle<Material>,
+    cartoon_material: Handle<Material>,
+    capture: Capture,
+    multisample: Multisample,
+    // Guarda los materiales con sus texturas y bind groups, el resto del State solo tiene handles
+    resources: ResourceManager,
+    debug_view: DebugView,
+    debug_mesh: DebugMesh,
 }
 
 impl State {
-    async fn new(window: &Window) -> Self {
+    async fn new(window: &Window, sample_count: u32) -> Self {
         let size = window.inner_size();
 
         // The instance is a handle to our GPU
@@ -121,114 +131,50 @@ impl State {
             present_mode: wgpu::PresentMode::Fifo,
         };
         let swap_chain = device.create_swap_chain(&surface, &sc_desc);
-
-        let texture_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStage::FRAGMENT,
-                        ty: wgpu::BindingType::SampledTexture {
-                            multisampled: false,
-                            dimension: wgpu::TextureViewDimension::D2,
-                            component_type: wgpu::TextureComponentType::Uint,
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: wgpu::ShaderStage::FRAGMENT,
-                        ty: wgpu::BindingType::Sampler { comparison: false },
-                        count: None,
-                    },
-                ],
-                label: Some("texture_bind_group_layout"),
-            });
-
-        let diffuse_bytes = include_bytes!("textures/happy-tree.png");
-        let diffuse_texture =
-            texture::from_bytes(&device, &queue, diffuse_bytes, "textures/happy-tree.png").unwrap();
-
-        let diffuse_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &texture_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&diffuse_texture.view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&diffuse_texture.sampler),
-                },
-            ],
-            label: Some("diffuse_bind_group"),
-        });
-
-        let cartoon_bytes = include_bytes!("textures/happy-tree-cartoon.png");
-        let cartoon_texture =
-            texture::from_bytes(&device, &queue, cartoon_bytes, "textures/happy-tree-cartoon.png")
-                .unwrap();
-
-        let cartoon_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &texture_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&cartoon_texture.view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&cartoon_texture.sampler),
-                },
-            ],
-            label: Some("cartoon_bind_group"),
-        });
-
+        let capture = Capture::new(&device, &sc_desc);
+        let multisample = Multisample::new(&device, &sc_desc, msaa_sample_count(&adapter, sample_count));
+        let mut resources = ResourceManager::new();
+
+        let globals = GlobalsUniform::new(&device, size);
+
+        // Los materiales se leen de src/examples/materials/, se pueden editar sin recompilar
+        let target = MaterialTarget {
+            globals_layout: &globals.bind_group_layout,
+            vertex_buffer: Vertex::desc(),
+            color_format: sc_desc.format,
+            sample_count: multisample.sample_count,
+        };
+        let materials_dir = concat!(env!("CARGO_MANIFEST_DIR"), "/src/examples/materials/");
+        let diffuse_material = load_material(
+            &device,
+            &queue,
+            &mut resources,
+            &target,
+            format!("{}happy_tree.ron", materials_dir),
+        )
+        .unwrap();
+        let cartoon_material = load_material(
+            &device,
+            &queue,
+            &mut resources,
+            &target,
+            format!("{}happy_tree_cartoon.ron", materials_dir),
+        )
+        .unwrap();
+
+        // El wireframe y los puntos usan los shaders de los materiales con el layout de los materiales de una textura
         let vs_module = device.create_shader_module(wgpu::include_spirv!("shaders/shader_1_5.vert.spv"));
-        let fs_module = device.create_shader_module(wgpu::include_spirv!("shaders/shader_1_5.frag.spv"));
-
-        let render_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[&texture_bind_group_layout],
-                push_constant_ranges: &[],
-            });
-
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex_stage: wgpu::ProgrammableStageDescriptor {
-                module: &vs_module,
-                entry_point: "main",
-            },
-            fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
-                module: &fs_module,
-                entry_point: "main",
-            }),
-            rasterization_state: Some(wgpu::RasterizationStateDescriptor {
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: wgpu::CullMode::Back,
-                depth_bias: 0,
-                depth_bias_slope_scale: 0.0,
-                depth_bias_clamp: 0.0,
-                clamp_depth: false,
-            }),
-            primitive_topology: wgpu::PrimitiveTopology::TriangleList,
-            color_states: &[wgpu::ColorStateDescriptor {
-                format: sc_desc.format,
-                color_blend: wgpu::BlendDescriptor::REPLACE,
-                alpha_blend: wgpu::BlendDescriptor::REPLACE,
-                write_mask: wgpu::ColorWrite::ALL,
-            }],
-            depth_stencil_state: None,
-            vertex_state: wgpu::VertexStateDescriptor {
-                index_format: wgpu::IndexFormat::Uint16,
-                vertex_buffers: &[Vertex::desc()],
-            },
-            sample_count: 1,
-            sample_mask: !0,
-            alpha_to_coverage_enabled: false,
-        });
+        let fs_module = device.create_shader_module(wgpu::include_spirv!("shaders/material.frag.spv"));
+        let material_layout = resources.material_layout(&device, 1);
+        let debug_view = DebugView::new(
+            &device,
+            &PipelineBuilder::new(&vs_module, sc_desc.format)
+                .label("Debug Pipeline")
+                .fragment_shader(&fs_module)
+                .bind_group_layouts(&[&globals.bind_group_layout, resources.bind_group_layout(material_layout)])
+                .vertex_buffer(Vertex::desc())
+                .sample_count(multisample.sample_count),
+        );
 
         let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Vertex Buffer"),
@@ -240,7 +186,13 @@ impl State {
             contents: bytemuck::cast_slice(INDICES),
             usage: wgpu::BufferUsage::INDEX,
         });
-        let num_indices = INDICES.len() as u32;
+        let mesh = Mesh {
+            vertex_buffer,
+            index_buffer,
+            num_indices: INDICES.len() as u32,
+            material: diffuse_material,
+        };
+        let debug_mesh = DebugMesh::new(&device, INDICES, VERTICES.len() as u32);
 
         Self {
             surface,
@@ -248,16 +200,16 @@ impl State {
             queue,
             sc_desc,
             swap_chain,
-            render_pipeline,
-            vertex_buffer,
-            index_buffer,
-            num_indices,
-            diffuse_texture,
-            diffuse_bind_group,
-            cartoon_texture,
-            cartoon_bind_group,
+            mesh,
+            globals,
+            diffuse_material,
+            cartoon_material,
             size,
-            is_space_pressed: false,
+            capture,
+            multisample,
+            resources,
+            debug_view,
+            debug_mesh,
         }
     }
 
@@ -266,9 +218,16 @@ impl State {
         self.sc_desc.width = new_size.width;
         self.sc_desc.height = new_size.height;
         self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+        self.capture.resize(&self.device, &self.sc_desc);
+        self.multisample.resize(&self.device, &self.sc_desc);
+        self.globals.resize(new_size);
     }
 
     fn input(&mut self, event: &WindowEvent) -> bool {
+        self.globals.input(event);
+        if self.capture.input(event) || self.debug_view.input(event) {
+            return true;
+        }
         match event {
             WindowEvent::KeyboardInput {
                 input:
@@ -279,14 +238,25 @@ impl State {
                     },
                 ..
             } => {
-                self.is_space_pressed = *state == ElementState::Pressed;
+                let material = if *state == ElementState::Pressed {
+                    self.cartoon_material
+                } else {
+                    self.diffuse_material
+                };
+                if material != self.mesh.material {
+                    self.mesh.material = material;
+                    println!("Material: {}", self.resources.material(material).name);
+                }
                 true
             }
             _ => false,
         }
     }
 
-    fn update(&mut self) {}
+    fn update(&mut self) {
+        self.globals.set_fixed_time_step(self.capture.time_step());
+        self.globals.update(&self.queue);
+    }
 
     fn render(&mut self) {
         let frame = self
@@ -301,42 +271,51 @@ impl State {
                 label: Some("Render Encoder"),
             });
 
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
-                    attachment: &frame.view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.1,
-                            g: 0.2,
-                            b: 0.3,
-                            a: 1.0,
-                        }),
-                        store: true,
-                    },
-                }],
-                depth_stencil_attachment: None,
-            });
-
-            let bind_group = if self.is_space_pressed {
-                &self.cartoon_bind_group
-            } else {
-                &self.diffuse_bind_group
-            };
-
-            render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.set_bind_group(0, bind_group, &[]);
-            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            render_pass.set_index_buffer(self.index_buffer.slice(..));
-            render_pass.draw_indexed(0..self.num_indices, 0, 0..1);
+        self.draw(&mut encoder, &frame.view);
+        // Si hay una captura pendiente volvemos a dibujar el frame en la textura de captura
+        if let Some(view) = self.capture.target() {
+            self.draw(&mut encoder, view);
         }
 
         self.queue.submit(iter::once(encoder.finish()));
+        self.capture.finish_frame(&self.device, &self.queue);
+    }
+
+    fn draw(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let (attachment, resolve_target) = self.multisample.color_attachment(view);
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                attachment,
+                resolve_target,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color {
+                        r: 0.1,
+                        g: 0.2,
+                        b: 0.3,
+                        a: 1.0,
+                    }),
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        });
+
+        let material = self.resources.material(self.mesh.material);
+
+        render_pass.set_bind_group(0, &self.globals.bind_group, &[]);
+        render_pass.set_bind_group(1, self.resources.bind_group(material.bind_group), &[]);
+        render_pass.set_vertex_buffer(0, self.mesh.vertex_buffer.slice(..));
+        self.debug_view.draw_indexed(
+            &mut render_pass,
+            &material.pipeline,
+            &self.mesh.index_buffer,
+            self.mesh.num_indices,
+            &self.debug_mesh,
+        );
     }
 }
 
-pub fn main_1_5_1() {
+pub fn main_1_5_1(sample_count: u32) {
     env_logger::init();
     let event_loop = EventLoop::new();
     let window = WindowBuilder::new().build(&event_loop).unwrap();
@@ -344,7 +323,7 @@ pub fn main_1_5_1() {
     use futures::executor::block_on;
 
     // Since main can't be async, we're going to need to block
-    let mut state = block_on(State::new(&window));
+    let mut state = block_on(State::new(&window, sample_count));
 
     event_loop.run(move |event, _, control_flow| {
         match event {
@@ -7,6 +7,9 @@ use winit::{
     window::{Window, WindowBuilder},
 };
 
+use crate::examples::{
+    msaa_sample_count, sampler_entry, texture_entry, BindGroup, Binding, BlendMode, Capture, DebugMesh, DebugView, GlobalsUniform, Handle, Multisample, PipelineBuilder, ResourceManager,
+};
 use crate::examples::Texture as texture;
 
 // Añadimos una variable mas de textures coordinates
@@ -80,13 +83,18 @@ struct State {
     index_buffer: wgpu::Buffer,
     num_indices: u32,
     // NEW!
-    #[allow(dead_code)]
-    diffuse_texture: texture,
-    diffuse_bind_group: wgpu::BindGroup,
+    diffuse_bind_group: Handle<BindGroup>,
+    globals: GlobalsUniform,
+    capture: Capture,
+    multisample: Multisample,
+    // Guarda las texturas y los bind groups, el resto del State solo tiene handles
+    resources: ResourceManager,
+    debug_view: DebugView,
+    debug_mesh: DebugMesh,
 }
 
 impl State {
-    async fn new(window: &Window) -> Self {
+    async fn new(window: &Window, sample_count: u32) -> Self {
         let size = window.inner_size();
 
         // The instance is a handle to our GPU
@@ -120,6 +128,9 @@ impl State {
             present_mode: wgpu::PresentMode::Fifo,
         };
         let swap_chain = device.create_swap_chain(&surface, &sc_desc);
+        let capture = Capture::new(&device, &sc_desc);
+        let multisample = Multisample::new(&device, &sc_desc, msaa_sample_count(&adapter, sample_count));
+        let mut resources = ResourceManager::new();
 
         // Cargamos la imagen, usamos el modulo helper Texture para hacer realmente
         //let diffuse_bytes = include_bytes!("textures/happy-tree.png");
@@ -129,98 +140,49 @@ impl State {
         //let dimensions = diffuse_image.dimensions();
 
         let diffuse_bytes = include_bytes!("textures/happy-tree.png");
-        let diffuse_texture =
-            texture::from_bytes(&device, &queue, diffuse_bytes, "textures/happy-tree.png").unwrap();
+        let diffuse_texture = resources.add_texture(
+            texture::from_bytes(&device, &queue, diffuse_bytes, "textures/happy-tree.png").unwrap(),
+        );
 
         // A BindGroup describes a set of resources and how they can be accessed by a shader. We create a BindGroup using a BindGroupLayout.
         // Our texture_bind_group_layout has two entries: one for a sampled texture at binding 0, and one for a sampler at binding 1. Both of these bindings 
         // are visible only to the fragment shader as specified by FRAGMENT. The possible values are any bit combination of NONE, VERTEX, FRAGMENT, or 
         // COMPUTE. Most of the time we'll only use FRAGMENT for textures and samplers, but it's good to know what's available.
-        let texture_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStage::FRAGMENT,
-                        ty: wgpu::BindingType::SampledTexture {
-                            multisampled: false,
-                            dimension: wgpu::TextureViewDimension::D2,
-                            component_type: wgpu::TextureComponentType::Uint,
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: wgpu::ShaderStage::FRAGMENT,
-                        ty: wgpu::BindingType::Sampler { comparison: false },
-                        count: None,
-                    },
-                ],
-                label: Some("texture_bind_group_layout"),
-            });
+        let texture_bind_group_layout = resources.create_bind_group_layout(
+            &device,
+            &[texture_entry(0), sampler_entry(1)],
+            Some("texture_bind_group_layout"),
+        );
 
         // Con el layout definido creamos realmente el bind group            
-        let diffuse_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &texture_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&diffuse_texture.view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&diffuse_texture.sampler),
-                },
-            ],
-            label: Some("diffuse_bind_group"),
-        });
+        let diffuse_bind_group = resources.create_bind_group(
+            &device,
+            texture_bind_group_layout,
+            &[Binding::Texture(diffuse_texture), Binding::TextureSampler(diffuse_texture)],
+            Some("diffuse_bind_group"),
+        );
+        // El bind group ya tiene su propia referencia a la textura, la soltamos para que se libere con el
+        resources.release_texture(diffuse_texture);
 
         let vs_module = device.create_shader_module(wgpu::include_spirv!("shaders/shader_1_5.vert.spv"));
         let fs_module = device.create_shader_module(wgpu::include_spirv!("shaders/shader_1_5.frag.spv"));
 
-        // Ahora podemos utilizarlo con un bind group
-        let render_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[&texture_bind_group_layout],
-                push_constant_ranges: &[],
-            });
-
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex_stage: wgpu::ProgrammableStageDescriptor {
-                module: &vs_module,
-                entry_point: "main",
-            },
-            fragment_stage: Some(wgpu::ProgrammableStageDescriptor {
-                module: &fs_module,
-                entry_point: "main",
-            }),
-            rasterization_state: Some(wgpu::RasterizationStateDescriptor {
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: wgpu::CullMode::Back,
-                depth_bias: 0,
-                depth_bias_slope_scale: 0.0,
-                depth_bias_clamp: 0.0,
-                clamp_depth: false,
-            }),
-            primitive_topology: wgpu::PrimitiveTopology::TriangleList,
-            color_states: &[wgpu::ColorStateDescriptor {
-                format: sc_desc.format,
-                color_blend: wgpu::BlendDescriptor::REPLACE,
-                alpha_blend: wgpu::BlendDescriptor::REPLACE,
-                write_mask: wgpu::ColorWrite::ALL,
-            }],
-            depth_stencil_state: None,
-            vertex_state: wgpu::VertexStateDescriptor {
-                index_format: wgpu::IndexFormat::Uint16,
-                vertex_buffers: &[Vertex::desc()],
-            },
-            sample_count: 1,
-            sample_mask: !0,
-            alpha_to_coverage_enabled: false,
-        });
+        // Ahora podemos utilizarlo con un bind group. El set 0 son los Globals y la textura pasa al set 1
+        let globals = GlobalsUniform::new(&device, size);
+
+        let pipeline_builder = PipelineBuilder::new(&vs_module, sc_desc.format)
+            .label("Render Pipeline")
+            .fragment_shader(&fs_module)
+            .bind_group_layouts(&[
+                &globals.bind_group_layout,
+                resources.bind_group_layout(texture_bind_group_layout),
+            ])
+            .vertex_buffer(Vertex::desc())
+            // El fondo de happy-tree.png es transparente, con REPLACE se veria el color que tenga debajo el alpha
+            .blend(BlendMode::Alpha)
+            .sample_count(multisample.sample_count);
+        let render_pipeline = pipeline_builder.build(&device);
+        let debug_view = DebugView::new(&device, &pipeline_builder);
 
         let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Vertex Buffer"),
@@ -233,6 +195,7 @@ impl State {
             usage: wgpu::BufferUsage::INDEX,
         });
         let num_indices = INDICES.len() as u32;
+        let debug_mesh = DebugMesh::new(&device, INDICES, VERTICES.len() as u32);
 
         Self {
             surface,
@@ -244,9 +207,14 @@ impl State {
             vertex_buffer,
             index_buffer,
             num_indices,
-            diffuse_texture,
             diffuse_bind_group,
+            globals,
             size,
+            capture,
+            multisample,
+            resources,
+            debug_view,
+            debug_mesh,
         }
     }
 
@@ -255,14 +223,20 @@ impl State {
         self.sc_desc.width = new_size.width;
         self.sc_desc.height = new_size.height;
         self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+        self.capture.resize(&self.device, &self.sc_desc);
+        self.multisample.resize(&self.device, &self.sc_desc);
+        self.globals.resize(new_size);
     }
 
-    #[allow(unused_variables)]
     fn input(&mut self, event: &WindowEvent) -> bool {
-        false
+        self.globals.input(event);
+        self.capture.input(event) || self.debug_view.input(event)
     }
 
-    fn update(&mut self) {}
+    fn update(&mut self) {
+        self.globals.set_fixed_time_step(self.capture.time_step());
+        self.globals.update(&self.queue);
+    }
 
     fn render(&mut self) {
         let frame = self
@@ -277,36 +251,49 @@ impl State {
                 label: Some("Render Encoder"),
             });
 
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
-                    attachment: &frame.view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.1,
-                            g: 0.2,
-                            b: 0.3,
-                            a: 1.0,
-                        }),
-                        store: true,
-                    },
-                }],
-                depth_stencil_attachment: None,
-            });
-
-            render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.set_bind_group(0, &self.diffuse_bind_group, &[]);
-            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            render_pass.set_index_buffer(self.index_buffer.slice(..));
-            render_pass.draw_indexed(0..self.num_indices, 0, 0..1);
+        self.draw(&mut encoder, &frame.view);
+        // Si hay una captura pendiente volvemos a dibujar el frame en la textura de captura
+        if let Some(view) = self.capture.target() {
+            self.draw(&mut encoder, view);
         }
 
         self.queue.submit(iter::once(encoder.finish()));
+        self.capture.finish_frame(&self.device, &self.queue);
+    }
+
+    fn draw(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let (attachment, resolve_target) = self.multisample.color_attachment(view);
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                attachment,
+                resolve_target,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color {
+                        r: 0.1,
+                        g: 0.2,
+                        b: 0.3,
+                        a: 1.0,
+                    }),
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        });
+
+        render_pass.set_bind_group(0, &self.globals.bind_group, &[]);
+        render_pass.set_bind_group(1, self.resources.bind_group(self.diffuse_bind_group), &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        self.debug_view.draw_indexed(
+            &mut render_pass,
+            &self.render_pipeline,
+            &self.index_buffer,
+            self.num_indices,
+            &self.debug_mesh,
+        );
     }
 }
 
-pub fn main_1_5() {
+pub fn main_1_5(sample_count: u32) {
     env_logger::init();
     let event_loop = EventLoop::new();
     let window = WindowBuilder::new().build(&event_loop).unwrap();
@@ -314,7 +301,7 @@ pub fn main_1_5() {
     use futures::executor::block_on;
 
     // Since main can't be async, we're going to need to block
-    let mut state = block_on(State::new(&window));
+    let mut state = block_on(State::new(&window, sample_count));
 
     event_loop.run(move |event, _, control_flow| {
         match event {
@@ -0,0 +1,448 @@
+//## Iluminacion: un cubo girando iluminado con el modelo de Phong (ambiente + difusa + especular) por una luz
+//## direccional y dos puntuales que dan vueltas a su alrededor. Los cubitos de colores son las luces puntuales.
+//## Por primera vez hay 3D de verdad: camara en perspectiva, normales en los vertices y depth buffer.
+//## B cambia entre Phong y Blinn-Phong, el panel (F1) permite tocar el material y la luz ambiente.
+
+use std::iter;
+
+use cgmath::{Matrix4, Point3, Rad};
+use wgpu::util::DeviceExt;
+use winit::{
+    event::*,
+    event_loop::{ControlFlow, EventLoop},
+    window::{Window, WindowBuilder},
+};
+
+use crate::examples::{
+    cube_mesh, material_layout_entries, msaa_sample_count, Camera, CameraUniform, Capture, DebugMesh, DebugView,
+    DepthBuffer, DirectionalLight, GlobalsUniform, InstanceData, LightGizmo, Lights, LightsUniform, MaterialParams,
+    MeshVertex, Multisample, PipelineBuilder, PointLight, SpecularModel, Ui, DEPTH_FORMAT,
+};
+
+const POINT_LIGHT_COLORS: [[f32; 3]; 2] = [[1.0, 0.4, 0.2], [0.2, 0.5, 1.0]];
+const POINT_LIGHT_ORBIT: f32 = 1.5;
+
+struct State {
+    surface: wgpu::Surface,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    sc_desc: wgpu::SwapChainDescriptor,
+    swap_chain: wgpu::SwapChain,
+    size: winit::dpi::PhysicalSize<u32>,
+    render_pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    num_indices: u32,
+    instance_buffer: wgpu::Buffer,
+    rotation: f32,
+    // El material del cubo: color difuso, intensidad especular y shininess
+    cube_color: [f32; 3],
+    specular: f32,
+    shininess: f32,
+    material_buffer: wgpu::Buffer,
+    material_bind_group: wgpu::BindGroup,
+    camera: Camera,
+    camera_uniform: CameraUniform,
+    lights: Lights,
+    lights_uniform: LightsUniform,
+    light_gizmo: LightGizmo,
+    depth_buffer: DepthBuffer,
+    globals: GlobalsUniform,
+    capture: Capture,
+    multisample: Multisample,
+    debug_view: DebugView,
+    debug_mesh: DebugMesh,
+    ui: Ui,
+}
+
+impl State {
+    async fn new(window: &Window, sample_count: u32) -> Self {
+        let size = window.inner_size();
+
+        let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+        let surface = unsafe { instance.create_surface(window) };
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::Default,
+                compatible_surface: Some(&surface),
+            })
+            .await
+            .unwrap();
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    features: wgpu::Features::empty(),
+                    limits: wgpu::Limits::default(),
+                    shader_validation: true,
+                },
+                None, // Trace path
+            )
+            .await
+            .unwrap();
+
+        let sc_desc = wgpu::SwapChainDescriptor {
+            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+            format: wgpu::TextureFormat::Bgra8UnormSrgb,
+            width: size.width,
+            height: size.height,
+            present_mode: wgpu::PresentMode::Fifo,
+        };
+        let swap_chain = device.create_swap_chain(&surface, &sc_desc);
+        let capture = Capture::new(&device, &sc_desc);
+        let multisample = Multisample::new(&device, &sc_desc, msaa_sample_count(&adapter, sample_count));
+        // El depth buffer tiene que tener las mismas muestras que el color
+        let depth_buffer = DepthBuffer::new(&device, &sc_desc, multisample.sample_count);
+
+        let globals = GlobalsUniform::new(&device, size);
+        let camera = Camera::new(Point3::new(0.0, 1.5, 4.0), Point3::new(0.0, 0.0, 0.0), size);
+        let camera_uniform = CameraUniform::new(&device, &camera);
+
+        let lights = Lights {
+            ambient: [0.05, 0.05, 0.08],
+            specular_model: SpecularModel::Phong,
+            point_lights: POINT_LIGHT_COLORS
+                .iter()
+                .map(|&color| PointLight {
+                    position: [0.0; 3],
+                    color,
+                    intensity: 4.0,
+                    range: 10.0,
+                })
+                .collect(),
+            directional_lights: vec![DirectionalLight {
+                direction: [-0.3, -1.0, -0.5],
+                color: [1.0, 1.0, 0.9],
+                intensity: 0.4,
+            }],
+            spot_lights: Vec::new(),
+        };
+        let lights_uniform = LightsUniform::new(&device, &lights);
+
+        // Los parametros del material con el mismo layout que los materiales sin texturas de material.rs
+        let cube_color = [0.8, 0.8, 0.8];
+        let specular = 0.5;
+        let shininess = 32.0;
+        let material_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Cube Material"),
+            contents: bytemuck::cast_slice(&[Self::material_params(cube_color, specular, shininess)]),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+        let material_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &material_layout_entries(0),
+            label: Some("material_bind_group_layout"),
+        });
+        let material_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &material_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(material_buffer.slice(..)),
+            }],
+            label: Some("material_bind_group"),
+        });
+
+        let vs_module = device.create_shader_module(wgpu::include_spirv!("shaders/lit.vert.spv"));
+        let fs_module = device.create_shader_module(wgpu::include_spirv!("shaders/lit.frag.spv"));
+
+        // Set 0 Globals, 1 material, 2 camara, 3 luces
+        let pipeline_builder = PipelineBuilder::new(&vs_module, sc_desc.format)
+            .label("Lit Pipeline")
+            .fragment_shader(&fs_module)
+            .bind_group_layouts(&[
+                &globals.bind_group_layout,
+                &material_layout,
+                &camera_uniform.bind_group_layout,
+                &lights_uniform.bind_group_layout,
+            ])
+            .vertex_buffer(MeshVertex::desc())
+            .vertex_buffer(InstanceData::desc())
+            .depth(DEPTH_FORMAT, wgpu::CompareFunction::Less)
+            .sample_count(multisample.sample_count);
+        let render_pipeline = pipeline_builder.build(&device);
+        let debug_view = DebugView::new(&device, &pipeline_builder);
+
+        let light_gizmo = LightGizmo::new(
+            &device,
+            &globals.bind_group_layout,
+            &camera_uniform.bind_group_layout,
+            &lights_uniform.bind_group_layout,
+            sc_desc.format,
+            multisample.sample_count,
+        );
+
+        let (vertices, indices) = cube_mesh(0.5);
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsage::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Index Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsage::INDEX,
+        });
+        let num_indices = indices.len() as u32;
+        let debug_mesh = DebugMesh::new(&device, &indices, vertices.len() as u32);
+
+        // Una sola instancia, su matriz model cambia cada frame
+        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Instance Buffer"),
+            contents: bytemuck::cast_slice(&[InstanceData::new(Matrix4::from_scale(1.0))]),
+            usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+        });
+
+        let ui = Ui::new(&device, &queue, &globals.bind_group_layout, sc_desc.format, multisample.sample_count);
+
+        Self {
+            surface,
+            device,
+            queue,
+            sc_desc,
+            swap_chain,
+            size,
+            render_pipeline,
+            vertex_buffer,
+            index_buffer,
+            num_indices,
+            instance_buffer,
+            rotation: 0.0,
+            cube_color,
+            specular,
+            shininess,
+            material_buffer,
+            material_bind_group,
+            camera,
+            camera_uniform,
+            lights,
+            lights_uniform,
+            light_gizmo,
+            depth_buffer,
+            globals,
+            capture,
+            multisample,
+            debug_view,
+            debug_mesh,
+            ui,
+        }
+    }
+
+    fn material_params(color: [f32; 3], specular: f32, shininess: f32) -> MaterialParams {
+        MaterialParams {
+            tint: [color[0], color[1], color[2], 1.0],
+            values: [specular, shininess, 0.0, 0.0],
+        }
+    }
+
+    fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.size = new_size;
+        self.sc_desc.width = new_size.width;
+        self.sc_desc.height = new_size.height;
+        self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+        self.capture.resize(&self.device, &self.sc_desc);
+        self.multisample.resize(&self.device, &self.sc_desc);
+        self.depth_buffer.resize(&self.device, &self.sc_desc);
+        self.globals.resize(new_size);
+        self.camera.resize(new_size);
+    }
+
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        self.globals.input(event);
+        if self.capture.input(event) || self.debug_view.input(event) {
+            return true;
+        }
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::B),
+                        ..
+                    },
+                ..
+            } => {
+                self.lights.specular_model = match self.lights.specular_model {
+                    SpecularModel::Phong => SpecularModel::BlinnPhong,
+                    SpecularModel::BlinnPhong => SpecularModel::Phong,
+                };
+                println!("Specular model: {:?}", self.lights.specular_model);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn update(&mut self) {
+        self.globals.set_fixed_time_step(self.capture.time_step());
+        self.globals.update(&self.queue);
+
+        self.rotation += self.globals.data.time_delta * 0.5;
+        let model = Matrix4::from_angle_y(Rad(self.rotation)) * Matrix4::from_angle_x(Rad(self.rotation * 0.3));
+        self.queue
+            .write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&[InstanceData::new(model)]));
+
+        // Las luces puntuales dan vueltas en sentidos opuestos a distinta altura
+        let time = self.globals.data.time;
+        for (i, light) in self.lights.point_lights.iter_mut().enumerate() {
+            let angle = if i % 2 == 0 { time } else { -time * 0.7 };
+            let height = if i % 2 == 0 { 0.5 } else { -0.3 };
+            light.position = [
+                POINT_LIGHT_ORBIT * angle.cos(),
+                height,
+                POINT_LIGHT_ORBIT * angle.sin(),
+            ];
+        }
+
+        // F1 oculta el panel
+        self.ui.label("1_6 Lighting");
+        let mut blinn = self.lights.specular_model == SpecularModel::BlinnPhong;
+        if self.ui.checkbox("Blinn-Phong (B)", &mut blinn) {
+            self.lights.specular_model = if blinn {
+                SpecularModel::BlinnPhong
+            } else {
+                SpecularModel::Phong
+            };
+        }
+        self.ui.slider("Specular", &mut self.specular, 0.0..=1.0);
+        self.ui.slider("Shininess", &mut self.shininess, 1.0..=256.0);
+        self.ui.color_edit("Cube color", &mut self.cube_color);
+        self.ui.color_edit("Ambient", &mut self.lights.ambient);
+        self.ui.prepare(&self.device, &self.queue);
+
+        let params = Self::material_params(self.cube_color, self.specular, self.shininess);
+        self.queue.write_buffer(&self.material_buffer, 0, bytemuck::cast_slice(&[params]));
+        self.camera_uniform.update(&self.queue, &self.camera);
+        self.lights_uniform.update(&self.queue, &self.lights);
+    }
+
+    fn render(&mut self) {
+        let frame = self
+            .swap_chain
+            .get_current_frame()
+            .expect("Timeout getting texture")
+            .output;
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        self.draw(&mut encoder, &frame.view);
+        // Si hay una captura pendiente volvemos a dibujar el frame en la textura de captura
+        if let Some(view) = self.capture.target() {
+            self.draw(&mut encoder, view);
+        }
+
+        self.queue.submit(iter::once(encoder.finish()));
+        self.capture.finish_frame(&self.device, &self.queue);
+    }
+
+    fn draw(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let (attachment, resolve_target) = self.multisample.color_attachment(view);
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                    attachment,
+                    resolve_target,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: 0.02,
+                            g: 0.02,
+                            b: 0.03,
+                            a: 1.0,
+                        }),
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: Some(self.depth_buffer.attachment()),
+            });
+
+            render_pass.set_bind_group(0, &self.globals.bind_group, &[]);
+            render_pass.set_bind_group(1, &self.material_bind_group, &[]);
+            render_pass.set_bind_group(2, &self.camera_uniform.bind_group, &[]);
+            render_pass.set_bind_group(3, &self.lights_uniform.bind_group, &[]);
+            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+            self.debug_view.draw_indexed(
+                &mut render_pass,
+                &self.render_pipeline,
+                &self.index_buffer,
+                self.num_indices,
+                &self.debug_mesh,
+            );
+
+            self.light_gizmo.draw(
+                &mut render_pass,
+                &self.globals.bind_group,
+                &self.camera_uniform.bind_group,
+                &self.lights_uniform,
+            );
+        }
+
+        // La UI no usa depth, y un pipeline sin depth no se puede usar en un render pass con depth attachment.
+        // Va en un segundo render pass que carga lo que ya hay dibujado
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                attachment,
+                resolve_target,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        });
+        self.ui.draw(&mut render_pass, &self.globals.bind_group);
+    }
+}
+
+pub fn main_1_6(sample_count: u32) {
+    env_logger::init();
+    let event_loop = EventLoop::new();
+    let window = WindowBuilder::new().build(&event_loop).unwrap();
+
+    use futures::executor::block_on;
+
+    // Since main can't be async, we're going to need to block
+    let mut state = block_on(State::new(&window, sample_count));
+
+    event_loop.run(move |event, _, control_flow| {
+        match event {
+            Event::WindowEvent {
+                ref event,
+                window_id,
+            } if window_id == window.id() => {
+                if !state.ui.input(event) && !state.input(event) {
+                    match event {
+                        WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+                        WindowEvent::KeyboardInput { input, .. } => match input {
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Escape),
+                                ..
+                            } => *control_flow = ControlFlow::Exit,
+                            _ => {}
+                        },
+                        WindowEvent::Resized(physical_size) => {
+                            state.resize(*physical_size);
+                        }
+                        WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                            // new_inner_size is &mut so w have to dereference it twice
+                            state.resize(**new_inner_size);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Event::RedrawRequested(_) => {
+                state.update();
+                state.render();
+            }
+            Event::MainEventsCleared => {
+                // RedrawRequested will only trigger once, unless we manually
+                // request it.
+                window.request_redraw();
+            }
+            _ => {}
+        }
+    });
+}
@@ -0,0 +1,503 @@
+//## Sombras: un suelo con cubos iluminado por el sol (luz direccional con cascaded shadow maps) y un foco que se mueve
+//## (con su propio mapa de sombras). Antes del pass de la escena, ShadowMap dibuja la profundidad de los cubos y el
+//## suelo vista desde cada luz, y lit_shadow.frag la compara para saber que puntos estan en sombra.
+//## C cambia entre 1 cascada y MAX_CASCADES, V tiñe cada cascada de un color, L enciende y apaga el foco.
+//## El panel (F1) permite tocar el numero de cascadas, el radio del PCF y la distancia de las sombras.
+
+use std::f32::consts::PI;
+use std::iter;
+
+use cgmath::{Matrix4, Point3, Rad, Vector3};
+use wgpu::util::DeviceExt;
+use winit::{
+    event::*,
+    event_loop::{ControlFlow, EventLoop},
+    window::{Window, WindowBuilder},
+};
+
+use crate::examples::{
+    cube_mesh, material_layout_entries, msaa_sample_count, plane_mesh, Camera, CameraUniform, Capture, DepthBuffer,
+    DirectionalLight, GlobalsUniform, GpuMesh, InstanceData, LightGizmo, Lights, LightsUniform, MaterialParams,
+    MeshVertex, Multisample, PipelineBuilder, ShadowCaster, ShadowMap, SpecularModel, SpotLight, Ui, DEPTH_FORMAT,
+    MAX_CASCADES,
+};
+
+const GROUND_HALF_SIZE: f32 = 30.0;
+const RING_CUBES: usize = 8;
+const RING_RADIUS: f32 = 4.0;
+// Una fila de cubos alejandose de la camara, para ver el cambio de cascada
+const FAR_CUBES: usize = 6;
+
+// Una malla con sus instancias y su material
+struct Object {
+    mesh: GpuMesh,
+    instance_buffer: wgpu::Buffer,
+    num_instances: u32,
+    // El bind group ya guarda su referencia al buffer, pero lo dejamos aqui para que quede claro de quien es
+    _material_buffer: wgpu::Buffer,
+    material_bind_group: wgpu::BindGroup,
+}
+
+impl Object {
+    fn new(
+        device: &wgpu::Device,
+        (vertices, indices): (Vec<MeshVertex>, Vec<u16>),
+        instances: &[InstanceData],
+        material_layout: &wgpu::BindGroupLayout,
+        material: MaterialParams,
+        label: &str,
+    ) -> Self {
+        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents: bytemuck::cast_slice(instances),
+            usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+        });
+        let material_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents: bytemuck::cast_slice(&[material]),
+            usage: wgpu::BufferUsage::UNIFORM,
+        });
+        let material_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: material_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(material_buffer.slice(..)),
+            }],
+            label: Some(label),
+        });
+        Self {
+            mesh: GpuMesh::new(device, &vertices, &indices, label),
+            instance_buffer,
+            num_instances: instances.len() as u32,
+            _material_buffer: material_buffer,
+            material_bind_group,
+        }
+    }
+
+    fn shadow_caster(&self) -> ShadowCaster<'_> {
+        ShadowCaster {
+            mesh: &self.mesh,
+            instance_buffer: &self.instance_buffer,
+            num_instances: self.num_instances,
+        }
+    }
+}
+
+// Los cubos de la escena. El del centro gira con el tiempo
+fn cube_instances(time: f32) -> Vec<InstanceData> {
+    let mut instances = vec![InstanceData::new(
+        Matrix4::from_translation(Vector3::new(0.0, 1.0, 0.0))
+            * Matrix4::from_angle_y(Rad(time * 0.5))
+            * Matrix4::from_nonuniform_scale(1.0, 2.0, 1.0),
+    )];
+    for i in 0..RING_CUBES {
+        let angle = i as f32 / RING_CUBES as f32 * 2.0 * PI;
+        let height = 0.5 + (i % 3) as f32 * 0.75;
+        instances.push(InstanceData::new(
+            Matrix4::from_translation(Vector3::new(
+                RING_RADIUS * angle.cos(),
+                height * 0.5,
+                RING_RADIUS * angle.sin(),
+            )) * Matrix4::from_angle_y(Rad(angle))
+                * Matrix4::from_nonuniform_scale(0.8, height, 0.8),
+        ));
+    }
+    for i in 0..FAR_CUBES {
+        instances.push(InstanceData::new(Matrix4::from_translation(Vector3::new(
+            -3.0,
+            0.5,
+            -6.0 - i as f32 * 4.0,
+        ))));
+    }
+    instances
+}
+
+fn material(color: [f32; 3], specular: f32, shininess: f32) -> MaterialParams {
+    MaterialParams {
+        tint: [color[0], color[1], color[2], 1.0],
+        values: [specular, shininess, 0.0, 0.0],
+    }
+}
+
+struct State {
+    surface: wgpu::Surface,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    sc_desc: wgpu::SwapChainDescriptor,
+    swap_chain: wgpu::SwapChain,
+    size: winit::dpi::PhysicalSize<u32>,
+    render_pipeline: wgpu::RenderPipeline,
+    ground: Object,
+    cubes: Object,
+    camera: Camera,
+    camera_uniform: CameraUniform,
+    lights: Lights,
+    spot_enabled: bool,
+    lights_uniform: LightsUniform,
+    light_gizmo: LightGizmo,
+    shadow_map: ShadowMap,
+    depth_buffer: DepthBuffer,
+    globals: GlobalsUniform,
+    capture: Capture,
+    multisample: Multisample,
+    ui: Ui,
+}
+
+impl State {
+    async fn new(window: &Window, sample_count: u32) -> Self {
+        let size = window.inner_size();
+
+        let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+        let surface = unsafe { instance.create_surface(window) };
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::Default,
+                compatible_surface: Some(&surface),
+            })
+            .await
+            .unwrap();
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    features: wgpu::Features::empty(),
+                    limits: wgpu::Limits::default(),
+                    shader_validation: true,
+                },
+                None, // Trace path
+            )
+            .await
+            .unwrap();
+
+        let sc_desc = wgpu::SwapChainDescriptor {
+            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+            format: wgpu::TextureFormat::Bgra8UnormSrgb,
+            width: size.width,
+            height: size.height,
+            present_mode: wgpu::PresentMode::Fifo,
+        };
+        let swap_chain = device.create_swap_chain(&surface, &sc_desc);
+        let capture = Capture::new(&device, &sc_desc);
+        let multisample = Multisample::new(&device, &sc_desc, msaa_sample_count(&adapter, sample_count));
+        let depth_buffer = DepthBuffer::new(&device, &sc_desc, multisample.sample_count);
+
+        let globals = GlobalsUniform::new(&device, size);
+        let camera = Camera::new(Point3::new(-7.0, 5.0, 10.0), Point3::new(0.0, 0.5, -2.0), size);
+        let camera_uniform = CameraUniform::new(&device, &camera);
+
+        let mut lights = Lights {
+            ambient: [0.08, 0.08, 0.1],
+            specular_model: SpecularModel::BlinnPhong,
+            point_lights: Vec::new(),
+            directional_lights: vec![DirectionalLight {
+                direction: [-0.4, -1.0, -0.6],
+                color: [1.0, 0.95, 0.85],
+                intensity: 1.0,
+            }],
+            spot_lights: Vec::new(),
+        };
+        Self::update_spot_light(&mut lights, true, 0.0);
+        let lights_uniform = LightsUniform::new(&device, &lights);
+        let shadow_map = ShadowMap::new(&device, &lights_uniform);
+
+        let material_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &material_layout_entries(0),
+            label: Some("material_bind_group_layout"),
+        });
+        let ground = Object::new(
+            &device,
+            plane_mesh(GROUND_HALF_SIZE),
+            &[InstanceData::new(Matrix4::from_scale(1.0))],
+            &material_layout,
+            material([0.6, 0.6, 0.55], 0.1, 8.0),
+            "Ground",
+        );
+        let cubes = Object::new(
+            &device,
+            cube_mesh(0.5),
+            &cube_instances(0.0),
+            &material_layout,
+            material([0.8, 0.35, 0.25], 0.5, 64.0),
+            "Cubes",
+        );
+
+        let vs_module = device.create_shader_module(wgpu::include_spirv!("shaders/lit.vert.spv"));
+        let fs_module = device.create_shader_module(wgpu::include_spirv!("shaders/lit_shadow.frag.spv"));
+
+        // Set 0 Globals, 1 material, 2 camara, 3 luces y sombras
+        let render_pipeline = PipelineBuilder::new(&vs_module, sc_desc.format)
+            .label("Lit Shadow Pipeline")
+            .fragment_shader(&fs_module)
+            .bind_group_layouts(&[
+                &globals.bind_group_layout,
+                &material_layout,
+                &camera_uniform.bind_group_layout,
+                &shadow_map.bind_group_layout,
+            ])
+            .vertex_buffer(MeshVertex::desc())
+            .vertex_buffer(InstanceData::desc())
+            .depth(DEPTH_FORMAT, wgpu::CompareFunction::Less)
+            .sample_count(multisample.sample_count)
+            .build(&device);
+
+        let light_gizmo = LightGizmo::new(
+            &device,
+            &globals.bind_group_layout,
+            &camera_uniform.bind_group_layout,
+            &lights_uniform.bind_group_layout,
+            sc_desc.format,
+            multisample.sample_count,
+        );
+
+        let ui = Ui::new(&device, &queue, &globals.bind_group_layout, sc_desc.format, multisample.sample_count);
+
+        Self {
+            surface,
+            device,
+            queue,
+            sc_desc,
+            swap_chain,
+            size,
+            render_pipeline,
+            ground,
+            cubes,
+            camera,
+            camera_uniform,
+            lights,
+            spot_enabled: true,
+            lights_uniform,
+            light_gizmo,
+            shadow_map,
+            depth_buffer,
+            globals,
+            capture,
+            multisample,
+            ui,
+        }
+    }
+
+    // El foco esta fijo y apunta a un punto que da vueltas alrededor del cubo central
+    fn update_spot_light(lights: &mut Lights, enabled: bool, time: f32) {
+        lights.spot_lights.clear();
+        if !enabled {
+            return;
+        }
+        let position = [3.0, 5.0, 3.0];
+        let target = [2.0 * (time * 0.8).cos(), 0.0, 2.0 * (time * 0.8).sin()];
+        lights.spot_lights.push(SpotLight {
+            position,
+            direction: [target[0] - position[0], target[1] - position[1], target[2] - position[2]],
+            color: [0.4, 0.6, 1.0],
+            intensity: 60.0,
+            range: 20.0,
+            inner_angle: 15.0_f32.to_radians(),
+            outer_angle: 25.0_f32.to_radians(),
+        });
+    }
+
+    fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.size = new_size;
+        self.sc_desc.width = new_size.width;
+        self.sc_desc.height = new_size.height;
+        self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+        self.capture.resize(&self.device, &self.sc_desc);
+        self.multisample.resize(&self.device, &self.sc_desc);
+        self.depth_buffer.resize(&self.device, &self.sc_desc);
+        self.globals.resize(new_size);
+        self.camera.resize(new_size);
+    }
+
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        self.globals.input(event);
+        if self.capture.input(event) {
+            return true;
+        }
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(keycode),
+                        ..
+                    },
+                ..
+            } => match keycode {
+                VirtualKeyCode::C => {
+                    self.shadow_map.num_cascades = if self.shadow_map.num_cascades == 1 {
+                        MAX_CASCADES as u32
+                    } else {
+                        1
+                    };
+                    println!("Cascades: {}", self.shadow_map.num_cascades);
+                    true
+                }
+                VirtualKeyCode::V => {
+                    self.shadow_map.show_cascades = !self.shadow_map.show_cascades;
+                    true
+                }
+                VirtualKeyCode::L => {
+                    self.spot_enabled = !self.spot_enabled;
+                    true
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    fn update(&mut self) {
+        self.globals.set_fixed_time_step(self.capture.time_step());
+        self.globals.update(&self.queue);
+
+        let time = self.globals.data.time;
+        self.queue
+            .write_buffer(&self.cubes.instance_buffer, 0, bytemuck::cast_slice(&cube_instances(time)));
+
+        // F1 oculta el panel
+        self.ui.label("1_7 Shadows");
+        self.ui
+            .slider_u32("Cascades (C)", &mut self.shadow_map.num_cascades, 1..=MAX_CASCADES as u32);
+        self.ui.checkbox("Show cascades (V)", &mut self.shadow_map.show_cascades);
+        self.ui.slider_u32("PCF radius", &mut self.shadow_map.pcf_radius, 0..=3);
+        self.ui.slider("Distance", &mut self.shadow_map.shadow_distance, 5.0..=60.0);
+        self.ui.checkbox("Spot light (L)", &mut self.spot_enabled);
+        self.ui.prepare(&self.device, &self.queue);
+
+        Self::update_spot_light(&mut self.lights, self.spot_enabled, time);
+        self.camera_uniform.update(&self.queue, &self.camera);
+        self.lights_uniform.update(&self.queue, &self.lights);
+        self.shadow_map.update(&self.queue, &self.camera, &self.lights);
+    }
+
+    fn render(&mut self) {
+        let frame = self
+            .swap_chain
+            .get_current_frame()
+            .expect("Timeout getting texture")
+            .output;
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        // Los mapas de sombras solo hace falta dibujarlos una vez aunque haya captura
+        self.shadow_map
+            .render(&mut encoder, &[self.ground.shadow_caster(), self.cubes.shadow_caster()]);
+
+        self.draw(&mut encoder, &frame.view);
+        // Si hay una captura pendiente volvemos a dibujar el frame en la textura de captura
+        if let Some(view) = self.capture.target() {
+            self.draw(&mut encoder, view);
+        }
+
+        self.queue.submit(iter::once(encoder.finish()));
+        self.capture.finish_frame(&self.device, &self.queue);
+    }
+
+    fn draw(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let (attachment, resolve_target) = self.multisample.color_attachment(view);
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                    attachment,
+                    resolve_target,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: 0.4,
+                            g: 0.55,
+                            b: 0.75,
+                            a: 1.0,
+                        }),
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: Some(self.depth_buffer.attachment()),
+            });
+
+            render_pass.set_pipeline(&self.render_pipeline);
+            render_pass.set_bind_group(0, &self.globals.bind_group, &[]);
+            render_pass.set_bind_group(2, &self.camera_uniform.bind_group, &[]);
+            render_pass.set_bind_group(3, &self.shadow_map.bind_group, &[]);
+            for object in &[&self.ground, &self.cubes] {
+                render_pass.set_bind_group(1, &object.material_bind_group, &[]);
+                object
+                    .mesh
+                    .draw_instanced(&mut render_pass, &object.instance_buffer, 0..object.num_instances);
+            }
+
+            self.light_gizmo.draw(
+                &mut render_pass,
+                &self.globals.bind_group,
+                &self.camera_uniform.bind_group,
+                &self.lights_uniform,
+            );
+        }
+
+        // La UI va en un pass sin depth, como en el 1_6
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                attachment,
+                resolve_target,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        });
+        self.ui.draw(&mut render_pass, &self.globals.bind_group);
+    }
+}
+
+pub fn main_1_7(sample_count: u32) {
+    env_logger::init();
+    let event_loop = EventLoop::new();
+    let window = WindowBuilder::new().build(&event_loop).unwrap();
+
+    use futures::executor::block_on;
+
+    // Since main can't be async, we're going to need to block
+    let mut state = block_on(State::new(&window, sample_count));
+
+    event_loop.run(move |event, _, control_flow| {
+        match event {
+            Event::WindowEvent {
+                ref event,
+                window_id,
+            } if window_id == window.id() => {
+                if !state.ui.input(event) && !state.input(event) {
+                    match event {
+                        WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+                        WindowEvent::KeyboardInput { input, .. } => match input {
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Escape),
+                                ..
+                            } => *control_flow = ControlFlow::Exit,
+                            _ => {}
+                        },
+                        WindowEvent::Resized(physical_size) => {
+                            state.resize(*physical_size);
+                        }
+                        WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                            // new_inner_size is &mut so w have to dereference it twice
+                            state.resize(**new_inner_size);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Event::RedrawRequested(_) => {
+                state.update();
+                state.render();
+            }
+            Event::MainEventsCleared => {
+                // RedrawRequested will only trigger once, unless we manually
+                // request it.
+                window.request_redraw();
+            }
+            _ => {}
+        }
+    });
+}
@@ -0,0 +1,492 @@
+//## Skybox: una esfera que refleja el cielo y unos cubos dando vueltas, con la camara orbitando alrededor. El cielo es
+//## un cubemap que se crea de dos formas: desde un panorama equirectangular (convertido a 6 caras en la CPU) y desde
+//## 6 imagenes sueltas, una por cara. Sin argumentos hay un panorama generado por codigo y unas caras de colores con
+//## una rejilla para ver como esta orientada cada una; con `1_8 panorama.png` o `1_8 px nx py ny pz nz` se añade el
+//## cielo cargado de disco. S cambia de cielo.
+
+use std::f32::consts::PI;
+use std::iter;
+
+use anyhow::{bail, Context, Result};
+use cgmath::{Matrix4, Point3, Rad, Vector3};
+use wgpu::util::DeviceExt;
+use winit::{
+    event::*,
+    event_loop::{ControlFlow, EventLoop},
+    window::{Window, WindowBuilder},
+};
+
+use crate::examples::{
+    cube_mesh, material_layout_entries, msaa_sample_count, procedural_sky, sphere_mesh, Camera, CameraUniform, Capture,
+    DepthBuffer, GlobalsUniform, GpuMesh, InstanceData, MaterialParams, MeshVertex, Multisample, PipelineBuilder,
+    Skybox, Texture, Ui, DEPTH_FORMAT,
+};
+
+const PANORAMA_WIDTH: u32 = 1024;
+const PANORAMA_HEIGHT: u32 = 512;
+const FACE_SIZE: u32 = 512;
+const NUM_CUBES: usize = 6;
+const CAMERA_DISTANCE: f32 = 6.0;
+
+// Una malla con sus instancias y su material
+struct Object {
+    mesh: GpuMesh,
+    instance_buffer: wgpu::Buffer,
+    num_instances: u32,
+    material_buffer: wgpu::Buffer,
+    material_bind_group: wgpu::BindGroup,
+}
+
+impl Object {
+    fn new(
+        device: &wgpu::Device,
+        (vertices, indices): (Vec<MeshVertex>, Vec<u16>),
+        instances: &[InstanceData],
+        material_layout: &wgpu::BindGroupLayout,
+        material: MaterialParams,
+        label: &str,
+    ) -> Self {
+        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents: bytemuck::cast_slice(instances),
+            usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+        });
+        let material_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents: bytemuck::cast_slice(&[material]),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+        let material_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: material_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(material_buffer.slice(..)),
+            }],
+            label: Some(label),
+        });
+        Self {
+            mesh: GpuMesh::new(device, &vertices, &indices, label),
+            instance_buffer,
+            num_instances: instances.len() as u32,
+            material_buffer,
+            material_bind_group,
+        }
+    }
+}
+
+// tint es el color difuso y values.x la reflectividad
+fn material(color: [f32; 3], reflectivity: f32) -> MaterialParams {
+    MaterialParams {
+        tint: [color[0], color[1], color[2], 1.0],
+        values: [reflectivity, 0.0, 0.0, 0.0],
+    }
+}
+
+fn cube_instances(time: f32) -> Vec<InstanceData> {
+    (0..NUM_CUBES)
+        .map(|i| {
+            let angle = i as f32 / NUM_CUBES as f32 * 2.0 * PI + time * 0.4;
+            InstanceData::new(
+                Matrix4::from_translation(Vector3::new(
+                    2.5 * angle.cos(),
+                    (time + i as f32).sin() * 0.5,
+                    2.5 * angle.sin(),
+                )) * Matrix4::from_angle_y(Rad(-angle))
+                    * Matrix4::from_angle_x(Rad(time)),
+            )
+        })
+        .collect()
+}
+
+// De lineal a sRGB (aproximado) y a bytes
+fn to_srgb8(color: [f32; 3]) -> image::Rgba<u8> {
+    let channel = |c: f32| (c.clamp(0.0, 1.0).powf(1.0 / 2.2) * 255.0).round() as u8;
+    image::Rgba([channel(color[0]), channel(color[1]), channel(color[2]), 255])
+}
+
+// Cada cara de un color (rojo en X, verde en Y, azul en Z, mas oscuro en las negativas) con una rejilla y un
+// cuadrado blanco en la esquina de arriba a la izquierda, para ver como se colocan las caras
+fn debug_faces(size: u32) -> Vec<image::RgbaImage> {
+    let colors = [
+        [0.9, 0.1, 0.1],
+        [0.3, 0.02, 0.02],
+        [0.1, 0.9, 0.1],
+        [0.02, 0.3, 0.02],
+        [0.1, 0.1, 0.9],
+        [0.02, 0.02, 0.3],
+    ];
+    let cell = size / 8;
+    colors
+        .iter()
+        .map(|&color| {
+            image::RgbaImage::from_fn(size, size, |x, y| {
+                if (x < cell && y < cell) || x % cell == 0 || y % cell == 0 {
+                    to_srgb8([1.0, 1.0, 1.0])
+                } else {
+                    to_srgb8(color)
+                }
+            })
+        })
+        .collect()
+}
+
+struct State {
+    surface: wgpu::Surface,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    sc_desc: wgpu::SwapChainDescriptor,
+    swap_chain: wgpu::SwapChain,
+    size: winit::dpi::PhysicalSize<u32>,
+    render_pipeline: wgpu::RenderPipeline,
+    sphere: Object,
+    cubes: Object,
+    reflectivity: f32,
+    orbit_speed: f32,
+    orbit_angle: f32,
+    camera: Camera,
+    camera_uniform: CameraUniform,
+    skies: Vec<(String, Texture)>,
+    current_sky: usize,
+    skybox: Skybox,
+    depth_buffer: DepthBuffer,
+    globals: GlobalsUniform,
+    capture: Capture,
+    multisample: Multisample,
+    ui: Ui,
+}
+
+impl State {
+    async fn new(window: &Window, args: &[String], sample_count: u32) -> Result<Self> {
+        let size = window.inner_size();
+
+        let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+        let surface = unsafe { instance.create_surface(window) };
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::Default,
+                compatible_surface: Some(&surface),
+            })
+            .await
+            .context("No suitable adapter")?;
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    features: wgpu::Features::empty(),
+                    limits: wgpu::Limits::default(),
+                    shader_validation: true,
+                },
+                None, // Trace path
+            )
+            .await?;
+
+        let sc_desc = wgpu::SwapChainDescriptor {
+            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+            format: wgpu::TextureFormat::Bgra8UnormSrgb,
+            width: size.width,
+            height: size.height,
+            present_mode: wgpu::PresentMode::Fifo,
+        };
+        let swap_chain = device.create_swap_chain(&surface, &sc_desc);
+        let capture = Capture::new(&device, &sc_desc);
+        let multisample = Multisample::new(&device, &sc_desc, msaa_sample_count(&adapter, sample_count));
+        let depth_buffer = DepthBuffer::new(&device, &sc_desc, multisample.sample_count);
+
+        let globals = GlobalsUniform::new(&device, size);
+        let camera = Camera::new(Point3::new(0.0, 1.5, CAMERA_DISTANCE), Point3::new(0.0, 0.0, 0.0), size);
+        let camera_uniform = CameraUniform::new(&device, &camera);
+
+        let mut skies = vec![
+            (
+                "Procedural panorama".to_string(),
+                Texture::cube_from_equirectangular(
+                    &device,
+                    &queue,
+                    &image::DynamicImage::ImageRgba8(procedural_sky(PANORAMA_WIDTH, PANORAMA_HEIGHT)),
+                    FACE_SIZE,
+                    Some("Procedural Sky"),
+                )?,
+            ),
+            (
+                "Debug faces".to_string(),
+                Texture::cube_from_faces(&device, &queue, &debug_faces(FACE_SIZE), Some("Debug Faces"))?,
+            ),
+        ];
+        match args {
+            [] => {}
+            [path] => skies.push((path.to_string(), Texture::load_equirectangular(&device, &queue, path, FACE_SIZE)?)),
+            paths if paths.len() == 6 => {
+                skies.push(("Loaded faces".to_string(), Texture::load_cube_faces(&device, &queue, paths)?))
+            }
+            paths => bail!("Expected a panorama or 6 cubemap faces, got {} files", paths.len()),
+        }
+        // Si se ha cargado un cielo se empieza por el
+        let current_sky = skies.len() - 1;
+        let skybox = Skybox::new(&device, &skies[current_sky].1, sc_desc.format, multisample.sample_count);
+
+        let material_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &material_layout_entries(0),
+            label: Some("material_bind_group_layout"),
+        });
+        let reflectivity = 0.8;
+        let sphere = Object::new(
+            &device,
+            sphere_mesh(1.0, 48, 24),
+            &[InstanceData::new(Matrix4::from_scale(1.0))],
+            &material_layout,
+            material([0.9, 0.9, 0.9], reflectivity),
+            "Sphere",
+        );
+        let cubes = Object::new(
+            &device,
+            cube_mesh(0.3),
+            &cube_instances(0.0),
+            &material_layout,
+            material([0.9, 0.5, 0.2], 0.15),
+            "Cubes",
+        );
+
+        let vs_module = device.create_shader_module(wgpu::include_spirv!("shaders/lit.vert.spv"));
+        let fs_module = device.create_shader_module(wgpu::include_spirv!("shaders/reflect.frag.spv"));
+
+        // Set 0 Globals, 1 material, 2 camara, 3 el cubemap del skybox
+        let render_pipeline = PipelineBuilder::new(&vs_module, sc_desc.format)
+            .label("Reflect Pipeline")
+            .fragment_shader(&fs_module)
+            .bind_group_layouts(&[
+                &globals.bind_group_layout,
+                &material_layout,
+                &camera_uniform.bind_group_layout,
+                &skybox.bind_group_layout,
+            ])
+            .vertex_buffer(MeshVertex::desc())
+            .vertex_buffer(InstanceData::desc())
+            .depth(DEPTH_FORMAT, wgpu::CompareFunction::Less)
+            .sample_count(multisample.sample_count)
+            .build(&device);
+
+        let ui = Ui::new(&device, &queue, &globals.bind_group_layout, sc_desc.format, multisample.sample_count);
+
+        Ok(Self {
+            surface,
+            device,
+            queue,
+            sc_desc,
+            swap_chain,
+            size,
+            render_pipeline,
+            sphere,
+            cubes,
+            reflectivity,
+            orbit_speed: 0.2,
+            orbit_angle: 0.0,
+            camera,
+            camera_uniform,
+            skies,
+            current_sky,
+            skybox,
+            depth_buffer,
+            globals,
+            capture,
+            multisample,
+            ui,
+        })
+    }
+
+    fn next_sky(&mut self) {
+        self.current_sky = (self.current_sky + 1) % self.skies.len();
+        self.skybox.set_cubemap(&self.device, &self.skies[self.current_sky].1);
+    }
+
+    fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.size = new_size;
+        self.sc_desc.width = new_size.width;
+        self.sc_desc.height = new_size.height;
+        self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+        self.capture.resize(&self.device, &self.sc_desc);
+        self.multisample.resize(&self.device, &self.sc_desc);
+        self.depth_buffer.resize(&self.device, &self.sc_desc);
+        self.globals.resize(new_size);
+        self.camera.resize(new_size);
+    }
+
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        self.globals.input(event);
+        if self.capture.input(event) {
+            return true;
+        }
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::S),
+                        ..
+                    },
+                ..
+            } => {
+                self.next_sky();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn update(&mut self) {
+        self.globals.set_fixed_time_step(self.capture.time_step());
+        self.globals.update(&self.queue);
+
+        let time = self.globals.data.time;
+        self.queue
+            .write_buffer(&self.cubes.instance_buffer, 0, bytemuck::cast_slice(&cube_instances(time)));
+
+        // F1 oculta el panel
+        self.ui.label("1_8 Skybox");
+        self.ui.label(&format!("Sky (S): {}", self.skies[self.current_sky].0));
+        self.ui.slider("Orbit speed", &mut self.orbit_speed, -1.0..=1.0);
+        self.ui.slider("FOV", &mut self.camera.fovy, 20.0..=100.0);
+        if self.ui.slider("Reflectivity", &mut self.reflectivity, 0.0..=1.0) {
+            self.queue.write_buffer(
+                &self.sphere.material_buffer,
+                0,
+                bytemuck::cast_slice(&[material([0.9, 0.9, 0.9], self.reflectivity)]),
+            );
+        }
+        self.ui.prepare(&self.device, &self.queue);
+
+        self.orbit_angle += self.orbit_speed * self.globals.data.time_delta;
+        self.camera.eye = Point3::new(
+            CAMERA_DISTANCE * self.orbit_angle.sin(),
+            1.5,
+            CAMERA_DISTANCE * self.orbit_angle.cos(),
+        );
+        self.camera_uniform.update(&self.queue, &self.camera);
+        self.skybox.update(&self.queue, &self.camera);
+    }
+
+    fn render(&mut self) {
+        let frame = self
+            .swap_chain
+            .get_current_frame()
+            .expect("Timeout getting texture")
+            .output;
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        self.draw(&mut encoder, &frame.view);
+        // Si hay una captura pendiente volvemos a dibujar el frame en la textura de captura
+        if let Some(view) = self.capture.target() {
+            self.draw(&mut encoder, view);
+        }
+
+        self.queue.submit(iter::once(encoder.finish()));
+        self.capture.finish_frame(&self.device, &self.queue);
+    }
+
+    fn draw(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let (attachment, resolve_target) = self.multisample.color_attachment(view);
+        {
+            // El skybox tapa todo el fondo, el color de clear no llega a verse
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                    attachment,
+                    resolve_target,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: Some(self.depth_buffer.attachment()),
+            });
+
+            render_pass.set_pipeline(&self.render_pipeline);
+            render_pass.set_bind_group(0, &self.globals.bind_group, &[]);
+            render_pass.set_bind_group(2, &self.camera_uniform.bind_group, &[]);
+            render_pass.set_bind_group(3, &self.skybox.bind_group, &[]);
+            for object in &[&self.sphere, &self.cubes] {
+                render_pass.set_bind_group(1, &object.material_bind_group, &[]);
+                object
+                    .mesh
+                    .draw_instanced(&mut render_pass, &object.instance_buffer, 0..object.num_instances);
+            }
+
+            // Despues de los objetos, para que el depth test descarte los pixeles que ya estan tapados
+            self.skybox.draw(&mut render_pass);
+        }
+
+        // La UI va en un pass sin depth, como en el 1_6
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                attachment,
+                resolve_target,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        });
+        self.ui.draw(&mut render_pass, &self.globals.bind_group);
+    }
+}
+
+pub fn main_1_8(args: &[String], sample_count: u32) {
+    env_logger::init();
+    let event_loop = EventLoop::new();
+    let window = WindowBuilder::new().build(&event_loop).unwrap();
+
+    use futures::executor::block_on;
+
+    // Since main can't be async, we're going to need to block
+    let mut state = match block_on(State::new(&window, args, sample_count)) {
+        Ok(state) => state,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            std::process::exit(1);
+        }
+    };
+
+    event_loop.run(move |event, _, control_flow| {
+        match event {
+            Event::WindowEvent {
+                ref event,
+                window_id,
+            } if window_id == window.id() => {
+                if !state.ui.input(event) && !state.input(event) {
+                    match event {
+                        WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+                        WindowEvent::KeyboardInput { input, .. } => match input {
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Escape),
+                                ..
+                            } => *control_flow = ControlFlow::Exit,
+                            _ => {}
+                        },
+                        WindowEvent::Resized(physical_size) => {
+                            state.resize(*physical_size);
+                        }
+                        WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                            // new_inner_size is &mut so w have to dereference it twice
+                            state.resize(**new_inner_size);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Event::RedrawRequested(_) => {
+                state.update();
+                state.render();
+            }
+            Event::MainEventsCleared => {
+                // RedrawRequested will only trigger once, unless we manually
+                // request it.
+                window.request_redraw();
+            }
+            _ => {}
+        }
+    });
+}
@@ -0,0 +1,530 @@
+//## Normal mapping: un suelo, un cubo y una esfera con una textura de ladrillos. La geometria es plana, el relieve sale
+//## de un normal map (generado a partir de un mapa de alturas) que cambia la normal en cada pixel, y del parallax
+//## occlusion mapping, que desplaza las uv segun la altura para que los ladrillos tapen a las juntas al mirarlos de
+//## lado. Las mallas llevan tangentes (generate_tangents) para pasar las normales del mapa al mundo.
+//## N activa el normal map, P el parallax. Una luz puntual da vueltas cerca del suelo para que se vea el relieve.
+
+use std::f32::consts::PI;
+use std::iter;
+
+use cgmath::{Matrix4, Point3, Rad, Vector3};
+use wgpu::util::DeviceExt;
+use winit::{
+    event::*,
+    event_loop::{ControlFlow, EventLoop},
+    window::{Window, WindowBuilder},
+};
+
+use crate::examples::{
+    cube_mesh, generate_tangents, material_layout_entries, msaa_sample_count, normal_map_from_height, plane_mesh,
+    sphere_mesh, Camera, CameraUniform, Capture, DepthBuffer, DirectionalLight, GlobalsUniform, GpuMesh, InstanceData,
+    LightGizmo, Lights, LightsUniform, MaterialParams, MeshVertex, Multisample, PipelineBuilder, PointLight,
+    SpecularModel, TangentVertex, Texture, Ui, DEPTH_FORMAT,
+};
+
+const TEXTURE_SIZE: u32 = 512;
+const BRICK_ROWS: u32 = 8;
+const BRICKS_PER_ROW: u32 = 4;
+const MORTAR_WIDTH: f32 = 6.0;
+// Pixeles que tarda el borde del ladrillo en llegar a su altura
+const BEVEL_WIDTH: f32 = 10.0;
+const MORTAR_HEIGHT: f32 = 0.15;
+const NORMAL_STRENGTH: f32 = 8.0;
+const LIGHT_ORBIT_RADIUS: f32 = 1.8;
+
+// Un numero entre 0 y 1 que parece aleatorio pero siempre sale igual para los mismos a y b
+fn hash(a: u32, b: u32) -> f32 {
+    let mut h = a.wrapping_mul(374_761_393) ^ b.wrapping_mul(668_265_263);
+    h = (h ^ (h >> 13)).wrapping_mul(1_274_126_177);
+    (h ^ (h >> 16)) as f32 / u32::MAX as f32
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+// El color y la altura de una pared de ladrillos, con cada fila desplazada medio ladrillo
+fn brick_textures(size: u32) -> (image::RgbaImage, image::GrayImage) {
+    let brick_width = size / BRICKS_PER_ROW;
+    let brick_height = size / BRICK_ROWS;
+    let mut diffuse = image::RgbaImage::new(size, size);
+    let mut height = image::GrayImage::new(size, size);
+    for y in 0..size {
+        let row = y / brick_height;
+        let offset = if row % 2 == 1 { brick_width / 2 } else { 0 };
+        for x in 0..size {
+            let column = (x + offset) / brick_width % BRICKS_PER_ROW;
+            let (bx, by) = ((x + offset) % brick_width, y % brick_height);
+            let edge = bx.min(brick_width - 1 - bx).min(by).min(brick_height - 1 - by) as f32;
+            let inside = smoothstep(MORTAR_WIDTH * 0.5, MORTAR_WIDTH * 0.5 + BEVEL_WIDTH, edge);
+
+            let noise = hash(x, y);
+            let tone = hash(row, column);
+            let brick = [0.55 + 0.15 * tone, 0.2 + 0.08 * tone, 0.13 + 0.04 * tone];
+            let mortar = [0.55, 0.53, 0.5];
+            let mut pixel = [255; 4];
+            for i in 0..3 {
+                let color = mortar[i] + (brick[i] - mortar[i]) * inside;
+                pixel[i] = (color * (0.92 + 0.08 * noise) * 255.0).round() as u8;
+            }
+            diffuse.put_pixel(x, y, image::Rgba(pixel));
+
+            let h = MORTAR_HEIGHT + (1.0 - MORTAR_HEIGHT) * inside;
+            height.put_pixel(x, y, image::Luma([(h * 255.0).round() as u8]));
+        }
+    }
+    (diffuse, height)
+}
+
+// El cubo esta apoyado en el suelo y gira despacio
+fn cube_model(time: f32) -> Matrix4<f32> {
+    Matrix4::from_translation(Vector3::new(0.6, 0.6, -0.4)) * Matrix4::from_angle_y(Rad(time * 0.3))
+}
+
+// Con tangentes y con una sola instancia que se puede mover
+struct Object {
+    mesh: GpuMesh,
+    instance_buffer: wgpu::Buffer,
+}
+
+impl Object {
+    fn new(
+        device: &wgpu::Device,
+        (vertices, indices): (Vec<MeshVertex>, Vec<u16>),
+        model: Matrix4<f32>,
+        label: &str,
+    ) -> Self {
+        let vertices: Vec<TangentVertex> = generate_tangents(&vertices, &indices);
+        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents: bytemuck::cast_slice(&[InstanceData::new(model)]),
+            usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+        });
+        Self {
+            mesh: GpuMesh::new(device, &vertices, &indices, label),
+            instance_buffer,
+        }
+    }
+}
+
+struct State {
+    surface: wgpu::Surface,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    sc_desc: wgpu::SwapChainDescriptor,
+    swap_chain: wgpu::SwapChain,
+    size: winit::dpi::PhysicalSize<u32>,
+    render_pipeline: wgpu::RenderPipeline,
+    objects: Vec<Object>,
+    // Las texturas las guarda el bind group, pero las dejamos aqui para que quede claro de quien son
+    _diffuse_texture: Texture,
+    _normal_texture: Texture,
+    material_buffer: wgpu::Buffer,
+    material_bind_group: wgpu::BindGroup,
+    normal_mapping: bool,
+    parallax: bool,
+    parallax_depth: f32,
+    light_height: f32,
+    camera: Camera,
+    camera_uniform: CameraUniform,
+    lights: Lights,
+    lights_uniform: LightsUniform,
+    light_gizmo: LightGizmo,
+    depth_buffer: DepthBuffer,
+    globals: GlobalsUniform,
+    capture: Capture,
+    multisample: Multisample,
+    ui: Ui,
+}
+
+impl State {
+    async fn new(window: &Window, sample_count: u32) -> Self {
+        let size = window.inner_size();
+
+        let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+        let surface = unsafe { instance.create_surface(window) };
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::Default,
+                compatible_surface: Some(&surface),
+            })
+            .await
+            .unwrap();
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    features: wgpu::Features::empty(),
+                    limits: wgpu::Limits::default(),
+                    shader_validation: true,
+                },
+                None, // Trace path
+            )
+            .await
+            .unwrap();
+
+        let sc_desc = wgpu::SwapChainDescriptor {
+            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+            format: wgpu::TextureFormat::Bgra8UnormSrgb,
+            width: size.width,
+            height: size.height,
+            present_mode: wgpu::PresentMode::Fifo,
+        };
+        let swap_chain = device.create_swap_chain(&surface, &sc_desc);
+        let capture = Capture::new(&device, &sc_desc);
+        let multisample = Multisample::new(&device, &sc_desc, msaa_sample_count(&adapter, sample_count));
+        let depth_buffer = DepthBuffer::new(&device, &sc_desc, multisample.sample_count);
+
+        let globals = GlobalsUniform::new(&device, size);
+        let camera = Camera::new(Point3::new(-2.5, 2.2, 4.0), Point3::new(0.0, 0.3, 0.0), size);
+        let camera_uniform = CameraUniform::new(&device, &camera);
+
+        let lights = Lights {
+            ambient: [0.05, 0.05, 0.06],
+            specular_model: SpecularModel::BlinnPhong,
+            point_lights: vec![PointLight {
+                position: [LIGHT_ORBIT_RADIUS, 0.5, 0.0],
+                color: [1.0, 0.85, 0.6],
+                intensity: 3.0,
+                range: 8.0,
+            }],
+            directional_lights: vec![DirectionalLight {
+                direction: [0.3, -1.0, -0.5],
+                color: [0.6, 0.7, 1.0],
+                intensity: 0.15,
+            }],
+            spot_lights: Vec::new(),
+        };
+        let lights_uniform = LightsUniform::new(&device, &lights);
+
+        let (diffuse, height) = brick_textures(TEXTURE_SIZE);
+        let normal_map = normal_map_from_height(&height, NORMAL_STRENGTH);
+        let diffuse_texture =
+            Texture::from_image(&device, &queue, &image::DynamicImage::ImageRgba8(diffuse), Some("Bricks")).unwrap();
+        let normal_texture = Texture::from_image_linear(
+            &device,
+            &queue,
+            &image::DynamicImage::ImageRgba8(normal_map),
+            Some("Bricks Normal Map"),
+        )
+        .unwrap();
+
+        // Set 1: los parametros en el binding 0, el color en el 1 y 2 y el normal map en el 3 y 4
+        let material_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &material_layout_entries(2),
+            label: Some("material_bind_group_layout"),
+        });
+        let material_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Material Buffer"),
+            contents: bytemuck::cast_slice(&[MaterialParams::default()]),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+        let material_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &material_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(material_buffer.slice(..)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&diffuse_texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&diffuse_texture.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&normal_texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::Sampler(&normal_texture.sampler),
+                },
+            ],
+            label: Some("material_bind_group"),
+        });
+
+        let objects = vec![
+            Object::new(&device, plane_mesh(3.0), Matrix4::from_scale(1.0), "Floor"),
+            // Se mueve en update()
+            Object::new(&device, cube_mesh(0.6), cube_model(0.0), "Cube"),
+            Object::new(
+                &device,
+                sphere_mesh(0.6, 48, 24),
+                Matrix4::from_translation(Vector3::new(-1.6, 0.6, 1.0)),
+                "Sphere",
+            ),
+        ];
+
+        let vs_module = device.create_shader_module(wgpu::include_spirv!("shaders/normal_map.vert.spv"));
+        let fs_module = device.create_shader_module(wgpu::include_spirv!("shaders/normal_map.frag.spv"));
+
+        // Set 0 Globals, 1 material, 2 camara, 3 luces
+        let render_pipeline = PipelineBuilder::new(&vs_module, sc_desc.format)
+            .label("Normal Map Pipeline")
+            .fragment_shader(&fs_module)
+            .bind_group_layouts(&[
+                &globals.bind_group_layout,
+                &material_layout,
+                &camera_uniform.bind_group_layout,
+                &lights_uniform.bind_group_layout,
+            ])
+            .vertex_buffer(TangentVertex::desc())
+            .vertex_buffer(InstanceData::desc())
+            .depth(DEPTH_FORMAT, wgpu::CompareFunction::Less)
+            .sample_count(multisample.sample_count)
+            .build(&device);
+
+        let light_gizmo = LightGizmo::new(
+            &device,
+            &globals.bind_group_layout,
+            &camera_uniform.bind_group_layout,
+            &lights_uniform.bind_group_layout,
+            sc_desc.format,
+            multisample.sample_count,
+        );
+
+        let ui = Ui::new(&device, &queue, &globals.bind_group_layout, sc_desc.format, multisample.sample_count);
+
+        Self {
+            surface,
+            device,
+            queue,
+            sc_desc,
+            swap_chain,
+            size,
+            render_pipeline,
+            objects,
+            _diffuse_texture: diffuse_texture,
+            _normal_texture: normal_texture,
+            material_buffer,
+            material_bind_group,
+            normal_mapping: true,
+            parallax: true,
+            parallax_depth: 0.04,
+            light_height: 0.5,
+            camera,
+            camera_uniform,
+            lights,
+            lights_uniform,
+            light_gizmo,
+            depth_buffer,
+            globals,
+            capture,
+            multisample,
+            ui,
+        }
+    }
+
+    fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.size = new_size;
+        self.sc_desc.width = new_size.width;
+        self.sc_desc.height = new_size.height;
+        self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+        self.capture.resize(&self.device, &self.sc_desc);
+        self.multisample.resize(&self.device, &self.sc_desc);
+        self.depth_buffer.resize(&self.device, &self.sc_desc);
+        self.globals.resize(new_size);
+        self.camera.resize(new_size);
+    }
+
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        self.globals.input(event);
+        if self.capture.input(event) {
+            return true;
+        }
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(keycode),
+                        ..
+                    },
+                ..
+            } => match keycode {
+                VirtualKeyCode::N => {
+                    self.normal_mapping = !self.normal_mapping;
+                    true
+                }
+                VirtualKeyCode::P => {
+                    self.parallax = !self.parallax;
+                    true
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    fn update(&mut self) {
+        self.globals.set_fixed_time_step(self.capture.time_step());
+        self.globals.update(&self.queue);
+
+        // F1 oculta el panel
+        self.ui.label("1_9 Normal mapping");
+        self.ui.checkbox("Normal map (N)", &mut self.normal_mapping);
+        self.ui.checkbox("Parallax (P)", &mut self.parallax);
+        self.ui.slider("Parallax depth", &mut self.parallax_depth, 0.0..=0.1);
+        self.ui.slider("Light height", &mut self.light_height, 0.05..=3.0);
+        self.ui.prepare(&self.device, &self.queue);
+
+        // El parallax necesita las normales del mapa, sin ellas no se nota
+        let mode = match (self.normal_mapping, self.parallax) {
+            (false, _) => 0.0,
+            (true, false) => 1.0,
+            (true, true) => 2.0,
+        };
+        let material = MaterialParams {
+            tint: [1.0; 4],
+            values: [0.3, 32.0, self.parallax_depth, mode],
+        };
+        self.queue
+            .write_buffer(&self.material_buffer, 0, bytemuck::cast_slice(&[material]));
+
+        let time = self.globals.data.time;
+        self.queue.write_buffer(
+            &self.objects[1].instance_buffer,
+            0,
+            bytemuck::cast_slice(&[InstanceData::new(cube_model(time))]),
+        );
+
+        let angle = time * 0.5 % (2.0 * PI);
+        self.lights.point_lights[0].position = [
+            LIGHT_ORBIT_RADIUS * angle.cos(),
+            self.light_height,
+            LIGHT_ORBIT_RADIUS * angle.sin(),
+        ];
+        self.camera_uniform.update(&self.queue, &self.camera);
+        self.lights_uniform.update(&self.queue, &self.lights);
+    }
+
+    fn render(&mut self) {
+        let frame = self
+            .swap_chain
+            .get_current_frame()
+            .expect("Timeout getting texture")
+            .output;
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        self.draw(&mut encoder, &frame.view);
+        // Si hay una captura pendiente volvemos a dibujar el frame en la textura de captura
+        if let Some(view) = self.capture.target() {
+            self.draw(&mut encoder, view);
+        }
+
+        self.queue.submit(iter::once(encoder.finish()));
+        self.capture.finish_frame(&self.device, &self.queue);
+    }
+
+    fn draw(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let (attachment, resolve_target) = self.multisample.color_attachment(view);
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                    attachment,
+                    resolve_target,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: 0.02,
+                            g: 0.02,
+                            b: 0.03,
+                            a: 1.0,
+                        }),
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: Some(self.depth_buffer.attachment()),
+            });
+
+            render_pass.set_pipeline(&self.render_pipeline);
+            render_pass.set_bind_group(0, &self.globals.bind_group, &[]);
+            render_pass.set_bind_group(1, &self.material_bind_group, &[]);
+            render_pass.set_bind_group(2, &self.camera_uniform.bind_group, &[]);
+            render_pass.set_bind_group(3, &self.lights_uniform.bind_group, &[]);
+            for object in &self.objects {
+                object.mesh.draw_instanced(&mut render_pass, &object.instance_buffer, 0..1);
+            }
+
+            self.light_gizmo.draw(
+                &mut render_pass,
+                &self.globals.bind_group,
+                &self.camera_uniform.bind_group,
+                &self.lights_uniform,
+            );
+        }
+
+        // La UI va en un pass sin depth, como en el 1_6
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                attachment,
+                resolve_target,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        });
+        self.ui.draw(&mut render_pass, &self.globals.bind_group);
+    }
+}
+
+pub fn main_1_9(sample_count: u32) {
+    env_logger::init();
+    let event_loop = EventLoop::new();
+    let window = WindowBuilder::new().build(&event_loop).unwrap();
+
+    use futures::executor::block_on;
+
+    // Since main can't be async, we're going to need to block
+    let mut state = block_on(State::new(&window, sample_count));
+
+    event_loop.run(move |event, _, control_flow| {
+        match event {
+            Event::WindowEvent {
+                ref event,
+                window_id,
+            } if window_id == window.id() => {
+                if !state.ui.input(event) && !state.input(event) {
+                    match event {
+                        WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+                        WindowEvent::KeyboardInput { input, .. } => match input {
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Escape),
+                                ..
+                            } => *control_flow = ControlFlow::Exit,
+                            _ => {}
+                        },
+                        WindowEvent::Resized(physical_size) => {
+                            state.resize(*physical_size);
+                        }
+                        WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                            // new_inner_size is &mut so w have to dereference it twice
+                            state.resize(**new_inner_size);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Event::RedrawRequested(_) => {
+                state.update();
+                state.render();
+            }
+            Event::MainEventsCleared => {
+                // RedrawRequested will only trigger once, unless we manually
+                // request it.
+                window.request_redraw();
+            }
+            _ => {}
+        }
+    });
+}
@@ -0,0 +1,411 @@
+//## Juego de la vida de Conway en un compute shader. El estado de las celulas vive en dos storage buffers que se
+//## van alternando (ping-pong), y el compute shader escribe ademas una storage texture que luego se dibuja en
+//## pantalla con un render pipeline, igual que la textura de _1_5_textures.rs.
+//## Espacio pausa la simulacion, C cuenta las celulas vivas leyendo el buffer desde la CPU.
+
+use std::iter;
+
+use wgpu::util::DeviceExt;
+use winit::{
+    event::*,
+    event_loop::{ControlFlow, EventLoop},
+    window::{Window, WindowBuilder},
+};
+
+use crate::examples::{
+    create_storage_buffer, read_buffer, storage_buffer_entry, storage_texture_entry, uniform_buffer_entry, Capture, ComputeKernel, GlobalsUniform, PipelineBuilder,
+};
+
+const GRID_WIDTH: u32 = 128;
+const GRID_HEIGHT: u32 = 128;
+
+// Tablero inicial aleatorio. Un xorshift basta y nos ahorramos la dependencia de rand
+fn random_cells(seed: u32) -> Vec<u32> {
+    let mut state = seed;
+    (0..GRID_WIDTH * GRID_HEIGHT)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            ((state & 3) == 0) as u32
+        })
+        .collect()
+}
+
+struct State {
+    surface: wgpu::Surface,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    sc_desc: wgpu::SwapChainDescriptor,
+    swap_chain: wgpu::SwapChain,
+    size: winit::dpi::PhysicalSize<u32>,
+    globals: GlobalsUniform,
+
+    // Compute
+    kernel: ComputeKernel,
+    cell_buffers: [wgpu::Buffer; 2],
+    compute_bind_groups: [wgpu::BindGroup; 2],
+    current: usize,     // Indice del buffer que tiene el estado actual
+    paused: bool,
+
+    // Render
+    render_pipeline: wgpu::RenderPipeline,
+    display_bind_group: wgpu::BindGroup,
+    capture: Capture,
+}
+
+impl State {
+    async fn new(window: &Window) -> Self {
+        let size = window.inner_size();
+
+        // The instance is a handle to our GPU
+        // BackendBit::PRIMARY => Vulkan + Metal + DX12 + Browser WebGPU
+        let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+        let surface = unsafe { instance.create_surface(window) };
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::Default,
+                compatible_surface: Some(&surface),
+            })
+            .await
+            .unwrap();
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    features: wgpu::Features::empty(),
+                    limits: wgpu::Limits::default(),
+                    shader_validation: true,
+                },
+                None, // Trace path
+            )
+            .await
+            .unwrap();
+
+        let sc_desc = wgpu::SwapChainDescriptor {
+            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+            format: wgpu::TextureFormat::Bgra8UnormSrgb,
+            width: size.width,
+            height: size.height,
+            present_mode: wgpu::PresentMode::Fifo,
+        };
+        let swap_chain = device.create_swap_chain(&surface, &sc_desc);
+        let capture = Capture::new(&device, &sc_desc);
+
+        let globals = GlobalsUniform::new(&device, size);
+
+        // La textura que escribe el compute shader. STORAGE para imageStore y SAMPLED para leerla al dibujar.
+        // Las storage textures no pueden ser Srgb, por eso usamos Rgba8Unorm
+        let display_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Game of Life Texture"),
+            size: wgpu::Extent3d {
+                width: GRID_WIDTH,
+                height: GRID_HEIGHT,
+                depth: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsage::STORAGE | wgpu::TextureUsage::SAMPLED,
+        });
+        let display_view = display_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        // Nearest para que cada celula se vea como un cuadrado
+        let display_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Game of Life Params"),
+            contents: bytemuck::cast_slice(&[GRID_WIDTH, GRID_HEIGHT]),
+            usage: wgpu::BufferUsage::UNIFORM,
+        });
+
+        let cells = random_cells(0x2545_f491);
+        let cell_buffers = [
+            create_storage_buffer(&device, &cells, Some("Cells Buffer 0")),
+            create_storage_buffer(&device, &cells, Some("Cells Buffer 1")),
+        ];
+
+        let cs_module = device.create_shader_module(wgpu::include_spirv!("shaders/game_of_life.comp.spv"));
+        let kernel = ComputeKernel::new(
+            &device,
+            &cs_module,
+            &[
+                uniform_buffer_entry(0),
+                storage_buffer_entry(1, true),
+                storage_buffer_entry(2, false),
+                storage_texture_entry(3, wgpu::TextureFormat::Rgba8Unorm, false),
+            ],
+            [8, 8, 1],
+            Some("Game of Life Kernel"),
+        );
+
+        // Un bind group por sentido: 0 lee del buffer 0 y escribe en el 1, el 1 al reves
+        let compute_bind_groups = [
+            kernel.create_bind_group(
+                &device,
+                &[
+                    wgpu::BindingResource::Buffer(params_buffer.slice(..)),
+                    wgpu::BindingResource::Buffer(cell_buffers[0].slice(..)),
+                    wgpu::BindingResource::Buffer(cell_buffers[1].slice(..)),
+                    wgpu::BindingResource::TextureView(&display_view),
+                ],
+                Some("Game of Life Bind Group 0"),
+            ),
+            kernel.create_bind_group(
+                &device,
+                &[
+                    wgpu::BindingResource::Buffer(params_buffer.slice(..)),
+                    wgpu::BindingResource::Buffer(cell_buffers[1].slice(..)),
+                    wgpu::BindingResource::Buffer(cell_buffers[0].slice(..)),
+                    wgpu::BindingResource::TextureView(&display_view),
+                ],
+                Some("Game of Life Bind Group 1"),
+            ),
+        ];
+
+        let texture_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStage::FRAGMENT,
+                        ty: wgpu::BindingType::SampledTexture {
+                            multisampled: false,
+                            dimension: wgpu::TextureViewDimension::D2,
+                            component_type: wgpu::TextureComponentType::Float,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStage::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler { comparison: false },
+                        count: None,
+                    },
+                ],
+                label: Some("texture_bind_group_layout"),
+            });
+
+        let display_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &texture_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&display_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&display_sampler),
+                },
+            ],
+            label: Some("display_bind_group"),
+        });
+
+        // Triangulo a pantalla completa con el mismo fragment shader que el ejemplo de texturas
+        let vs_module = device.create_shader_module(wgpu::include_spirv!("shaders/fullscreen.vert.spv"));
+        let fs_module = device.create_shader_module(wgpu::include_spirv!("shaders/shader_1_5.frag.spv"));
+
+        let render_pipeline = PipelineBuilder::new(&vs_module, sc_desc.format)
+            .label("Render Pipeline")
+            .fragment_shader(&fs_module)
+            .bind_group_layouts(&[&globals.bind_group_layout, &texture_bind_group_layout])
+            .sample_count(1)
+            .build(&device);
+
+        Self {
+            surface,
+            device,
+            queue,
+            sc_desc,
+            swap_chain,
+            size,
+            globals,
+            kernel,
+            cell_buffers,
+            compute_bind_groups,
+            current: 0,
+            paused: false,
+            render_pipeline,
+            display_bind_group,
+            capture,
+        }
+    }
+
+    fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.size = new_size;
+        self.sc_desc.width = new_size.width;
+        self.sc_desc.height = new_size.height;
+        self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+        self.capture.resize(&self.device, &self.sc_desc);
+        self.globals.resize(new_size);
+    }
+
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        self.globals.input(event);
+        if self.capture.input(event) {
+            return true;
+        }
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::Space),
+                        ..
+                    },
+                ..
+            } => {
+                self.paused = !self.paused;
+                true
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::C),
+                        ..
+                    },
+                ..
+            } => {
+                self.count_alive_cells();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // Lee el estado actual de la GPU. Bloquea hasta que la GPU termina, solo para depurar
+    fn count_alive_cells(&self) {
+        use futures::executor::block_on;
+        let len = (GRID_WIDTH * GRID_HEIGHT) as usize;
+        match block_on(read_buffer::<u32>(
+            &self.device,
+            &self.queue,
+            &self.cell_buffers[self.current],
+            len,
+        )) {
+            Ok(cells) => println!("Alive cells: {}", cells.iter().sum::<u32>()),
+            Err(e) => eprintln!("{:#}", e),
+        }
+    }
+
+    fn update(&mut self) {
+        self.globals.set_fixed_time_step(self.capture.time_step());
+        self.globals.update(&self.queue);
+    }
+
+    fn render(&mut self) {
+        let frame = self
+            .swap_chain
+            .get_current_frame()
+            .expect("Timeout getting texture")
+            .output;
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        // Primero un paso de la simulacion, el render pass de despues ya ve la textura actualizada
+        if !self.paused {
+            let mut compute_pass = encoder.begin_compute_pass();
+            self.kernel.dispatch(
+                &mut compute_pass,
+                &self.compute_bind_groups[self.current],
+                [GRID_WIDTH, GRID_HEIGHT, 1],
+            );
+        }
+
+        self.draw(&mut encoder, &frame.view);
+        // Si hay una captura pendiente volvemos a dibujar el frame en la textura de captura
+        if let Some(view) = self.capture.target() {
+            self.draw(&mut encoder, view);
+        }
+
+        self.queue.submit(iter::once(encoder.finish()));
+        self.capture.finish_frame(&self.device, &self.queue);
+
+        if !self.paused {
+            self.current = 1 - self.current;
+        }
+    }
+
+    fn draw(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                attachment: view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        });
+
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, &self.globals.bind_group, &[]);
+        render_pass.set_bind_group(1, &self.display_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+pub fn main_2_1() {
+    env_logger::init();
+    let event_loop = EventLoop::new();
+    let window = WindowBuilder::new().build(&event_loop).unwrap();
+
+    use futures::executor::block_on;
+
+    // Since main can't be async, we're going to need to block
+    let mut state = block_on(State::new(&window));
+
+    event_loop.run(move |event, _, control_flow| {
+        match event {
+            Event::WindowEvent {
+                ref event,
+                window_id,
+            } if window_id == window.id() => {
+                if !state.input(event) {
+                    match event {
+                        WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+                        WindowEvent::KeyboardInput { input, .. } => match input {
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Escape),
+                                ..
+                            } => *control_flow = ControlFlow::Exit,
+                            _ => {}
+                        },
+                        WindowEvent::Resized(physical_size) => {
+                            state.resize(*physical_size);
+                        }
+                        WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                            // new_inner_size is &mut so w have to dereference it twice
+                            state.resize(**new_inner_size);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Event::RedrawRequested(_) => {
+                state.update();
+                state.render();
+            }
+            Event::MainEventsCleared => {
+                // RedrawRequested will only trigger once, unless we manually
+                // request it.
+                window.request_redraw();
+            }
+            _ => {}
+        }
+    });
+}
@@ -0,0 +1,480 @@
+//## Transparencias: varios sprites de happy-tree.png girando en un carrusel. Los sprites tienen fondo transparente y
+//## un tinte semitransparente, asi que se tienen que dibujar de atras hacia delante para mezclarse bien.
+//## B cambia el modo de blending (un pipeline por modo), S activa/desactiva la ordenacion para ver la diferencia.
+
+use std::f32::consts::PI;
+use std::iter;
+
+use wgpu::util::DeviceExt;
+use winit::{
+    event::*,
+    event_loop::{ControlFlow, EventLoop},
+    window::{Window, WindowBuilder},
+};
+
+use crate::examples::{
+    msaa_sample_count, sort_back_to_front, BlendMode, Capture, GlobalsUniform, Multisample,
+    PipelineBuilder, TextRenderer,
+};
+use crate::examples::Texture as texture;
+
+const NUM_SPRITES: usize = 6;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+struct Vertex {
+    position: [f32; 2],
+    tex_coords: [f32; 2],
+}
+
+unsafe impl bytemuck::Pod for Vertex {}
+unsafe impl bytemuck::Zeroable for Vertex {}
+
+impl Vertex {
+    fn desc<'a>() -> wgpu::VertexBufferDescriptor<'a> {
+        use std::mem;
+        wgpu::VertexBufferDescriptor {
+            stride: mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::InputStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttributeDescriptor {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float2,
+                },
+                wgpu::VertexAttributeDescriptor {
+                    offset: mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float2,
+                },
+            ],
+        }
+    }
+}
+
+// Un sprite. Va en un segundo vertex buffer con step_mode Instance, asi se dibujan todos con un solo draw call
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+struct SpriteInstance {
+    // xy en pantalla, z la profundidad (0 delante, 1 detras)
+    offset: [f32; 3],
+    scale: f32,
+    tint: [f32; 4],
+}
+
+unsafe impl bytemuck::Pod for SpriteInstance {}
+unsafe impl bytemuck::Zeroable for SpriteInstance {}
+
+impl SpriteInstance {
+    fn desc<'a>() -> wgpu::VertexBufferDescriptor<'a> {
+        use std::mem;
+        wgpu::VertexBufferDescriptor {
+            stride: mem::size_of::<SpriteInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::InputStepMode::Instance,
+            attributes: &[
+                wgpu::VertexAttributeDescriptor {
+                    offset: 0,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float3,
+                },
+                wgpu::VertexAttributeDescriptor {
+                    offset: mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float,
+                },
+                wgpu::VertexAttributeDescriptor {
+                    offset: mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                    shader_location: 4,
+                    format: wgpu::VertexFormat::Float4,
+                },
+            ],
+        }
+    }
+}
+
+const VERTICES: &[Vertex] = &[
+    Vertex { position: [-0.5, -0.5], tex_coords: [0.0, 1.0] },
+    Vertex { position: [0.5, -0.5], tex_coords: [1.0, 1.0] },
+    Vertex { position: [0.5, 0.5], tex_coords: [1.0, 0.0] },
+    Vertex { position: [-0.5, 0.5], tex_coords: [0.0, 0.0] },
+];
+
+const INDICES: &[u16] = &[0, 1, 2, 0, 2, 3];
+
+const TINTS: [[f32; 4]; NUM_SPRITES] = [
+    [1.0, 1.0, 1.0, 0.8],
+    [1.0, 0.4, 0.4, 0.8],
+    [0.4, 1.0, 0.4, 0.8],
+    [0.4, 0.4, 1.0, 0.8],
+    [1.0, 1.0, 0.4, 0.8],
+    [1.0, 0.4, 1.0, 0.8],
+];
+
+// Posicion de los sprites en el carrusel en el instante `time`, sin ordenar
+fn carousel(time: f32) -> Vec<SpriteInstance> {
+    (0..NUM_SPRITES)
+        .map(|i| {
+            let angle = time * 0.5 + i as f32 * 2.0 * PI / NUM_SPRITES as f32;
+            let depth = (angle.sin() + 1.0) * 0.5;
+            SpriteInstance {
+                // Los de detras un poco mas arriba y mas pequeños, una perspectiva de pega
+                offset: [angle.cos() * 0.6, -0.15 + depth * 0.3, depth],
+                scale: 0.7 - depth * 0.3,
+                tint: TINTS[i],
+            }
+        })
+        .collect()
+}
+
+struct State {
+    surface: wgpu::Surface,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    sc_desc: wgpu::SwapChainDescriptor,
+    swap_chain: wgpu::SwapChain,
+    size: winit::dpi::PhysicalSize<u32>,
+    // Un pipeline por cada modo de BlendMode::ALL, en el mismo orden
+    pipelines: Vec<wgpu::RenderPipeline>,
+    blend_mode: BlendMode,
+    sort_sprites: bool,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    instance_buffer: wgpu::Buffer,
+    #[allow(dead_code)]
+    diffuse_texture: texture,
+    diffuse_bind_group: wgpu::BindGroup,
+    globals: GlobalsUniform,
+    text: TextRenderer,
+    capture: Capture,
+    multisample: Multisample,
+}
+
+impl State {
+    async fn new(window: &Window, sample_count: u32) -> Self {
+        let size = window.inner_size();
+
+        let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+        let surface = unsafe { instance.create_surface(window) };
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::Default,
+                compatible_surface: Some(&surface),
+            })
+            .await
+            .unwrap();
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    features: wgpu::Features::empty(),
+                    limits: wgpu::Limits::default(),
+                    shader_validation: true,
+                },
+                None, // Trace path
+            )
+            .await
+            .unwrap();
+
+        let sc_desc = wgpu::SwapChainDescriptor {
+            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+            format: wgpu::TextureFormat::Bgra8UnormSrgb,
+            width: size.width,
+            height: size.height,
+            present_mode: wgpu::PresentMode::Fifo,
+        };
+        let swap_chain = device.create_swap_chain(&surface, &sc_desc);
+        let capture = Capture::new(&device, &sc_desc);
+        let multisample = Multisample::new(&device, &sc_desc, msaa_sample_count(&adapter, sample_count));
+
+        let diffuse_bytes = include_bytes!("textures/happy-tree.png");
+        let diffuse_texture =
+            texture::from_bytes(&device, &queue, diffuse_bytes, "textures/happy-tree.png").unwrap();
+
+        let texture_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStage::FRAGMENT,
+                        ty: wgpu::BindingType::SampledTexture {
+                            multisampled: false,
+                            dimension: wgpu::TextureViewDimension::D2,
+                            component_type: wgpu::TextureComponentType::Uint,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStage::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler { comparison: false },
+                        count: None,
+                    },
+                ],
+                label: Some("texture_bind_group_layout"),
+            });
+
+        let diffuse_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &texture_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&diffuse_texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&diffuse_texture.sampler),
+                },
+            ],
+            label: Some("diffuse_bind_group"),
+        });
+
+        let globals = GlobalsUniform::new(&device, size);
+
+        let vs_module = device.create_shader_module(wgpu::include_spirv!("shaders/sprite.vert.spv"));
+        let fs_module = device.create_shader_module(wgpu::include_spirv!("shaders/sprite.frag.spv"));
+        let fs_premultiplied_module =
+            device.create_shader_module(wgpu::include_spirv!("shaders/sprite_premultiplied.frag.spv"));
+
+        // El modo de blending forma parte del pipeline, asi que creamos uno por modo
+        let builder = PipelineBuilder::new(&vs_module, sc_desc.format)
+            .label("Sprite Pipeline")
+            .bind_group_layouts(&[&globals.bind_group_layout, &texture_bind_group_layout])
+            .vertex_buffer(Vertex::desc())
+            .vertex_buffer(SpriteInstance::desc())
+            .cull_mode(wgpu::CullMode::None)
+            .sample_count(multisample.sample_count);
+        let pipelines = BlendMode::ALL
+            .iter()
+            .map(|&mode| {
+                let fs_module = if mode.premultiplied_source() {
+                    &fs_premultiplied_module
+                } else {
+                    &fs_module
+                };
+                builder.clone().fragment_shader(fs_module).blend(mode).build(&device)
+            })
+            .collect();
+
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(VERTICES),
+            usage: wgpu::BufferUsage::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Index Buffer"),
+            contents: bytemuck::cast_slice(INDICES),
+            usage: wgpu::BufferUsage::INDEX,
+        });
+        // COPY_DST porque las instancias se reordenan y se vuelven a subir cada frame
+        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Instance Buffer"),
+            contents: bytemuck::cast_slice(&carousel(0.0)),
+            usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+        });
+
+        let text = TextRenderer::new(
+            &device,
+            &queue,
+            include_bytes!("fonts/DejaVuSans.ttf"),
+            &globals.bind_group_layout,
+            sc_desc.format,
+            multisample.sample_count,
+        )
+        .unwrap();
+
+        Self {
+            surface,
+            device,
+            queue,
+            sc_desc,
+            swap_chain,
+            size,
+            pipelines,
+            blend_mode: BlendMode::Alpha,
+            sort_sprites: true,
+            vertex_buffer,
+            index_buffer,
+            instance_buffer,
+            diffuse_texture,
+            diffuse_bind_group,
+            globals,
+            text,
+            capture,
+            multisample,
+        }
+    }
+
+    fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.size = new_size;
+        self.sc_desc.width = new_size.width;
+        self.sc_desc.height = new_size.height;
+        self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+        self.capture.resize(&self.device, &self.sc_desc);
+        self.multisample.resize(&self.device, &self.sc_desc);
+        self.globals.resize(new_size);
+    }
+
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        self.globals.input(event);
+        if self.capture.input(event) {
+            return true;
+        }
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::B),
+                        ..
+                    },
+                ..
+            } => {
+                self.blend_mode = self.blend_mode.next();
+                println!("Blend mode: {:?}", self.blend_mode);
+                true
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::S),
+                        ..
+                    },
+                ..
+            } => {
+                self.sort_sprites = !self.sort_sprites;
+                println!("Back to front sorting: {}", self.sort_sprites);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn update(&mut self) {
+        self.globals.set_fixed_time_step(self.capture.time_step());
+        self.globals.update(&self.queue);
+
+        // Sin depth buffer los sprites se pintan en el orden de las instancias, que es el que ordenamos aqui
+        let mut sprites = carousel(self.globals.data.time);
+        if self.sort_sprites {
+            sort_back_to_front(&mut sprites, |sprite| sprite.offset[2]);
+        }
+        self.queue
+            .write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&sprites));
+
+        let overlay = format!(
+            "Blend mode: {:?} (B)\nBack to front sorting: {} (S)",
+            self.blend_mode, self.sort_sprites
+        );
+        self.text.queue_text(&overlay, [10.0, 10.0], 20.0, [1.0, 1.0, 1.0, 1.0], None);
+        self.text.prepare(&self.device, &self.queue);
+    }
+
+    fn render(&mut self) {
+        let frame = self
+            .swap_chain
+            .get_current_frame()
+            .expect("Timeout getting texture")
+            .output;
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        self.draw(&mut encoder, &frame.view);
+        // Si hay una captura pendiente volvemos a dibujar el frame en la textura de captura
+        if let Some(view) = self.capture.target() {
+            self.draw(&mut encoder, view);
+        }
+
+        self.queue.submit(iter::once(encoder.finish()));
+        self.capture.finish_frame(&self.device, &self.queue);
+    }
+
+    fn draw(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let (attachment, resolve_target) = self.multisample.color_attachment(view);
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                attachment,
+                resolve_target,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color {
+                        r: 0.1,
+                        g: 0.2,
+                        b: 0.3,
+                        a: 1.0,
+                    }),
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        });
+
+        let mode_index = BlendMode::ALL
+            .iter()
+            .position(|&mode| mode == self.blend_mode)
+            .unwrap_or(0);
+        render_pass.set_pipeline(&self.pipelines[mode_index]);
+        render_pass.set_bind_group(0, &self.globals.bind_group, &[]);
+        render_pass.set_bind_group(1, &self.diffuse_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..));
+        render_pass.draw_indexed(0..INDICES.len() as u32, 0, 0..NUM_SPRITES as u32);
+
+        self.text.draw(&mut render_pass, &self.globals.bind_group);
+    }
+}
+
+pub fn main_2_2(sample_count: u32) {
+    env_logger::init();
+    let event_loop = EventLoop::new();
+    let window = WindowBuilder::new().build(&event_loop).unwrap();
+
+    use futures::executor::block_on;
+
+    // Since main can't be async, we're going to need to block
+    let mut state = block_on(State::new(&window, sample_count));
+
+    event_loop.run(move |event, _, control_flow| {
+        match event {
+            Event::WindowEvent {
+                ref event,
+                window_id,
+            } if window_id == window.id() => {
+                if !state.input(event) {
+                    match event {
+                        WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+                        WindowEvent::KeyboardInput { input, .. } => match input {
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Escape),
+                                ..
+                            } => *control_flow = ControlFlow::Exit,
+                            _ => {}
+                        },
+                        WindowEvent::Resized(physical_size) => {
+                            state.resize(*physical_size);
+                        }
+                        WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                            // new_inner_size is &mut so w have to dereference it twice
+                            state.resize(**new_inner_size);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Event::RedrawRequested(_) => {
+                state.update();
+                state.render();
+            }
+            Event::MainEventsCleared => {
+                // RedrawRequested will only trigger once, unless we manually
+                // request it.
+                window.request_redraw();
+            }
+            _ => {}
+        }
+    });
+}
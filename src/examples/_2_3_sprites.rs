@@ -0,0 +1,374 @@
+//## Sprites: miles de sprites rebotando por la ventana, cada uno con su imagen, tinte, giro, escala y profundidad,
+//## dibujados con un solo draw gracias a un atlas de texturas y a SpriteBatch.
+//## Las flechas arriba/abajo duplican o dividen a la mitad el numero de sprites.
+
+use std::f32::consts::PI;
+use std::iter;
+
+use winit::{
+    event::*,
+    event_loop::{ControlFlow, EventLoop},
+    window::{Window, WindowBuilder},
+};
+
+use crate::examples::{
+    msaa_sample_count, Capture, FpsCounter, GlobalsUniform, Multisample, Sprite, SpriteBatch, TextRenderer,
+    TextureAtlas,
+};
+
+const INITIAL_SPRITES: usize = 10_000;
+const MAX_SPRITES: usize = 1 << 20;
+
+// Un xorshift basta y nos ahorramos la dependencia de rand
+struct Random(u32);
+
+impl Random {
+    // Entre 0 y 1
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        (self.0 >> 8) as f32 / (1 << 24) as f32
+    }
+
+    fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next()
+    }
+}
+
+// Imagenes blancas generadas por codigo, el tinte del sprite les da el color
+fn shape_image(size: u32, inside: impl Fn(f32, f32) -> bool) -> image::RgbaImage {
+    image::RgbaImage::from_fn(size, size, |x, y| {
+        // Coordenadas entre -1 y 1 desde el centro del pixel
+        let u = (x as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+        let v = (y as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+        if inside(u, v) {
+            image::Rgba([255, 255, 255, 255])
+        } else {
+            image::Rgba([0, 0, 0, 0])
+        }
+    })
+}
+
+fn load_images() -> Vec<image::RgbaImage> {
+    let diffuse_bytes = include_bytes!("textures/happy-tree.png");
+    let cartoon_bytes = include_bytes!("textures/happy-tree-cartoon.png");
+    vec![
+        image::load_from_memory(diffuse_bytes).unwrap().to_rgba8(),
+        image::load_from_memory(cartoon_bytes).unwrap().to_rgba8(),
+        shape_image(32, |u, v| u * u + v * v <= 1.0),
+        shape_image(48, |u, v| (0.6..=1.0).contains(&(u * u + v * v))),
+        shape_image(24, |u, v| u.abs() + v.abs() <= 1.0),
+        shape_image(16, |_, _| true),
+    ]
+}
+
+// Lo que se mueve de cada sprite, el resto va en el Sprite
+struct Motion {
+    velocity: [f32; 2],
+    spin: f32,
+}
+
+struct State {
+    surface: wgpu::Surface,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    sc_desc: wgpu::SwapChainDescriptor,
+    swap_chain: wgpu::SwapChain,
+    size: winit::dpi::PhysicalSize<u32>,
+    sprite_batch: SpriteBatch,
+    sprites: Vec<Sprite>,
+    motions: Vec<Motion>,
+    random: Random,
+    text: TextRenderer,
+    fps: FpsCounter,
+    globals: GlobalsUniform,
+    capture: Capture,
+    multisample: Multisample,
+}
+
+impl State {
+    async fn new(window: &Window, sample_count: u32) -> Self {
+        let size = window.inner_size();
+
+        let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+        let surface = unsafe { instance.create_surface(window) };
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::Default,
+                compatible_surface: Some(&surface),
+            })
+            .await
+            .unwrap();
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    features: wgpu::Features::empty(),
+                    limits: wgpu::Limits::default(),
+                    shader_validation: true,
+                },
+                None, // Trace path
+            )
+            .await
+            .unwrap();
+
+        let sc_desc = wgpu::SwapChainDescriptor {
+            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+            format: wgpu::TextureFormat::Bgra8UnormSrgb,
+            width: size.width,
+            height: size.height,
+            present_mode: wgpu::PresentMode::Fifo,
+        };
+        let swap_chain = device.create_swap_chain(&surface, &sc_desc);
+        let capture = Capture::new(&device, &sc_desc);
+        let multisample = Multisample::new(&device, &sc_desc, msaa_sample_count(&adapter, sample_count));
+
+        let globals = GlobalsUniform::new(&device, size);
+
+        let atlas = TextureAtlas::new(&device, &queue, &load_images(), "Sprite Atlas").unwrap();
+        let sprite_batch = SpriteBatch::new(
+            &device,
+            atlas,
+            &globals.bind_group_layout,
+            sc_desc.format,
+            multisample.sample_count,
+        );
+        let text = TextRenderer::new(
+            &device,
+            &queue,
+            include_bytes!("fonts/DejaVuSans.ttf"),
+            &globals.bind_group_layout,
+            sc_desc.format,
+            multisample.sample_count,
+        )
+        .unwrap();
+
+        let mut state = Self {
+            surface,
+            device,
+            queue,
+            sc_desc,
+            swap_chain,
+            size,
+            sprite_batch,
+            sprites: Vec::new(),
+            motions: Vec::new(),
+            random: Random(0x2545_f491),
+            text,
+            fps: FpsCounter::default(),
+            globals,
+            capture,
+            multisample,
+        };
+        state.set_sprite_count(INITIAL_SPRITES);
+        state
+    }
+
+    fn set_sprite_count(&mut self, count: usize) {
+        self.sprites.truncate(count);
+        self.motions.truncate(count);
+        let num_regions = self.sprite_batch.regions().len();
+        while self.sprites.len() < count {
+            let random = &mut self.random;
+            let region = (random.next() * num_regions as f32) as usize % num_regions;
+            // Los arboles son mucho mas grandes que las formas
+            let scale = if region < 2 {
+                random.range(0.1, 0.25)
+            } else {
+                random.range(0.5, 1.5)
+            };
+            self.sprites.push(Sprite {
+                position: [
+                    random.range(0.0, self.size.width as f32),
+                    random.range(0.0, self.size.height as f32),
+                ],
+                region,
+                scale: [scale, scale],
+                rotation: random.range(0.0, 2.0 * PI),
+                depth: random.next(),
+                tint: [random.range(0.3, 1.0), random.range(0.3, 1.0), random.range(0.3, 1.0), 0.9],
+            });
+            let angle = random.range(0.0, 2.0 * PI);
+            let speed = random.range(20.0, 200.0);
+            self.motions.push(Motion {
+                velocity: [angle.cos() * speed, angle.sin() * speed],
+                spin: random.range(-2.0, 2.0),
+            });
+        }
+        println!("Sprites: {}", self.sprites.len());
+    }
+
+    fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.size = new_size;
+        self.sc_desc.width = new_size.width;
+        self.sc_desc.height = new_size.height;
+        self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+        self.capture.resize(&self.device, &self.sc_desc);
+        self.multisample.resize(&self.device, &self.sc_desc);
+        self.globals.resize(new_size);
+    }
+
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        self.globals.input(event);
+        if self.capture.input(event) {
+            return true;
+        }
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::Up),
+                        ..
+                    },
+                ..
+            } => {
+                self.set_sprite_count((self.sprites.len() * 2).clamp(1, MAX_SPRITES));
+                true
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::Down),
+                        ..
+                    },
+                ..
+            } => {
+                self.set_sprite_count(self.sprites.len() / 2);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn update(&mut self) {
+        self.globals.set_fixed_time_step(self.capture.time_step());
+        self.globals.update(&self.queue);
+
+        // Rebotan contra los bordes de la ventana
+        let delta = self.globals.data.time_delta;
+        let bounds = [self.size.width as f32, self.size.height as f32];
+        for (sprite, motion) in self.sprites.iter_mut().zip(&mut self.motions) {
+            let axes = sprite.position.iter_mut().zip(&mut motion.velocity).zip(&bounds);
+            for ((position, velocity), &bound) in axes {
+                *position += *velocity * delta;
+                if *position < 0.0 || *position > bound {
+                    *position = position.clamp(0.0, bound);
+                    *velocity = -*velocity;
+                }
+            }
+            sprite.rotation += motion.spin * delta;
+        }
+
+        self.sprite_batch.prepare(&self.device, &self.queue, &self.sprites);
+
+        self.fps.update(delta);
+        let overlay = format!(
+            "2_3 Sprites\n{} sprites, {:.0} FPS\nUp/Down: more/fewer sprites",
+            self.sprites.len(),
+            self.fps.fps
+        );
+        self.text.queue_text(&overlay, [10.0, 10.0], 20.0, [1.0, 1.0, 1.0, 1.0], None);
+        self.text.prepare(&self.device, &self.queue);
+    }
+
+    fn render(&mut self) {
+        let frame = self
+            .swap_chain
+            .get_current_frame()
+            .expect("Timeout getting texture")
+            .output;
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        self.draw(&mut encoder, &frame.view);
+        // Si hay una captura pendiente volvemos a dibujar el frame en la textura de captura
+        if let Some(view) = self.capture.target() {
+            self.draw(&mut encoder, view);
+        }
+
+        self.queue.submit(iter::once(encoder.finish()));
+        self.capture.finish_frame(&self.device, &self.queue);
+    }
+
+    fn draw(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let (attachment, resolve_target) = self.multisample.color_attachment(view);
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                attachment,
+                resolve_target,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color {
+                        r: 0.1,
+                        g: 0.2,
+                        b: 0.3,
+                        a: 1.0,
+                    }),
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        });
+
+        self.sprite_batch.draw(&mut render_pass, &self.globals.bind_group);
+        // El texto al final para que quede por encima de los sprites
+        self.text.draw(&mut render_pass, &self.globals.bind_group);
+    }
+}
+
+pub fn main_2_3(sample_count: u32) {
+    env_logger::init();
+    let event_loop = EventLoop::new();
+    let window = WindowBuilder::new().build(&event_loop).unwrap();
+
+    use futures::executor::block_on;
+
+    // Since main can't be async, we're going to need to block
+    let mut state = block_on(State::new(&window, sample_count));
+
+    event_loop.run(move |event, _, control_flow| {
+        match event {
+            Event::WindowEvent {
+                ref event,
+                window_id,
+            } if window_id == window.id() => {
+                if !state.input(event) {
+                    match event {
+                        WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+                        WindowEvent::KeyboardInput { input, .. } => match input {
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Escape),
+                                ..
+                            } => *control_flow = ControlFlow::Exit,
+                            _ => {}
+                        },
+                        WindowEvent::Resized(physical_size) => {
+                            state.resize(*physical_size);
+                        }
+                        WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                            // new_inner_size is &mut so w have to dereference it twice
+                            state.resize(**new_inner_size);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Event::RedrawRequested(_) => {
+                state.update();
+                state.render();
+            }
+            Event::MainEventsCleared => {
+                // RedrawRequested will only trigger once, unless we manually
+                // request it.
+                window.request_redraw();
+            }
+            _ => {}
+        }
+    });
+}
@@ -0,0 +1,306 @@
+//## Atlas de texturas: muchas imagenes pequeñas copiadas en una sola textura grande, para poder dibujar sprites con
+//## imagenes distintas sin cambiar de bind group (y por tanto en un solo draw, ver sprite_batch.rs).
+//##
+//## El empaquetado es por estanterias (shelf packing): las imagenes se colocan de izquierda a derecha en filas, cada fila
+//## tan alta como su imagen mas alta. Ordenando antes las imagenes de mas alta a mas baja se desperdicia poco espacio.
+//## AtlasPacker no toca la GPU, solo calcula rectangulos, asi que se puede probar con cargo test.
+//##
+//## Un TextureAtlas guarda su packer, asi que se le pueden añadir imagenes despues (add) en el espacio que queda libre
+//## sin volver a empaquetar ni subir las que ya tenia. Solo cuando se llena hay que crear otro mas grande.
+
+use anyhow::{bail, Result};
+
+use crate::examples::Texture;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AtlasRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl AtlasRect {
+    pub fn right(&self) -> u32 {
+        self.x + self.width
+    }
+
+    pub fn bottom(&self) -> u32 {
+        self.y + self.height
+    }
+}
+
+struct Shelf {
+    y: u32,
+    height: u32,
+    // Donde empieza el hueco libre de la estanteria
+    next_x: u32,
+}
+
+pub struct AtlasPacker {
+    width: u32,
+    height: u32,
+    // Pixels libres alrededor de cada imagen, para que el filtrado lineal no mezcle imagenes vecinas
+    padding: u32,
+    shelves: Vec<Shelf>,
+}
+
+impl AtlasPacker {
+    pub fn new(width: u32, height: u32, padding: u32) -> Self {
+        Self {
+            width,
+            height,
+            padding,
+            shelves: Vec::new(),
+        }
+    }
+
+    // Busca sitio para un rectangulo de width x height. Devuelve None si ya no cabe
+    pub fn insert(&mut self, width: u32, height: u32) -> Option<AtlasRect> {
+        let padded_width = width + self.padding * 2;
+        let padded_height = height + self.padding * 2;
+        if padded_width > self.width {
+            return None;
+        }
+
+        // La estanteria donde cabe desperdiciando menos altura
+        let atlas_width = self.width;
+        let best_shelf = self
+            .shelves
+            .iter_mut()
+            .filter(|shelf| shelf.height >= padded_height && shelf.next_x + padded_width <= atlas_width)
+            .min_by_key(|shelf| shelf.height - padded_height);
+
+        let shelf = match best_shelf {
+            Some(shelf) => shelf,
+            None => {
+                let y = self.shelves.last().map_or(0, |shelf| shelf.y + shelf.height);
+                if y + padded_height > self.height {
+                    return None;
+                }
+                self.shelves.push(Shelf {
+                    y,
+                    height: padded_height,
+                    next_x: 0,
+                });
+                self.shelves.last_mut().unwrap()
+            }
+        };
+
+        let rect = AtlasRect {
+            x: shelf.next_x + self.padding,
+            y: shelf.y + self.padding,
+            width,
+            height,
+        };
+        shelf.next_x += padded_width;
+        Some(rect)
+    }
+}
+
+// Empaqueta todos los tamaños en el espacio libre del packer, de mas alto a mas bajo. Los rectangulos se devuelven en
+// el mismo orden que sizes, o None si no caben todos
+pub fn pack_into(packer: &mut AtlasPacker, sizes: &[(u32, u32)]) -> Option<Vec<AtlasRect>> {
+    let mut order = (0..sizes.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&i| std::cmp::Reverse(sizes[i].1));
+
+    let mut rects = vec![None; sizes.len()];
+    for i in order {
+        let (w, h) = sizes[i];
+        rects[i] = Some(packer.insert(w, h)?);
+    }
+    Some(rects.into_iter().map(Option::unwrap).collect())
+}
+
+// Coordenadas de textura de una imagen del atlas y su tamaño original en pixels
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AtlasRegion {
+    pub uv_min: [f32; 2],
+    pub uv_max: [f32; 2],
+    pub size: [f32; 2],
+}
+
+pub struct TextureAtlas {
+    pub texture: Texture,
+    // Una por imagen, en el mismo orden que se pasaron a new() y despues a add()
+    pub regions: Vec<AtlasRegion>,
+    packer: AtlasPacker,
+    size: u32,
+}
+
+const ATLAS_PADDING: u32 = 1;
+const MIN_ATLAS_SIZE: u32 = 256;
+// El limite de tamaño de textura 2D que garantiza wgpu
+const MAX_ATLAS_SIZE: u32 = 8192;
+
+impl TextureAtlas {
+    // Empaqueta las imagenes en el atlas cuadrado mas pequeño (potencia de 2) en el que caben y lo sube a la GPU
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, images: &[image::RgbaImage], label: &str) -> Result<Self> {
+        Self::with_min_size(device, queue, images, label, MIN_ATLAS_SIZE)
+    }
+
+    // Como new() pero con al menos min_size de lado, para dejar sitio a las imagenes que se añadan despues
+    pub fn with_min_size(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        images: &[image::RgbaImage],
+        label: &str,
+        min_size: u32,
+    ) -> Result<Self> {
+        let sizes = images.iter().map(|img| img.dimensions()).collect::<Vec<_>>();
+        let mut size = min_size.min(MAX_ATLAS_SIZE);
+        let (packer, rects) = loop {
+            let mut packer = AtlasPacker::new(size, size, ATLAS_PADDING);
+            if let Some(rects) = pack_into(&mut packer, &sizes) {
+                break (packer, rects);
+            }
+            if size >= MAX_ATLAS_SIZE {
+                bail!("{} images don't fit in a {}x{} atlas", images.len(), MAX_ATLAS_SIZE, MAX_ATLAS_SIZE);
+            }
+            size *= 2;
+        };
+
+        let mut atlas = image::RgbaImage::new(size, size);
+        for (img, rect) in images.iter().zip(&rects) {
+            image::imageops::replace(&mut atlas, img, rect.x, rect.y);
+        }
+        let texture = Texture::from_image(device, queue, &image::DynamicImage::ImageRgba8(atlas), Some(label))?;
+        let regions = rects.iter().map(|rect| region(rect, size)).collect();
+
+        Ok(Self {
+            texture,
+            regions,
+            packer,
+            size,
+        })
+    }
+
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    // Copia las imagenes al espacio libre del atlas y añade sus regiones a continuacion de las que ya habia. Devuelve
+    // false si alguna no cabe; las anteriores si se quedan, pero hay que crear un atlas mas grande con todas
+    pub fn add(&mut self, queue: &wgpu::Queue, images: &[image::RgbaImage]) -> bool {
+        for img in images {
+            let (width, height) = img.dimensions();
+            let rect = match self.packer.insert(width, height) {
+                Some(rect) => rect,
+                None => return false,
+            };
+            if width > 0 && height > 0 {
+                queue.write_texture(
+                    wgpu::TextureCopyView {
+                        texture: &self.texture.texture,
+                        mip_level: 0,
+                        origin: wgpu::Origin3d {
+                            x: rect.x,
+                            y: rect.y,
+                            z: 0,
+                        },
+                    },
+                    img,
+                    wgpu::TextureDataLayout {
+                        offset: 0,
+                        bytes_per_row: 4 * width,
+                        rows_per_image: height,
+                    },
+                    wgpu::Extent3d {
+                        width,
+                        height,
+                        depth: 1,
+                    },
+                );
+            }
+            self.regions.push(region(&rect, self.size));
+        }
+        true
+    }
+}
+
+// Medio texel hacia dentro para que el filtrado lineal no lea el padding en los bordes
+fn region(rect: &AtlasRect, atlas_size: u32) -> AtlasRegion {
+    let texel = 1.0 / atlas_size as f32;
+    AtlasRegion {
+        uv_min: [(rect.x as f32 + 0.5) * texel, (rect.y as f32 + 0.5) * texel],
+        uv_max: [(rect.right() as f32 - 0.5) * texel, (rect.bottom() as f32 - 0.5) * texel],
+        size: [rect.width as f32, rect.height as f32],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pack_rects(width: u32, height: u32, padding: u32, sizes: &[(u32, u32)]) -> Option<Vec<AtlasRect>> {
+        pack_into(&mut AtlasPacker::new(width, height, padding), sizes)
+    }
+
+    fn intersects(a: &AtlasRect, b: &AtlasRect) -> bool {
+        a.x < b.right() && b.x < a.right() && a.y < b.bottom() && b.y < a.bottom()
+    }
+
+    fn assert_valid(width: u32, height: u32, padding: u32, rects: &[AtlasRect]) {
+        for (i, a) in rects.iter().enumerate() {
+            assert!(a.x >= padding && a.y >= padding, "{:?} touches the border", a);
+            assert!(a.right() + padding <= width && a.bottom() + padding <= height, "{:?} is outside the atlas", a);
+            for b in &rects[i + 1..] {
+                let padded = AtlasRect {
+                    x: a.x - padding,
+                    y: a.y - padding,
+                    width: a.width + padding * 2,
+                    height: a.height + padding * 2,
+                };
+                assert!(!intersects(&padded, b), "{:?} and {:?} overlap", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn packs_in_input_order() {
+        let sizes = [(10, 20), (30, 5), (8, 8)];
+        let rects = pack_rects(64, 64, 0, &sizes).unwrap();
+        for (rect, &(w, h)) in rects.iter().zip(&sizes) {
+            assert_eq!((rect.width, rect.height), (w, h));
+        }
+        assert_valid(64, 64, 0, &rects);
+    }
+
+    #[test]
+    fn many_rects_dont_overlap() {
+        // Tamaños pseudoaleatorios pero reproducibles
+        let sizes = (0..200u32)
+            .map(|i| (4 + (i * 7919) % 29, 4 + (i * 104_729) % 23))
+            .collect::<Vec<_>>();
+        let rects = pack_rects(512, 512, 1, &sizes).unwrap();
+        assert_valid(512, 512, 1, &rects);
+    }
+
+    #[test]
+    fn fills_exactly() {
+        // 16 cuadrados de 16x16 llenan justo un atlas de 64x64 sin padding
+        let sizes = vec![(16, 16); 16];
+        let rects = pack_rects(64, 64, 0, &sizes).unwrap();
+        assert_valid(64, 64, 0, &rects);
+        assert!(pack_rects(64, 64, 0, &[(16, 16); 17]).is_none());
+    }
+
+    #[test]
+    fn reuses_shelves_with_room() {
+        let mut packer = AtlasPacker::new(100, 100, 0);
+        let tall = packer.insert(50, 40).unwrap();
+        let short = packer.insert(20, 10).unwrap();
+        // El bajo cabe en la estanteria del alto, no abre una nueva
+        assert_eq!((short.x, short.y), (50, 0));
+        assert_eq!(tall.y, 0);
+    }
+
+    #[test]
+    fn too_big_doesnt_fit() {
+        let mut packer = AtlasPacker::new(32, 32, 1);
+        assert!(packer.insert(31, 4).is_none());
+        assert!(packer.insert(4, 31).is_none());
+        assert!(packer.insert(30, 30).is_some());
+        assert!(packer.insert(1, 1).is_none());
+    }
+}
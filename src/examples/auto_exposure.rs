@@ -0,0 +1,297 @@
+//## Exposicion automatica: cada frame se mide la luminancia de la escena HDR y se calcula la exposicion que la lleva
+//## al gris medio, como hace una camara (o el ojo, que tarda un poco en acostumbrarse al cambiar de luz).
+//##   1. luminance_histogram.comp: histograma de log2(luminancia) de todos los pixels, en HISTOGRAM_BINS bins.
+//##   2. luminance_average.comp: la media del histograma (sin los pixels negros), que se acerca a la del frame
+//##      anterior segun adaptation_rate, y la exposicion key / media.
+//## El resultado queda en exposure_buffer, que el tonemapping lee sin pasar por la CPU:
+//##
+//## layout(set=N, binding=0) uniform Exposure { float u_luminance; float u_exposure; };
+//##
+//## La escena se pasa a compute() en cada frame, asi puede ser una textura temporal de un RenderGraph.
+//##
+//## draw_histogram() pinta el histograma en una esquina, con una linea en la luminancia media.
+
+use wgpu::util::DeviceExt;
+
+use crate::examples::{storage_buffer_entry, uniform_buffer_entry, BlendMode, ComputeKernel, PipelineBuilder};
+
+pub const HISTOGRAM_BINS: u32 = 256;
+// Tamaño de la vista del histograma en pixels
+const HISTOGRAM_VIEW_SIZE: [f32; 2] = [384.0, 128.0];
+const HISTOGRAM_VIEW_MARGIN: f32 = 16.0;
+
+#[derive(Copy, Clone, Debug)]
+pub struct AutoExposureParams {
+    // Rango de log2(luminancia) que cubre el histograma, lo que se sale cuenta en el primer o el ultimo bin
+    pub min_log_luminance: f32,
+    pub max_log_luminance: f32,
+    // Lo rapido que se adapta: con 1.0 recorre el 63% del cambio en un segundo
+    pub adaptation_rate: f32,
+    // A que luminancia se lleva la media. 0.18 es el gris medio de fotografia
+    pub key: f32,
+}
+
+impl Default for AutoExposureParams {
+    fn default() -> Self {
+        Self {
+            min_log_luminance: -8.0,
+            max_log_luminance: 6.0,
+            adaptation_rate: 1.5,
+            key: 0.18,
+        }
+    }
+}
+
+// El bloque Params de los shaders
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+struct AutoExposureData {
+    min_log_luminance: f32,
+    log_luminance_range: f32,
+    adaptation_rate: f32,
+    key: f32,
+    time_delta: f32,
+    num_pixels: u32,
+    _padding: [u32; 2],
+}
+
+unsafe impl bytemuck::Pod for AutoExposureData {}
+unsafe impl bytemuck::Zeroable for AutoExposureData {}
+
+pub struct AutoExposure {
+    pub params: AutoExposureParams,
+    params_buffer: wgpu::Buffer,
+    histogram_buffer: wgpu::Buffer,
+    pub exposure_buffer: wgpu::Buffer,
+    histogram_kernel: ComputeKernel,
+    average_kernel: ComputeKernel,
+    scene_sampler: wgpu::Sampler,
+    average_bind_group: wgpu::BindGroup,
+    scene_size: [u32; 2],
+    pub exposure_bind_group_layout: wgpu::BindGroupLayout,
+    pub exposure_bind_group: wgpu::BindGroup,
+    view_pipeline: wgpu::RenderPipeline,
+    view_bind_group: wgpu::BindGroup,
+}
+
+impl AutoExposure {
+    // scene_size es el tamaño de la textura HDR que se mide y output_format el formato en el que se dibuja el
+    // histograma
+    pub fn new(device: &wgpu::Device, scene_size: [u32; 2], output_format: wgpu::TextureFormat) -> Self {
+        let params = AutoExposureParams::default();
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Auto Exposure Params"),
+            size: std::mem::size_of::<AutoExposureData>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let histogram_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Luminance Histogram"),
+            contents: bytemuck::cast_slice(&[0u32; HISTOGRAM_BINS as usize]),
+            usage: wgpu::BufferUsage::STORAGE | wgpu::BufferUsage::COPY_DST,
+        });
+        // Empieza con luminancia 1.0: los primeros frames se adaptan desde ahi
+        let exposure_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Exposure Buffer"),
+            contents: bytemuck::cast_slice(&[1.0f32, params.key, 0.0, 0.0]),
+            usage: wgpu::BufferUsage::STORAGE | wgpu::BufferUsage::UNIFORM,
+        });
+
+        let scene_entries = [
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStage::COMPUTE,
+                ty: wgpu::BindingType::SampledTexture {
+                    multisampled: false,
+                    dimension: wgpu::TextureViewDimension::D2,
+                    component_type: wgpu::TextureComponentType::Float,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStage::COMPUTE,
+                ty: wgpu::BindingType::Sampler { comparison: false },
+                count: None,
+            },
+            uniform_buffer_entry(2),
+            storage_buffer_entry(3, false),
+        ];
+        let histogram_module =
+            device.create_shader_module(wgpu::include_spirv!("shaders/luminance_histogram.comp.spv"));
+        let histogram_kernel = ComputeKernel::new(
+            device,
+            &histogram_module,
+            &scene_entries,
+            [16, 16, 1],
+            Some("Luminance Histogram"),
+        );
+        let average_module = device.create_shader_module(wgpu::include_spirv!("shaders/luminance_average.comp.spv"));
+        let average_kernel = ComputeKernel::new(
+            device,
+            &average_module,
+            &[
+                uniform_buffer_entry(0),
+                storage_buffer_entry(1, true),
+                storage_buffer_entry(2, false),
+            ],
+            [HISTOGRAM_BINS, 1, 1],
+            Some("Luminance Average"),
+        );
+        // texelFetch no filtra, pero en Vulkan GLSL la textura siempre va con un sampler
+        let scene_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Luminance Sampler"),
+            ..Default::default()
+        });
+        let average_bind_group = average_kernel.create_bind_group(
+            device,
+            &[
+                wgpu::BindingResource::Buffer(params_buffer.slice(..)),
+                wgpu::BindingResource::Buffer(histogram_buffer.slice(..)),
+                wgpu::BindingResource::Buffer(exposure_buffer.slice(..)),
+            ],
+            Some("Luminance Average"),
+        );
+
+        let exposure_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStage::FRAGMENT,
+                ty: wgpu::BindingType::UniformBuffer {
+                    dynamic: false,
+                    min_binding_size: wgpu::BufferSize::new(16),
+                },
+                count: None,
+            }],
+            label: Some("exposure_bind_group_layout"),
+        });
+        let exposure_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &exposure_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(exposure_buffer.slice(..)),
+            }],
+            label: Some("exposure_bind_group"),
+        });
+
+        // La vista del histograma lee los mismos buffers desde el fragment shader
+        let view_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ..uniform_buffer_entry(0)
+                },
+                wgpu::BindGroupLayoutEntry {
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ..storage_buffer_entry(1, true)
+                },
+                wgpu::BindGroupLayoutEntry {
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ..uniform_buffer_entry(2)
+                },
+            ],
+            label: Some("histogram_view_bind_group_layout"),
+        });
+        let view_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &view_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(params_buffer.slice(..)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Buffer(histogram_buffer.slice(..)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Buffer(exposure_buffer.slice(..)),
+                },
+            ],
+            label: Some("histogram_view_bind_group"),
+        });
+        let vs_module = device.create_shader_module(wgpu::include_spirv!("shaders/fullscreen.vert.spv"));
+        let fs_module = device.create_shader_module(wgpu::include_spirv!("shaders/luminance_histogram.frag.spv"));
+        let view_pipeline = PipelineBuilder::new(&vs_module, output_format)
+            .label("Histogram View Pipeline")
+            .fragment_shader(&fs_module)
+            .bind_group_layouts(&[&view_layout])
+            .blend(BlendMode::Alpha)
+            .build(device);
+
+        Self {
+            params,
+            params_buffer,
+            histogram_buffer,
+            exposure_buffer,
+            histogram_kernel,
+            average_kernel,
+            scene_sampler,
+            average_bind_group,
+            scene_size,
+            exposure_bind_group_layout,
+            exposure_bind_group,
+            view_pipeline,
+            view_bind_group,
+        }
+    }
+
+    // Cuando cambia el tamaño de la escena (al cambiar el tamaño de la ventana)
+    pub fn set_scene_size(&mut self, scene_size: [u32; 2]) {
+        self.scene_size = scene_size;
+    }
+
+    // Una vez por frame. El histograma se vacia aqui, write_buffer se ejecuta antes que los comandos del frame
+    pub fn update(&self, queue: &wgpu::Queue, time_delta: f32) {
+        let params = &self.params;
+        let data = AutoExposureData {
+            min_log_luminance: params.min_log_luminance,
+            log_luminance_range: (params.max_log_luminance - params.min_log_luminance).max(0.01),
+            adaptation_rate: params.adaptation_rate,
+            key: params.key,
+            time_delta,
+            num_pixels: self.scene_size[0] * self.scene_size[1],
+            _padding: [0; 2],
+        };
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&[data]));
+        queue.write_buffer(
+            &self.histogram_buffer,
+            0,
+            bytemuck::cast_slice(&[0u32; HISTOGRAM_BINS as usize]),
+        );
+    }
+
+    // Despues de dibujar la escena y antes del tonemapping, y solo una vez por frame: cada vez suma la escena al
+    // histograma y da otro paso de adaptacion
+    pub fn compute(&self, device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder, scene: &wgpu::TextureView) {
+        let histogram_bind_group = self.histogram_kernel.create_bind_group(
+            device,
+            &[
+                wgpu::BindingResource::TextureView(scene),
+                wgpu::BindingResource::Sampler(&self.scene_sampler),
+                wgpu::BindingResource::Buffer(self.params_buffer.slice(..)),
+                wgpu::BindingResource::Buffer(self.histogram_buffer.slice(..)),
+            ],
+            Some("Luminance Histogram"),
+        );
+        let mut compute_pass = encoder.begin_compute_pass();
+        let [width, height] = self.scene_size;
+        self.histogram_kernel
+            .dispatch(&mut compute_pass, &histogram_bind_group, [width, height, 1]);
+        self.average_kernel
+            .dispatch(&mut compute_pass, &self.average_bind_group, [HISTOGRAM_BINS, 1, 1]);
+    }
+
+    // En la esquina de abajo a la izquierda de un render pass sobre la pantalla (de output_size pixels)
+    pub fn draw_histogram<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, output_size: [u32; 2]) {
+        let [width, height] = HISTOGRAM_VIEW_SIZE;
+        let y = output_size[1] as f32 - height - HISTOGRAM_VIEW_MARGIN;
+        if y < 0.0 || output_size[0] as f32 <= width + HISTOGRAM_VIEW_MARGIN {
+            return;
+        }
+        render_pass.set_viewport(HISTOGRAM_VIEW_MARGIN, y, width, height, 0.0, 1.0);
+        render_pass.set_pipeline(&self.view_pipeline);
+        render_pass.set_bind_group(0, &self.view_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
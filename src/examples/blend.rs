@@ -0,0 +1,127 @@
+//## Modos de mezcla (blending). El color final del pixel es
+//##     color_blend.operation(src * src_factor, dst * dst_factor)
+//## donde src es lo que devuelve el fragment shader y dst lo que ya habia en el color attachment.
+//## El modo se fija al crear el pipeline (en el ColorStateDescriptor), asi que cada modo necesita su propio pipeline.
+//##
+//## Con blending el orden importa: los objetos transparentes se dibujan despues de los opacos y de atras hacia delante
+//## (sort_back_to_front), si no los de delante tapan a los de detras sin mezclarse.
+
+use std::cmp::Ordering;
+
+use serde::Deserialize;
+
+// Deserialize para poder elegirlo en los ficheros de materiales (ver material.rs)
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Deserialize)]
+pub enum BlendMode {
+    // Sin mezcla, el alpha se ignora
+    Replace,
+    // Transparencia normal con alpha sin premultiplicar: src * a + dst * (1 - a)
+    Alpha,
+    // El shader devuelve el color ya multiplicado por alpha: src + dst * (1 - a)
+    PremultipliedAlpha,
+    // Suma luz (fuego, particulas, brillos): src * a + dst
+    Additive,
+    // Oscurece (sombras, tintes): dst * src, interpolado por alpha. Espera el color premultiplicado
+    Multiply,
+}
+
+impl BlendMode {
+    pub const ALL: [BlendMode; 5] = [
+        BlendMode::Replace,
+        BlendMode::Alpha,
+        BlendMode::PremultipliedAlpha,
+        BlendMode::Additive,
+        BlendMode::Multiply,
+    ];
+
+    pub fn color_blend(self) -> wgpu::BlendDescriptor {
+        let (src_factor, dst_factor) = match self {
+            BlendMode::Replace => return wgpu::BlendDescriptor::REPLACE,
+            BlendMode::Alpha => (wgpu::BlendFactor::SrcAlpha, wgpu::BlendFactor::OneMinusSrcAlpha),
+            BlendMode::PremultipliedAlpha => (wgpu::BlendFactor::One, wgpu::BlendFactor::OneMinusSrcAlpha),
+            BlendMode::Additive => (wgpu::BlendFactor::SrcAlpha, wgpu::BlendFactor::One),
+            BlendMode::Multiply => (wgpu::BlendFactor::DstColor, wgpu::BlendFactor::OneMinusSrcAlpha),
+        };
+        wgpu::BlendDescriptor {
+            src_factor,
+            dst_factor,
+            operation: wgpu::BlendOperation::Add,
+        }
+    }
+
+    // El alpha del destino se acumula como "cobertura", salvo en Replace y Multiply que lo dejan como estaba
+    pub fn alpha_blend(self) -> wgpu::BlendDescriptor {
+        let (src_factor, dst_factor) = match self {
+            BlendMode::Replace => return wgpu::BlendDescriptor::REPLACE,
+            BlendMode::Alpha | BlendMode::PremultipliedAlpha => {
+                (wgpu::BlendFactor::One, wgpu::BlendFactor::OneMinusSrcAlpha)
+            }
+            BlendMode::Additive => (wgpu::BlendFactor::One, wgpu::BlendFactor::One),
+            BlendMode::Multiply => (wgpu::BlendFactor::Zero, wgpu::BlendFactor::One),
+        };
+        wgpu::BlendDescriptor {
+            src_factor,
+            dst_factor,
+            operation: wgpu::BlendOperation::Add,
+        }
+    }
+
+    pub fn color_state(self, format: wgpu::TextureFormat) -> wgpu::ColorStateDescriptor {
+        wgpu::ColorStateDescriptor {
+            format,
+            color_blend: self.color_blend(),
+            alpha_blend: self.alpha_blend(),
+            write_mask: wgpu::ColorWrite::ALL,
+        }
+    }
+
+    // Si el fragment shader tiene que devolver rgb * a para que la formula del modo sea correcta
+    pub fn premultiplied_source(self) -> bool {
+        matches!(self, BlendMode::PremultipliedAlpha | BlendMode::Multiply)
+    }
+
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&mode| mode == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+// Ordena los objetos transparentes de atras hacia delante, `depth` es la distancia a la camara (mayor = mas lejos)
+pub fn sort_back_to_front<T, F: Fn(&T) -> f32>(items: &mut [T], depth: F) {
+    items.sort_by(|a, b| depth(b).partial_cmp(&depth(a)).unwrap_or(Ordering::Equal));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn farthest_items_come_first() {
+        let mut items = vec![("a", 1.0), ("b", 5.0), ("c", 3.0), ("d", 0.5)];
+        sort_back_to_front(&mut items, |&(_, depth)| depth);
+        let names = items.iter().map(|&(name, _)| name).collect::<Vec<_>>();
+        assert_eq!(names, ["b", "c", "a", "d"]);
+    }
+
+    #[test]
+    fn equal_distances_keep_their_order() {
+        let mut items = vec![("a", 2.0), ("b", 4.0), ("c", 2.0), ("d", 4.0), ("e", 2.0)];
+        sort_back_to_front(&mut items, |&(_, depth)| depth);
+        let names = items.iter().map(|&(name, _)| name).collect::<Vec<_>>();
+        assert_eq!(names, ["b", "d", "a", "c", "e"]);
+    }
+
+    #[test]
+    fn next_cycles_through_every_mode() {
+        let mut mode = BlendMode::Replace;
+        let mut seen = Vec::new();
+        for _ in 0..BlendMode::ALL.len() {
+            seen.push(mode);
+            mode = mode.next();
+        }
+        assert_eq!(seen, BlendMode::ALL);
+        // Despues del ultimo vuelve al primero
+        assert_eq!(mode, BlendMode::Replace);
+        assert_eq!(BlendMode::Multiply.next(), BlendMode::Replace);
+    }
+}
@@ -0,0 +1,382 @@
+//## Volumenes envolventes y frustum culling: antes de grabar el render pass se descartan en la CPU los objetos que
+//## quedan fuera de la vista de la camara, en vez de mandar todos los draw a la GPU y que el clipping los tire uno a
+//## uno despues del vertex shader.
+//##
+//## Cada malla guarda sus Bounds, calculados una vez a partir de los vertices: una caja alineada con los ejes (Aabb) y
+//## una esfera (BoundingSphere). Cada frame se sacan los seis planos del frustum de la matriz view_proj
+//## (Frustum::from_matrix) y cada objeto se comprueba con sus Bounds llevados al mundo por su matriz model. La esfera
+//## es la prueba barata y descarta casi todo lo que esta lejos; la caja solo se mira si la esfera toca el frustum.
+//##
+//## Las pruebas son conservadoras: un objeto puede darse por visible estando fuera (cerca de una esquina del
+//## frustum), pero nunca al reves.
+
+use cgmath::{EuclideanSpace, InnerSpace, Matrix, Matrix4, Point3, Transform, Vector3, Vector4};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Point3<f32>,
+    pub max: Point3<f32>,
+}
+
+impl Aabb {
+    // None si no hay puntos
+    pub fn from_points<I: IntoIterator<Item = [f32; 3]>>(points: I) -> Option<Self> {
+        let mut points = points.into_iter().map(Point3::from);
+        let first = points.next()?;
+        Some(points.fold(Self { min: first, max: first }, |aabb, point| Self {
+            min: Point3::new(
+                aabb.min.x.min(point.x),
+                aabb.min.y.min(point.y),
+                aabb.min.z.min(point.z),
+            ),
+            max: Point3::new(
+                aabb.max.x.max(point.x),
+                aabb.max.y.max(point.y),
+                aabb.max.z.max(point.z),
+            ),
+        }))
+    }
+
+    pub fn center(&self) -> Point3<f32> {
+        self.min.midpoint(self.max)
+    }
+
+    // La mitad del tamaño en cada eje
+    pub fn half_extents(&self) -> Vector3<f32> {
+        (self.max - self.min) * 0.5
+    }
+
+    // La caja alineada con los ejes que contiene a esta caja transformada (girada ya no lo estaria). Cada eje de la
+    // caja nueva suma lo que aportan los tres ejes de la original, en valor absoluto
+    pub fn transform(&self, matrix: &Matrix4<f32>) -> Self {
+        let center = matrix.transform_point(self.center());
+        let half = self.half_extents();
+        let extent = |row: usize| {
+            let row = matrix.row(row);
+            row.x.abs() * half.x + row.y.abs() * half.y + row.z.abs() * half.z
+        };
+        let half = Vector3::new(extent(0), extent(1), extent(2));
+        Self {
+            min: center - half,
+            max: center + half,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BoundingSphere {
+    pub center: Point3<f32>,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    // Centrada en la caja de los puntos y con el radio justo para llegar al mas lejano. No es la esfera minima, pero
+    // se calcula en dos pasadas y para mallas normales se queda cerca
+    pub fn from_points<I: IntoIterator<Item = [f32; 3]> + Clone>(points: I) -> Option<Self> {
+        let center = Aabb::from_points(points.clone())?.center();
+        let radius = points
+            .into_iter()
+            .map(|point| (Point3::from(point) - center).magnitude())
+            .fold(0.0, f32::max);
+        Some(Self { center, radius })
+    }
+
+    // Con escalas distintas en cada eje la esfera deja de serlo, asi que el radio crece con la escala mayor
+    pub fn transform(&self, matrix: &Matrix4<f32>) -> Self {
+        let scale = matrix
+            .x
+            .truncate()
+            .magnitude()
+            .max(matrix.y.truncate().magnitude())
+            .max(matrix.z.truncate().magnitude());
+        Self {
+            center: matrix.transform_point(self.center),
+            radius: self.radius * scale,
+        }
+    }
+}
+
+// Lo que se guarda de cada malla
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Bounds {
+    pub aabb: Aabb,
+    pub sphere: BoundingSphere,
+}
+
+impl Bounds {
+    // Por ejemplo Bounds::from_points(vertices.iter().map(|vertex| vertex.position))
+    pub fn from_points<I: IntoIterator<Item = [f32; 3]> + Clone>(points: I) -> Option<Self> {
+        Some(Self {
+            aabb: Aabb::from_points(points.clone())?,
+            sphere: BoundingSphere::from_points(points)?,
+        })
+    }
+
+    // Si la malla con esta matriz model puede verse
+    pub fn is_visible(&self, frustum: &Frustum, model: &Matrix4<f32>) -> bool {
+        frustum.intersects_sphere(&self.sphere.transform(model)) && frustum.intersects_aabb(&self.aabb.transform(model))
+    }
+}
+
+// Los puntos p con normal · p + distance >= 0 estan en el lado de dentro
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Plane {
+    pub normal: Vector3<f32>,
+    pub distance: f32,
+}
+
+impl Plane {
+    // A partir de los coeficientes (a, b, c, d) de ax + by + cz + d = 0. Se normaliza para que signed_distance de la
+    // distancia de verdad
+    pub fn from_coefficients(coefficients: Vector4<f32>) -> Self {
+        let normal = coefficients.truncate();
+        let length = normal.magnitude();
+        Self {
+            normal: normal / length,
+            distance: coefficients.w / length,
+        }
+    }
+
+    // Positiva dentro, negativa fuera
+    pub fn signed_distance(&self, point: Point3<f32>) -> f32 {
+        self.normal.dot(point.to_vec()) + self.distance
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Frustum {
+    // Izquierda, derecha, abajo, arriba, near y far
+    pub planes: [Plane; 6],
+}
+
+impl Frustum {
+    // Los planos salen directamente de las filas de la matriz (Gribb y Hartmann): un punto esta dentro si en clip
+    // space -w <= x <= w, -w <= y <= w y, como en wgpu, 0 <= z <= w. Cada desigualdad es un plano en el mundo (o en
+    // el espacio del que parta la matriz)
+    pub fn from_matrix(view_proj: &Matrix4<f32>) -> Self {
+        let row = |i| view_proj.row(i);
+        let plane = Plane::from_coefficients;
+        Self {
+            planes: [
+                plane(row(3) + row(0)),
+                plane(row(3) - row(0)),
+                plane(row(3) + row(1)),
+                plane(row(3) - row(1)),
+                plane(row(2)),
+                plane(row(3) - row(2)),
+            ],
+        }
+    }
+
+    // Fuera solo si la esfera esta entera detras de algun plano
+    pub fn intersects_sphere(&self, sphere: &BoundingSphere) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.signed_distance(sphere.center) >= -sphere.radius)
+    }
+
+    // Como con la esfera, pero con el "radio" de la caja en la direccion de la normal de cada plano
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        let center = aabb.center();
+        let half = aabb.half_extents();
+        self.planes.iter().all(|plane| {
+            let n = plane.normal;
+            let radius = half.x * n.x.abs() + half.y * n.y.abs() + half.z * n.z.abs();
+            plane.signed_distance(center) >= -radius
+        })
+    }
+}
+
+// Cuantos objetos se han dibujado y cuantos se han descartado en el frame
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CullingStats {
+    pub visible: u32,
+    pub culled: u32,
+}
+
+impl CullingStats {
+    // Devuelve visible, para usarlo en un filter
+    pub fn record(&mut self, visible: bool) -> bool {
+        if visible {
+            self.visible += 1;
+        } else {
+            self.culled += 1;
+        }
+        visible
+    }
+
+    pub fn total(&self) -> u32 {
+        self.visible + self.culled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cgmath::{Deg, Rad};
+
+    use crate::examples::OPENGL_TO_WGPU_MATRIX;
+
+    const CUBE: [[f32; 3]; 8] = [
+        [-1.0, -1.0, -1.0],
+        [1.0, -1.0, -1.0],
+        [-1.0, 1.0, -1.0],
+        [1.0, 1.0, -1.0],
+        [-1.0, -1.0, 1.0],
+        [1.0, -1.0, 1.0],
+        [-1.0, 1.0, 1.0],
+        [1.0, 1.0, 1.0],
+    ];
+
+    fn assert_close(a: Point3<f32>, b: Point3<f32>) {
+        assert!((a - b).magnitude() < 1e-4, "{:?} != {:?}", a, b);
+    }
+
+    // Camara en el origen mirando hacia -z, con 90 grados de vision, near 1 y far 100
+    fn camera_frustum() -> Frustum {
+        let view = Matrix4::look_at(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, -1.0),
+            Vector3::unit_y(),
+        );
+        let projection = OPENGL_TO_WGPU_MATRIX * cgmath::perspective(Deg(90.0), 1.0, 1.0, 100.0);
+        Frustum::from_matrix(&(projection * view))
+    }
+
+    fn sphere(center: [f32; 3], radius: f32) -> BoundingSphere {
+        BoundingSphere {
+            center: center.into(),
+            radius,
+        }
+    }
+
+    fn aabb(min: [f32; 3], max: [f32; 3]) -> Aabb {
+        Aabb {
+            min: min.into(),
+            max: max.into(),
+        }
+    }
+
+    // Un punto es una esfera de radio cero
+    fn contains(frustum: &Frustum, point: Point3<f32>) -> bool {
+        frustum.intersects_sphere(&sphere(point.into(), 0.0))
+    }
+
+    #[test]
+    fn aabb_from_points() {
+        let points = [[1.0, -2.0, 3.0], [-4.0, 5.0, 0.5], [0.0, 0.0, -6.0]];
+        assert_eq!(
+            Aabb::from_points(points.iter().copied()),
+            Some(aabb([-4.0, -2.0, -6.0], [1.0, 5.0, 3.0]))
+        );
+        assert_eq!(Aabb::from_points(Vec::new()), None);
+    }
+
+    #[test]
+    fn aabb_transform_contains_rotated_box() {
+        let unit = Aabb::from_points(CUBE.iter().copied()).unwrap();
+        let translated = unit.transform(&Matrix4::from_translation(Vector3::new(5.0, 0.0, -2.0)));
+        assert_close(translated.min, Point3::new(4.0, -1.0, -3.0));
+        assert_close(translated.max, Point3::new(6.0, 1.0, -1.0));
+
+        // Girada 45 grados alrededor de y la caja ocupa la diagonal del cuadrado en x y z
+        let rotated = unit.transform(&Matrix4::from_angle_y(Rad(std::f32::consts::FRAC_PI_4)));
+        let diagonal = 2.0f32.sqrt();
+        assert_close(rotated.min, Point3::new(-diagonal, -1.0, -diagonal));
+        assert_close(rotated.max, Point3::new(diagonal, 1.0, diagonal));
+
+        let scaled = unit.transform(&Matrix4::from_nonuniform_scale(2.0, 3.0, 0.5));
+        assert_close(scaled.max, Point3::new(2.0, 3.0, 0.5));
+    }
+
+    #[test]
+    fn sphere_contains_all_points() {
+        let points = [[2.0, 0.0, 0.0], [-1.0, 3.0, 1.0], [0.5, -2.0, 4.0], [0.0, 0.0, -1.0]];
+        let bounds = BoundingSphere::from_points(points.iter().copied()).unwrap();
+        for &point in &points {
+            assert!((Point3::from(point) - bounds.center).magnitude() <= bounds.radius + 1e-5);
+        }
+        assert_eq!(BoundingSphere::from_points(Vec::new()), None);
+    }
+
+    #[test]
+    fn sphere_transform_uses_largest_scale() {
+        let unit = sphere([0.0, 0.0, 0.0], 1.0);
+        let model =
+            Matrix4::from_translation(Vector3::new(1.0, 2.0, 3.0)) * Matrix4::from_nonuniform_scale(1.0, 4.0, 2.0);
+        let transformed = unit.transform(&model);
+        assert_close(transformed.center, Point3::new(1.0, 2.0, 3.0));
+        assert!((transformed.radius - 4.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn planes_point_inwards() {
+        let frustum = camera_frustum();
+        for plane in &frustum.planes {
+            assert!((plane.normal.magnitude() - 1.0).abs() < 1e-5);
+            assert!(plane.signed_distance(Point3::new(0.0, 0.0, -10.0)) > 0.0, "{:?}", plane);
+        }
+        // Near y far a la distancia justa
+        assert!((frustum.planes[4].signed_distance(Point3::new(0.0, 0.0, -3.0)) - 2.0).abs() < 1e-3);
+        assert!((frustum.planes[5].signed_distance(Point3::new(0.0, 0.0, -90.0)) - 10.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn frustum_contains_points() {
+        let frustum = camera_frustum();
+        assert!(contains(&frustum, Point3::new(0.0, 0.0, -10.0)));
+        assert!(contains(&frustum, Point3::new(9.0, -9.0, -10.0)));
+        // Detras, demasiado cerca, demasiado lejos y a los lados
+        assert!(!contains(&frustum, Point3::new(0.0, 0.0, 10.0)));
+        assert!(!contains(&frustum, Point3::new(0.0, 0.0, -0.5)));
+        assert!(!contains(&frustum, Point3::new(0.0, 0.0, -101.0)));
+        assert!(!contains(&frustum, Point3::new(11.0, 0.0, -10.0)));
+        assert!(!contains(&frustum, Point3::new(0.0, -11.0, -10.0)));
+    }
+
+    #[test]
+    fn spheres_are_culled_only_when_fully_outside() {
+        let frustum = camera_frustum();
+        assert!(frustum.intersects_sphere(&sphere([0.0, 0.0, -10.0], 1.0)));
+        // El centro esta fuera pero la esfera asoma por el lado derecho
+        assert!(frustum.intersects_sphere(&sphere([11.0, 0.0, -10.0], 1.0)));
+        assert!(!frustum.intersects_sphere(&sphere([13.0, 0.0, -10.0], 1.0)));
+        assert!(!frustum.intersects_sphere(&sphere([0.0, 0.0, 5.0], 1.0)));
+        assert!(!frustum.intersects_sphere(&sphere([0.0, 0.0, -105.0], 2.0)));
+    }
+
+    #[test]
+    fn boxes_are_culled_only_when_fully_outside() {
+        let frustum = camera_frustum();
+        assert!(frustum.intersects_aabb(&aabb([-1.0, -1.0, -11.0], [1.0, 1.0, -9.0])));
+        // Una caja enorme que contiene todo el frustum
+        assert!(frustum.intersects_aabb(&aabb([-500.0; 3], [500.0; 3])));
+        assert!(frustum.intersects_aabb(&aabb([9.0, -1.0, -11.0], [12.0, 1.0, -9.0])));
+        assert!(!frustum.intersects_aabb(&aabb([12.0, -1.0, -11.0], [14.0, 1.0, -9.0])));
+        assert!(!frustum.intersects_aabb(&aabb([-1.0, -1.0, 1.0], [1.0, 1.0, 3.0])));
+    }
+
+    #[test]
+    fn bounds_follow_the_model_matrix() {
+        let frustum = camera_frustum();
+        let bounds = Bounds::from_points(CUBE.iter().copied()).unwrap();
+        assert!(bounds.is_visible(&frustum, &Matrix4::from_translation(Vector3::new(0.0, 0.0, -10.0))));
+        assert!(!bounds.is_visible(&frustum, &Matrix4::from_translation(Vector3::new(0.0, 0.0, 10.0))));
+        // Escalada llega a verse desde fuera del frustum
+        let model = Matrix4::from_translation(Vector3::new(14.0, 0.0, -10.0)) * Matrix4::from_scale(3.0);
+        assert!(bounds.is_visible(&frustum, &model));
+    }
+
+    #[test]
+    fn stats_count_visible_and_culled() {
+        let mut stats = CullingStats::default();
+        let visible = [true, false, true, true]
+            .iter()
+            .filter(|&&visible| stats.record(visible))
+            .count();
+        assert_eq!(visible, 3);
+        assert_eq!(stats, CullingStats { visible: 3, culled: 1 });
+        assert_eq!(stats.total(), 4);
+    }
+}
@@ -0,0 +1,132 @@
+//## Camara en perspectiva: la matriz view (desde donde y hacia donde se mira) por la projection (el frustum) lleva los
+//## vertices del mundo al clip space. Se sube en un uniform con la posicion de la camara, que hace falta para la luz
+//## especular:
+//##
+//## layout(set=N, binding=0) uniform Camera {
+//##     mat4 u_view_proj;
+//##     vec4 u_view_position;   // w = 1
+//## };
+
+use cgmath::{Matrix4, Point3, Vector3};
+use wgpu::util::DeviceExt;
+
+// cgmath sigue el convenio de OpenGL, con la z del clip space entre -1 y 1. En wgpu (como en Vulkan, Metal y DX) va
+// de 0 a 1, asi que la reescalamos despues de la proyeccion
+#[rustfmt::skip]
+pub const OPENGL_TO_WGPU_MATRIX: Matrix4<f32> = Matrix4::new(
+    1.0, 0.0, 0.0, 0.0,
+    0.0, 1.0, 0.0, 0.0,
+    0.0, 0.0, 0.5, 0.0,
+    0.0, 0.0, 0.5, 1.0,
+);
+
+pub struct Camera {
+    pub eye: Point3<f32>,
+    pub target: Point3<f32>,
+    pub up: Vector3<f32>,
+    pub aspect: f32,
+    // Angulo de vision vertical en grados
+    pub fovy: f32,
+    pub znear: f32,
+    pub zfar: f32,
+}
+
+impl Camera {
+    pub fn new(eye: Point3<f32>, target: Point3<f32>, size: winit::dpi::PhysicalSize<u32>) -> Self {
+        Self {
+            eye,
+            target,
+            up: Vector3::unit_y(),
+            aspect: size.width as f32 / size.height as f32,
+            fovy: 45.0,
+            znear: 0.1,
+            zfar: 100.0,
+        }
+    }
+
+    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.aspect = new_size.width as f32 / new_size.height as f32;
+    }
+
+    pub fn view(&self) -> Matrix4<f32> {
+        Matrix4::look_at(self.eye, self.target, self.up)
+    }
+
+    // Ya con la z reescalada para wgpu
+    pub fn projection(&self) -> Matrix4<f32> {
+        OPENGL_TO_WGPU_MATRIX * cgmath::perspective(cgmath::Deg(self.fovy), self.aspect, self.znear, self.zfar)
+    }
+
+    pub fn view_projection(&self) -> Matrix4<f32> {
+        self.projection() * self.view()
+    }
+}
+
+// Tiene que coincidir con el bloque Camera de los shaders (std140)
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct CameraData {
+    pub view_proj: [[f32; 4]; 4],
+    pub view_position: [f32; 4],
+}
+
+unsafe impl bytemuck::Pod for CameraData {}
+unsafe impl bytemuck::Zeroable for CameraData {}
+
+impl CameraData {
+    pub fn new(camera: &Camera) -> Self {
+        Self {
+            view_proj: camera.view_projection().into(),
+            view_position: camera.eye.to_homogeneous().into(),
+        }
+    }
+}
+
+// Como GlobalsUniform: el buffer y el bind group de la camara. update() sube la camara una vez por frame
+pub struct CameraUniform {
+    pub buffer: wgpu::Buffer,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl CameraUniform {
+    pub fn new(device: &wgpu::Device, camera: &Camera) -> Self {
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::cast_slice(&[CameraData::new(camera)]),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStage::VERTEX | wgpu::ShaderStage::FRAGMENT,
+                ty: wgpu::BindingType::UniformBuffer {
+                    dynamic: false,
+                    min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<CameraData>() as _),
+                },
+                count: None,
+            }],
+            label: Some("camera_bind_group_layout"),
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(buffer.slice(..)),
+            }],
+            label: Some("camera_bind_group"),
+        });
+
+        Self {
+            buffer,
+            bind_group_layout,
+            bind_group,
+        }
+    }
+
+    pub fn update(&self, queue: &wgpu::Queue, camera: &Camera) {
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[CameraData::new(camera)]));
+    }
+}
@@ -0,0 +1,201 @@
+//## Capturas de pantalla y grabacion de secuencias de frames.
+//## F12 guarda el frame actual como PNG, F11 graba RECORD_SECONDS segundos como una secuencia de PNGs numerados.
+//## Mientras se graba el tiempo avanza a pasos fijos de 1/RECORD_FPS (ver GlobalsUniform::set_fixed_time_step), asi
+//## que la animacion grabada es siempre la misma aunque guardar cada frame sea mucho mas lento que tiempo real.
+//##
+//## Las texturas del swap chain solo se pueden usar como OUTPUT_ATTACHMENT, no se pueden copiar a un buffer. Por eso
+//## cuando hay una captura pendiente el ejemplo dibuja el frame una segunda vez en una textura propia con COPY_SRC.
+
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use winit::event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent};
+
+use crate::examples::read_texture_to_image;
+
+const CAPTURE_DIR: &str = "captures";
+const RECORD_SECONDS: f32 = 5.0;
+const RECORD_FPS: u32 = 60;
+
+struct Recording {
+    dir: PathBuf,
+    frame: u32,
+    total_frames: u32,
+}
+
+pub struct Capture {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+    screenshot_requested: bool,
+    recording: Option<Recording>,
+}
+
+// Milisegundos desde epoch, suficiente para no pisar capturas anteriores
+fn timestamp() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0)
+}
+
+impl Capture {
+    pub fn new(device: &wgpu::Device, sc_desc: &wgpu::SwapChainDescriptor) -> Self {
+        let (texture, view) = Self::create_target(device, sc_desc);
+        Self {
+            texture,
+            view,
+            width: sc_desc.width,
+            height: sc_desc.height,
+            format: sc_desc.format,
+            screenshot_requested: false,
+            recording: None,
+        }
+    }
+
+    // Mismo tamaño y formato que el swap chain para que los pipelines del ejemplo sirvan tal cual
+    fn create_target(
+        device: &wgpu::Device,
+        sc_desc: &wgpu::SwapChainDescriptor,
+    ) -> (wgpu::Texture, wgpu::TextureView) {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Capture Texture"),
+            size: wgpu::Extent3d {
+                width: sc_desc.width,
+                height: sc_desc.height,
+                depth: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: sc_desc.format,
+            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT | wgpu::TextureUsage::COPY_SRC,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        (texture, view)
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, sc_desc: &wgpu::SwapChainDescriptor) {
+        let (texture, view) = Self::create_target(device, sc_desc);
+        self.texture = texture;
+        self.view = view;
+        self.width = sc_desc.width;
+        self.height = sc_desc.height;
+    }
+
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::F12),
+                        ..
+                    },
+                ..
+            } => {
+                self.screenshot_requested = true;
+                true
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::F11),
+                        ..
+                    },
+                ..
+            } => {
+                if self.recording.is_some() {
+                    self.stop_recording();
+                } else {
+                    self.start_recording();
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn start_recording(&mut self) {
+        let dir = PathBuf::from(CAPTURE_DIR).join(format!("recording_{}", timestamp()));
+        if let Err(e) = std::fs::create_dir_all(&dir) {
+            eprintln!("Unable to create {}: {}", dir.display(), e);
+            return;
+        }
+        println!("Recording {} seconds into {}", RECORD_SECONDS, dir.display());
+        self.recording = Some(Recording {
+            dir,
+            frame: 0,
+            total_frames: (RECORD_SECONDS * RECORD_FPS as f32) as u32,
+        });
+    }
+
+    fn stop_recording(&mut self) {
+        if let Some(recording) = self.recording.take() {
+            println!("Saved {} frames in {}", recording.frame, recording.dir.display());
+        }
+    }
+
+    // El paso de tiempo fijo mientras se graba, para pasarselo a GlobalsUniform::set_fixed_time_step
+    pub fn time_step(&self) -> Option<f32> {
+        self.recording.as_ref().map(|_| 1.0 / RECORD_FPS as f32)
+    }
+
+    // Si este frame hay que capturarlo devuelve la vista donde el ejemplo tiene que volver a dibujarlo
+    pub fn target(&self) -> Option<&wgpu::TextureView> {
+        if self.screenshot_requested || self.recording.is_some() {
+            Some(&self.view)
+        } else {
+            None
+        }
+    }
+
+    // Llamar despues de queue.submit: lee la textura de captura y la guarda en disco
+    pub fn finish_frame(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if self.screenshot_requested {
+            self.screenshot_requested = false;
+            let path = PathBuf::from(CAPTURE_DIR).join(format!("screenshot_{}.png", timestamp()));
+            match self.save(device, queue, &path) {
+                Ok(()) => println!("Saved {}", path.display()),
+                Err(e) => eprintln!("{:#}", e),
+            }
+        }
+
+        let next_frame = self.recording.as_mut().map(|recording| {
+            let path = recording.dir.join(format!("frame_{:05}.png", recording.frame));
+            recording.frame += 1;
+            (path, recording.frame >= recording.total_frames)
+        });
+        if let Some((path, done)) = next_frame {
+            if let Err(e) = self.save(device, queue, &path) {
+                eprintln!("{:#}", e);
+                self.stop_recording();
+            } else if done {
+                self.stop_recording();
+            }
+        }
+    }
+
+    fn save(&self, device: &wgpu::Device, queue: &wgpu::Queue, path: &std::path::Path) -> Result<()> {
+        use futures::executor::block_on;
+
+        let image = block_on(read_texture_to_image(
+            device,
+            queue,
+            &self.texture,
+            self.width,
+            self.height,
+            self.format,
+        ))?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        image
+            .save(path)
+            .with_context(|| format!("Unable to save {}", path.display()))
+    }
+}
@@ -0,0 +1,181 @@
+use wgpu::util::DeviceExt;
+
+// Helpers para crear los BindGroupLayoutEntry mas habituales de un compute shader.
+// Todos son visibles solo desde el stage COMPUTE.
+pub fn uniform_buffer_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStage::COMPUTE,
+        ty: wgpu::BindingType::UniformBuffer {
+            dynamic: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+// layout(set=0, binding=N) [readonly] buffer Nombre { uint datos[]; };
+pub fn storage_buffer_entry(binding: u32, readonly: bool) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStage::COMPUTE,
+        ty: wgpu::BindingType::StorageBuffer {
+            dynamic: false,
+            min_binding_size: None,
+            readonly,
+        },
+        count: None,
+    }
+}
+
+// layout(set=0, binding=N, rgba8) uniform [readonly|writeonly] image2D nombre;
+// El formato tiene que coincidir con el del shader
+pub fn storage_texture_entry(
+    binding: u32,
+    format: wgpu::TextureFormat,
+    readonly: bool,
+) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStage::COMPUTE,
+        ty: wgpu::BindingType::StorageTexture {
+            dimension: wgpu::TextureViewDimension::D2,
+            format,
+            readonly,
+        },
+        count: None,
+    }
+}
+
+// Numero de work groups necesarios para cubrir `size` invocaciones con grupos de `work_group_size`.
+// Sin u32::div_ceil, que necesita Rust 1.73
+#[allow(clippy::manual_div_ceil)]
+pub fn dispatch_size(size: u32, work_group_size: u32) -> u32 {
+    (size + work_group_size - 1) / work_group_size
+}
+
+// Un compute pipeline con un unico bind group (set 0) y el tamaño de work group con el que se compilo el shader,
+// es decir el local_size_x/y/z del layout(...) in; del .comp
+pub struct ComputeKernel {
+    pub pipeline: wgpu::ComputePipeline,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub work_group_size: [u32; 3],
+}
+
+impl ComputeKernel {
+    pub fn new(
+        device: &wgpu::Device,
+        module: &wgpu::ShaderModule,
+        entries: &[wgpu::BindGroupLayoutEntry],
+        work_group_size: [u32; 3],
+        label: Option<&str>,
+    ) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries,
+            label,
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        // A diferencia del render pipeline solo hay un stage, y no hay rasterizacion ni color states
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label,
+            layout: Some(&pipeline_layout),
+            compute_stage: wgpu::ProgrammableStageDescriptor {
+                module,
+                entry_point: "main",
+            },
+        });
+
+        Self {
+            pipeline,
+            bind_group_layout,
+            work_group_size,
+        }
+    }
+
+    // Crea un bind group para este kernel, los recursos van en el orden de los bindings
+    pub fn create_bind_group(
+        &self,
+        device: &wgpu::Device,
+        resources: &[wgpu::BindingResource],
+        label: Option<&str>,
+    ) -> wgpu::BindGroup {
+        let entries = resources
+            .iter()
+            .enumerate()
+            .map(|(i, resource)| wgpu::BindGroupEntry {
+                binding: i as u32,
+                resource: resource.clone(),
+            })
+            .collect::<Vec<_>>();
+
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.bind_group_layout,
+            entries: &entries,
+            label,
+        })
+    }
+
+    // Numero de work groups para cubrir size invocaciones en cada eje
+    pub fn work_groups(&self, size: [u32; 3]) -> [u32; 3] {
+        [
+            dispatch_size(size[0], self.work_group_size[0]),
+            dispatch_size(size[1], self.work_group_size[1]),
+            dispatch_size(size[2], self.work_group_size[2]),
+        ]
+    }
+
+    // Graba el dispatch en un compute pass ya abierto. El shader tiene que descartar las invocaciones que se salen de size
+    pub fn dispatch<'a>(
+        &'a self,
+        compute_pass: &mut wgpu::ComputePass<'a>,
+        bind_group: &'a wgpu::BindGroup,
+        size: [u32; 3],
+    ) {
+        let [x, y, z] = self.work_groups(size);
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(0, bind_group, &[]);
+        compute_pass.dispatch(x, y, z);
+    }
+}
+
+// Storage buffer inicializado con datos, se puede usar en varios kernels y leer de vuelta con readback::read_buffer
+pub fn create_storage_buffer<T: bytemuck::Pod>(
+    device: &wgpu::Device,
+    data: &[T],
+    label: Option<&str>,
+) -> wgpu::Buffer {
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label,
+        contents: bytemuck::cast_slice(data),
+        usage: wgpu::BufferUsage::STORAGE
+            | wgpu::BufferUsage::COPY_SRC
+            | wgpu::BufferUsage::COPY_DST,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exact_multiples_need_no_extra_group() {
+        assert_eq!(dispatch_size(0, 64), 0);
+        assert_eq!(dispatch_size(64, 64), 1);
+        assert_eq!(dispatch_size(256, 64), 4);
+        assert_eq!(dispatch_size(7, 1), 7);
+    }
+
+    #[test]
+    fn remainders_add_one_group() {
+        assert_eq!(dispatch_size(1, 64), 1);
+        assert_eq!(dispatch_size(65, 64), 2);
+        assert_eq!(dispatch_size(255, 64), 4);
+        assert_eq!(dispatch_size(1080, 16), 68);
+    }
+}
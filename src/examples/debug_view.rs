@@ -0,0 +1,167 @@
+//## Modos de depuracion de la geometria: relleno normal, wireframe (solo las aristas) y puntos (solo los vertices).
+//## F3 cambia entre ellos.
+//##
+//## wgpu 0.6 no tiene PolygonMode::Line (el feature NON_FILL_POLYGON_MODE llega en versiones posteriores), asi que el
+//## wireframe se dibuja con un pipeline LineList y un index buffer de lineas generado a partir de los triangulos.
+//## Los puntos usan un pipeline PointList sobre el vertex buffer tal cual, sin indices.
+
+use std::collections::HashSet;
+use std::hash::Hash;
+
+use wgpu::util::DeviceExt;
+use winit::event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent};
+
+use crate::examples::PipelineBuilder;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RenderMode {
+    Fill,
+    Wireframe,
+    Points,
+}
+
+impl RenderMode {
+    pub fn next(self) -> Self {
+        match self {
+            RenderMode::Fill => RenderMode::Wireframe,
+            RenderMode::Wireframe => RenderMode::Points,
+            RenderMode::Points => RenderMode::Fill,
+        }
+    }
+}
+
+// Convierte una TriangleList indexada en una LineList con cada arista una sola vez
+// (las aristas compartidas por dos triangulos solo se dibujan una vez)
+pub fn wireframe_indices<I: Copy + Ord + Hash>(indices: &[I]) -> Vec<I> {
+    let mut seen = HashSet::new();
+    let mut lines = Vec::with_capacity(indices.len() * 2);
+    for triangle in indices.chunks_exact(3) {
+        for &(a, b) in &[(triangle[0], triangle[1]), (triangle[1], triangle[2]), (triangle[2], triangle[0])] {
+            if seen.insert((a.min(b), a.max(b))) {
+                lines.push(a);
+                lines.push(b);
+            }
+        }
+    }
+    lines
+}
+
+// Lo que necesita una malla para dibujarse en modo wireframe y puntos
+pub struct DebugMesh {
+    line_index_buffer: wgpu::Buffer,
+    num_line_indices: u32,
+    num_vertices: u32,
+}
+
+impl DebugMesh {
+    pub fn new(device: &wgpu::Device, indices: &[u16], num_vertices: u32) -> Self {
+        let line_indices = wireframe_indices(indices);
+        let line_index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Wireframe Index Buffer"),
+            contents: bytemuck::cast_slice(&line_indices),
+            usage: wgpu::BufferUsage::INDEX,
+        });
+        Self {
+            line_index_buffer,
+            num_line_indices: line_indices.len() as u32,
+            num_vertices,
+        }
+    }
+}
+
+// Los pipelines de wireframe y puntos de un ejemplo, creados a partir del builder de su pipeline normal
+pub struct DebugView {
+    pub mode: RenderMode,
+    wireframe_pipeline: wgpu::RenderPipeline,
+    points_pipeline: wgpu::RenderPipeline,
+}
+
+impl DebugView {
+    pub fn new(device: &wgpu::Device, builder: &PipelineBuilder) -> Self {
+        // Sin culling: en wireframe interesa ver tambien las caras de atras
+        let wireframe_pipeline = builder
+            .clone()
+            .topology(wgpu::PrimitiveTopology::LineList)
+            .cull_mode(wgpu::CullMode::None)
+            .build(device);
+        let points_pipeline = builder
+            .clone()
+            .topology(wgpu::PrimitiveTopology::PointList)
+            .cull_mode(wgpu::CullMode::None)
+            .build(device);
+        Self {
+            mode: RenderMode::Fill,
+            wireframe_pipeline,
+            points_pipeline,
+        }
+    }
+
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::F3),
+                        ..
+                    },
+                ..
+            } => {
+                self.mode = self.mode.next();
+                println!("Render mode: {:?}", self.mode);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // Dibuja la malla en el modo actual. Los bind groups y vertex buffers ya tienen que estar puestos en el render pass
+    pub fn draw_indexed<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        fill_pipeline: &'a wgpu::RenderPipeline,
+        index_buffer: &'a wgpu::Buffer,
+        num_indices: u32,
+        mesh: &'a DebugMesh,
+    ) {
+        match self.mode {
+            RenderMode::Fill => {
+                render_pass.set_pipeline(fill_pipeline);
+                render_pass.set_index_buffer(index_buffer.slice(..));
+                render_pass.draw_indexed(0..num_indices, 0, 0..1);
+            }
+            RenderMode::Wireframe => {
+                render_pass.set_pipeline(&self.wireframe_pipeline);
+                render_pass.set_index_buffer(mesh.line_index_buffer.slice(..));
+                render_pass.draw_indexed(0..mesh.num_line_indices, 0, 0..1);
+            }
+            RenderMode::Points => {
+                render_pass.set_pipeline(&self.points_pipeline);
+                render_pass.draw(0..mesh.num_vertices, 0..1);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_edges_are_emitted_once() {
+        // Un quad: dos triangulos que comparten la arista 0-2
+        let indices: [u16; 6] = [0, 1, 2, 0, 2, 3];
+        let lines = wireframe_indices(&indices);
+        let edges = lines
+            .chunks_exact(2)
+            .map(|line| (line[0].min(line[1]), line[0].max(line[1])))
+            .collect::<Vec<_>>();
+        assert_eq!(edges.len(), 5);
+        assert_eq!(edges.iter().filter(|&&edge| edge == (0, 2)).count(), 1);
+        let unique = edges.iter().collect::<HashSet<_>>();
+        assert_eq!(unique.len(), 5);
+        for edge in &[(0, 1), (1, 2), (0, 2), (2, 3), (0, 3)] {
+            assert!(unique.contains(edge), "{:?}", edge);
+        }
+    }
+}
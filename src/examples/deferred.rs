@@ -0,0 +1,503 @@
+//## Deferred shading: con cientos de luces no se puede calcular cada una en cada fragmento de cada objeto, asi que la
+//## escena se dibuja en dos pasos.
+//##   1. Geometria (deferred_geometry.frag): cada objeto escribe en el GBuffer su color base, su normal, su material
+//##      (metalicidad, rugosidad y oclusion) y su emision, ademas de la profundidad. Un pipeline con varios color
+//##      targets, en el orden de GBUFFER_FORMATS.
+//##   2. Luz (deferred_lighting.frag): un pass a pantalla completa que lee el G-buffer y calcula la luz una sola vez
+//##      por pixel visible.
+//## Entre los dos, un compute shader (deferred_light_culling.comp) divide la pantalla en tiles de TILE_SIZE pixels y
+//## hace para cada uno la lista de las luces puntuales que le pueden llegar, y el pass de luz solo recorre esa lista.
+//##
+//## layout(set=0, ...) uniform texture2D t_albedo, t_normal, t_material, t_emissive, t_depth; sampler s_gbuffer;
+//## layout(set=1, binding=0) uniform Deferred { ... };          // DeferredData
+//## layout(set=1, binding=1) readonly buffer PointLights { ... };  // todas las luces puntuales
+//## layout(set=1, binding=2) readonly buffer Tiles { ... };        // las listas de los tiles
+//## layout(set=2, binding=0) uniform Lights { ... };               // el sol, los focos y la luz ambiente
+//##
+//## draw_debug() muestra las texturas del G-buffer en una rejilla, en lugar del resultado.
+
+use cgmath::{Matrix4, SquareMatrix};
+use wgpu::util::DeviceExt;
+
+use crate::examples::{
+    dispatch_size, storage_buffer_entry, uniform_buffer_entry, Camera, ComputeKernel, Lights, PipelineBuilder,
+    DEPTH_FORMAT,
+};
+
+// Color base, normal en el mundo, material y emision. Las normales y la emision necesitan mas de 8 bits
+pub const GBUFFER_FORMATS: [wgpu::TextureFormat; 4] = [
+    wgpu::TextureFormat::Rgba8UnormSrgb,
+    wgpu::TextureFormat::Rgba16Float,
+    wgpu::TextureFormat::Rgba8Unorm,
+    wgpu::TextureFormat::Rgba16Float,
+];
+pub const MAX_DEFERRED_LIGHTS: usize = 1024;
+// Tienen que coincidir con los shaders deferred_*
+const TILE_SIZE: u32 = 16;
+const MAX_LIGHTS_PER_TILE: u32 = 255;
+// Cada tile guarda su numero de luces y despues los indices
+const TILE_STRIDE: u32 = MAX_LIGHTS_PER_TILE + 1;
+
+pub struct GBuffer {
+    color_views: Vec<wgpu::TextureView>,
+    depth_view: wgpu::TextureView,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+    size: [u32; 2],
+}
+
+impl GBuffer {
+    pub fn new(device: &wgpu::Device, sc_desc: &wgpu::SwapChainDescriptor) -> Self {
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStage::FRAGMENT | wgpu::ShaderStage::COMPUTE,
+            ty: wgpu::BindingType::SampledTexture {
+                multisampled: false,
+                dimension: wgpu::TextureViewDimension::D2,
+                component_type: wgpu::TextureComponentType::Float,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                texture_entry(0),
+                texture_entry(1),
+                texture_entry(2),
+                texture_entry(3),
+                texture_entry(4),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: wgpu::ShaderStage::FRAGMENT | wgpu::ShaderStage::COMPUTE,
+                    ty: wgpu::BindingType::Sampler { comparison: false },
+                    count: None,
+                },
+            ],
+            label: Some("gbuffer_bind_group_layout"),
+        });
+        let (color_views, depth_view, bind_group) = Self::create_textures(device, &bind_group_layout, sc_desc);
+        Self {
+            color_views,
+            depth_view,
+            bind_group_layout,
+            bind_group,
+            size: [sc_desc.width, sc_desc.height],
+        }
+    }
+
+    fn create_textures(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        sc_desc: &wgpu::SwapChainDescriptor,
+    ) -> (Vec<wgpu::TextureView>, wgpu::TextureView, wgpu::BindGroup) {
+        let view = |format, label| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some(label),
+                    size: wgpu::Extent3d {
+                        width: sc_desc.width.max(1),
+                        height: sc_desc.height.max(1),
+                        depth: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT | wgpu::TextureUsage::SAMPLED,
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        };
+        let labels = [
+            "GBuffer Albedo",
+            "GBuffer Normal",
+            "GBuffer Material",
+            "GBuffer Emissive",
+        ];
+        let color_views = GBUFFER_FORMATS
+            .iter()
+            .zip(&labels)
+            .map(|(&format, &label)| view(format, label))
+            .collect::<Vec<_>>();
+        let depth_view = view(DEPTH_FORMAT, "GBuffer Depth");
+
+        // Todo se lee con texelFetch, el sampler solo esta porque GLSL lo pide
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("GBuffer Sampler"),
+            ..Default::default()
+        });
+        let mut entries = color_views
+            .iter()
+            .chain(Some(&depth_view))
+            .enumerate()
+            .map(|(i, view)| wgpu::BindGroupEntry {
+                binding: i as u32,
+                resource: wgpu::BindingResource::TextureView(view),
+            })
+            .collect::<Vec<_>>();
+        entries.push(wgpu::BindGroupEntry {
+            binding: 5,
+            resource: wgpu::BindingResource::Sampler(&sampler),
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &entries,
+            label: Some("gbuffer_bind_group"),
+        });
+        (color_views, depth_view, bind_group)
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, sc_desc: &wgpu::SwapChainDescriptor) {
+        let (color_views, depth_view, bind_group) = Self::create_textures(device, &self.bind_group_layout, sc_desc);
+        self.color_views = color_views;
+        self.depth_view = depth_view;
+        self.bind_group = bind_group;
+        self.size = [sc_desc.width, sc_desc.height];
+    }
+
+    // Para el render pass de la geometria: todas las texturas se limpian a 0
+    pub fn color_attachments(&self) -> Vec<wgpu::RenderPassColorAttachmentDescriptor<'_>> {
+        self.color_views
+            .iter()
+            .map(|view| wgpu::RenderPassColorAttachmentDescriptor {
+                attachment: view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: true,
+                },
+            })
+            .collect()
+    }
+
+    // Todas sus texturas, la de profundidad la ultima. Para declararlas en un RenderGraph
+    pub fn views(&self) -> impl Iterator<Item = &wgpu::TextureView> {
+        self.color_views.iter().chain(std::iter::once(&self.depth_view))
+    }
+
+    pub fn depth_attachment(&self) -> wgpu::RenderPassDepthStencilAttachmentDescriptor<'_> {
+        wgpu::RenderPassDepthStencilAttachmentDescriptor {
+            attachment: &self.depth_view,
+            depth_ops: Some(wgpu::Operations {
+                load: wgpu::LoadOp::Clear(1.0),
+                store: true,
+            }),
+            stencil_ops: None,
+        }
+    }
+}
+
+// El bloque Deferred de los shaders (std140)
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+struct DeferredData {
+    view: [[f32; 4]; 4],
+    inv_projection: [[f32; 4]; 4],
+    inv_view_proj: [[f32; 4]; 4],
+    view_position: [f32; 4],
+    screen_size: [u32; 2],
+    num_tiles: [u32; 2],
+    num_lights: u32,
+    show_tiles: u32,
+    _padding: [u32; 2],
+}
+
+unsafe impl bytemuck::Pod for DeferredData {}
+unsafe impl bytemuck::Zeroable for DeferredData {}
+
+// Una luz puntual en el storage buffer (std430), como PointLightData de lighting.rs
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+struct DeferredLightData {
+    position: [f32; 3],
+    range: f32,
+    color: [f32; 3],
+    intensity: f32,
+}
+
+unsafe impl bytemuck::Pod for DeferredLightData {}
+unsafe impl bytemuck::Zeroable for DeferredLightData {}
+
+fn num_tiles(size: [u32; 2]) -> [u32; 2] {
+    [
+        dispatch_size(size[0], TILE_SIZE).max(1),
+        dispatch_size(size[1], TILE_SIZE).max(1),
+    ]
+}
+
+// El culling de las luces, el pass de luz y la vista de depuracion. Las luces puntuales son las de Lights (todas,
+// hasta MAX_DEFERRED_LIGHTS), el resto de Lights llega en el LightsUniform
+pub struct DeferredLighting {
+    pub show_tiles: bool,
+    params_buffer: wgpu::Buffer,
+    lights_buffer: wgpu::Buffer,
+    // Depende del numero de tiles, se recrea con resize()
+    tiles_buffer: wgpu::Buffer,
+    cull_kernel: ComputeKernel,
+    cull_bind_group: wgpu::BindGroup,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    lighting_pipeline: wgpu::RenderPipeline,
+    debug_pipeline: wgpu::RenderPipeline,
+    size: [u32; 2],
+}
+
+impl DeferredLighting {
+    // output_format es el de la textura donde se suma la luz y debug_format el de la vista de depuracion
+    pub fn new(
+        device: &wgpu::Device,
+        gbuffer: &GBuffer,
+        lights_layout: &wgpu::BindGroupLayout,
+        output_format: wgpu::TextureFormat,
+        debug_format: wgpu::TextureFormat,
+    ) -> Self {
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Deferred Params"),
+            size: std::mem::size_of::<DeferredData>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let lights_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Deferred Lights"),
+            contents: bytemuck::cast_slice(&[DeferredLightData::default(); MAX_DEFERRED_LIGHTS]),
+            usage: wgpu::BufferUsage::STORAGE | wgpu::BufferUsage::COPY_DST,
+        });
+        let tiles_buffer = Self::create_tiles_buffer(device, gbuffer.size);
+
+        let cull_module = device.create_shader_module(wgpu::include_spirv!("shaders/deferred_light_culling.comp.spv"));
+        let cull_kernel = ComputeKernel::new(
+            device,
+            &cull_module,
+            &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::COMPUTE,
+                    ty: wgpu::BindingType::SampledTexture {
+                        multisampled: false,
+                        dimension: wgpu::TextureViewDimension::D2,
+                        component_type: wgpu::TextureComponentType::Float,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::COMPUTE,
+                    ty: wgpu::BindingType::Sampler { comparison: false },
+                    count: None,
+                },
+                uniform_buffer_entry(2),
+                storage_buffer_entry(3, true),
+                storage_buffer_entry(4, false),
+            ],
+            [TILE_SIZE, TILE_SIZE, 1],
+            Some("Light Culling"),
+        );
+        let cull_bind_group = Self::create_cull_bind_group(
+            device,
+            &cull_kernel,
+            gbuffer,
+            &params_buffer,
+            &lights_buffer,
+            &tiles_buffer,
+        );
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ..uniform_buffer_entry(0)
+                },
+                wgpu::BindGroupLayoutEntry {
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ..storage_buffer_entry(1, true)
+                },
+                wgpu::BindGroupLayoutEntry {
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ..storage_buffer_entry(2, true)
+                },
+            ],
+            label: Some("deferred_bind_group_layout"),
+        });
+        let bind_group = Self::create_bind_group(
+            device,
+            &bind_group_layout,
+            &params_buffer,
+            &lights_buffer,
+            &tiles_buffer,
+        );
+
+        let vs_module = device.create_shader_module(wgpu::include_spirv!("shaders/fullscreen.vert.spv"));
+        let lighting_module = device.create_shader_module(wgpu::include_spirv!("shaders/deferred_lighting.frag.spv"));
+        let lighting_pipeline = PipelineBuilder::new(&vs_module, output_format)
+            .label("Deferred Lighting Pipeline")
+            .fragment_shader(&lighting_module)
+            .bind_group_layouts(&[&gbuffer.bind_group_layout, &bind_group_layout, lights_layout])
+            .build(device);
+        let debug_module = device.create_shader_module(wgpu::include_spirv!("shaders/deferred_debug.frag.spv"));
+        let debug_pipeline = PipelineBuilder::new(&vs_module, debug_format)
+            .label("GBuffer Debug Pipeline")
+            .fragment_shader(&debug_module)
+            .bind_group_layouts(&[&gbuffer.bind_group_layout, &bind_group_layout])
+            .build(device);
+
+        Self {
+            show_tiles: false,
+            params_buffer,
+            lights_buffer,
+            tiles_buffer,
+            cull_kernel,
+            cull_bind_group,
+            bind_group_layout,
+            bind_group,
+            lighting_pipeline,
+            debug_pipeline,
+            size: gbuffer.size,
+        }
+    }
+
+    fn create_tiles_buffer(device: &wgpu::Device, size: [u32; 2]) -> wgpu::Buffer {
+        let [x, y] = num_tiles(size);
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Light Tiles"),
+            size: (x * y * TILE_STRIDE) as wgpu::BufferAddress * std::mem::size_of::<u32>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::STORAGE,
+            mapped_at_creation: false,
+        })
+    }
+
+    fn create_cull_bind_group(
+        device: &wgpu::Device,
+        kernel: &ComputeKernel,
+        gbuffer: &GBuffer,
+        params_buffer: &wgpu::Buffer,
+        lights_buffer: &wgpu::Buffer,
+        tiles_buffer: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Light Culling Sampler"),
+            ..Default::default()
+        });
+        kernel.create_bind_group(
+            device,
+            &[
+                wgpu::BindingResource::TextureView(&gbuffer.depth_view),
+                wgpu::BindingResource::Sampler(&sampler),
+                wgpu::BindingResource::Buffer(params_buffer.slice(..)),
+                wgpu::BindingResource::Buffer(lights_buffer.slice(..)),
+                wgpu::BindingResource::Buffer(tiles_buffer.slice(..)),
+            ],
+            Some("Light Culling"),
+        )
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        params_buffer: &wgpu::Buffer,
+        lights_buffer: &wgpu::Buffer,
+        tiles_buffer: &wgpu::Buffer,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(params_buffer.slice(..)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Buffer(lights_buffer.slice(..)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Buffer(tiles_buffer.slice(..)),
+                },
+            ],
+            label: Some("deferred_bind_group"),
+        })
+    }
+
+    // Despues de GBuffer::resize, las texturas y el numero de tiles han cambiado
+    pub fn resize(&mut self, device: &wgpu::Device, gbuffer: &GBuffer) {
+        self.size = gbuffer.size;
+        self.tiles_buffer = Self::create_tiles_buffer(device, self.size);
+        self.cull_bind_group = Self::create_cull_bind_group(
+            device,
+            &self.cull_kernel,
+            gbuffer,
+            &self.params_buffer,
+            &self.lights_buffer,
+            &self.tiles_buffer,
+        );
+        self.bind_group = Self::create_bind_group(
+            device,
+            &self.bind_group_layout,
+            &self.params_buffer,
+            &self.lights_buffer,
+            &self.tiles_buffer,
+        );
+    }
+
+    pub fn update(&self, queue: &wgpu::Queue, camera: &Camera, lights: &Lights) {
+        let lights_data = lights
+            .point_lights
+            .iter()
+            .take(MAX_DEFERRED_LIGHTS)
+            .map(|light| DeferredLightData {
+                position: light.position,
+                range: light.range,
+                color: light.color,
+                intensity: light.intensity,
+            })
+            .collect::<Vec<_>>();
+        if !lights_data.is_empty() {
+            queue.write_buffer(&self.lights_buffer, 0, bytemuck::cast_slice(&lights_data));
+        }
+
+        let inverse = |matrix: Matrix4<f32>| matrix.invert().unwrap_or_else(Matrix4::identity).into();
+        let data = DeferredData {
+            view: camera.view().into(),
+            inv_projection: inverse(camera.projection()),
+            inv_view_proj: inverse(camera.view_projection()),
+            view_position: [camera.eye.x, camera.eye.y, camera.eye.z, 1.0],
+            screen_size: self.size,
+            num_tiles: num_tiles(self.size),
+            num_lights: lights_data.len() as u32,
+            show_tiles: self.show_tiles as u32,
+            _padding: [0; 2],
+        };
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&[data]));
+    }
+
+    // Entre el pass de geometria y el de luz
+    pub fn cull_lights(&self, encoder: &mut wgpu::CommandEncoder) {
+        let mut compute_pass = encoder.begin_compute_pass();
+        let [x, y] = num_tiles(self.size);
+        // Un work group por tile
+        self.cull_kernel.dispatch(
+            &mut compute_pass,
+            &self.cull_bind_group,
+            [x * TILE_SIZE, y * TILE_SIZE, 1],
+        );
+    }
+
+    // Un triangulo a pantalla completa; los pixels sin geometria no se tocan
+    pub fn draw_lighting<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        gbuffer: &'a GBuffer,
+        lights_bind_group: &'a wgpu::BindGroup,
+    ) {
+        render_pass.set_pipeline(&self.lighting_pipeline);
+        render_pass.set_bind_group(0, &gbuffer.bind_group, &[]);
+        render_pass.set_bind_group(1, &self.bind_group, &[]);
+        render_pass.set_bind_group(2, lights_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+
+    // Las texturas del G-buffer y las luces de cada tile en una rejilla de 3 x 2 que ocupa todo el render pass
+    pub fn draw_debug<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, gbuffer: &'a GBuffer) {
+        render_pass.set_pipeline(&self.debug_pipeline);
+        render_pass.set_bind_group(0, &gbuffer.bind_group, &[]);
+        render_pass.set_bind_group(1, &self.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
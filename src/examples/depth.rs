@@ -0,0 +1,52 @@
+//## Depth buffer: guarda por pixel la profundidad de lo que ya se ha dibujado para que las caras de atras no tapen
+//## a las de delante aunque se dibujen despues. Como la textura multisampled de Multisample, es del tamaño del swap
+//## chain (hay que recrearla en resize) y tiene el mismo sample_count que el pipeline.
+
+pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+pub struct DepthBuffer {
+    sample_count: u32,
+    view: wgpu::TextureView,
+}
+
+impl DepthBuffer {
+    pub fn new(device: &wgpu::Device, sc_desc: &wgpu::SwapChainDescriptor, sample_count: u32) -> Self {
+        Self {
+            sample_count,
+            view: Self::create_view(device, sc_desc, sample_count),
+        }
+    }
+
+    fn create_view(device: &wgpu::Device, sc_desc: &wgpu::SwapChainDescriptor, sample_count: u32) -> wgpu::TextureView {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Depth Buffer"),
+            size: wgpu::Extent3d {
+                width: sc_desc.width,
+                height: sc_desc.height,
+                depth: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: DEPTH_FORMAT,
+            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+        });
+        texture.create_view(&wgpu::TextureViewDescriptor::default())
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, sc_desc: &wgpu::SwapChainDescriptor) {
+        self.view = Self::create_view(device, sc_desc, self.sample_count);
+    }
+
+    // Se limpia a 1.0 (lo mas lejos) al empezar el render pass, para usar con CompareFunction::Less
+    pub fn attachment(&self) -> wgpu::RenderPassDepthStencilAttachmentDescriptor<'_> {
+        wgpu::RenderPassDepthStencilAttachmentDescriptor {
+            attachment: &self.view,
+            depth_ops: Some(wgpu::Operations {
+                load: wgpu::LoadOp::Clear(1.0),
+                store: true,
+            }),
+            stencil_ops: None,
+        }
+    }
+}
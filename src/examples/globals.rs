@@ -0,0 +1,155 @@
+use std::rc::Rc;
+use std::time::Instant;
+
+use wgpu::util::DeviceExt;
+use winit::event::{ElementState, MouseButton, WindowEvent};
+
+// Bloque de uniforms por frame, al estilo de los shader playgrounds (Shadertoy, glslsandbox...).
+// Tiene que coincidir con el layout std140 del bloque Globals de los shaders:
+//
+// layout(set=0, binding=0) uniform Globals {
+//     vec2 u_resolution;   // Tamaño del frame en pixels
+//     float u_time;        // Segundos desde que arranco el ejemplo
+//     float u_time_delta;  // Segundos desde el frame anterior
+//     vec4 u_mouse;        // xy: posicion del cursor en pixels, zw: posicion del ultimo click (negativa si no hay boton pulsado)
+//     uint u_frame;        // Numero de frame
+// };
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct Globals {
+    pub resolution: [f32; 2],
+    pub time: f32,
+    pub time_delta: f32,
+    pub mouse: [f32; 4],
+    pub frame: u32,
+    // std140 redondea el tamaño del bloque a 16 bytes
+    _padding: [u32; 3],
+}
+
+unsafe impl bytemuck::Pod for Globals {}
+unsafe impl bytemuck::Zeroable for Globals {}
+
+impl Globals {
+    pub fn new(size: winit::dpi::PhysicalSize<u32>) -> Self {
+        Self {
+            resolution: [size.width as f32, size.height as f32],
+            time: 0.0,
+            time_delta: 0.0,
+            mouse: [0.0; 4],
+            frame: 0,
+            _padding: [0; 3],
+        }
+    }
+}
+
+// Helper que guarda el buffer y el bind group de los Globals. Cada ejemplo lo pone en el set 0 de su pipeline,
+// llama a input() con los eventos de la ventana, a resize() y a update() una vez por frame antes de renderizar.
+pub struct GlobalsUniform {
+    pub data: Globals,
+    pub buffer: wgpu::Buffer,
+    // En Rc para poder usarlo en los pipelines de un PipelineCache
+    pub bind_group_layout: Rc<wgpu::BindGroupLayout>,
+    pub bind_group: wgpu::BindGroup,
+    last_frame: Instant,
+    mouse_pressed: bool,
+    // Si es Some el tiempo avanza siempre ese paso por frame, independientemente del tiempo real
+    fixed_time_step: Option<f32>,
+}
+
+impl GlobalsUniform {
+    pub fn new(device: &wgpu::Device, size: winit::dpi::PhysicalSize<u32>) -> Self {
+        let data = Globals::new(size);
+
+        // COPY_DST para poder actualizarlo cada frame con queue.write_buffer
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Globals Buffer"),
+            contents: bytemuck::cast_slice(&[data]),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStage::VERTEX | wgpu::ShaderStage::FRAGMENT,
+                ty: wgpu::BindingType::UniformBuffer {
+                    dynamic: false,
+                    min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<Globals>() as _),
+                },
+                count: None,
+            }],
+            label: Some("globals_bind_group_layout"),
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(buffer.slice(..)),
+            }],
+            label: Some("globals_bind_group"),
+        });
+
+        Self {
+            data,
+            buffer,
+            bind_group_layout: Rc::new(bind_group_layout),
+            bind_group,
+            last_frame: Instant::now(),
+            mouse_pressed: false,
+            fixed_time_step: None,
+        }
+    }
+
+    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.data.resolution = [new_size.width as f32, new_size.height as f32];
+    }
+
+    // Solo observa los eventos, siempre devuelve false para que el ejemplo los pueda seguir procesando
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.data.mouse[0] = position.x as f32;
+                self.data.mouse[1] = position.y as f32;
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => {
+                self.mouse_pressed = *state == ElementState::Pressed;
+                if self.mouse_pressed {
+                    self.data.mouse[2] = self.data.mouse[0];
+                    self.data.mouse[3] = self.data.mouse[1];
+                } else {
+                    self.data.mouse[2] = -self.data.mouse[2].abs();
+                    self.data.mouse[3] = -self.data.mouse[3].abs();
+                }
+            }
+            _ => {}
+        }
+        false
+    }
+
+    // Paso de tiempo fijo para grabar animaciones deterministas (ver Capture). Al activarlo el reloj y el contador
+    // de frames vuelven a 0 para que la grabacion empiece siempre desde el mismo estado.
+    pub fn set_fixed_time_step(&mut self, time_step: Option<f32>) {
+        if self.fixed_time_step.is_none() && time_step.is_some() {
+            self.data.time = 0.0;
+            self.data.frame = 0;
+        }
+        self.fixed_time_step = time_step;
+    }
+
+    // Avanza el reloj y el contador de frames y sube los datos a la GPU
+    pub fn update(&mut self, queue: &wgpu::Queue) {
+        let now = Instant::now();
+        self.data.time_delta = self
+            .fixed_time_step
+            .unwrap_or_else(|| (now - self.last_frame).as_secs_f32());
+        self.data.time += self.data.time_delta;
+        self.last_frame = now;
+
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[self.data]));
+        self.data.frame = self.data.frame.wrapping_add(1);
+    }
+}
@@ -0,0 +1,300 @@
+//## Luces para el modelo de Phong / Blinn-Phong: una luz ambiente, luces puntuales (con posicion, se atenuan con la
+//## distancia), direccionales (solo direccion, como el sol) y focos (spot, una puntual que solo ilumina dentro de un
+//## cono). Todas van en un uniform de tamaño fijo con MAX_* luces de cada tipo y el numero de las que se usan:
+//##
+//## struct PointLight { vec3 position; float range; vec3 color; float intensity; };
+//## struct DirectionalLight { vec3 direction; float _padding; vec3 color; float intensity; };
+//## struct SpotLight {
+//##     vec3 position; float range; vec3 direction; float intensity; vec3 color; float cos_inner; float cos_outer;
+//## };
+//## layout(set=N, binding=0) uniform Lights {
+//##     vec3 u_ambient;
+//##     uint u_blinn;           // 0: Phong, 1: Blinn-Phong
+//##     PointLight u_point_lights[MAX_POINT_LIGHTS];
+//##     DirectionalLight u_directional_lights[MAX_DIRECTIONAL_LIGHTS];
+//##     SpotLight u_spot_lights[MAX_SPOT_LIGHTS];
+//##     uint u_num_point_lights;
+//##     uint u_num_directional_lights;
+//##     uint u_num_spot_lights;
+//## };
+//##
+//## LightGizmo dibuja un cubito del color de cada luz puntual y cada foco en su posicion, para ver de donde viene
+//## la luz.
+
+use wgpu::util::DeviceExt;
+
+use crate::examples::{cube_mesh, MeshVertex, PipelineBuilder, DEPTH_FORMAT};
+
+pub const MAX_POINT_LIGHTS: usize = 4;
+pub const MAX_DIRECTIONAL_LIGHTS: usize = 2;
+pub const MAX_SPOT_LIGHTS: usize = 2;
+
+const GIZMO_HALF_SIZE: f32 = 0.05;
+
+#[derive(Copy, Clone, Debug)]
+pub struct PointLight {
+    pub position: [f32; 3],
+    pub color: [f32; 3],
+    pub intensity: f32,
+    // A partir de esta distancia no ilumina
+    pub range: f32,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct DirectionalLight {
+    // Hacia donde va la luz (no hace falta que este normalizada)
+    pub direction: [f32; 3],
+    pub color: [f32; 3],
+    pub intensity: f32,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct SpotLight {
+    pub position: [f32; 3],
+    // Hacia donde apunta el foco (no hace falta que este normalizada)
+    pub direction: [f32; 3],
+    pub color: [f32; 3],
+    pub intensity: f32,
+    pub range: f32,
+    // Semiangulos del cono en radianes: hasta inner_angle ilumina del todo y de ahi a outer_angle se va apagando
+    pub inner_angle: f32,
+    pub outer_angle: f32,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SpecularModel {
+    // Angulo entre la luz reflejada y la direccion a la camara
+    Phong,
+    // Angulo entre la normal y el vector medio entre la luz y la camara. Con el mismo shininess el brillo es mas
+    // grande que con Phong (hace falta unas 4 veces mas para que se parezcan)
+    BlinnPhong,
+}
+
+pub struct Lights {
+    pub ambient: [f32; 3],
+    pub specular_model: SpecularModel,
+    // Las que pasen de MAX_POINT_LIGHTS, MAX_DIRECTIONAL_LIGHTS y MAX_SPOT_LIGHTS se ignoran
+    pub point_lights: Vec<PointLight>,
+    pub directional_lights: Vec<DirectionalLight>,
+    pub spot_lights: Vec<SpotLight>,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+struct PointLightData {
+    position: [f32; 3],
+    range: f32,
+    color: [f32; 3],
+    intensity: f32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+struct DirectionalLightData {
+    direction: [f32; 3],
+    _padding: u32,
+    color: [f32; 3],
+    intensity: f32,
+}
+
+// Los angulos van como cosenos para compararlos directamente con el producto escalar en el shader
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+struct SpotLightData {
+    position: [f32; 3],
+    range: f32,
+    direction: [f32; 3],
+    intensity: f32,
+    color: [f32; 3],
+    cos_inner: f32,
+    cos_outer: f32,
+    _padding: [u32; 3],
+}
+
+// Tiene que coincidir con el bloque Lights de los shaders (std140)
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct LightsData {
+    ambient: [f32; 3],
+    blinn: u32,
+    point_lights: [PointLightData; MAX_POINT_LIGHTS],
+    directional_lights: [DirectionalLightData; MAX_DIRECTIONAL_LIGHTS],
+    spot_lights: [SpotLightData; MAX_SPOT_LIGHTS],
+    num_point_lights: u32,
+    num_directional_lights: u32,
+    num_spot_lights: u32,
+    // std140 redondea el tamaño del bloque a 16 bytes
+    _padding: u32,
+}
+
+unsafe impl bytemuck::Pod for LightsData {}
+unsafe impl bytemuck::Zeroable for LightsData {}
+
+impl LightsData {
+    pub fn new(lights: &Lights) -> Self {
+        let mut data = Self {
+            ambient: lights.ambient,
+            blinn: (lights.specular_model == SpecularModel::BlinnPhong) as u32,
+            ..Self::default()
+        };
+        for (data, light) in data.point_lights.iter_mut().zip(&lights.point_lights) {
+            *data = PointLightData {
+                position: light.position,
+                range: light.range,
+                color: light.color,
+                intensity: light.intensity,
+            };
+        }
+        for (data, light) in data.directional_lights.iter_mut().zip(&lights.directional_lights) {
+            *data = DirectionalLightData {
+                direction: light.direction,
+                _padding: 0,
+                color: light.color,
+                intensity: light.intensity,
+            };
+        }
+        for (data, light) in data.spot_lights.iter_mut().zip(&lights.spot_lights) {
+            *data = SpotLightData {
+                position: light.position,
+                range: light.range,
+                direction: light.direction,
+                intensity: light.intensity,
+                color: light.color,
+                cos_inner: light.inner_angle.cos(),
+                cos_outer: light.outer_angle.cos(),
+                _padding: [0; 3],
+            };
+        }
+        data.num_point_lights = lights.point_lights.len().min(MAX_POINT_LIGHTS) as u32;
+        data.num_directional_lights = lights.directional_lights.len().min(MAX_DIRECTIONAL_LIGHTS) as u32;
+        data.num_spot_lights = lights.spot_lights.len().min(MAX_SPOT_LIGHTS) as u32;
+        data
+    }
+}
+
+// Como GlobalsUniform: el buffer y el bind group de las luces. update() las sube una vez por frame
+pub struct LightsUniform {
+    pub data: LightsData,
+    pub buffer: wgpu::Buffer,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl LightsUniform {
+    pub fn new(device: &wgpu::Device, lights: &Lights) -> Self {
+        let data = LightsData::new(lights);
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Lights Buffer"),
+            contents: bytemuck::cast_slice(&[data]),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+
+        // En el vertex shader lo usa LightGizmo para colocar los cubitos
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStage::VERTEX | wgpu::ShaderStage::FRAGMENT,
+                ty: wgpu::BindingType::UniformBuffer {
+                    dynamic: false,
+                    min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<LightsData>() as _),
+                },
+                count: None,
+            }],
+            label: Some("lights_bind_group_layout"),
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(buffer.slice(..)),
+            }],
+            label: Some("lights_bind_group"),
+        });
+
+        Self {
+            data,
+            buffer,
+            bind_group_layout,
+            bind_group,
+        }
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue, lights: &Lights) {
+        self.data = LightsData::new(lights);
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[self.data]));
+    }
+}
+
+// Un cubito sin iluminar por cada luz puntual y cada foco, todos en un draw con una instancia por luz (primero las
+// puntuales y despues los focos). El vertex shader saca la posicion y el color de la luz del uniform Lights con
+// gl_InstanceIndex
+pub struct LightGizmo {
+    pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    num_indices: u32,
+}
+
+impl LightGizmo {
+    // El pipeline usa el set 0 para los Globals, el 1 para la camara y el 2 para las luces
+    pub fn new(
+        device: &wgpu::Device,
+        globals_layout: &wgpu::BindGroupLayout,
+        camera_layout: &wgpu::BindGroupLayout,
+        lights_layout: &wgpu::BindGroupLayout,
+        color_format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Self {
+        let vs_module = device.create_shader_module(wgpu::include_spirv!("shaders/light_gizmo.vert.spv"));
+        let fs_module = device.create_shader_module(wgpu::include_spirv!("shaders/light_gizmo.frag.spv"));
+        let pipeline = PipelineBuilder::new(&vs_module, color_format)
+            .label("Light Gizmo Pipeline")
+            .fragment_shader(&fs_module)
+            .bind_group_layouts(&[globals_layout, camera_layout, lights_layout])
+            .vertex_buffer(MeshVertex::desc())
+            .depth(DEPTH_FORMAT, wgpu::CompareFunction::Less)
+            .sample_count(sample_count)
+            .build(device);
+
+        let (vertices, indices) = cube_mesh(GIZMO_HALF_SIZE);
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Light Gizmo Vertex Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsage::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Light Gizmo Index Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsage::INDEX,
+        });
+
+        Self {
+            pipeline,
+            vertex_buffer,
+            index_buffer,
+            num_indices: indices.len() as u32,
+        }
+    }
+
+    // Pone sus propios bind groups, despues de llamarlo hay que volver a poner los del ejemplo
+    pub fn draw<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        globals_bind_group: &'a wgpu::BindGroup,
+        camera_bind_group: &'a wgpu::BindGroup,
+        lights: &'a LightsUniform,
+    ) {
+        let num_lights = lights.data.num_point_lights + lights.data.num_spot_lights;
+        if num_lights == 0 {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, globals_bind_group, &[]);
+        render_pass.set_bind_group(1, camera_bind_group, &[]);
+        render_pass.set_bind_group(2, &lights.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..));
+        render_pass.draw_indexed(0..self.num_indices, 0, 0..num_lights);
+    }
+}
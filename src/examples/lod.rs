@@ -0,0 +1,211 @@
+//## Niveles de detalle (LOD): cada objeto se dibuja con la malla que le toca segun lo grande que se ve en pantalla, el
+//## diametro en pixeles de su esfera envolvente. Las mallas de cada nivel salen de generate_lods (simplify.rs).
+//##
+//## El cambio de un nivel a otro se nota, asi que opcionalmente hay cross-fade: cerca del umbral se dibujan los dos
+//## niveles a la vez, cada uno en los pixeles que le tocan de un patron de dithering (screen-door), y la proporcion va
+//## cambiando con el tamaño. Con dithering no hace falta blending ni ordenar, que con el G-buffer no se puede.
+
+use std::mem;
+
+use cgmath::InnerSpace;
+
+use crate::examples::{BoundingSphere, Camera};
+
+// Diametro en pixeles de la esfera (ya en coordenadas del mundo) vista desde la camara
+pub fn projected_size(sphere: &BoundingSphere, camera: &Camera, screen_height: f32) -> f32 {
+    let distance = (sphere.center - camera.eye).magnitude();
+    // Con la camara dentro de la esfera ocupa toda la pantalla
+    if distance <= sphere.radius {
+        return f32::INFINITY;
+    }
+    let tan_half_fovy = (camera.fovy.to_radians() * 0.5).tan();
+    sphere.radius / (distance * tan_half_fovy) * screen_height
+}
+
+// Umbral del dithering de una instancia, en el vertex buffer del slot 2 (ver deferred_geometry.frag). Hasta 1 se
+// dibujan los pixeles en los que el patron esta por debajo del umbral; de 1 a 2 los que estan por encima de
+// umbral - 1. Asi los dos niveles de un cross-fade se reparten los pixeles sin repetir ninguno
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LodFade {
+    pub threshold: f32,
+}
+
+unsafe impl bytemuck::Pod for LodFade {}
+unsafe impl bytemuck::Zeroable for LodFade {}
+
+impl LodFade {
+    // Todos los pixeles
+    pub const OPAQUE: LodFade = LodFade { threshold: 1.0 };
+
+    // Despues de las 4 locations de la matriz de InstanceData
+    pub fn desc<'a>() -> wgpu::VertexBufferDescriptor<'a> {
+        wgpu::VertexBufferDescriptor {
+            stride: mem::size_of::<LodFade>() as wgpu::BufferAddress,
+            step_mode: wgpu::InputStepMode::Instance,
+            attributes: &[wgpu::VertexAttributeDescriptor {
+                offset: 0,
+                shader_location: 9,
+                format: wgpu::VertexFormat::Float,
+            }],
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LodSelection {
+    pub level: usize,
+    // Cuanto queda de level mientras se funde con level + 1: con 1 solo se dibuja level
+    pub fade: f32,
+}
+
+impl LodSelection {
+    pub fn is_fading(&self) -> bool {
+        self.fade < 1.0
+    }
+
+    // Los umbrales para level y para level + 1
+    pub fn fades(&self) -> (LodFade, LodFade) {
+        (
+            LodFade { threshold: self.fade },
+            LodFade {
+                threshold: 1.0 + self.fade,
+            },
+        )
+    }
+}
+
+pub struct LodSelector {
+    // Tamaño minimo en pixeles de cada nivel, de mas detalle a menos. El ultimo nivel no tiene: es para todo lo que
+    // queda por debajo
+    pub thresholds: Vec<f32>,
+    // Multiplica el tamaño: por encima de 1 da mas detalle
+    pub bias: f32,
+    pub cross_fade: bool,
+    // Ancho de la transicion, como fraccion del umbral
+    pub fade_band: f32,
+}
+
+impl LodSelector {
+    pub fn new(thresholds: &[f32]) -> Self {
+        Self {
+            thresholds: thresholds.to_vec(),
+            bias: 1.0,
+            cross_fade: true,
+            fade_band: 0.3,
+        }
+    }
+
+    // levels es cuantas mallas tiene el objeto, al menos una. Si tiene menos niveles que umbrales se queda en su ultimo
+    pub fn select(&self, size: f32, levels: usize) -> LodSelection {
+        debug_assert!(levels >= 1, "LodSelector::select needs at least one level");
+        let size = size * self.bias;
+        let last = levels - 1;
+        let level = self
+            .thresholds
+            .iter()
+            .position(|&threshold| size >= threshold)
+            .unwrap_or(self.thresholds.len())
+            .min(last);
+        // Justo por encima del umbral se va mezclando con el siguiente nivel
+        let fade = match self.thresholds.get(level) {
+            Some(&threshold) if self.cross_fade && level < last => {
+                ((size - threshold) / (threshold * self.fade_band)).min(1.0)
+            }
+            _ => 1.0,
+        };
+        LodSelection { level, fade }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // La matriz de Bayer y el discard de deferred_geometry.frag
+    const BAYER: [f32; 16] = [
+        0.0, 8.0, 2.0, 10.0, 12.0, 4.0, 14.0, 6.0, 3.0, 11.0, 1.0, 9.0, 15.0, 7.0, 13.0, 5.0,
+    ];
+
+    fn drawn(fade: LodFade, bayer: f32) -> bool {
+        let dither = (bayer + 0.5) / 16.0;
+        if fade.threshold <= 1.0 {
+            dither < fade.threshold
+        } else {
+            dither >= fade.threshold - 1.0
+        }
+    }
+
+    fn lod_selector(cross_fade: bool) -> LodSelector {
+        let mut selector = LodSelector::new(&[200.0, 100.0, 50.0]);
+        selector.cross_fade = cross_fade;
+        selector
+    }
+
+    #[test]
+    fn levels_follow_the_thresholds() {
+        let selector = lod_selector(false);
+        let level = |size| selector.select(size, 4).level;
+        assert_eq!(level(f32::INFINITY), 0);
+        assert_eq!(level(200.0), 0);
+        assert_eq!(level(199.0), 1);
+        assert_eq!(level(100.0), 1);
+        assert_eq!(level(60.0), 2);
+        assert_eq!(level(10.0), 3);
+        // Sin mas mallas se queda en la ultima
+        assert_eq!(selector.select(10.0, 2).level, 1);
+        assert_eq!(selector.select(10.0, 1).level, 0);
+    }
+
+    #[test]
+    fn bias_scales_the_size() {
+        let mut selector = lod_selector(false);
+        selector.bias = 2.0;
+        assert_eq!(selector.select(60.0, 4).level, 1);
+        selector.bias = 0.5;
+        assert_eq!(selector.select(300.0, 4).level, 1);
+    }
+
+    #[test]
+    fn fade_only_inside_the_band() {
+        let selector = lod_selector(true);
+        // La banda del umbral de 100 va de 100 a 130
+        let selection = selector.select(115.0, 4);
+        assert_eq!(selection.level, 1);
+        assert!((selection.fade - 0.5).abs() < 1e-5, "{:?}", selection);
+        assert!(selection.is_fading());
+        assert_eq!(selector.select(100.0, 4).fade, 0.0);
+        assert!(!selector.select(131.0, 4).is_fading());
+        assert!(!selector.select(160.0, 4).is_fading());
+        // El ultimo nivel no tiene con que mezclarse
+        assert!(!selector.select(10.0, 4).is_fading());
+        assert!(!selector.select(115.0, 2).is_fading());
+        // Sin cross-fade nunca
+        assert!(!lod_selector(false).select(115.0, 4).is_fading());
+    }
+
+    #[test]
+    fn fades_split_the_pattern_between_both_levels() {
+        for step in 0..=32 {
+            let selection = LodSelection {
+                level: 0,
+                fade: step as f32 / 32.0,
+            };
+            let (current, next) = selection.fades();
+            let mut current_pixels = 0;
+            for &bayer in &BAYER {
+                // Cada pixel lo dibuja uno de los dos niveles, nunca los dos ni ninguno
+                assert_ne!(drawn(current, bayer), drawn(next, bayer), "{:?} {}", selection, bayer);
+                if drawn(current, bayer) {
+                    current_pixels += 1;
+                }
+            }
+            // Y el nivel actual se queda con una proporcion de pixeles que va con fade
+            assert!(
+                (current_pixels as f32 / 16.0 - selection.fade).abs() <= 1.0 / 16.0,
+                "{:?}",
+                selection
+            );
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//## Materiales: todo lo que define el aspecto de una superficie (los shaders, las texturas, unos parametros uniform
+//## y el blending) junto en un Material que se guarda en el ResourceManager. Las mallas solo guardan un Handle<Material>,
+//## asi que cambiar el aspecto de una malla es cambiar ese handle.
+//##
+//## Los materiales se describen en ficheros RON (ver src/examples/materials/) que se leen en tiempo de ejecucion:
+//##
+//##     (
+//##         name: "happy-tree",
+//##         vertex_shader: "shader_1_5.vert",
+//##         fragment_shader: "material.frag",
+//##         textures: ["../textures/happy-tree.png"],
+//##         params: (tint: (1.0, 1.0, 1.0, 1.0)),
+//##         blend: Alpha,
+//##     )
+//##
+//## Los shaders se buscan ya compilados (.spv) en src/examples/shaders/ y las texturas son relativas al fichero.
+//## En el set 1 van los parametros en el binding 0 y despues las texturas (textura N en el binding 2 * N + 1, su sampler
+//## en el 2 * N + 2), asi un shader encuentra los parametros en el mismo sitio tenga las texturas que tenga.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use wgpu::util::DeviceExt;
+
+use crate::examples::{
+    sampler_entry, texture_entry, BindGroup, Binding, BlendMode, Handle, PipelineBuilder, ResourceManager, Texture,
+};
+
+// layout(set = 1, binding = 0) uniform MaterialParams { vec4 u_tint; vec4 u_values; };
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct MaterialParams {
+    pub tint: [f32; 4],
+    // Valores libres para lo que necesite cada shader
+    pub values: [f32; 4],
+}
+
+unsafe impl bytemuck::Pod for MaterialParams {}
+unsafe impl bytemuck::Zeroable for MaterialParams {}
+
+impl Default for MaterialParams {
+    fn default() -> Self {
+        Self {
+            tint: [1.0; 4],
+            values: [0.0; 4],
+        }
+    }
+}
+
+// El contenido de un fichero de material
+#[derive(Debug, Deserialize)]
+pub struct MaterialDesc {
+    pub name: String,
+    pub vertex_shader: String,
+    pub fragment_shader: String,
+    #[serde(default)]
+    pub textures: Vec<PathBuf>,
+    #[serde(default)]
+    pub params: MaterialParams,
+    #[serde(default = "default_blend")]
+    pub blend: BlendMode,
+}
+
+fn default_blend() -> BlendMode {
+    BlendMode::Replace
+}
+
+impl MaterialDesc {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).with_context(|| format!("Unable to read {}", path.display()))?;
+        let mut desc: Self = ron::de::from_str(&text).with_context(|| format!("Invalid material {}", path.display()))?;
+        // Las rutas de las texturas son relativas al fichero del material
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        for texture in &mut desc.textures {
+            *texture = dir.join(&texture);
+        }
+        Ok(desc)
+    }
+}
+
+pub struct Material {
+    pub name: String,
+    // El blending va en el pipeline
+    pub pipeline: wgpu::RenderPipeline,
+    pub bind_group: Handle<BindGroup>,
+}
+
+// Lo que el material necesita saber de donde se va a dibujar para crear su pipeline
+pub struct MaterialTarget<'a> {
+    pub globals_layout: &'a wgpu::BindGroupLayout,
+    pub vertex_buffer: wgpu::VertexBufferDescriptor<'a>,
+    pub color_format: wgpu::TextureFormat,
+    pub sample_count: u32,
+}
+
+// Las entradas del layout del set 1 para un material con num_textures texturas
+pub fn material_layout_entries(num_textures: u32) -> Vec<wgpu::BindGroupLayoutEntry> {
+    let params = wgpu::BindGroupLayoutEntry {
+        binding: 0,
+        visibility: wgpu::ShaderStage::FRAGMENT,
+        ty: wgpu::BindingType::UniformBuffer {
+            dynamic: false,
+            min_binding_size: None,
+        },
+        count: None,
+    };
+    std::iter::once(params)
+        .chain((0..num_textures).flat_map(|i| vec![texture_entry(i * 2 + 1), sampler_entry(i * 2 + 2)]))
+        .collect()
+}
+
+// Lee un .spv de src/examples/shaders/ en tiempo de ejecucion (en vez de include_spirv!)
+fn load_shader(device: &wgpu::Device, name: &str) -> Result<wgpu::ShaderModule> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("src/examples/shaders")
+        .join(format!("{}.spv", name));
+    let spirv = std::fs::read(&path).with_context(|| format!("Unable to read {}", path.display()))?;
+    Ok(device.create_shader_module(wgpu::util::make_spirv(&spirv)))
+}
+
+pub fn load_material<P: AsRef<Path>>(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    resources: &mut ResourceManager,
+    target: &MaterialTarget,
+    path: P,
+) -> Result<Handle<Material>> {
+    let desc = MaterialDesc::load(path)?;
+    create_material(device, queue, resources, target, &desc)
+}
+
+pub fn create_material(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    resources: &mut ResourceManager,
+    target: &MaterialTarget,
+    desc: &MaterialDesc,
+) -> Result<Handle<Material>> {
+    let vs_module = load_shader(device, &desc.vertex_shader)?;
+    let fs_module = load_shader(device, &desc.fragment_shader)?;
+
+    let mut bindings = Vec::with_capacity(desc.textures.len() * 2 + 1);
+    for path in &desc.textures {
+        let texture = resources.add_texture(Texture::load(device, queue, path)?);
+        bindings.push(Binding::Texture(texture));
+        bindings.push(Binding::TextureSampler(texture));
+    }
+    let params_buffer = resources.add_buffer(device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some(&desc.name),
+        contents: bytemuck::cast_slice(&[desc.params]),
+        usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+    }));
+    // Los parametros van en el binding 0, delante de las texturas
+    bindings.insert(0, Binding::Buffer(params_buffer));
+
+    let layout = resources.material_layout(device, desc.textures.len() as u32);
+    let bind_group = resources.create_bind_group(device, layout, &bindings, Some(&desc.name));
+    // El bind group ya tiene sus referencias a las texturas y al buffer, los liberara el al liberar el material
+    for &binding in &bindings {
+        match binding {
+            Binding::Texture(texture) => resources.release_texture(texture),
+            Binding::Buffer(buffer) => resources.release_buffer(buffer),
+            _ => {}
+        }
+    }
+
+    let pipeline = PipelineBuilder::new(&vs_module, target.color_format)
+        .label(&desc.name)
+        .fragment_shader(&fs_module)
+        .bind_group_layouts(&[target.globals_layout, resources.bind_group_layout(layout)])
+        .vertex_buffer(target.vertex_buffer.clone())
+        .blend(desc.blend)
+        .sample_count(target.sample_count)
+        .build(device);
+
+    Ok(resources.add_material(Material {
+        name: desc.name.clone(),
+        pipeline,
+        bind_group,
+    }))
+}
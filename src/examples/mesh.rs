@@ -0,0 +1,439 @@
+//## Geometria 3D para los ejemplos con luz: vertices con normal y coordenadas de textura, y la matriz model de cada
+//## instancia en un segundo vertex buffer con step_mode Instance (una matriz por instancia en vez de por vertice).
+//##
+//## Las normales son por cara, asi que el cubo tiene 24 vertices (4 por cara) y no 8: un vertice de la esquina tiene
+//## una normal distinta en cada una de las tres caras que la comparten.
+
+use std::f32::consts::PI;
+use std::mem;
+use std::ops::Range;
+
+use cgmath::{InnerSpace, Matrix4, Vector3};
+use wgpu::util::DeviceExt;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct MeshVertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub tex_coords: [f32; 2],
+}
+
+unsafe impl bytemuck::Pod for MeshVertex {}
+unsafe impl bytemuck::Zeroable for MeshVertex {}
+
+impl MeshVertex {
+    pub fn desc<'a>() -> wgpu::VertexBufferDescriptor<'a> {
+        wgpu::VertexBufferDescriptor {
+            stride: mem::size_of::<MeshVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::InputStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttributeDescriptor {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float3,
+                },
+                wgpu::VertexAttributeDescriptor {
+                    offset: mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float3,
+                },
+                wgpu::VertexAttributeDescriptor {
+                    offset: mem::size_of::<[f32; 6]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float2,
+                },
+            ],
+        }
+    }
+}
+
+// MeshVertex con la tangente, para los normal maps. La w de la tangente es el signo de la bitangente (+1 o -1), que
+// se reconstruye en el shader como cross(normal, tangent.xyz) * tangent.w
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct TangentVertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    pub tex_coords: [f32; 2],
+    pub tangent: [f32; 4],
+}
+
+unsafe impl bytemuck::Pod for TangentVertex {}
+unsafe impl bytemuck::Zeroable for TangentVertex {}
+
+impl TangentVertex {
+    // Las mismas locations que MeshVertex y la tangente en la 3
+    pub fn desc<'a>() -> wgpu::VertexBufferDescriptor<'a> {
+        wgpu::VertexBufferDescriptor {
+            stride: mem::size_of::<TangentVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::InputStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttributeDescriptor {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float3,
+                },
+                wgpu::VertexAttributeDescriptor {
+                    offset: mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float3,
+                },
+                wgpu::VertexAttributeDescriptor {
+                    offset: mem::size_of::<[f32; 6]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float2,
+                },
+                wgpu::VertexAttributeDescriptor {
+                    offset: mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float4,
+                },
+            ],
+        }
+    }
+}
+
+// Tangentes por vertice con las mismas convenciones que MikkTSpace: la tangente va hacia donde crece u y la bitangente
+// hacia donde crece v, cada triangulo aporta a sus vertices en proporcion al angulo de su esquina, y al final la
+// tangente se hace perpendicular a la normal (Gram-Schmidt) y se guarda el signo de la bitangente en la w.
+// A diferencia de MikkTSpace no se separan vertices: si dos triangulos que comparten un vertice tienen las uv en
+// espejo, la malla ya tiene que tener ese vertice duplicado (como en las costuras de las uv)
+pub fn generate_tangents(vertices: &[MeshVertex], indices: &[u16]) -> Vec<TangentVertex> {
+    let zero = Vector3::new(0.0, 0.0, 0.0);
+    let mut tangents = vec![zero; vertices.len()];
+    let mut bitangents = vec![zero; vertices.len()];
+
+    for triangle in indices.chunks_exact(3) {
+        let corners = [triangle[0] as usize, triangle[1] as usize, triangle[2] as usize];
+        let positions = [
+            Vector3::from(vertices[corners[0]].position),
+            Vector3::from(vertices[corners[1]].position),
+            Vector3::from(vertices[corners[2]].position),
+        ];
+        let [p0, p1, p2] = positions;
+        let uv0 = vertices[corners[0]].tex_coords;
+        let uv1 = vertices[corners[1]].tex_coords;
+        let uv2 = vertices[corners[2]].tex_coords;
+
+        let (edge1, edge2) = (p1 - p0, p2 - p0);
+        let (du1, dv1) = (uv1[0] - uv0[0], uv1[1] - uv0[1]);
+        let (du2, dv2) = (uv2[0] - uv0[0], uv2[1] - uv0[1]);
+        let det = du1 * dv2 - du2 * dv1;
+        // Sin area en las uv no hay direccion de u ni de v
+        if det.abs() < 1e-12 {
+            continue;
+        }
+        let tangent = (edge1 * dv2 - edge2 * dv1) / det;
+        let bitangent = (edge2 * du1 - edge1 * du2) / det;
+
+        for (k, &i) in corners.iter().enumerate() {
+            let a = positions[(k + 1) % 3] - positions[k];
+            let b = positions[(k + 2) % 3] - positions[k];
+            let cos = a.dot(b) / (a.magnitude() * b.magnitude());
+            let angle = if cos.is_nan() { 0.0 } else { cos.clamp(-1.0, 1.0).acos() };
+            tangents[i] += tangent * angle;
+            bitangents[i] += bitangent * angle;
+        }
+    }
+
+    vertices
+        .iter()
+        .zip(tangents.iter().zip(&bitangents))
+        .map(|(vertex, (&tangent, &bitangent))| {
+            let normal = Vector3::from(vertex.normal).normalize();
+            let mut t = tangent - normal * normal.dot(tangent);
+            // Vertices sin triangulos con uv validas: cualquier perpendicular a la normal
+            if t.magnitude2() < 1e-12 {
+                let axis = if normal.x.abs() < 0.9 { Vector3::unit_x() } else { Vector3::unit_y() };
+                t = axis - normal * normal.dot(axis);
+            }
+            let t = t.normalize();
+            let w = if normal.cross(t).dot(bitangent) < 0.0 { -1.0 } else { 1.0 };
+            TangentVertex {
+                position: vertex.position,
+                normal: vertex.normal,
+                tex_coords: vertex.tex_coords,
+                tangent: [t.x, t.y, t.z, w],
+            }
+        })
+        .collect()
+}
+
+// Cubo centrado en el origen de lado 2 * half_size, triangulos CCW vistos desde fuera
+pub fn cube_mesh(half_size: f32) -> (Vec<MeshVertex>, Vec<u16>) {
+    // (normal, u, v) de cada cara, con u x v = normal para que el orden de los vertices salga CCW
+    let faces: [([f32; 3], [f32; 3], [f32; 3]); 6] = [
+        ([1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0]),
+        ([-1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]),
+        ([0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, -1.0]),
+        ([0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+        ([0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+        ([0.0, 0.0, -1.0], [-1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+    ];
+
+    let mut vertices = Vec::with_capacity(24);
+    let mut indices = Vec::with_capacity(36);
+    for (normal, u, v) in faces.iter() {
+        let base = vertices.len() as u16;
+        for &(su, sv, tex_coords) in &[
+            (-1.0, -1.0, [0.0, 1.0]),
+            (1.0, -1.0, [1.0, 1.0]),
+            (1.0, 1.0, [1.0, 0.0]),
+            (-1.0, 1.0, [0.0, 0.0]),
+        ] {
+            let mut position = [0.0; 3];
+            for (i, p) in position.iter_mut().enumerate() {
+                *p = (normal[i] + u[i] * su + v[i] * sv) * half_size;
+            }
+            vertices.push(MeshVertex {
+                position,
+                normal: *normal,
+                tex_coords,
+            });
+        }
+        indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+    (vertices, indices)
+}
+
+// Cuadrado horizontal (en el plano y = 0) de lado 2 * half_size mirando hacia arriba, para usar como suelo
+pub fn plane_mesh(half_size: f32) -> (Vec<MeshVertex>, Vec<u16>) {
+    let corners = [(-1.0, 1.0), (1.0, 1.0), (1.0, -1.0), (-1.0, -1.0)];
+    let vertices = corners
+        .iter()
+        .map(|&(x, z)| MeshVertex {
+            position: [x * half_size, 0.0, z * half_size],
+            normal: [0.0, 1.0, 0.0],
+            tex_coords: [(x + 1.0) * 0.5, (1.0 - z) * 0.5],
+        })
+        .collect();
+    (vertices, vec![0, 1, 2, 0, 2, 3])
+}
+
+// Esfera UV: sectors divisiones en longitud y stacks en latitud. La costura y los polos repiten vertices para que
+// las coordenadas de textura no den un salto
+pub fn sphere_mesh(radius: f32, sectors: u32, stacks: u32) -> (Vec<MeshVertex>, Vec<u16>) {
+    let mut vertices = Vec::with_capacity(((sectors + 1) * (stacks + 1)) as usize);
+    for i in 0..=stacks {
+        // Desde el polo norte (phi = 0) hasta el sur (phi = PI)
+        let phi = PI * i as f32 / stacks as f32;
+        for j in 0..=sectors {
+            let theta = 2.0 * PI * j as f32 / sectors as f32;
+            let normal = [phi.sin() * theta.cos(), phi.cos(), phi.sin() * theta.sin()];
+            vertices.push(MeshVertex {
+                position: [normal[0] * radius, normal[1] * radius, normal[2] * radius],
+                normal,
+                tex_coords: [j as f32 / sectors as f32, i as f32 / stacks as f32],
+            });
+        }
+    }
+
+    let mut indices = Vec::with_capacity((sectors * stacks * 6) as usize);
+    for i in 0..stacks {
+        for j in 0..sectors {
+            let k1 = (i * (sectors + 1) + j) as u16;
+            let k2 = k1 + sectors as u16 + 1;
+            // En los polos uno de los dos triangulos del cuadrado tiene dos vertices en el mismo punto
+            if i != 0 {
+                indices.extend_from_slice(&[k1, k1 + 1, k2]);
+            }
+            if i != stacks - 1 {
+                indices.extend_from_slice(&[k1 + 1, k2 + 1, k2]);
+            }
+        }
+    }
+    (vertices, indices)
+}
+
+// Los buffers de una malla ya subida a la GPU
+pub struct GpuMesh {
+    pub vertex_buffer: wgpu::Buffer,
+    pub index_buffer: wgpu::Buffer,
+    pub num_indices: u32,
+}
+
+impl GpuMesh {
+    // Vale para cualquier tipo de vertice (MeshVertex, TangentVertex...)
+    pub fn new<V: bytemuck::Pod>(device: &wgpu::Device, vertices: &[V], indices: &[u16], label: &str) -> Self {
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents: bytemuck::cast_slice(vertices),
+            usage: wgpu::BufferUsage::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents: bytemuck::cast_slice(indices),
+            usage: wgpu::BufferUsage::INDEX,
+        });
+        Self {
+            vertex_buffer,
+            index_buffer,
+            num_indices: indices.len() as u32,
+        }
+    }
+
+    // Con las matrices de las instancias en el slot 1. El pipeline y los bind groups ya tienen que estar puestos
+    pub fn draw_instanced<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        instance_buffer: &'a wgpu::Buffer,
+        instances: Range<u32>,
+    ) {
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..));
+        render_pass.draw_indexed(0..self.num_indices, 0, instances);
+    }
+}
+
+// La matriz model de una instancia, en el vertex buffer del slot 1
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct InstanceData {
+    pub model: [[f32; 4]; 4],
+}
+
+unsafe impl bytemuck::Pod for InstanceData {}
+unsafe impl bytemuck::Zeroable for InstanceData {}
+
+impl InstanceData {
+    pub fn new(model: Matrix4<f32>) -> Self {
+        Self { model: model.into() }
+    }
+
+    // Un atributo solo puede ser como mucho un vec4, asi que la mat4 ocupa 4 locations, una por columna.
+    // Empiezan en la 5 para dejar sitio a mas atributos de vertice
+    pub fn desc<'a>() -> wgpu::VertexBufferDescriptor<'a> {
+        wgpu::VertexBufferDescriptor {
+            stride: mem::size_of::<InstanceData>() as wgpu::BufferAddress,
+            step_mode: wgpu::InputStepMode::Instance,
+            attributes: &[
+                wgpu::VertexAttributeDescriptor {
+                    offset: 0,
+                    shader_location: 5,
+                    format: wgpu::VertexFormat::Float4,
+                },
+                wgpu::VertexAttributeDescriptor {
+                    offset: mem::size_of::<[f32; 4]>() as wgpu::BufferAddress,
+                    shader_location: 6,
+                    format: wgpu::VertexFormat::Float4,
+                },
+                wgpu::VertexAttributeDescriptor {
+                    offset: mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
+                    shader_location: 7,
+                    format: wgpu::VertexFormat::Float4,
+                },
+                wgpu::VertexAttributeDescriptor {
+                    offset: mem::size_of::<[f32; 12]>() as wgpu::BufferAddress,
+                    shader_location: 8,
+                    format: wgpu::VertexFormat::Float4,
+                },
+            ],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Vector3<f32>, b: Vector3<f32>) {
+        assert!((a - b).magnitude() < 1e-4, "{:?} != {:?}", a, b);
+    }
+
+    fn tangent(vertex: &TangentVertex) -> Vector3<f32> {
+        Vector3::new(vertex.tangent[0], vertex.tangent[1], vertex.tangent[2])
+    }
+
+    fn bitangent(vertex: &TangentVertex) -> Vector3<f32> {
+        Vector3::from(vertex.normal).cross(tangent(vertex)) * vertex.tangent[3]
+    }
+
+    fn assert_orthonormal(vertices: &[TangentVertex]) {
+        for vertex in vertices {
+            let t = tangent(vertex);
+            assert!((t.magnitude() - 1.0).abs() < 1e-4, "{:?}", vertex);
+            assert!(Vector3::from(vertex.normal).dot(t).abs() < 1e-4, "{:?}", vertex);
+            assert!(vertex.tangent[3] == 1.0 || vertex.tangent[3] == -1.0, "{:?}", vertex);
+        }
+    }
+
+    #[test]
+    fn plane_tangent_follows_u() {
+        let (vertices, indices) = plane_mesh(1.0);
+        let vertices = generate_tangents(&vertices, &indices);
+        for vertex in &vertices {
+            assert_eq!(vertex.tangent, [1.0, 0.0, 0.0, 1.0]);
+            // v crece hacia -z
+            assert_close(bitangent(vertex), Vector3::new(0.0, 0.0, -1.0));
+        }
+    }
+
+    #[test]
+    fn cube_tangents_follow_uv_directions() {
+        let (vertices, indices) = cube_mesh(0.5);
+        let tangent_vertices = generate_tangents(&vertices, &indices);
+        assert_orthonormal(&tangent_vertices);
+        // En cada cara el vertice 0 tiene uv (0, 1), el 1 (1, 1) y el 3 (0, 0)
+        for face in tangent_vertices.chunks_exact(4) {
+            let p = |i: usize| Vector3::from(face[i].position);
+            let along_u = (p(1) - p(0)).normalize();
+            let along_v = (p(0) - p(3)).normalize();
+            for vertex in face {
+                assert_close(tangent(vertex), along_u);
+                assert_close(bitangent(vertex), along_v);
+            }
+        }
+    }
+
+    #[test]
+    fn sphere_tangents_are_orthonormal() {
+        let (vertices, indices) = sphere_mesh(2.0, 16, 8);
+        assert_orthonormal(&generate_tangents(&vertices, &indices));
+    }
+
+    #[test]
+    fn mirrored_uvs_flip_the_bitangent_sign() {
+        let (mut vertices, indices) = plane_mesh(1.0);
+        for vertex in &mut vertices {
+            vertex.tex_coords[0] = 1.0 - vertex.tex_coords[0];
+        }
+        let vertices = generate_tangents(&vertices, &indices);
+        for vertex in &vertices {
+            assert_eq!(vertex.tangent, [-1.0, 0.0, 0.0, -1.0]);
+            assert_close(bitangent(vertex), Vector3::new(0.0, 0.0, -1.0));
+        }
+    }
+
+    #[test]
+    fn degenerate_uvs_still_give_a_valid_tangent() {
+        let (mut vertices, indices) = cube_mesh(1.0);
+        for vertex in &mut vertices {
+            vertex.tex_coords = [0.5, 0.5];
+        }
+        assert_orthonormal(&generate_tangents(&vertices, &indices));
+    }
+
+    #[test]
+    fn tangents_are_averaged_over_shared_vertices() {
+        // Dos triangulos que comparten una arista y doblan 90 grados: la tangente del vertice compartido queda en
+        // medio de las de los dos triangulos
+        let vertex = |position: [f32; 3], tex_coords: [f32; 2]| MeshVertex {
+            position,
+            normal: [0.0, 1.0, 1.0],
+            tex_coords,
+        };
+        let vertices = vec![
+            vertex([0.0, 0.0, 0.0], [0.0, 1.0]),
+            vertex([0.0, 0.0, -1.0], [0.0, 0.0]),
+            vertex([1.0, 0.0, 0.0], [1.0, 1.0]),
+            vertex([0.0, -1.0, 0.0], [-1.0, 1.0]),
+        ];
+        let tangent_vertices = generate_tangents(&vertices, &[0, 2, 1, 0, 1, 3]);
+        assert_orthonormal(&tangent_vertices);
+        assert_close(tangent(&tangent_vertices[2]), Vector3::new(1.0, 0.0, 0.0));
+        let shared = tangent(&tangent_vertices[0]);
+        assert!(shared.x > 0.0 && shared.y > 0.0, "{:?}", shared);
+    }
+}
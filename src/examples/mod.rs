@@ -16,5 +16,83 @@ mod _1_5_textures;
 pub use self::_1_5_textures::*;
 mod _1_5_1_challenge;
 pub use self::_1_5_1_challenge::*;
+mod _1_6_lighting;
+pub use self::_1_6_lighting::*;
+mod _1_7_shadows;
+pub use self::_1_7_shadows::*;
+mod _1_8_skybox;
+pub use self::_1_8_skybox::*;
+mod _1_9_normal_mapping;
+pub use self::_1_9_normal_mapping::*;
+mod _1_10_pbr;
+pub use self::_1_10_pbr::*;
+mod _1_11_post_processing;
+pub use self::_1_11_post_processing::*;
+mod _1_12_deferred;
+pub use self::_1_12_deferred::*;
+mod _2_1_game_of_life;
+pub use self::_2_1_game_of_life::*;
+mod _2_2_transparency;
+pub use self::_2_2_transparency::*;
+mod _2_3_sprites;
+pub use self::_2_3_sprites::*;
 mod texture;
-pub use self::texture::*;
\ No newline at end of file
+pub use self::texture::*;
+mod globals;
+pub use self::globals::*;
+mod shadertoy;
+pub use self::shadertoy::*;
+mod compute;
+pub use self::compute::*;
+mod readback;
+pub use self::readback::*;
+mod capture;
+pub use self::capture::*;
+mod msaa;
+pub use self::msaa::*;
+mod blend;
+pub use self::blend::*;
+mod pipeline;
+pub use self::pipeline::*;
+mod debug_view;
+pub use self::debug_view::*;
+mod resources;
+pub use self::resources::*;
+mod material;
+pub use self::material::*;
+mod atlas;
+pub use self::atlas::*;
+mod sprite_batch;
+pub use self::sprite_batch::*;
+mod text;
+pub use self::text::*;
+mod ui;
+pub use self::ui::*;
+mod depth;
+pub use self::depth::*;
+mod camera;
+pub use self::camera::*;
+mod mesh;
+pub use self::mesh::*;
+mod lighting;
+pub use self::lighting::*;
+mod shadow;
+pub use self::shadow::*;
+mod skybox;
+pub use self::skybox::*;
+mod pbr;
+pub use self::pbr::*;
+mod postprocess;
+pub use self::postprocess::*;
+mod render_graph;
+pub use self::render_graph::*;
+mod auto_exposure;
+pub use self::auto_exposure::*;
+mod deferred;
+pub use self::deferred::*;
+mod bounds;
+pub use self::bounds::*;
+mod simplify;
+pub use self::simplify::*;
+mod lod;
+pub use self::lod::*;
\ No newline at end of file
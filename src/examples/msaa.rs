@@ -0,0 +1,167 @@
+//## MSAA: el pipeline rasteriza cada pixel con varias muestras (sample_count) sobre una textura multisampled, y al
+//## acabar el render pass esa textura se "resuelve" (se promedian las muestras) en la textura final a traves del
+//## resolve_target del color attachment. El swap chain no puede ser multisampled, por eso hace falta la textura intermedia.
+//##
+//## El numero de muestras se elige con `--msaa N` en la linea de comandos, por ejemplo `cargo run 1_4 --msaa 8`. Sin
+//## --msaa no hay MSAA. main.rs quita el argumento antes de llamar al ejemplo y le pasa el numero.
+
+pub const DEFAULT_SAMPLE_COUNT: u32 = 1;
+
+// Numeros de muestras que soporta el backend. WebGPU solo garantiza 1 y 4, en nativo (Vulkan, Metal, DX12) los
+// formatos de color que usamos soportan 1, 2, 4 y 8. wgpu 0.6 no deja consultar el limite exacto por formato.
+pub fn supported_sample_counts(adapter: &wgpu::Adapter) -> &'static [u32] {
+    match adapter.get_info().backend {
+        wgpu::Backend::BrowserWebGpu => &[1, 4],
+        _ => &[1, 2, 4, 8],
+    }
+}
+
+// Quita `--msaa N` de los argumentos y devuelve N, para que los ejemplos que reciben rutas (1_8, toy) no vean el
+// argumento como una de ellas
+pub fn take_msaa_arg(args: &mut Vec<String>) -> Option<u32> {
+    let position = args.iter().position(|arg| arg == "--msaa")?;
+    args.remove(position);
+    match args.get(position).map(|value| value.parse::<u32>()) {
+        Some(Ok(count)) => {
+            args.remove(position);
+            Some(count)
+        }
+        _ => {
+            eprintln!("--msaa expects a sample count (1, 2, 4 or 8)");
+            None
+        }
+    }
+}
+
+// El mayor sample count de supported que no pasa de requested (o 1 si no hay ninguno)
+fn closest_sample_count(supported: &[u32], requested: u32) -> u32 {
+    supported
+        .iter()
+        .copied()
+        .filter(|&count| count <= requested)
+        .max()
+        .unwrap_or(1)
+}
+
+// Devuelve el sample count pedido si el adapter lo soporta, si no el mayor soportado que sea menor
+pub fn validate_sample_count(adapter: &wgpu::Adapter, requested: u32) -> u32 {
+    let supported = supported_sample_counts(adapter);
+    if supported.contains(&requested) {
+        return requested;
+    }
+    let fallback = closest_sample_count(supported, requested);
+    eprintln!(
+        "MSAA x{} is not supported by this adapter (supported: {:?}), using x{}",
+        requested, supported, fallback
+    );
+    fallback
+}
+
+// Sample count con el que crear los pipelines del ejemplo: el que llega de main.rs (--msaa o DEFAULT_SAMPLE_COUNT),
+// ya validado
+pub fn msaa_sample_count(adapter: &wgpu::Adapter, requested: u32) -> u32 {
+    validate_sample_count(adapter, requested)
+}
+
+// Textura de color multisampled del tamaño del swap chain. Con sample_count 1 no se crea nada y se dibuja
+// directamente en el frame.
+pub struct Multisample {
+    pub sample_count: u32,
+    view: Option<wgpu::TextureView>,
+}
+
+impl Multisample {
+    pub fn new(device: &wgpu::Device, sc_desc: &wgpu::SwapChainDescriptor, sample_count: u32) -> Self {
+        Self {
+            sample_count,
+            view: Self::create_view(device, sc_desc, sample_count),
+        }
+    }
+
+    fn create_view(
+        device: &wgpu::Device,
+        sc_desc: &wgpu::SwapChainDescriptor,
+        sample_count: u32,
+    ) -> Option<wgpu::TextureView> {
+        if sample_count == 1 {
+            return None;
+        }
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Multisampled Framebuffer"),
+            size: wgpu::Extent3d {
+                width: sc_desc.width,
+                height: sc_desc.height,
+                depth: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: sc_desc.format,
+            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+        });
+        Some(texture.create_view(&wgpu::TextureViewDescriptor::default()))
+    }
+
+    // Hay que recrearla con el swap chain, tiene que tener el mismo tamaño que el resolve target
+    pub fn resize(&mut self, device: &wgpu::Device, sc_desc: &wgpu::SwapChainDescriptor) {
+        self.view = Self::create_view(device, sc_desc, self.sample_count);
+    }
+
+    // (attachment, resolve_target) para el RenderPassColorAttachmentDescriptor que acaba en `target`
+    pub fn color_attachment<'a>(
+        &'a self,
+        target: &'a wgpu::TextureView,
+    ) -> (&'a wgpu::TextureView, Option<&'a wgpu::TextureView>) {
+        match &self.view {
+            Some(view) => (view, Some(target)),
+            None => (target, None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn msaa_arg_is_removed_from_the_middle() {
+        let mut list = args(&["toy", "--msaa", "8", "shader.glsl", "channel0.png"]);
+        assert_eq!(take_msaa_arg(&mut list), Some(8));
+        assert_eq!(list, args(&["toy", "shader.glsl", "channel0.png"]));
+    }
+
+    #[test]
+    fn no_msaa_arg_keeps_the_args() {
+        let mut list = args(&["1_8", "sky.hdr"]);
+        assert_eq!(take_msaa_arg(&mut list), None);
+        assert_eq!(list, args(&["1_8", "sky.hdr"]));
+    }
+
+    #[test]
+    fn msaa_arg_without_value() {
+        let mut list = args(&["1_4", "--msaa"]);
+        assert_eq!(take_msaa_arg(&mut list), None);
+        assert_eq!(list, args(&["1_4"]));
+    }
+
+    #[test]
+    fn msaa_arg_with_a_value_that_is_not_a_number() {
+        // El valor se queda: lo mas probable es que sea el argumento siguiente y falte el numero
+        let mut list = args(&["toy", "--msaa", "shader.glsl"]);
+        assert_eq!(take_msaa_arg(&mut list), None);
+        assert_eq!(list, args(&["toy", "shader.glsl"]));
+    }
+
+    #[test]
+    fn unsupported_counts_fall_back_to_the_closest_lower_one() {
+        let native = [1, 2, 4, 8];
+        assert_eq!(closest_sample_count(&native, 3), 2);
+        assert_eq!(closest_sample_count(&native, 16), 8);
+        assert_eq!(closest_sample_count(&[1, 4], 2), 1);
+        assert_eq!(closest_sample_count(&native, 0), 1);
+    }
+}
@@ -0,0 +1,501 @@
+//## PBR (physically based rendering) con el modelo metallic-roughness de glTF. Un PbrMaterial son unos factores
+//## (PbrParams) y hasta 5 texturas: color base, metallic-roughness, normal map, oclusion y emisiva. Las que no tiene
+//## se sustituyen por texturas de 1x1 (PbrDefaults) que no cambian el resultado, asi el shader siempre es el mismo.
+//## Van en el set 1 como en material_layout_entries(5): los parametros en el binding 0 y la textura N en el 2 * N + 1.
+//##
+//## Environment es la luz del cielo (image based lighting). A partir del cubemap del skybox se calculan en la GPU,
+//## una sola vez:
+//##   - El mapa de irradiancia: la luz difusa que llega a una superficie con cada normal.
+//##   - El mapa especular prefiltrado: el cielo borroso segun la rugosidad, un mip por nivel de rugosidad.
+//##   - La tabla de la BRDF: lo que falta del split sum, que solo depende del angulo de vista y la rugosidad.
+//## Como no quedan mas sets libres, el bind group de Environment lleva tambien el buffer de las luces (como
+//## ShadowMap) y sustituye al de LightsUniform en el set 3:
+//##
+//## layout(set=3, binding=0) uniform Lights { ... };             // el de lighting.rs
+//## layout(set=3, binding=1) uniform textureCube t_irradiance;
+//## layout(set=3, binding=2) uniform textureCube t_prefiltered;
+//## layout(set=3, binding=3) uniform texture2D t_brdf_lut;
+//## layout(set=3, binding=4) uniform sampler s_environment;
+//## layout(set=3, binding=5) uniform Environment { float u_intensity; float u_max_lod; };
+
+use std::iter;
+use std::num::NonZeroU32;
+
+use wgpu::util::DeviceExt;
+
+use crate::examples::{material_layout_entries, LightsData, LightsUniform, PipelineBuilder, Texture};
+
+pub const IRRADIANCE_SIZE: u32 = 32;
+pub const PREFILTERED_SIZE: u32 = 128;
+// 128, 64, 32, 16 y 8: el ultimo es para rugosidad 1
+pub const PREFILTERED_MIP_LEVELS: u32 = 5;
+pub const BRDF_LUT_SIZE: u32 = 256;
+// El cielo puede tener mas de 1.0 (el sol), asi que los mapas son de coma flotante
+const ENVIRONMENT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+const BRDF_LUT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rg16Float;
+const NUM_PBR_TEXTURES: u32 = 5;
+
+// Tiene que coincidir con el bloque PbrParams de pbr.frag (std140). Multiplican a lo que se lee de las texturas
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct PbrParams {
+    pub base_color: [f32; 4],
+    // rgb, la w no se usa
+    pub emissive: [f32; 4],
+    pub metallic: f32,
+    pub roughness: f32,
+    // Escala la inclinacion (xy) de las normales del normal map
+    pub normal_scale: f32,
+    // 0 ignora la textura de oclusion, 1 la aplica entera
+    pub occlusion_strength: f32,
+}
+
+unsafe impl bytemuck::Pod for PbrParams {}
+unsafe impl bytemuck::Zeroable for PbrParams {}
+
+impl Default for PbrParams {
+    fn default() -> Self {
+        Self {
+            base_color: [1.0; 4],
+            emissive: [0.0; 4],
+            metallic: 0.0,
+            roughness: 0.5,
+            normal_scale: 1.0,
+            occlusion_strength: 1.0,
+        }
+    }
+}
+
+// Las texturas de un material. El color base y la emisiva son colores (sRGB, Texture::from_image) y las demas son
+// datos (Texture::from_image_linear)
+#[derive(Default)]
+pub struct PbrTextures<'a> {
+    pub base_color: Option<&'a Texture>,
+    // Rugosidad en el verde y metalicidad en el azul
+    pub metallic_roughness: Option<&'a Texture>,
+    pub normal: Option<&'a Texture>,
+    // En el rojo
+    pub occlusion: Option<&'a Texture>,
+    pub emissive: Option<&'a Texture>,
+}
+
+// Texturas de 1x1 para los huecos de los materiales, se crean una vez y las comparten todos
+pub struct PbrDefaults {
+    white: Texture,
+    white_linear: Texture,
+    flat_normal: Texture,
+    black: Texture,
+}
+
+impl PbrDefaults {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let pixel = |rgba: [u8; 4]| {
+            image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(1, 1, image::Rgba(rgba)))
+        };
+        let color = |rgba, label| Texture::from_image(device, queue, &pixel(rgba), Some(label)).unwrap();
+        let data = |rgba, label| Texture::from_image_linear(device, queue, &pixel(rgba), Some(label)).unwrap();
+        Self {
+            white: color([255; 4], "PBR White"),
+            white_linear: data([255; 4], "PBR White Linear"),
+            flat_normal: data([128, 128, 255, 255], "PBR Flat Normal"),
+            black: color([0, 0, 0, 255], "PBR Black"),
+        }
+    }
+}
+
+pub struct PbrMaterial {
+    buffer: wgpu::Buffer,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl PbrMaterial {
+    // El layout del set 1 que usan todos los materiales PBR
+    pub fn create_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &material_layout_entries(NUM_PBR_TEXTURES),
+            label: Some("pbr_material_bind_group_layout"),
+        })
+    }
+
+    pub fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        defaults: &PbrDefaults,
+        textures: &PbrTextures,
+        params: PbrParams,
+    ) -> Self {
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("PBR Params Buffer"),
+            contents: bytemuck::cast_slice(&[params]),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+
+        let slots = [
+            textures.base_color.unwrap_or(&defaults.white),
+            textures.metallic_roughness.unwrap_or(&defaults.white_linear),
+            textures.normal.unwrap_or(&defaults.flat_normal),
+            textures.occlusion.unwrap_or(&defaults.white_linear),
+            textures.emissive.unwrap_or(&defaults.black),
+        ];
+        let mut entries = vec![wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::Buffer(buffer.slice(..)),
+        }];
+        for (i, texture) in slots.iter().enumerate() {
+            entries.push(wgpu::BindGroupEntry {
+                binding: i as u32 * 2 + 1,
+                resource: wgpu::BindingResource::TextureView(&texture.view),
+            });
+            entries.push(wgpu::BindGroupEntry {
+                binding: i as u32 * 2 + 2,
+                resource: wgpu::BindingResource::Sampler(&texture.sampler),
+            });
+        }
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &entries,
+            label: Some("pbr_material_bind_group"),
+        });
+
+        Self { buffer, bind_group }
+    }
+
+    pub fn update(&self, queue: &wgpu::Queue, params: PbrParams) {
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[params]));
+    }
+}
+
+// Que cara del cubo (y con que rugosidad, para el mapa prefiltrado) dibujan los shaders ibl_*.frag
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+struct FaceData {
+    face: u32,
+    roughness: f32,
+    _padding: [u32; 2],
+}
+
+unsafe impl bytemuck::Pod for FaceData {}
+unsafe impl bytemuck::Zeroable for FaceData {}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+struct EnvironmentData {
+    intensity: f32,
+    max_lod: f32,
+    _padding: [u32; 2],
+}
+
+unsafe impl bytemuck::Pod for EnvironmentData {}
+unsafe impl bytemuck::Zeroable for EnvironmentData {}
+
+pub struct Environment {
+    // Multiplica la luz del cielo. Se sube con update()
+    pub intensity: f32,
+    buffer: wgpu::Buffer,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl Environment {
+    // sky tiene que ser un cubemap (Texture::cube_from_*)
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, sky: &Texture, lights: &LightsUniform) -> Self {
+        let irradiance = Self::create_cube_texture(device, IRRADIANCE_SIZE, 1, "Irradiance Map");
+        let prefiltered =
+            Self::create_cube_texture(device, PREFILTERED_SIZE, PREFILTERED_MIP_LEVELS, "Prefiltered Map");
+        let brdf_lut = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("BRDF LUT"),
+            size: wgpu::Extent3d {
+                width: BRDF_LUT_SIZE,
+                height: BRDF_LUT_SIZE,
+                depth: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: BRDF_LUT_FORMAT,
+            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Environment Encoder"),
+        });
+        Self::filter_sky(device, &mut encoder, sky, &irradiance, &prefiltered);
+        Self::render_brdf_lut(device, &mut encoder, &brdf_lut);
+        queue.submit(iter::once(encoder.finish()));
+
+        let cube_view = |texture: &wgpu::Texture| {
+            texture.create_view(&wgpu::TextureViewDescriptor {
+                dimension: Some(wgpu::TextureViewDimension::Cube),
+                ..Default::default()
+            })
+        };
+        let irradiance_view = cube_view(&irradiance);
+        let prefiltered_view = cube_view(&prefiltered);
+        let brdf_lut_view = brdf_lut.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Environment Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let intensity = 1.0;
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Environment Buffer"),
+            contents: bytemuck::cast_slice(&[Self::data(intensity)]),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+
+        let texture_entry = |binding, dimension| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStage::FRAGMENT,
+            ty: wgpu::BindingType::SampledTexture {
+                multisampled: false,
+                dimension,
+                component_type: wgpu::TextureComponentType::Float,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::VERTEX | wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::UniformBuffer {
+                        dynamic: false,
+                        min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<LightsData>() as _),
+                    },
+                    count: None,
+                },
+                texture_entry(1, wgpu::TextureViewDimension::Cube),
+                texture_entry(2, wgpu::TextureViewDimension::Cube),
+                texture_entry(3, wgpu::TextureViewDimension::D2),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler { comparison: false },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::UniformBuffer {
+                        dynamic: false,
+                        min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<EnvironmentData>() as _),
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("environment_bind_group_layout"),
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(lights.buffer.slice(..)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&irradiance_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&prefiltered_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&brdf_lut_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: wgpu::BindingResource::Buffer(buffer.slice(..)),
+                },
+            ],
+            label: Some("environment_bind_group"),
+        });
+
+        Self {
+            intensity,
+            buffer,
+            bind_group_layout,
+            bind_group,
+        }
+    }
+
+    fn data(intensity: f32) -> EnvironmentData {
+        EnvironmentData {
+            intensity,
+            max_lod: (PREFILTERED_MIP_LEVELS - 1) as f32,
+            _padding: [0; 2],
+        }
+    }
+
+    pub fn update(&self, queue: &wgpu::Queue) {
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[Self::data(self.intensity)]));
+    }
+
+    fn create_cube_texture(device: &wgpu::Device, size: u32, mip_level_count: u32, label: &str) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth: 6,
+            },
+            mip_level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: ENVIRONMENT_FORMAT,
+            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+        })
+    }
+
+    // Dibuja cada cara (y cada mip del prefiltrado) con un triangulo que cubre la pantalla, leyendo el cielo
+    fn filter_sky(
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        sky: &Texture,
+        irradiance: &wgpu::Texture,
+        prefiltered: &wgpu::Texture,
+    ) {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::UniformBuffer {
+                        dynamic: false,
+                        min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<FaceData>() as _),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::SampledTexture {
+                        multisampled: false,
+                        dimension: wgpu::TextureViewDimension::Cube,
+                        component_type: wgpu::TextureComponentType::Float,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler { comparison: false },
+                    count: None,
+                },
+            ],
+            label: Some("environment_face_bind_group_layout"),
+        });
+
+        let vs_module = device.create_shader_module(wgpu::include_spirv!("shaders/fullscreen.vert.spv"));
+        let irradiance_module = device.create_shader_module(wgpu::include_spirv!("shaders/ibl_irradiance.frag.spv"));
+        let prefilter_module = device.create_shader_module(wgpu::include_spirv!("shaders/ibl_prefilter.frag.spv"));
+        let pipeline = |label, fs_module| {
+            PipelineBuilder::new(&vs_module, ENVIRONMENT_FORMAT)
+                .label(label)
+                .fragment_shader(fs_module)
+                .bind_group_layouts(&[&layout])
+                .build(device)
+        };
+        let irradiance_pipeline = pipeline("Irradiance Pipeline", &irradiance_module);
+        let prefilter_pipeline = pipeline("Prefilter Pipeline", &prefilter_module);
+
+        let mut targets = Vec::new();
+        for face in 0..6 {
+            targets.push((&irradiance_pipeline, irradiance, 0, face, 0.0));
+            for mip_level in 0..PREFILTERED_MIP_LEVELS {
+                let roughness = mip_level as f32 / (PREFILTERED_MIP_LEVELS - 1) as f32;
+                targets.push((&prefilter_pipeline, prefiltered, mip_level, face, roughness));
+            }
+        }
+
+        for (pipeline, texture, mip_level, face, roughness) in targets {
+            let view = texture.create_view(&wgpu::TextureViewDescriptor {
+                label: Some("Environment Face"),
+                dimension: Some(wgpu::TextureViewDimension::D2),
+                base_mip_level: mip_level,
+                level_count: NonZeroU32::new(1),
+                base_array_layer: face,
+                array_layer_count: NonZeroU32::new(1),
+                ..Default::default()
+            });
+            let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Environment Face Buffer"),
+                contents: bytemuck::cast_slice(&[FaceData {
+                    face,
+                    roughness,
+                    _padding: [0; 2],
+                }]),
+                usage: wgpu::BufferUsage::UNIFORM,
+            });
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::Buffer(buffer.slice(..)),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(&sky.view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::Sampler(&sky.sampler),
+                    },
+                ],
+                label: Some("environment_face_bind_group"),
+            });
+
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                    attachment: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: None,
+            });
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(0, &bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+    }
+
+    fn render_brdf_lut(device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder, brdf_lut: &wgpu::Texture) {
+        let vs_module = device.create_shader_module(wgpu::include_spirv!("shaders/fullscreen.vert.spv"));
+        let fs_module = device.create_shader_module(wgpu::include_spirv!("shaders/ibl_brdf.frag.spv"));
+        let pipeline = PipelineBuilder::new(&vs_module, BRDF_LUT_FORMAT)
+            .label("BRDF LUT Pipeline")
+            .fragment_shader(&fs_module)
+            .build(device);
+
+        let view = brdf_lut.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                attachment: &view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_pipeline(&pipeline);
+        render_pass.draw(0..3, 0..1);
+    }
+}
@@ -0,0 +1,407 @@
+//## PipelineBuilder: casi todos los RenderPipelineDescriptor de los ejemplos son iguales salvo los shaders y algun
+//## detalle, asi que el builder parte de los valores que usa el tutorial (triangulos CCW, culling de la cara de atras,
+//## indices u16, sin blending, sin depth, sin MSAA) y solo cambiamos lo que haga falta:
+//##
+//##     let pipeline = PipelineBuilder::new(&vs_module, sc_desc.format)
+//##         .fragment_shader(&fs_module)
+//##         .bind_group_layouts(&[&globals.bind_group_layout])
+//##         .vertex_buffer(Vertex::desc())
+//##         .build(&device);
+//##
+//## Los shaders y los layouts se pueden pasar prestados (&module) o compartidos con Rc. PipelineCache solo acepta
+//## builders 'static, con todo en Rc (o 'static de verdad), porque guarda en su clave los shaders y los layouts.
+//##
+//## El 1_3 sigue creando el descriptor a mano porque es el capitulo que lo explica campo a campo.
+
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+
+use crate::examples::BlendMode;
+
+#[derive(Clone)]
+pub struct PipelineBuilder<'a> {
+    label: Option<&'a str>,
+    vertex_shader: Shared<'a, wgpu::ShaderModule>,
+    fragment_shader: Option<Shared<'a, wgpu::ShaderModule>>,
+    bind_group_layouts: Vec<Shared<'a, wgpu::BindGroupLayout>>,
+    vertex_buffers: Vec<wgpu::VertexBufferDescriptor<'a>>,
+    topology: wgpu::PrimitiveTopology,
+    front_face: wgpu::FrontFace,
+    cull_mode: wgpu::CullMode,
+    index_format: wgpu::IndexFormat,
+    // Vacio para pipelines sin color attachment (por ejemplo los que solo escriben depth), varios para escribir en
+    // varias texturas a la vez (layout(location=N) out en el fragment shader)
+    color_formats: Vec<wgpu::TextureFormat>,
+    blend: BlendMode,
+    depth_stencil: Option<wgpu::DepthStencilStateDescriptor>,
+    depth_bias: i32,
+    depth_bias_slope_scale: f32,
+    sample_count: u32,
+}
+
+// No todos los ejemplos usan todas las opciones
+#[allow(dead_code)]
+impl<'a> PipelineBuilder<'a> {
+    pub fn new(vertex_shader: impl Into<Shared<'a, wgpu::ShaderModule>>, color_format: wgpu::TextureFormat) -> Self {
+        Self {
+            label: None,
+            vertex_shader: vertex_shader.into(),
+            fragment_shader: None,
+            bind_group_layouts: Vec::new(),
+            vertex_buffers: Vec::new(),
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: wgpu::CullMode::Back,
+            index_format: wgpu::IndexFormat::Uint16,
+            color_formats: vec![color_format],
+            blend: BlendMode::Replace,
+            depth_stencil: None,
+            depth_bias: 0,
+            depth_bias_slope_scale: 0.0,
+            sample_count: 1,
+        }
+    }
+
+    pub fn label(mut self, label: &'a str) -> Self {
+        self.label = Some(label);
+        self
+    }
+
+    pub fn vertex_shader(mut self, module: impl Into<Shared<'a, wgpu::ShaderModule>>) -> Self {
+        self.vertex_shader = module.into();
+        self
+    }
+
+    pub fn fragment_shader(mut self, module: impl Into<Shared<'a, wgpu::ShaderModule>>) -> Self {
+        self.fragment_shader = Some(module.into());
+        self
+    }
+
+    // En orden de set: el primero es el set 0 (los Globals en todos los ejemplos)
+    pub fn bind_group_layouts<L>(mut self, layouts: &[L]) -> Self
+    where
+        L: Into<Shared<'a, wgpu::BindGroupLayout>> + Clone,
+    {
+        self.bind_group_layouts = layouts.iter().cloned().map(Into::into).collect();
+        self
+    }
+
+    // Se añaden en orden de slot, el primero es el slot 0 de set_vertex_buffer
+    pub fn vertex_buffer(mut self, desc: wgpu::VertexBufferDescriptor<'a>) -> Self {
+        self.vertex_buffers.push(desc);
+        self
+    }
+
+    pub fn topology(mut self, topology: wgpu::PrimitiveTopology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    pub fn front_face(mut self, front_face: wgpu::FrontFace) -> Self {
+        self.front_face = front_face;
+        self
+    }
+
+    pub fn cull_mode(mut self, cull_mode: wgpu::CullMode) -> Self {
+        self.cull_mode = cull_mode;
+        self
+    }
+
+    pub fn index_format(mut self, index_format: wgpu::IndexFormat) -> Self {
+        self.index_format = index_format;
+        self
+    }
+
+    pub fn color_format(mut self, color_format: Option<wgpu::TextureFormat>) -> Self {
+        self.color_formats = color_format.into_iter().collect();
+        self
+    }
+
+    // Multiple render targets, el blend es el mismo para todos
+    pub fn color_formats(mut self, color_formats: &[wgpu::TextureFormat]) -> Self {
+        self.color_formats = color_formats.to_vec();
+        self
+    }
+
+    pub fn blend(mut self, blend: BlendMode) -> Self {
+        self.blend = blend;
+        self
+    }
+
+    // Depth test y escritura con la comparacion dada, sin stencil
+    pub fn depth(self, format: wgpu::TextureFormat, depth_compare: wgpu::CompareFunction) -> Self {
+        self.depth_stencil(Some(wgpu::DepthStencilStateDescriptor {
+            format,
+            depth_write_enabled: true,
+            depth_compare,
+            stencil: wgpu::StencilStateDescriptor::default(),
+        }))
+    }
+
+    pub fn depth_stencil(mut self, depth_stencil: Option<wgpu::DepthStencilStateDescriptor>) -> Self {
+        self.depth_stencil = depth_stencil;
+        self
+    }
+
+    // Se suma a la profundidad que se escribe: constant en unidades minimas del formato de depth y slope_scale
+    // multiplicado por la pendiente del triangulo. En los mapas de sombras evita que una superficie se sombree a si
+    // misma
+    pub fn depth_bias(mut self, constant: i32, slope_scale: f32) -> Self {
+        self.depth_bias = constant;
+        self.depth_bias_slope_scale = slope_scale;
+        self
+    }
+
+    pub fn sample_count(mut self, sample_count: u32) -> Self {
+        self.sample_count = sample_count;
+        self
+    }
+
+    pub fn build(&self, device: &wgpu::Device) -> wgpu::RenderPipeline {
+        let bind_group_layouts = self.bind_group_layouts.iter().map(Shared::get).collect::<Vec<_>>();
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: self.label,
+            bind_group_layouts: &bind_group_layouts,
+            push_constant_ranges: &[],
+        });
+
+        let color_states = self
+            .color_formats
+            .iter()
+            .map(|&format| self.blend.color_state(format))
+            .collect::<Vec<_>>();
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: self.label,
+            layout: Some(&layout),
+            vertex_stage: wgpu::ProgrammableStageDescriptor {
+                module: self.vertex_shader.get(),
+                entry_point: "main",
+            },
+            fragment_stage: self
+                .fragment_shader
+                .as_ref()
+                .map(|module| wgpu::ProgrammableStageDescriptor {
+                    module: module.get(),
+                    entry_point: "main",
+                }),
+            rasterization_state: Some(wgpu::RasterizationStateDescriptor {
+                front_face: self.front_face,
+                cull_mode: self.cull_mode,
+                depth_bias: self.depth_bias,
+                depth_bias_slope_scale: self.depth_bias_slope_scale,
+                depth_bias_clamp: 0.0,
+                clamp_depth: false,
+            }),
+            primitive_topology: self.topology,
+            color_states: &color_states,
+            depth_stencil_state: self.depth_stencil.clone(),
+            vertex_state: wgpu::VertexStateDescriptor {
+                index_format: self.index_format,
+                vertex_buffers: &self.vertex_buffers,
+            },
+            sample_count: self.sample_count,
+            sample_mask: !0,
+            alpha_to_coverage_enabled: false,
+        })
+    }
+}
+
+impl PipelineBuilder<'static> {
+    // Clave con todo lo que define el pipeline, para PipelineCache
+    pub fn key(&self) -> PipelineKey {
+        PipelineKey {
+            vertex_shader: self.vertex_shader.clone(),
+            fragment_shader: self.fragment_shader.clone(),
+            bind_group_layouts: self.bind_group_layouts.clone(),
+            vertex_buffers: self
+                .vertex_buffers
+                .iter()
+                .map(|desc| VertexBufferKey {
+                    stride: desc.stride,
+                    step_mode: desc.step_mode,
+                    attributes: desc.attributes.to_vec(),
+                })
+                .collect(),
+            topology: self.topology,
+            front_face: self.front_face,
+            cull_mode: self.cull_mode,
+            index_format: self.index_format,
+            color_formats: self.color_formats.clone(),
+            blend: self.blend,
+            depth_stencil: self.depth_stencil.clone(),
+            depth_bias: self.depth_bias,
+            depth_bias_slope_scale: self.depth_bias_slope_scale.to_bits(),
+            sample_count: self.sample_count,
+        }
+    }
+}
+
+// Un shader o un layout del builder, prestado o compartido con Rc
+pub enum Shared<'a, T> {
+    Borrowed(&'a T),
+    Rc(Rc<T>),
+}
+
+impl<T> Shared<'_, T> {
+    pub fn get(&self) -> &T {
+        match self {
+            Shared::Borrowed(value) => value,
+            Shared::Rc(value) => value,
+        }
+    }
+}
+
+impl<T> Clone for Shared<'_, T> {
+    fn clone(&self) -> Self {
+        match self {
+            Shared::Borrowed(value) => Shared::Borrowed(value),
+            Shared::Rc(value) => Shared::Rc(value.clone()),
+        }
+    }
+}
+
+impl<'a, T> From<&'a T> for Shared<'a, T> {
+    fn from(value: &'a T) -> Self {
+        Shared::Borrowed(value)
+    }
+}
+
+// Como el anterior, para los que estan en un Rc pero se pasan prestados
+impl<'a, T> From<&'a Rc<T>> for Shared<'a, T> {
+    fn from(value: &'a Rc<T>) -> Self {
+        Shared::Borrowed(value)
+    }
+}
+
+impl<T> From<Rc<T>> for Shared<'_, T> {
+    fn from(value: Rc<T>) -> Self {
+        Shared::Rc(value)
+    }
+}
+
+// wgpu no expone un id de los ShaderModule ni de los BindGroupLayout, asi que los identificamos por su direccion. En
+// la clave son 'static: o viven todo el programa o los mantiene vivos su Rc, asi que mientras la clave exista esa
+// direccion no puede pasar a ser la de otro modulo
+impl<T> PartialEq for Shared<'_, T> {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.get(), other.get())
+    }
+}
+
+impl<T> Eq for Shared<'_, T> {}
+
+impl<T> Hash for Shared<'_, T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::ptr::hash(self.get(), state)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct VertexBufferKey {
+    stride: wgpu::BufferAddress,
+    step_mode: wgpu::InputStepMode,
+    attributes: Vec<wgpu::VertexAttributeDescriptor>,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct PipelineKey {
+    vertex_shader: Shared<'static, wgpu::ShaderModule>,
+    fragment_shader: Option<Shared<'static, wgpu::ShaderModule>>,
+    bind_group_layouts: Vec<Shared<'static, wgpu::BindGroupLayout>>,
+    vertex_buffers: Vec<VertexBufferKey>,
+    topology: wgpu::PrimitiveTopology,
+    front_face: wgpu::FrontFace,
+    cull_mode: wgpu::CullMode,
+    index_format: wgpu::IndexFormat,
+    color_formats: Vec<wgpu::TextureFormat>,
+    blend: BlendMode,
+    depth_stencil: Option<wgpu::DepthStencilStateDescriptor>,
+    depth_bias: i32,
+    // f32 no implementa Eq ni Hash, se guardan sus bits
+    depth_bias_slope_scale: u32,
+    sample_count: u32,
+}
+
+// Devuelve el valor de key, creandolo con create solo si no estaba
+fn get_or_insert_with<K: Hash + Eq, V>(values: &mut HashMap<K, Rc<V>>, key: K, create: impl FnOnce() -> V) -> Rc<V> {
+    values.entry(key).or_insert_with(|| Rc::new(create())).clone()
+}
+
+// Cache de pipelines: si ya se creo uno con la misma clave se devuelve ese en vez de compilarlo otra vez. Las claves
+// guardan los shaders y layouts de sus pipelines, asi que el cache no depende de cuanto vivan y se puede guardar
+// junto a ellos
+#[derive(Default)]
+pub struct PipelineCache {
+    pipelines: HashMap<PipelineKey, Rc<wgpu::RenderPipeline>>,
+}
+
+impl PipelineCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_or_create(
+        &mut self,
+        device: &wgpu::Device,
+        builder: &PipelineBuilder<'static>,
+    ) -> Rc<wgpu::RenderPipeline> {
+        get_or_insert_with(&mut self.pipelines, builder.key(), || builder.build(device))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Como una peticion al cache: un shader, que se compara por direccion, y una opcion
+    fn request(shader: &Rc<String>, sample_count: u32) -> (Shared<'static, String>, u32) {
+        (Shared::Rc(shader.clone()), sample_count)
+    }
+
+    #[test]
+    fn identical_requests_share_the_value() {
+        let mut values = HashMap::new();
+        let shader = Rc::new("shader".to_string());
+        let mut created = 0;
+        let mut get = |key| {
+            get_or_insert_with(&mut values, key, || {
+                created += 1;
+                created
+            })
+        };
+        let first = get(request(&shader, 1));
+        let second = get(request(&shader, 1));
+        assert!(Rc::ptr_eq(&first, &second));
+        assert_eq!(created, 1);
+    }
+
+    #[test]
+    fn different_requests_do_not() {
+        let mut values = HashMap::new();
+        let shader = Rc::new("shader".to_string());
+        // Mismo codigo pero otro modulo
+        let other_shader = Rc::new("shader".to_string());
+        let mut created = 0;
+        let mut get = |key| {
+            get_or_insert_with(&mut values, key, || {
+                created += 1;
+                created
+            })
+        };
+        let first = get(request(&shader, 1));
+        assert!(!Rc::ptr_eq(&first, &get(request(&shader, 4))));
+        assert!(!Rc::ptr_eq(&first, &get(request(&other_shader, 1))));
+        assert_eq!(created, 3);
+    }
+
+    #[test]
+    fn borrowed_and_shared_compare_by_address() {
+        static SHADER: &str = "shader";
+        let shader = Rc::new("shader");
+        assert!(Shared::Rc(shader.clone()) == Shared::Borrowed(&*shader));
+        assert!(Shared::Borrowed(&SHADER) == Shared::Borrowed(&SHADER));
+        assert!(Shared::Borrowed(&SHADER) != Shared::Borrowed(&*shader));
+    }
+}
@@ -0,0 +1,547 @@
+//## Post-procesado: en vez de dibujar directamente en el frame del swap chain, la escena se dibuja en una textura HDR
+//## (formato SCENE_FORMAT) y despues una cadena de passes a pantalla completa la va transformando hasta llegar al
+//## frame. Cada efecto es un fragment shader pequeño (shaders/post_*.frag) sobre fullscreen.vert que lee la salida del
+//## anterior, y al final se copia el resultado al formato del swap chain.
+//##
+//## Cada efecto, y el umbral y los dos blur del bloom, es un pass de un RenderGraph que escribe en una textura
+//## temporal, asi que el grafo los ordena y reutiliza las texturas. El bloom se difumina a media resolucion:
+//##
+//##     let scene = graph.add_texture("Scene", TransientDesc::new(SCENE_FORMAT));
+//##     graph.add_pass("Scene").write(scene)...;
+//##     let exposure = post.add_exposure_pass(&mut graph, scene);
+//##     post.add_passes(&mut graph, &globals.bind_group, scene, exposure, frame);
+//##
+//## layout(set=0, binding=0) uniform Globals { ... };
+//## layout(set=1, binding=0) uniform texture2D t_input;     // la salida del efecto anterior (o la escena)
+//## layout(set=1, binding=1) uniform sampler s_input;
+//## layout(set=2, binding=0) uniform Effect { vec4 u_params; };  // los params del PostEffect
+//##
+//## El orden de la cadena importa: el bloom trabaja con los colores HDR y va antes del tonemapping, el FXAA y los
+//## efectos de "camara" van despues, con los colores ya en [0, 1]. Las teclas 1-9 activan y desactivan los efectos
+//## en el orden de la cadena.
+//##
+//## Los pipelines de la escena tienen que usar SCENE_FORMAT, tambien la textura del MSAA si la hay.
+//##
+//## El tonemapping puede usar la exposicion que calcula AutoExposure sobre la escena (en el set 3), y con la H se
+//## ve el histograma de luminancia encima del resultado.
+
+use wgpu::util::DeviceExt;
+use winit::event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent};
+
+use crate::examples::{
+    AutoExposure, BufferHandle, PipelineBuilder, RenderGraph, TargetSize, TextureHandle, TransientDesc,
+};
+
+// Con 16 bits por canal la escena puede pasar de 1.0 y el tonemapping decide como se ve
+pub const SCENE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+const TOGGLE_KEYS: [VirtualKeyCode; 9] = [
+    VirtualKeyCode::Key1,
+    VirtualKeyCode::Key2,
+    VirtualKeyCode::Key3,
+    VirtualKeyCode::Key4,
+    VirtualKeyCode::Key5,
+    VirtualKeyCode::Key6,
+    VirtualKeyCode::Key7,
+    VirtualKeyCode::Key8,
+    VirtualKeyCode::Key9,
+];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Effect {
+    Bloom,
+    Tonemap,
+    Fxaa,
+    ChromaticAberration,
+    Vignette,
+    FilmGrain,
+    Pixelate,
+    Crt,
+}
+
+impl Effect {
+    // Todos, en un orden que tiene sentido como cadena
+    pub const ALL: [Effect; 8] = [
+        Effect::Bloom,
+        Effect::Tonemap,
+        Effect::Fxaa,
+        Effect::ChromaticAberration,
+        Effect::Vignette,
+        Effect::FilmGrain,
+        Effect::Pixelate,
+        Effect::Crt,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Effect::Bloom => "Bloom",
+            Effect::Tonemap => "Tonemapping",
+            Effect::Fxaa => "FXAA",
+            Effect::ChromaticAberration => "Chromatic aberration",
+            Effect::Vignette => "Vignette",
+            Effect::FilmGrain => "Film grain",
+            Effect::Pixelate => "Pixelate",
+            Effect::Crt => "CRT",
+        }
+    }
+
+    // Los u_params con los que empieza cada efecto, el significado de cada componente esta en su shader
+    pub fn default_params(self) -> [f32; 4] {
+        match self {
+            // umbral, intensidad, knee
+            Effect::Bloom => [1.0, 0.6, 0.5, 0.0],
+            // exposicion (compensacion si es automatica), operador (0 Reinhard, 1 ACES, 2 filmico), automatica (0 o 1)
+            Effect::Tonemap => [1.0, 1.0, 1.0, 0.0],
+            // longitud maxima de la busqueda en pixels
+            Effect::Fxaa => [8.0, 0.0, 0.0, 0.0],
+            // separacion en el borde
+            Effect::ChromaticAberration => [0.012, 0.0, 0.0, 0.0],
+            // intensidad, radio
+            Effect::Vignette => [0.6, 0.35, 0.0, 0.0],
+            // cantidad
+            Effect::FilmGrain => [0.08, 0.0, 0.0, 0.0],
+            // tamaño del bloque en pixels
+            Effect::Pixelate => [6.0, 0.0, 0.0, 0.0],
+            // curvatura, lineas de barrido
+            Effect::Crt => [0.3, 0.35, 0.0, 0.0],
+        }
+    }
+
+    pub fn enabled_by_default(self) -> bool {
+        matches!(self, Effect::Bloom | Effect::Tonemap | Effect::Fxaa | Effect::Vignette)
+    }
+
+    fn shader(self, device: &wgpu::Device) -> wgpu::ShaderModule {
+        match self {
+            // El del bloom es el pass que combina, los otros dos los crea Bloom
+            Effect::Bloom => device.create_shader_module(wgpu::include_spirv!("shaders/post_bloom_combine.frag.spv")),
+            Effect::Tonemap => device.create_shader_module(wgpu::include_spirv!("shaders/post_tonemap.frag.spv")),
+            Effect::Fxaa => device.create_shader_module(wgpu::include_spirv!("shaders/post_fxaa.frag.spv")),
+            Effect::ChromaticAberration => {
+                device.create_shader_module(wgpu::include_spirv!("shaders/post_chromatic_aberration.frag.spv"))
+            }
+            Effect::Vignette => device.create_shader_module(wgpu::include_spirv!("shaders/post_vignette.frag.spv")),
+            Effect::FilmGrain => device.create_shader_module(wgpu::include_spirv!("shaders/post_film_grain.frag.spv")),
+            Effect::Pixelate => device.create_shader_module(wgpu::include_spirv!("shaders/post_pixelate.frag.spv")),
+            Effect::Crt => device.create_shader_module(wgpu::include_spirv!("shaders/post_crt.frag.spv")),
+        }
+    }
+}
+
+// Un eslabon de la cadena. enabled y params se pueden cambiar en cualquier momento, se suben en update()
+pub struct PostEffect {
+    pub effect: Effect,
+    pub enabled: bool,
+    pub params: [f32; 4],
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+}
+
+// Lo que lee un pass de la cadena en cada set despues del 0 (los Globals)
+enum Input<'a> {
+    // Una textura temporal, su bind group se crea al ejecutar el pass
+    Texture(TextureHandle),
+    // Un bind group fijo, con el buffer que usa si lo escribe otro pass del grafo
+    BindGroup(&'a wgpu::BindGroup, Option<BufferHandle>),
+}
+
+// Los passes del bloom que van antes del combine: umbral y blur horizontal y vertical
+struct Bloom {
+    threshold_pipeline: wgpu::RenderPipeline,
+    blur_pipeline: wgpu::RenderPipeline,
+    _blur_buffers: [wgpu::Buffer; 2],
+    blur_bind_groups: [wgpu::BindGroup; 2],
+}
+
+pub struct PostProcess {
+    pub chain: Vec<PostEffect>,
+    texture_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    bloom: Bloom,
+    copy_pipeline: wgpu::RenderPipeline,
+    pub auto_exposure: AutoExposure,
+    pub show_histogram: bool,
+    output_size: [u32; 2],
+}
+
+impl PostProcess {
+    // chain es el orden de los efectos, por ejemplo &Effect::ALL. El ultimo pass escribe en el formato de sc_desc
+    pub fn new(
+        device: &wgpu::Device,
+        globals_layout: &wgpu::BindGroupLayout,
+        sc_desc: &wgpu::SwapChainDescriptor,
+        chain: &[Effect],
+    ) -> Self {
+        let texture_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::SampledTexture {
+                        multisampled: false,
+                        dimension: wgpu::TextureViewDimension::D2,
+                        component_type: wgpu::TextureComponentType::Float,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler { comparison: false },
+                    count: None,
+                },
+            ],
+            label: Some("post_texture_bind_group_layout"),
+        });
+        let params_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStage::FRAGMENT,
+                ty: wgpu::BindingType::UniformBuffer {
+                    dynamic: false,
+                    min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<[f32; 4]>() as _),
+                },
+                count: None,
+            }],
+            label: Some("post_params_bind_group_layout"),
+        });
+        let params_bind_group = |params: [f32; 4], label| {
+            let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(label),
+                contents: bytemuck::cast_slice(&[params]),
+                usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            });
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &params_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(buffer.slice(..)),
+                }],
+                label: Some(label),
+            });
+            (buffer, bind_group)
+        };
+
+        // Linear y clamp: el blur y la aberracion cromatica leen entre pixels y fuera de la pantalla
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Post Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        // La escena es del tamaño de la ventana, como la salida
+        let output_size = [sc_desc.width, sc_desc.height];
+        let auto_exposure = AutoExposure::new(device, output_size, sc_desc.format);
+
+        let vs_module = device.create_shader_module(wgpu::include_spirv!("shaders/fullscreen.vert.spv"));
+        let pipeline = |label: &str, fs_module: &wgpu::ShaderModule, format, layouts: &[&wgpu::BindGroupLayout]| {
+            PipelineBuilder::new(&vs_module, format)
+                .label(label)
+                .fragment_shader(fs_module)
+                .bind_group_layouts(layouts)
+                .build(device)
+        };
+        let effect_layouts = [globals_layout, &texture_layout, &params_layout];
+        // El combine del bloom lee tambien la textura difuminada en el set 3
+        let bloom_layouts = [globals_layout, &texture_layout, &params_layout, &texture_layout];
+        let tonemap_layouts = [
+            globals_layout,
+            &texture_layout,
+            &params_layout,
+            &auto_exposure.exposure_bind_group_layout,
+        ];
+
+        let chain = chain
+            .iter()
+            .map(|&effect| {
+                let params = effect.default_params();
+                let (buffer, bind_group) = params_bind_group(params, effect.name());
+                let layouts: &[&wgpu::BindGroupLayout] = match effect {
+                    Effect::Bloom => &bloom_layouts,
+                    Effect::Tonemap => &tonemap_layouts,
+                    _ => &effect_layouts,
+                };
+                let fs_module = effect.shader(device);
+                PostEffect {
+                    effect,
+                    enabled: effect.enabled_by_default(),
+                    params,
+                    buffer,
+                    bind_group,
+                    pipeline: pipeline(effect.name(), &fs_module, SCENE_FORMAT, layouts),
+                }
+            })
+            .collect();
+
+        let threshold_module =
+            device.create_shader_module(wgpu::include_spirv!("shaders/post_bloom_threshold.frag.spv"));
+        let blur_module = device.create_shader_module(wgpu::include_spirv!("shaders/post_blur.frag.spv"));
+        let (horizontal_buffer, horizontal_bind_group) = params_bind_group([1.0, 0.0, 0.0, 0.0], "Blur Horizontal");
+        let (vertical_buffer, vertical_bind_group) = params_bind_group([0.0, 1.0, 0.0, 0.0], "Blur Vertical");
+        let bloom = Bloom {
+            threshold_pipeline: pipeline("Bloom Threshold", &threshold_module, SCENE_FORMAT, &effect_layouts),
+            blur_pipeline: pipeline("Blur", &blur_module, SCENE_FORMAT, &effect_layouts),
+            _blur_buffers: [horizontal_buffer, vertical_buffer],
+            blur_bind_groups: [horizontal_bind_group, vertical_bind_group],
+        };
+
+        let copy_module = device.create_shader_module(wgpu::include_spirv!("shaders/post_copy.frag.spv"));
+        let copy_pipeline = pipeline("Post Copy", &copy_module, sc_desc.format, &effect_layouts[..2]);
+
+        Self {
+            chain,
+            texture_layout,
+            sampler,
+            bloom,
+            copy_pipeline,
+            auto_exposure,
+            show_histogram: false,
+            output_size,
+        }
+    }
+
+    pub fn resize(&mut self, sc_desc: &wgpu::SwapChainDescriptor) {
+        self.output_size = [sc_desc.width, sc_desc.height];
+        self.auto_exposure.set_scene_size(self.output_size);
+    }
+
+    pub fn effect_mut(&mut self, effect: Effect) -> Option<&mut PostEffect> {
+        self.chain.iter_mut().find(|post_effect| post_effect.effect == effect)
+    }
+
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(keycode),
+                    ..
+                },
+            ..
+        } = event
+        {
+            if *keycode == VirtualKeyCode::H {
+                self.show_histogram = !self.show_histogram;
+                return true;
+            }
+            let index = TOGGLE_KEYS.iter().position(|key| key == keycode);
+            if let Some(post_effect) = index.and_then(|index| self.chain.get_mut(index)) {
+                post_effect.enabled = !post_effect.enabled;
+                println!(
+                    "{}: {}",
+                    post_effect.effect.name(),
+                    if post_effect.enabled { "on" } else { "off" }
+                );
+                return true;
+            }
+        }
+        false
+    }
+
+    // time_delta en segundos, para que la exposicion automatica se adapte igual con cualquier frame rate
+    pub fn update(&self, queue: &wgpu::Queue, time_delta: f32) {
+        for post_effect in &self.chain {
+            queue.write_buffer(&post_effect.buffer, 0, bytemuck::cast_slice(&[post_effect.params]));
+        }
+        self.auto_exposure.update(queue, time_delta);
+    }
+
+    // Mide la exposicion de scene, la textura HDR de la escena, y devuelve el buffer en el que la deja. Va en su propio
+    // pass para que se haga una vez por frame aunque add_passes() se llame mas de una vez
+    pub fn add_exposure_pass<'a>(&'a self, graph: &mut RenderGraph<'a>, scene: TextureHandle) -> BufferHandle {
+        let auto_exposure = &self.auto_exposure;
+        let exposure = graph.import_buffer(&auto_exposure.exposure_buffer);
+        graph
+            .add_pass("Auto exposure")
+            .read(scene)
+            .write(exposure)
+            .execute(move |ctx| auto_exposure.compute(ctx.device, ctx.encoder, ctx.texture(scene)));
+        exposure
+    }
+
+    // Añade los passes de los efectos activos, que llevan scene a output, y el del histograma encima si esta activo.
+    // exposure es lo que devuelve add_exposure_pass()
+    pub fn add_passes<'a>(
+        &'a self,
+        graph: &mut RenderGraph<'a>,
+        globals_bind_group: &'a wgpu::BindGroup,
+        scene: TextureHandle,
+        exposure: BufferHandle,
+        output: TextureHandle,
+    ) {
+        let mut input = scene;
+        for post_effect in self.chain.iter().filter(|post_effect| post_effect.enabled) {
+            let name = post_effect.effect.name();
+            let mut inputs = vec![Input::Texture(input), Input::BindGroup(&post_effect.bind_group, None)];
+            match post_effect.effect {
+                Effect::Bloom => {
+                    let blurred = self.add_bloom_passes(graph, globals_bind_group, input, post_effect);
+                    inputs.push(Input::Texture(blurred));
+                }
+                Effect::Tonemap => {
+                    let exposure_bind_group = &self.auto_exposure.exposure_bind_group;
+                    inputs.push(Input::BindGroup(exposure_bind_group, Some(exposure)));
+                }
+                _ => {}
+            }
+            let effect_output = graph.add_texture(name, TransientDesc::new(SCENE_FORMAT));
+            self.add_fullscreen_pass(
+                graph,
+                name,
+                &post_effect.pipeline,
+                globals_bind_group,
+                inputs,
+                effect_output,
+            );
+            input = effect_output;
+        }
+        let inputs = vec![Input::Texture(input)];
+        self.add_fullscreen_pass(
+            graph,
+            "Post copy",
+            &self.copy_pipeline,
+            globals_bind_group,
+            inputs,
+            output,
+        );
+
+        if self.show_histogram {
+            let auto_exposure = &self.auto_exposure;
+            let output_size = self.output_size;
+            graph
+                .add_pass("Histogram")
+                .read(exposure)
+                .write(output)
+                .execute(move |ctx| {
+                    let mut render_pass = ctx.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                            attachment: ctx.texture(output),
+                            resolve_target: None,
+                            ops: wgpu::Operations {
+                                load: wgpu::LoadOp::Load,
+                                store: true,
+                            },
+                        }],
+                        depth_stencil_attachment: None,
+                    });
+                    auto_exposure.draw_histogram(&mut render_pass, output_size);
+                });
+        }
+    }
+
+    // El umbral y el blur horizontal y vertical del bloom, a media resolucion. Devuelve la textura difuminada, que lee
+    // el combine en el set 3
+    fn add_bloom_passes<'a>(
+        &'a self,
+        graph: &mut RenderGraph<'a>,
+        globals_bind_group: &'a wgpu::BindGroup,
+        input: TextureHandle,
+        post_effect: &'a PostEffect,
+    ) -> TextureHandle {
+        let bloom = &self.bloom;
+        let half = TransientDesc::new(SCENE_FORMAT).size(TargetSize::Scaled(0.5));
+        let passes = [
+            ("Bloom threshold", &bloom.threshold_pipeline, &post_effect.bind_group),
+            (
+                "Bloom blur horizontal",
+                &bloom.blur_pipeline,
+                &bloom.blur_bind_groups[0],
+            ),
+            ("Bloom blur vertical", &bloom.blur_pipeline, &bloom.blur_bind_groups[1]),
+        ];
+        let mut input = input;
+        for &(name, pipeline, params) in &passes {
+            let output = graph.add_texture(name, half);
+            let inputs = vec![Input::Texture(input), Input::BindGroup(params, None)];
+            self.add_fullscreen_pass(graph, name, pipeline, globals_bind_group, inputs, output);
+            input = output;
+        }
+        input
+    }
+
+    // Un pass que dibuja un triangulo a pantalla completa en output con los bind groups de inputs
+    fn add_fullscreen_pass<'a>(
+        &'a self,
+        graph: &mut RenderGraph<'a>,
+        name: &str,
+        pipeline: &'a wgpu::RenderPipeline,
+        globals_bind_group: &'a wgpu::BindGroup,
+        inputs: Vec<Input<'a>>,
+        output: TextureHandle,
+    ) {
+        let mut pass = graph.add_pass(name).write(output);
+        for input in &inputs {
+            pass = match *input {
+                Input::Texture(texture) => pass.read(texture),
+                Input::BindGroup(_, Some(buffer)) => pass.read(buffer),
+                Input::BindGroup(_, None) => pass,
+            };
+        }
+        pass.execute(move |ctx| {
+            // Las texturas temporales cambian de un frame a otro, asi que sus bind groups se crean aqui
+            let texture_bind_groups: Vec<Option<wgpu::BindGroup>> = inputs
+                .iter()
+                .map(|input| match *input {
+                    Input::Texture(texture) => Some(self.texture_bind_group(ctx.device, ctx.texture(texture))),
+                    Input::BindGroup(..) => None,
+                })
+                .collect();
+            let bind_groups: Vec<&wgpu::BindGroup> =
+                std::iter::once(globals_bind_group)
+                    .chain(inputs.iter().zip(&texture_bind_groups).filter_map(
+                        |(input, texture_bind_group)| match *input {
+                            Input::Texture(_) => texture_bind_group.as_ref(),
+                            Input::BindGroup(bind_group, _) => Some(bind_group),
+                        },
+                    ))
+                    .collect();
+            fullscreen_pass(ctx.encoder, pipeline, &bind_groups, ctx.texture(output));
+        });
+    }
+
+    // Para leer una textura de la cadena desde un shader
+    fn texture_bind_group(&self, device: &wgpu::Device, view: &wgpu::TextureView) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.texture_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+            label: Some("post_texture_bind_group"),
+        })
+    }
+}
+
+fn fullscreen_pass(
+    encoder: &mut wgpu::CommandEncoder,
+    pipeline: &wgpu::RenderPipeline,
+    bind_groups: &[&wgpu::BindGroup],
+    target: &wgpu::TextureView,
+) {
+    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+            attachment: target,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                store: true,
+            },
+        }],
+        depth_stencil_attachment: None,
+    });
+    render_pass.set_pipeline(pipeline);
+    for (index, bind_group) in bind_groups.iter().enumerate() {
+        render_pass.set_bind_group(index as u32, bind_group, &[]);
+    }
+    render_pass.draw(0..3, 0..1);
+}
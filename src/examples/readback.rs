@@ -0,0 +1,198 @@
+//## Helpers para leer datos de la GPU desde la CPU (capturas de pantalla, resultados de compute shaders, tests...).
+//## La GPU no deja mapear directamente buffers de uso general ni texturas, asi que siempre copiamos a un buffer
+//## intermedio con MAP_READ, esperamos a que la GPU termine (device.poll) y mapeamos ese buffer.
+
+use anyhow::{bail, Context, Result};
+
+// El primer multiplo de align mayor o igual que size. Sin div_ceil, que necesita Rust 1.73
+#[allow(clippy::manual_div_ceil)]
+fn align_to(size: u64, align: u64) -> u64 {
+    (size + align - 1) / align * align
+}
+
+// copy_texture_to_buffer exige que cada fila ocupe un multiplo de 256 bytes en el buffer
+pub fn padded_bytes_per_row(width: u32, bytes_per_pixel: u32) -> u32 {
+    let unpadded = (width * bytes_per_pixel) as u64;
+    align_to(unpadded, wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as u64) as u32
+}
+
+// Las copias entre buffers tienen que ser de un multiplo de 4 bytes
+fn buffer_copy_size(size: wgpu::BufferAddress) -> wgpu::BufferAddress {
+    align_to(size, wgpu::COPY_BUFFER_ALIGNMENT)
+}
+
+// Quita el padding del final de cada fila que deja copy_texture_to_buffer
+fn strip_row_padding(data: &[u8], unpadded_bytes_per_row: usize, padded_bytes_per_row: usize) -> Vec<u8> {
+    let mut pixels = Vec::with_capacity(data.len() / padded_bytes_per_row * unpadded_bytes_per_row);
+    for row in data.chunks(padded_bytes_per_row) {
+        pixels.extend_from_slice(&row[..unpadded_bytes_per_row]);
+    }
+    pixels
+}
+
+// El Vec<u8> mapeado no tiene por que estar alineado para T, asi que copiamos a un Vec<T>
+fn bytes_to_vec<T: bytemuck::Pod>(bytes: &[u8]) -> Vec<T> {
+    let mut result = vec![T::zeroed(); bytes.len() / std::mem::size_of::<T>()];
+    bytemuck::cast_slice_mut(&mut result).copy_from_slice(bytes);
+    result
+}
+
+// Envia el encoder (que ya tiene la copia a `staging_buffer` grabada) y mapea el buffer. Espera a la GPU.
+async fn map_staging(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    encoder: wgpu::CommandEncoder,
+    staging_buffer: &wgpu::Buffer,
+) -> Result<Vec<u8>> {
+    queue.submit(std::iter::once(encoder.finish()));
+
+    let buffer_slice = staging_buffer.slice(..);
+    let mapping = buffer_slice.map_async(wgpu::MapMode::Read);
+    // Sin el poll el future no se resuelve nunca en nativo
+    device.poll(wgpu::Maintain::Wait);
+    mapping.await.context("Unable to map readback buffer")?;
+
+    let data = buffer_slice.get_mapped_range().to_vec();
+    staging_buffer.unmap();
+    Ok(data)
+}
+
+fn create_staging_buffer(device: &wgpu::Device, size: wgpu::BufferAddress) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Readback Buffer"),
+        size,
+        usage: wgpu::BufferUsage::MAP_READ | wgpu::BufferUsage::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+// Lee los primeros `len` elementos de tipo T de un buffer con COPY_SRC. El buffer tiene que ocupar al menos hasta el
+// siguiente multiplo de 4 bytes, como los que crea create_buffer_init
+pub async fn read_buffer<T: bytemuck::Pod>(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    buffer: &wgpu::Buffer,
+    len: usize,
+) -> Result<Vec<T>> {
+    let size = (len * std::mem::size_of::<T>()) as wgpu::BufferAddress;
+    // Lo que se copia de mas para redondear a 4 bytes se quita al final
+    let copy_size = buffer_copy_size(size);
+    let staging_buffer = create_staging_buffer(device, copy_size);
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Readback Encoder"),
+    });
+    encoder.copy_buffer_to_buffer(buffer, 0, &staging_buffer, 0, copy_size);
+
+    let data = map_staging(device, queue, encoder, &staging_buffer).await?;
+    Ok(bytes_to_vec(&data[..size as usize]))
+}
+
+// Lee el mip 0 de una textura 2D con COPY_SRC. T es el tipo de un texel (por ejemplo [u8; 4] para Rgba8Unorm o f32
+// para R32Float/Depth32Float). Devuelve las filas seguidas sin el padding de 256 bytes, width * height texels.
+pub async fn read_texture<T: bytemuck::Pod>(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    width: u32,
+    height: u32,
+) -> Result<Vec<T>> {
+    let bytes_per_pixel = std::mem::size_of::<T>() as u32;
+    let unpadded_bytes_per_row = width * bytes_per_pixel;
+    let padded_bytes_per_row = padded_bytes_per_row(width, bytes_per_pixel);
+
+    let staging_buffer =
+        create_staging_buffer(device, (padded_bytes_per_row * height) as wgpu::BufferAddress);
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Readback Encoder"),
+    });
+    encoder.copy_texture_to_buffer(
+        wgpu::TextureCopyView {
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+        },
+        wgpu::BufferCopyView {
+            buffer: &staging_buffer,
+            layout: wgpu::TextureDataLayout {
+                offset: 0,
+                bytes_per_row: padded_bytes_per_row,
+                rows_per_image: height,
+            },
+        },
+        wgpu::Extent3d {
+            width,
+            height,
+            depth: 1,
+        },
+    );
+
+    let data = map_staging(device, queue, encoder, &staging_buffer).await?;
+    let pixels = strip_row_padding(&data, unpadded_bytes_per_row as usize, padded_bytes_per_row as usize);
+    Ok(bytes_to_vec(&pixels))
+}
+
+// Lee una textura de 8 bits por canal como imagen RGBA, lista para guardar con image (.save("captura.png")).
+// Los formatos Bgra (como el del swap chain) se reordenan a RGBA.
+pub async fn read_texture_to_image(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+) -> Result<image::RgbaImage> {
+    let swap_red_blue = match format {
+        wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+        wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+        _ => bail!("Unsupported format for image readback: {:?}", format),
+    };
+
+    let mut pixels = read_texture::<[u8; 4]>(device, queue, texture, width, height).await?;
+    if swap_red_blue {
+        for pixel in pixels.iter_mut() {
+            pixel.swap(0, 2);
+        }
+    }
+
+    image::RgbaImage::from_raw(width, height, pixels.concat())
+        .context("Readback size does not match the image size")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_are_padded_to_the_row_alignment() {
+        assert_eq!(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT, 256);
+        assert_eq!(padded_bytes_per_row(64, 4), 256);
+        assert_eq!(padded_bytes_per_row(1, 4), 256);
+        assert_eq!(padded_bytes_per_row(65, 4), 512);
+        assert_eq!(padded_bytes_per_row(100, 1), 256);
+        assert_eq!(padded_bytes_per_row(800, 4), 3328);
+    }
+
+    #[test]
+    fn buffer_copies_are_rounded_to_the_copy_alignment() {
+        assert_eq!(wgpu::COPY_BUFFER_ALIGNMENT, 4);
+        assert_eq!(buffer_copy_size(0), 0);
+        assert_eq!(buffer_copy_size(1), 4);
+        assert_eq!(buffer_copy_size(4), 4);
+        assert_eq!(buffer_copy_size(6), 8);
+        assert_eq!(buffer_copy_size(12), 12);
+    }
+
+    #[test]
+    fn row_padding_is_stripped() {
+        // 3 filas de 5 bytes con las filas a 8 bytes
+        let data = [
+            1, 2, 3, 4, 5, 0, 0, 0, //
+            6, 7, 8, 9, 10, 0, 0, 0, //
+            11, 12, 13, 14, 15, 0, 0, 0,
+        ];
+        let pixels = strip_row_padding(&data, 5, 8);
+        assert_eq!(pixels, (1..=15).collect::<Vec<u8>>());
+    }
+}
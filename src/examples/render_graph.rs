@@ -0,0 +1,644 @@
+//## Render graph: en vez de encadenar a mano los render passes en render(), cada frame se declara un grafo con las
+//## texturas y buffers que se usan y los passes que los leen y escriben, y el grafo se encarga del resto:
+//##   - Ordena los passes segun sus dependencias (no hace falta declararlos en orden).
+//##   - Quita los passes cuyo resultado no llega a ningun recurso importado (el frame, un buffer...).
+//##   - Crea las texturas temporales (add_texture) y las reutiliza: dentro del frame, una textura que ya no se va a
+//##     leer mas la puede usar otro pass con el mismo formato, y entre frames se guardan en TransientTextures, que
+//##     se recrea con la ventana.
+//##   - Graba todos los passes en un solo command encoder y lo envia a la cola.
+//##
+//##     let mut graph = RenderGraph::new();
+//##     let frame = graph.import_texture("Frame", &frame.view);
+//##     let depth = graph.add_texture("Depth", TransientDesc::new(DEPTH_FORMAT));
+//##     graph.add_pass("Scene").write(frame).write(depth).execute(move |ctx| {
+//##         let mut render_pass = ctx.encoder.begin_render_pass(...ctx.texture(frame)...ctx.texture(depth)...);
+//##     });
+//##     graph.execute(&device, &queue, &mut transients)?;
+//##
+//## Las reglas de orden: los passes que escriben un mismo recurso van en el orden en el que se declararon (el segundo
+//## dibuja encima del primero). Un pass que solo lee un recurso va despues del ultimo que lo escribe antes que el, o
+//## del primero que lo escribe si se declaro antes que todos. Y un pass que escribe va despues de los que leian lo
+//## que habia antes, asi se puede leer una textura y luego sobrescribirla.
+
+use anyhow::{bail, Result};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TextureHandle(usize);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BufferHandle(usize);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Resource {
+    Texture(TextureHandle),
+    Buffer(BufferHandle),
+}
+
+impl From<TextureHandle> for Resource {
+    fn from(handle: TextureHandle) -> Self {
+        Resource::Texture(handle)
+    }
+}
+
+impl From<BufferHandle> for Resource {
+    fn from(handle: BufferHandle) -> Self {
+        Resource::Buffer(handle)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TargetSize {
+    // El tamaño del swap chain
+    Window,
+    // Una fraccion del swap chain, por ejemplo 0.5 para media resolucion
+    Scaled(f32),
+}
+
+// Como es una textura temporal. Por defecto del tamaño de la ventana, sin MSAA y usable como attachment y para leer
+// desde un shader
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TransientDesc {
+    pub size: TargetSize,
+    pub format: wgpu::TextureFormat,
+    pub sample_count: u32,
+    pub usage: wgpu::TextureUsage,
+}
+
+impl TransientDesc {
+    pub fn new(format: wgpu::TextureFormat) -> Self {
+        Self {
+            size: TargetSize::Window,
+            format,
+            sample_count: 1,
+            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT | wgpu::TextureUsage::SAMPLED,
+        }
+    }
+
+    pub fn size(mut self, size: TargetSize) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn sample_count(mut self, sample_count: u32) -> Self {
+        self.sample_count = sample_count;
+        self
+    }
+
+    pub fn usage(mut self, usage: wgpu::TextureUsage) -> Self {
+        self.usage = usage;
+        self
+    }
+}
+
+// Lo que tiene que coincidir para que dos texturas temporales sean intercambiables
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct TextureKey {
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+    usage: wgpu::TextureUsage,
+}
+
+struct PooledTexture {
+    key: TextureKey,
+    view: wgpu::TextureView,
+}
+
+// Las texturas temporales de los grafos, que se guardan entre frames. Hay que llamar a resize() con el swap chain
+#[derive(Default)]
+pub struct TransientTextures {
+    width: u32,
+    height: u32,
+    textures: Vec<PooledTexture>,
+}
+
+impl TransientTextures {
+    pub fn new(sc_desc: &wgpu::SwapChainDescriptor) -> Self {
+        Self {
+            width: sc_desc.width,
+            height: sc_desc.height,
+            textures: Vec::new(),
+        }
+    }
+
+    // Las que dependen del tamaño de la ventana ya no sirven, se vuelven a crear segun se necesiten
+    pub fn resize(&mut self, sc_desc: &wgpu::SwapChainDescriptor) {
+        self.width = sc_desc.width;
+        self.height = sc_desc.height;
+        self.textures.clear();
+    }
+
+    // Cuantas texturas hay creadas, para ver que se reutilizan
+    pub fn texture_count(&self) -> usize {
+        self.textures.len()
+    }
+
+    fn key(&self, desc: &TransientDesc) -> TextureKey {
+        let (width, height) = match desc.size {
+            TargetSize::Window => (self.width, self.height),
+            TargetSize::Scaled(scale) => (
+                (self.width as f32 * scale).round() as u32,
+                (self.height as f32 * scale).round() as u32,
+            ),
+        };
+        TextureKey {
+            width: width.max(1),
+            height: height.max(1),
+            format: desc.format,
+            sample_count: desc.sample_count,
+            usage: desc.usage,
+        }
+    }
+
+    fn keys(&self) -> Vec<TextureKey> {
+        self.textures.iter().map(|texture| texture.key).collect()
+    }
+
+    fn create(&mut self, device: &wgpu::Device, name: &str, key: TextureKey) {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(name),
+            size: wgpu::Extent3d {
+                width: key.width,
+                height: key.height,
+                depth: 1,
+            },
+            mip_level_count: 1,
+            sample_count: key.sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: key.format,
+            usage: key.usage,
+        });
+        self.textures.push(PooledTexture {
+            key,
+            view: texture.create_view(&wgpu::TextureViewDescriptor::default()),
+        });
+    }
+}
+
+// Una textura de keys con key que no este ocupada en el pass first. busy_until dice hasta que pass esta ocupada cada
+// una. Si no hay ninguna libre se añade la clave al final y execute() crea la textura
+fn acquire(
+    keys: &mut Vec<TextureKey>,
+    busy_until: &mut Vec<Option<usize>>,
+    key: TextureKey,
+    first: usize,
+    last: usize,
+) -> usize {
+    let free = (0..keys.len()).find(|&i| keys[i] == key && !matches!(busy_until[i], Some(until) if until >= first));
+    let index = free.unwrap_or_else(|| {
+        keys.push(key);
+        busy_until.push(None);
+        keys.len() - 1
+    });
+    busy_until[index] = Some(last);
+    index
+}
+
+enum TextureSource<'a> {
+    Imported(&'a wgpu::TextureView),
+    Transient(TransientDesc),
+}
+
+struct TextureEntry<'a> {
+    name: String,
+    source: TextureSource<'a>,
+}
+
+type PassFn<'a> = Box<dyn FnOnce(&mut PassContext<'_>) + 'a>;
+
+struct Pass<'a> {
+    name: String,
+    reads: Vec<Resource>,
+    writes: Vec<Resource>,
+    execute: PassFn<'a>,
+}
+
+impl Pass<'_> {
+    fn uses(&self, resource: Resource) -> bool {
+        self.reads.contains(&resource) || self.writes.contains(&resource)
+    }
+}
+
+// Lo que recibe cada pass al ejecutarse: el encoder en el que grabar y los recursos que declaro. El device es para
+// crear los bind groups que usan texturas temporales, que cambian de un frame a otro
+#[allow(dead_code)]
+pub struct PassContext<'r> {
+    pub device: &'r wgpu::Device,
+    pub encoder: &'r mut wgpu::CommandEncoder,
+    pass: &'r str,
+    declared: &'r [Resource],
+    textures: &'r [Option<&'r wgpu::TextureView>],
+    buffers: &'r [&'r wgpu::Buffer],
+}
+
+#[allow(dead_code)]
+impl<'r> PassContext<'r> {
+    // Solo se pueden usar recursos declarados con read() o write(), si no el orden del grafo no seria valido
+    pub fn texture(&self, handle: TextureHandle) -> &'r wgpu::TextureView {
+        assert!(
+            self.declared.contains(&Resource::Texture(handle)),
+            "Pass {} uses a texture it did not declare",
+            self.pass
+        );
+        // Las temporales de los passes que se ejecutan siempre tienen textura
+        self.textures[handle.0].expect("Transient texture was not allocated")
+    }
+
+    pub fn buffer(&self, handle: BufferHandle) -> &'r wgpu::Buffer {
+        assert!(
+            self.declared.contains(&Resource::Buffer(handle)),
+            "Pass {} uses a buffer it did not declare",
+            self.pass
+        );
+        self.buffers[handle.0]
+    }
+}
+
+pub struct PassBuilder<'g, 'a> {
+    graph: &'g mut RenderGraph<'a>,
+    name: String,
+    reads: Vec<Resource>,
+    writes: Vec<Resource>,
+}
+
+impl<'g, 'a> PassBuilder<'g, 'a> {
+    // Lo que el pass lee, por ejemplo una textura que usa en un bind group
+    pub fn read(mut self, resource: impl Into<Resource>) -> Self {
+        self.reads.push(resource.into());
+        self
+    }
+
+    // Lo que escribe: sus attachments y los buffers que modifica
+    pub fn write(mut self, resource: impl Into<Resource>) -> Self {
+        self.writes.push(resource.into());
+        self
+    }
+
+    pub fn execute(self, execute: impl FnOnce(&mut PassContext<'_>) + 'a) {
+        self.graph.passes.push(Pass {
+            name: self.name,
+            reads: self.reads,
+            writes: self.writes,
+            execute: Box::new(execute),
+        });
+    }
+}
+
+// Vive un frame: las closures de los passes pueden tomar prestado el estado del ejemplo durante 'a
+#[derive(Default)]
+pub struct RenderGraph<'a> {
+    textures: Vec<TextureEntry<'a>>,
+    buffers: Vec<&'a wgpu::Buffer>,
+    passes: Vec<Pass<'a>>,
+}
+
+impl<'a> RenderGraph<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Una textura que existe fuera del grafo (el frame del swap chain, un shadow map...). Lo que se escribe en ella
+    // es el resultado del grafo
+    pub fn import_texture(&mut self, name: &str, view: &'a wgpu::TextureView) -> TextureHandle {
+        self.textures.push(TextureEntry {
+            name: name.to_string(),
+            source: TextureSource::Imported(view),
+        });
+        TextureHandle(self.textures.len() - 1)
+    }
+
+    // Una textura que solo existe durante el frame y la crea el grafo
+    pub fn add_texture(&mut self, name: &str, desc: TransientDesc) -> TextureHandle {
+        self.textures.push(TextureEntry {
+            name: name.to_string(),
+            source: TextureSource::Transient(desc),
+        });
+        TextureHandle(self.textures.len() - 1)
+    }
+
+    // Los buffers siempre son externos, el grafo solo los usa para ordenar los passes
+    pub fn import_buffer(&mut self, buffer: &'a wgpu::Buffer) -> BufferHandle {
+        self.buffers.push(buffer);
+        BufferHandle(self.buffers.len() - 1)
+    }
+
+    pub fn add_pass<'g>(&'g mut self, name: &str) -> PassBuilder<'g, 'a> {
+        PassBuilder {
+            graph: self,
+            name: name.to_string(),
+            reads: Vec::new(),
+            writes: Vec::new(),
+        }
+    }
+
+    fn is_imported(&self, resource: Resource) -> bool {
+        match resource {
+            Resource::Texture(handle) => matches!(self.textures[handle.0].source, TextureSource::Imported(_)),
+            Resource::Buffer(_) => true,
+        }
+    }
+
+    // Los passes que tienen que ir antes de cada uno: inputs son de los que usa el resultado, y after ademas los que
+    // tienen que haber leido un recurso antes de que se sobrescriba, que solo cuentan para el orden
+    fn dependencies(&self) -> (Vec<Vec<usize>>, Vec<Vec<usize>>) {
+        let mut inputs = vec![Vec::new(); self.passes.len()];
+        let mut after = vec![Vec::new(); self.passes.len()];
+        let resources = self
+            .passes
+            .iter()
+            .flat_map(|pass| pass.reads.iter().chain(pass.writes.iter()).copied());
+        let mut seen = Vec::new();
+        for resource in resources {
+            if seen.contains(&resource) {
+                continue;
+            }
+            seen.push(resource);
+            let first_writer = self.passes.iter().position(|pass| pass.writes.contains(&resource));
+            let mut last_writer = None;
+            let mut readers = Vec::new();
+            for (p, pass) in self.passes.iter().enumerate() {
+                if pass.writes.contains(&resource) {
+                    inputs[p].extend(last_writer);
+                    after[p].append(&mut readers);
+                    last_writer = Some(p);
+                } else if pass.reads.contains(&resource) {
+                    match last_writer {
+                        Some(writer) => {
+                            inputs[p].push(writer);
+                            readers.push(p);
+                        }
+                        // Declarado antes que quien lo escribe: lee lo que escriba el primero
+                        None => inputs[p].extend(first_writer),
+                    }
+                }
+            }
+        }
+        for (after, inputs) in after.iter_mut().zip(&inputs) {
+            after.extend(inputs.iter().copied());
+        }
+        (inputs, after)
+    }
+
+    // Los passes que hay que ejecutar, en orden
+    fn schedule(&self) -> Result<Vec<usize>> {
+        self.schedule_for(|resource| self.is_imported(resource))
+    }
+
+    // Como schedule() pero con los recursos que cuentan como resultado del grafo, para los tests
+    fn schedule_for(&self, is_output: impl Fn(Resource) -> bool) -> Result<Vec<usize>> {
+        let (inputs, after) = self.dependencies();
+
+        // Se quedan los que escriben algo importado y todo lo que necesitan
+        let mut needed = vec![false; self.passes.len()];
+        let mut pending: Vec<usize> = (0..self.passes.len())
+            .filter(|&p| self.passes[p].writes.iter().any(|&resource| is_output(resource)))
+            .collect();
+        while let Some(p) = pending.pop() {
+            if !needed[p] {
+                needed[p] = true;
+                pending.extend(inputs[p].iter().copied());
+            }
+        }
+
+        // Orden topologico. Entre los que estan listos va primero el que se declaro antes
+        let mut order = Vec::new();
+        let mut done = vec![false; self.passes.len()];
+        while order.len() < needed.iter().filter(|&&n| n).count() {
+            let ready = (0..self.passes.len())
+                .find(|&p| needed[p] && !done[p] && after[p].iter().all(|&d| done[d] || !needed[d]));
+            match ready {
+                Some(p) => {
+                    done[p] = true;
+                    order.push(p);
+                }
+                None => {
+                    let blocked: Vec<&str> = (0..self.passes.len())
+                        .filter(|&p| needed[p] && !done[p])
+                        .map(|p| self.passes[p].name.as_str())
+                        .collect();
+                    bail!("Render graph has a cycle between passes {:?}", blocked);
+                }
+            }
+        }
+        Ok(order)
+    }
+
+    // Que textura de keys usa cada temporal. Cada una esta ocupada desde el primer pass que la usa hasta el ultimo, y
+    // despues la puede usar otra. keys empieza con las que ya hay y al final quedan las que hay que crear
+    fn assign_textures(
+        &self,
+        order: &[usize],
+        transients: &TransientTextures,
+        keys: &mut Vec<TextureKey>,
+    ) -> Vec<Option<usize>> {
+        let mut assigned = vec![None; self.textures.len()];
+        let mut busy_until = vec![None; keys.len()];
+        for (position, &p) in order.iter().enumerate() {
+            for (t, texture) in self.textures.iter().enumerate() {
+                let desc = match &texture.source {
+                    TextureSource::Transient(desc) => desc,
+                    TextureSource::Imported(_) => continue,
+                };
+                let resource = Resource::Texture(TextureHandle(t));
+                if assigned[t].is_some() || !self.passes[p].uses(resource) {
+                    continue;
+                }
+                let last = order
+                    .iter()
+                    .rposition(|&q| self.passes[q].uses(resource))
+                    .unwrap_or(position);
+                assigned[t] = Some(acquire(keys, &mut busy_until, transients.key(desc), position, last));
+            }
+        }
+        assigned
+    }
+
+    // Ordena, crea las texturas temporales y graba y envia los passes
+    pub fn execute(self, device: &wgpu::Device, queue: &wgpu::Queue, transients: &mut TransientTextures) -> Result<()> {
+        let order = self.schedule()?;
+
+        let mut keys = transients.keys();
+        let assigned = self.assign_textures(&order, transients, &mut keys);
+        // Las que faltan se crean con el nombre de la primera temporal que la usa, aunque luego la compartan otras
+        for (index, &key) in keys.iter().enumerate().skip(transients.textures.len()) {
+            let t = assigned
+                .iter()
+                .position(|&a| a == Some(index))
+                .expect("Unused transient texture");
+            transients.create(device, &self.textures[t].name, key);
+        }
+
+        let views: Vec<Option<&wgpu::TextureView>> = self
+            .textures
+            .iter()
+            .zip(&assigned)
+            .map(|(texture, assigned)| match texture.source {
+                TextureSource::Imported(view) => Some(view),
+                TextureSource::Transient(_) => assigned.map(|index: usize| &transients.textures[index].view),
+            })
+            .collect();
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Render Graph Encoder"),
+        });
+        let mut passes: Vec<Option<Pass>> = self.passes.into_iter().map(Some).collect();
+        for p in order {
+            let pass = passes[p].take().expect("Pass scheduled twice");
+            let declared: Vec<Resource> = pass.reads.iter().chain(pass.writes.iter()).copied().collect();
+            let mut context = PassContext {
+                device,
+                encoder: &mut encoder,
+                pass: &pass.name,
+                declared: &declared,
+                textures: &views,
+                buffers: &self.buffers,
+            };
+            (pass.execute)(&mut context);
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+    fn texture(graph: &mut RenderGraph, name: &str) -> TextureHandle {
+        graph.add_texture(name, TransientDesc::new(FORMAT))
+    }
+
+    fn pass(graph: &mut RenderGraph, name: &str, reads: &[TextureHandle], writes: &[TextureHandle]) {
+        let mut builder = graph.add_pass(name);
+        for &read in reads {
+            builder = builder.read(read);
+        }
+        for &write in writes {
+            builder = builder.write(write);
+        }
+        builder.execute(|_| {});
+    }
+
+    // Los nombres de los passes en el orden en el que se ejecutarian, con output como resultado del grafo
+    fn schedule<'g>(graph: &'g RenderGraph, output: TextureHandle) -> Result<Vec<&'g str>> {
+        let order = graph.schedule_for(|resource| resource == output.into())?;
+        Ok(order.iter().map(|&p| graph.passes[p].name.as_str()).collect())
+    }
+
+    #[test]
+    fn readers_go_after_writers_declared_later() {
+        let mut graph = RenderGraph::new();
+        let (scene, output) = (texture(&mut graph, "Scene"), texture(&mut graph, "Output"));
+        pass(&mut graph, "Post", &[scene], &[output]);
+        pass(&mut graph, "UI", &[], &[output]);
+        pass(&mut graph, "Scene", &[], &[scene]);
+        assert_eq!(schedule(&graph, output).unwrap(), ["Scene", "Post", "UI"]);
+    }
+
+    #[test]
+    fn read_then_overwrite() {
+        let mut graph = RenderGraph::new();
+        let (a, output) = (texture(&mut graph, "A"), texture(&mut graph, "Output"));
+        pass(&mut graph, "Draw A", &[], &[a]);
+        pass(&mut graph, "Copy A", &[a], &[output]);
+        pass(&mut graph, "Overwrite A", &[], &[a]);
+        pass(&mut graph, "Blend A", &[a], &[output]);
+        // Copy A lee lo que dibujo Draw A, no lo que hay despues de Overwrite A
+        assert_eq!(
+            schedule(&graph, output).unwrap(),
+            ["Draw A", "Copy A", "Overwrite A", "Blend A"]
+        );
+    }
+
+    #[test]
+    fn unused_passes_are_culled() {
+        let mut graph = RenderGraph::new();
+        let (a, unused, output) = (
+            texture(&mut graph, "A"),
+            texture(&mut graph, "Unused"),
+            texture(&mut graph, "Output"),
+        );
+        pass(&mut graph, "Draw A", &[], &[a]);
+        // Lee A antes de que se sobrescriba, pero su resultado no llega a output
+        pass(&mut graph, "Debug", &[a], &[unused]);
+        pass(&mut graph, "Overwrite A", &[a], &[a]);
+        pass(&mut graph, "Final", &[a], &[output]);
+        assert_eq!(schedule(&graph, output).unwrap(), ["Draw A", "Overwrite A", "Final"]);
+    }
+
+    #[test]
+    fn cycles_are_errors() {
+        let mut graph = RenderGraph::new();
+        let (a, b, output) = (
+            texture(&mut graph, "A"),
+            texture(&mut graph, "B"),
+            texture(&mut graph, "Output"),
+        );
+        pass(&mut graph, "First", &[b], &[a]);
+        pass(&mut graph, "Second", &[a], &[b]);
+        pass(&mut graph, "Final", &[b], &[output]);
+        let error = schedule(&graph, output).unwrap_err().to_string();
+        assert!(error.contains("cycle"), "{}", error);
+        assert!(error.contains("First") && error.contains("Second"), "{}", error);
+    }
+
+    #[test]
+    fn textures_are_reused_after_their_last_reader() {
+        let mut graph = RenderGraph::new();
+        let [a, b, c, output] = ["A", "B", "C", "Output"].map(|name| texture(&mut graph, name));
+        pass(&mut graph, "Write A", &[], &[a]);
+        pass(&mut graph, "A to B", &[a], &[b]);
+        pass(&mut graph, "B to C", &[b], &[c]);
+        pass(&mut graph, "C to Output", &[c], &[output]);
+        let order = graph.schedule_for(|resource| resource == output.into()).unwrap();
+
+        let transients = TransientTextures::default();
+        let mut keys = Vec::new();
+        let assigned = graph.assign_textures(&order, &transients, &mut keys);
+        // A y B se usan a la vez en "A to B", pero C ya puede usar la de A
+        assert_ne!(assigned[a.0], assigned[b.0]);
+        assert_ne!(assigned[b.0], assigned[c.0]);
+        assert_eq!(assigned[c.0], assigned[a.0]);
+        assert_eq!(keys.len(), 2);
+
+        // En el siguiente frame se reutilizan las que ya hay
+        let assigned_again = graph.assign_textures(&order, &transients, &mut keys);
+        assert_eq!(assigned_again, assigned);
+        assert_eq!(keys.len(), 2);
+    }
+
+    #[test]
+    fn scaled_textures_are_rounded_and_not_shared_with_full_size_ones() {
+        let transients = TransientTextures {
+            width: 101,
+            height: 1,
+            textures: Vec::new(),
+        };
+        let half = TransientDesc::new(FORMAT).size(TargetSize::Scaled(0.5));
+        let key = transients.key(&half);
+        assert_eq!((key.width, key.height), (51, 1));
+        let key = transients.key(&TransientDesc::new(FORMAT));
+        assert_eq!((key.width, key.height), (101, 1));
+
+        // Como el bloom: de la escena a media resolucion y vuelta
+        let mut graph = RenderGraph::new();
+        let scene = texture(&mut graph, "Scene");
+        let bright = graph.add_texture("Bright", half);
+        let blurred = graph.add_texture("Blurred", half);
+        let output = texture(&mut graph, "Output");
+        pass(&mut graph, "Scene", &[], &[scene]);
+        pass(&mut graph, "Threshold", &[scene], &[bright]);
+        pass(&mut graph, "Blur", &[bright], &[blurred]);
+        pass(&mut graph, "Combine", &[scene, blurred], &[output]);
+        let order = graph.schedule_for(|resource| resource == output.into()).unwrap();
+        let mut keys = Vec::new();
+        let assigned = graph.assign_textures(&order, &transients, &mut keys);
+        assert_ne!(assigned[bright.0], assigned[blurred.0]);
+        // La salida no puede usar la de Bright aunque ya este libre, es de otro tamaño
+        assert_ne!(assigned[output.0], assigned[bright.0]);
+        assert_eq!(keys.len(), 4);
+    }
+}
@@ -0,0 +1,540 @@
+//## ResourceManager: guarda las texturas, samplers, buffers, layouts y bind groups de un ejemplo y los devuelve como
+//## handles tipados (Handle<wgpu::Buffer>, Handle<Texture>...), que son Copy y se pueden guardar en cualquier struct.
+//##
+//## Cada recurso lleva un contador de referencias. Al crearlo empieza en 1 (la referencia de quien lo crea), un bind
+//## group suma una referencia a su layout y a cada recurso que usa, y release() resta una. Cuando llega a 0 el recurso
+//## se elimina (y wgpu libera la memoria de la GPU), asi que una textura que solo usa un bind group vive lo mismo que el.
+//## Un material (ver material.rs) tiene la referencia a su bind group, que a su vez tiene las de sus texturas.
+
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use crate::examples::{material_layout_entries, Material, Texture};
+
+// Indice en el pool mas una generacion, para detectar handles de recursos que ya se liberaron
+pub struct Handle<T> {
+    index: u32,
+    generation: u32,
+    _marker: PhantomData<T>,
+}
+
+// Implementados a mano para no exigir que T sea Clone, Eq...
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index && self.generation == other.generation
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> std::hash::Hash for Handle<T> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+        self.generation.hash(state);
+    }
+}
+
+impl<T> std::fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Handle({}v{})", self.index, self.generation)
+    }
+}
+
+struct Slot<T> {
+    generation: u32,
+    value: Option<T>,
+    ref_count: u32,
+}
+
+// Almacen de recursos de un tipo con contador de referencias. Los huecos que dejan los recursos liberados se reutilizan.
+struct Pool<T> {
+    slots: Vec<Slot<T>>,
+    free: Vec<u32>,
+}
+
+impl<T> Default for Pool<T> {
+    fn default() -> Self {
+        Self {
+            slots: Vec::new(),
+            free: Vec::new(),
+        }
+    }
+}
+
+impl<T> Pool<T> {
+    pub fn insert(&mut self, value: T) -> Handle<T> {
+        let index = match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                slot.value = Some(value);
+                slot.ref_count = 1;
+                index
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    value: Some(value),
+                    ref_count: 1,
+                });
+                self.slots.len() as u32 - 1
+            }
+        };
+        Handle {
+            index,
+            generation: self.slots[index as usize].generation,
+            _marker: PhantomData,
+        }
+    }
+
+    fn slot(&self, handle: Handle<T>) -> Option<&Slot<T>> {
+        self.slots
+            .get(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation && slot.value.is_some())
+    }
+
+    pub fn get(&self, handle: Handle<T>) -> Option<&T> {
+        self.slot(handle).and_then(|slot| slot.value.as_ref())
+    }
+
+    pub fn retain(&mut self, handle: Handle<T>) {
+        if self.slot(handle).is_some() {
+            self.slots[handle.index as usize].ref_count += 1;
+        }
+    }
+
+    // Resta una referencia. Si era la ultima devuelve el recurso (para que quien llama lo suelte o libere lo que usaba)
+    pub fn release(&mut self, handle: Handle<T>) -> Option<T> {
+        self.slot(handle)?;
+        let slot = &mut self.slots[handle.index as usize];
+        slot.ref_count -= 1;
+        if slot.ref_count > 0 {
+            return None;
+        }
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(handle.index);
+        slot.value.take()
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+}
+
+// Suelta una referencia de first y, cada vez que un recurso se queda sin referencias, una de cada recurso que mantenia
+// vivo. release resta una referencia y, si era la ultima, devuelve esos recursos
+fn release_cascade<R>(first: R, mut release: impl FnMut(R) -> Option<Vec<R>>) {
+    let mut pending = vec![first];
+    while let Some(resource) = pending.pop() {
+        if let Some(dependencies) = release(resource) {
+            pending.extend(dependencies);
+        }
+    }
+}
+
+// Un recurso para un binding del bind group
+#[allow(dead_code)]
+#[derive(Copy, Clone, Debug)]
+pub enum Binding {
+    Buffer(Handle<wgpu::Buffer>),
+    // La vista de la textura
+    Texture(Handle<Texture>),
+    // El sampler que se creo con la textura
+    TextureSampler(Handle<Texture>),
+    Sampler(Handle<wgpu::Sampler>),
+}
+
+pub struct BindGroup {
+    pub bind_group: wgpu::BindGroup,
+    layout: Handle<wgpu::BindGroupLayout>,
+    bindings: Vec<Binding>,
+}
+
+// Un recurso de cualquier tipo, para la cascada de release_cascade
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Resource {
+    Texture(Handle<Texture>),
+    Sampler(Handle<wgpu::Sampler>),
+    Buffer(Handle<wgpu::Buffer>),
+    BindGroupLayout(Handle<wgpu::BindGroupLayout>),
+    BindGroup(Handle<BindGroup>),
+    Material(Handle<Material>),
+}
+
+// Las referencias que tiene un bind group: su layout y una por binding (dos a una textura si tambien usa su sampler)
+fn bind_group_dependencies(layout: Handle<wgpu::BindGroupLayout>, bindings: &[Binding]) -> Vec<Resource> {
+    let mut dependencies = vec![Resource::BindGroupLayout(layout)];
+    dependencies.extend(bindings.iter().map(|&binding| match binding {
+        Binding::Buffer(handle) => Resource::Buffer(handle),
+        Binding::Texture(handle) | Binding::TextureSampler(handle) => Resource::Texture(handle),
+        Binding::Sampler(handle) => Resource::Sampler(handle),
+    }));
+    dependencies
+}
+
+// layout(set=N, binding=M) uniform texture2D t_nombre; en el fragment shader
+pub fn texture_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStage::FRAGMENT,
+        ty: wgpu::BindingType::SampledTexture {
+            multisampled: false,
+            dimension: wgpu::TextureViewDimension::D2,
+            component_type: wgpu::TextureComponentType::Uint,
+        },
+        count: None,
+    }
+}
+
+// layout(set=N, binding=M) uniform sampler s_nombre; en el fragment shader
+pub fn sampler_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStage::FRAGMENT,
+        ty: wgpu::BindingType::Sampler { comparison: false },
+        count: None,
+    }
+}
+
+#[derive(Default)]
+pub struct ResourceManager {
+    textures: Pool<Texture>,
+    samplers: Pool<wgpu::Sampler>,
+    buffers: Pool<wgpu::Buffer>,
+    bind_group_layouts: Pool<wgpu::BindGroupLayout>,
+    bind_groups: Pool<BindGroup>,
+    materials: Pool<Material>,
+    // Un layout por numero de texturas, compartido por todos los materiales con ese numero de texturas
+    material_layouts: HashMap<u32, Handle<wgpu::BindGroupLayout>>,
+}
+
+// No todos los ejemplos usan todos los tipos de recurso
+#[allow(dead_code)]
+impl ResourceManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_texture(&mut self, texture: Texture) -> Handle<Texture> {
+        self.textures.insert(texture)
+    }
+
+    pub fn add_sampler(&mut self, sampler: wgpu::Sampler) -> Handle<wgpu::Sampler> {
+        self.samplers.insert(sampler)
+    }
+
+    pub fn add_buffer(&mut self, buffer: wgpu::Buffer) -> Handle<wgpu::Buffer> {
+        self.buffers.insert(buffer)
+    }
+
+    pub fn add_material(&mut self, material: Material) -> Handle<Material> {
+        self.materials.insert(material)
+    }
+
+    pub fn create_bind_group_layout(
+        &mut self,
+        device: &wgpu::Device,
+        entries: &[wgpu::BindGroupLayoutEntry],
+        label: Option<&str>,
+    ) -> Handle<wgpu::BindGroupLayout> {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor { entries, label });
+        self.bind_group_layouts.insert(layout)
+    }
+
+    // El layout del set 1 de los materiales con num_textures texturas. Lo mantiene vivo el propio ResourceManager
+    pub fn material_layout(&mut self, device: &wgpu::Device, num_textures: u32) -> Handle<wgpu::BindGroupLayout> {
+        if let Some(&layout) = self.material_layouts.get(&num_textures) {
+            return layout;
+        }
+        let layout =
+            self.create_bind_group_layout(device, &material_layout_entries(num_textures), Some("material_layout"));
+        self.material_layouts.insert(num_textures, layout);
+        layout
+    }
+
+    // Crea un bind group con los recursos en el orden de los bindings (el primero es el binding 0).
+    // El bind group mantiene vivos el layout y los recursos hasta que se libera.
+    pub fn create_bind_group(
+        &mut self,
+        device: &wgpu::Device,
+        layout: Handle<wgpu::BindGroupLayout>,
+        bindings: &[Binding],
+        label: Option<&str>,
+    ) -> Handle<BindGroup> {
+        let bind_group = {
+            let entries = bindings
+                .iter()
+                .enumerate()
+                .map(|(i, &binding)| wgpu::BindGroupEntry {
+                    binding: i as u32,
+                    resource: self.binding_resource(binding),
+                })
+                .collect::<Vec<_>>();
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: self.bind_group_layout(layout),
+                entries: &entries,
+                label,
+            })
+        };
+
+        self.bind_group_layouts.retain(layout);
+        for &binding in bindings {
+            self.retain_binding(binding);
+        }
+        self.bind_groups.insert(BindGroup {
+            bind_group,
+            layout,
+            bindings: bindings.to_vec(),
+        })
+    }
+
+    fn binding_resource(&self, binding: Binding) -> wgpu::BindingResource<'_> {
+        match binding {
+            Binding::Buffer(handle) => wgpu::BindingResource::Buffer(self.buffer(handle).slice(..)),
+            Binding::Texture(handle) => wgpu::BindingResource::TextureView(&self.texture(handle).view),
+            Binding::TextureSampler(handle) => wgpu::BindingResource::Sampler(&self.texture(handle).sampler),
+            Binding::Sampler(handle) => wgpu::BindingResource::Sampler(self.sampler(handle)),
+        }
+    }
+
+    fn retain_binding(&mut self, binding: Binding) {
+        match binding {
+            Binding::Buffer(handle) => self.buffers.retain(handle),
+            Binding::Texture(handle) | Binding::TextureSampler(handle) => self.textures.retain(handle),
+            Binding::Sampler(handle) => self.samplers.retain(handle),
+        }
+    }
+
+    // Suelta una referencia y, si era la ultima, libera el recurso y devuelve los que mantenia vivos
+    fn release_one(&mut self, resource: Resource) -> Option<Vec<Resource>> {
+        match resource {
+            Resource::Texture(handle) => self.textures.release(handle).map(|_| Vec::new()),
+            Resource::Sampler(handle) => self.samplers.release(handle).map(|_| Vec::new()),
+            Resource::Buffer(handle) => self.buffers.release(handle).map(|_| Vec::new()),
+            Resource::BindGroupLayout(handle) => self.bind_group_layouts.release(handle).map(|_| Vec::new()),
+            Resource::BindGroup(handle) => self
+                .bind_groups
+                .release(handle)
+                .map(|bind_group| bind_group_dependencies(bind_group.layout, &bind_group.bindings)),
+            Resource::Material(handle) => self
+                .materials
+                .release(handle)
+                .map(|material| vec![Resource::BindGroup(material.bind_group)]),
+        }
+    }
+
+    fn release(&mut self, resource: Resource) {
+        release_cascade(resource, |resource| self.release_one(resource));
+    }
+
+    // Las referencias solo hay que soltarlas si ya no vamos a usar el handle, lo que quede sin referencias se libera
+    pub fn release_texture(&mut self, handle: Handle<Texture>) {
+        self.release(Resource::Texture(handle));
+    }
+
+    pub fn release_sampler(&mut self, handle: Handle<wgpu::Sampler>) {
+        self.release(Resource::Sampler(handle));
+    }
+
+    pub fn release_buffer(&mut self, handle: Handle<wgpu::Buffer>) {
+        self.release(Resource::Buffer(handle));
+    }
+
+    pub fn release_bind_group_layout(&mut self, handle: Handle<wgpu::BindGroupLayout>) {
+        self.release(Resource::BindGroupLayout(handle));
+    }
+
+    // Al liberar un bind group se sueltan tambien las referencias que tenia a su layout y sus recursos
+    pub fn release_bind_group(&mut self, handle: Handle<BindGroup>) {
+        self.release(Resource::BindGroup(handle));
+    }
+
+    // Y al liberar un material, la de su bind group
+    pub fn release_material(&mut self, handle: Handle<Material>) {
+        self.release(Resource::Material(handle));
+    }
+
+    // Los accesores hacen panic con un handle ya liberado, igual que indexar un Vec fuera de rango
+    pub fn texture(&self, handle: Handle<Texture>) -> &Texture {
+        self.textures.get(handle).expect("Texture handle was released")
+    }
+
+    pub fn sampler(&self, handle: Handle<wgpu::Sampler>) -> &wgpu::Sampler {
+        self.samplers.get(handle).expect("Sampler handle was released")
+    }
+
+    pub fn buffer(&self, handle: Handle<wgpu::Buffer>) -> &wgpu::Buffer {
+        self.buffers.get(handle).expect("Buffer handle was released")
+    }
+
+    pub fn bind_group_layout(&self, handle: Handle<wgpu::BindGroupLayout>) -> &wgpu::BindGroupLayout {
+        self.bind_group_layouts
+            .get(handle)
+            .expect("Bind group layout handle was released")
+    }
+
+    pub fn bind_group(&self, handle: Handle<BindGroup>) -> &wgpu::BindGroup {
+        &self
+            .bind_groups
+            .get(handle)
+            .expect("Bind group handle was released")
+            .bind_group
+    }
+
+    pub fn material(&self, handle: Handle<Material>) -> &Material {
+        self.materials.get(handle).expect("Material handle was released")
+    }
+
+    // Cuantos recursos siguen vivos, util para comprobar que no se queda nada sin liberar
+    pub fn texture_count(&self) -> usize {
+        self.textures.len()
+    }
+
+    pub fn bind_group_count(&self) -> usize {
+        self.bind_groups.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Un recurso de prueba con los handles de los que mantiene vivos, como un bind group o un material
+    struct Node {
+        name: &'static str,
+        dependencies: Vec<Handle<Node>>,
+    }
+
+    fn node(pool: &mut Pool<Node>, name: &'static str, dependencies: &[Handle<Node>]) -> Handle<Node> {
+        // Como create_bind_group: una referencia mas a cada dependencia
+        for &dependency in dependencies {
+            pool.retain(dependency);
+        }
+        pool.insert(Node {
+            name,
+            dependencies: dependencies.to_vec(),
+        })
+    }
+
+    fn release(pool: &mut Pool<Node>, handle: Handle<Node>) {
+        release_cascade(handle, |handle| pool.release(handle).map(|node| node.dependencies));
+    }
+
+    fn ref_count<T>(pool: &Pool<T>, handle: Handle<T>) -> Option<u32> {
+        pool.slot(handle).map(|slot| slot.ref_count)
+    }
+
+    fn handle<T>(index: u32) -> Handle<T> {
+        Handle {
+            index,
+            generation: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    #[test]
+    fn release_keeps_the_value_while_there_are_references() {
+        let mut pool = Pool::default();
+        let handle = pool.insert("a".to_string());
+        pool.retain(handle);
+        assert_eq!(pool.release(handle), None);
+        assert_eq!(pool.get(handle).map(String::as_str), Some("a"));
+        assert_eq!(pool.len(), 1);
+    }
+
+    #[test]
+    fn last_release_returns_the_value() {
+        let mut pool = Pool::default();
+        let handle = pool.insert("a".to_string());
+        pool.retain(handle);
+        pool.release(handle);
+        assert_eq!(pool.release(handle), Some("a".to_string()));
+        assert_eq!(pool.get(handle), None);
+        assert_eq!(pool.len(), 0);
+        // Un handle ya liberado no hace nada
+        assert_eq!(pool.release(handle), None);
+    }
+
+    #[test]
+    fn freed_slots_are_reused() {
+        let mut pool = Pool::default();
+        let a = pool.insert("a".to_string());
+        let b = pool.insert("b".to_string());
+        pool.release(a);
+        let c = pool.insert("c".to_string());
+        assert_eq!(pool.slots.len(), 2);
+        assert_eq!(c.index, a.index);
+        // El handle viejo no ve el recurso nuevo que ocupa su hueco
+        assert_ne!(c, a);
+        assert_eq!(pool.get(a), None);
+        pool.retain(a);
+        assert_eq!(ref_count(&pool, c), Some(1));
+        assert_eq!(pool.get(c).map(String::as_str), Some("c"));
+        assert_eq!(pool.get(b).map(String::as_str), Some("b"));
+    }
+
+    #[test]
+    fn textures_with_their_sampler_are_referenced_twice() {
+        let (layout, texture, buffer, sampler) = (handle(0), handle(1), handle(2), handle(3));
+        let bindings = [
+            Binding::Buffer(buffer),
+            Binding::Texture(texture),
+            Binding::TextureSampler(texture),
+            Binding::Sampler(sampler),
+        ];
+        assert_eq!(
+            bind_group_dependencies(layout, &bindings),
+            vec![
+                Resource::BindGroupLayout(layout),
+                Resource::Buffer(buffer),
+                Resource::Texture(texture),
+                Resource::Texture(texture),
+                Resource::Sampler(sampler),
+            ]
+        );
+    }
+
+    #[test]
+    fn releasing_a_material_releases_its_bind_group_and_textures_once() {
+        let mut pool = Pool::default();
+        let layout = node(&mut pool, "layout", &[]);
+        let texture = node(&mut pool, "texture", &[]);
+        let buffer = node(&mut pool, "buffer", &[]);
+        // La textura dos veces, por la vista y el sampler
+        let bind_group = node(&mut pool, "bind group", &[layout, texture, texture, buffer]);
+        let other_bind_group = node(&mut pool, "other bind group", &[layout, texture]);
+        // Como en create_material: el material se queda la referencia del bind group y se sueltan las de quien los creo
+        let material = pool.insert(Node {
+            name: "material",
+            dependencies: vec![bind_group],
+        });
+        release(&mut pool, texture);
+        release(&mut pool, buffer);
+        assert_eq!(ref_count(&pool, texture), Some(3));
+
+        release(&mut pool, material);
+        assert!(pool.get(material).is_none());
+        assert!(pool.get(bind_group).is_none());
+        assert!(pool.get(buffer).is_none());
+        // La textura sigue viva con la referencia del otro bind group
+        assert_eq!(ref_count(&pool, texture), Some(1));
+        assert_eq!(ref_count(&pool, layout), Some(2));
+
+        // Liberar otra vez el material no suelta nada mas
+        release(&mut pool, material);
+        assert_eq!(ref_count(&pool, texture), Some(1));
+
+        release(&mut pool, other_bind_group);
+        assert!(pool.get(texture).is_none());
+        // El layout lo sigue teniendo quien lo creo
+        assert_eq!(pool.get(layout).map(|node| node.name), Some("layout"));
+        assert_eq!(pool.len(), 1);
+    }
+}
@@ -0,0 +1,81 @@
+#version 450
+
+// Vista de depuracion del G-buffer: la pantalla se divide en 3 x 2 y cada trozo muestra entera una de las texturas.
+// Arriba el color base, las normales y el material (metalicidad en el rojo, rugosidad en el verde, oclusion en el
+// azul); abajo la emision, la profundidad y cuantas luces le tocan a cada tile. Usa fullscreen.vert
+layout(location=0) in vec2 v_tex_coords;
+layout(location=0) out vec4 f_color;
+
+layout(set=0, binding=0) uniform texture2D t_albedo;
+layout(set=0, binding=1) uniform texture2D t_normal;
+layout(set=0, binding=2) uniform texture2D t_material;
+layout(set=0, binding=3) uniform texture2D t_emissive;
+layout(set=0, binding=4) uniform texture2D t_depth;
+layout(set=0, binding=5) uniform sampler s_gbuffer;
+
+layout(set=1, binding=0) uniform Deferred {
+    mat4 u_view;
+    mat4 u_inv_projection;
+    mat4 u_inv_view_proj;
+    vec4 u_view_position;
+    uvec2 u_screen_size;
+    uvec2 u_num_tiles;
+    uint u_num_lights;
+    uint u_show_tiles;
+};
+
+layout(set=1, binding=2) readonly buffer Tiles {
+    uint tile_lights[];
+};
+
+const uint TILE_SIZE = 16;
+const uint MAX_LIGHTS_PER_TILE = 255;
+const uint TILE_STRIDE = MAX_LIGHTS_PER_TILE + 1;
+const float HEAT_LIGHTS = 32.0;
+// A que distancia la profundidad se ve gris medio
+const float DEPTH_SCALE = 10.0;
+
+vec3 heat(uint count) {
+    float t = clamp(float(count) / HEAT_LIGHTS, 0.0, 1.0);
+    return clamp(vec3(t * 2.0 - 0.5, 1.0 - abs(t * 2.0 - 1.0), 1.5 - t * 2.0), 0.0, 1.0);
+}
+
+void main() {
+    vec2 grid = v_tex_coords * vec2(3.0, 2.0);
+    ivec2 cell = min(ivec2(grid), ivec2(2, 1));
+    ivec2 pixel = min(ivec2(fract(grid) * vec2(u_screen_size)), ivec2(u_screen_size) - 1);
+
+    vec3 color;
+    switch (cell.y * 3 + cell.x) {
+        case 0:
+            color = texelFetch(sampler2D(t_albedo, s_gbuffer), pixel, 0).rgb;
+            break;
+        case 1:
+            color = texelFetch(sampler2D(t_normal, s_gbuffer), pixel, 0).xyz * 0.5 + 0.5;
+            break;
+        case 2:
+            color = texelFetch(sampler2D(t_material, s_gbuffer), pixel, 0).rgb;
+            break;
+        case 3: {
+            // La emision puede pasar de 1.0
+            vec3 emissive = texelFetch(sampler2D(t_emissive, s_gbuffer), pixel, 0).rgb;
+            color = emissive / (1.0 + emissive);
+            break;
+        }
+        case 4: {
+            // La profundidad del depth buffer casi siempre esta cerca de 1, se muestra la distancia a la camara
+            float depth = texelFetch(sampler2D(t_depth, s_gbuffer), pixel, 0).r;
+            vec4 position = u_inv_projection * vec4(0.0, 0.0, depth, 1.0);
+            float distance = -position.z / position.w;
+            color = vec3(depth < 1.0 ? exp(-distance / DEPTH_SCALE * 0.69) : 0.0);
+            break;
+        }
+        default: {
+            uvec2 tile = uvec2(pixel) / TILE_SIZE;
+            uint count = tile_lights[(tile.y * u_num_tiles.x + tile.x) * TILE_STRIDE];
+            color = heat(count) * (count > 0 ? 1.0 : 0.2);
+            break;
+        }
+    }
+    f_color = vec4(color, 1.0);
+}
\ No newline at end of file
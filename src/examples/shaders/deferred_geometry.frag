@@ -0,0 +1,77 @@
+#version 450
+
+// Pass de geometria del deferred: en vez de calcular la luz, guarda en el G-buffer (deferred.rs) lo que hace falta
+// para calcularla despues. Lee los mismos materiales que pbr.frag y usa deferred_geometry.vert
+layout(location=0) in vec3 v_world_position;
+layout(location=1) in vec3 v_world_normal;
+layout(location=2) in vec2 v_tex_coords;
+layout(location=3) in vec4 v_world_tangent;
+layout(location=4) flat in float v_fade;
+
+// Uno por textura del G-buffer, en el orden de GBUFFER_FORMATS
+layout(location=0) out vec4 f_albedo;
+layout(location=1) out vec4 f_normal;
+layout(location=2) out vec4 f_material;
+layout(location=3) out vec4 f_emissive;
+
+// Las texturas de PbrMaterial (pbr.rs). Las que faltan son de 1x1 y no cambian nada
+layout(set = 1, binding = 1) uniform texture2D t_base_color;
+layout(set = 1, binding = 2) uniform sampler s_base_color;
+// Como en glTF: rugosidad en el verde y metalicidad en el azul
+layout(set = 1, binding = 3) uniform texture2D t_metallic_roughness;
+layout(set = 1, binding = 4) uniform sampler s_metallic_roughness;
+layout(set = 1, binding = 5) uniform texture2D t_normal;
+layout(set = 1, binding = 6) uniform sampler s_normal;
+layout(set = 1, binding = 7) uniform texture2D t_occlusion;
+layout(set = 1, binding = 8) uniform sampler s_occlusion;
+layout(set = 1, binding = 9) uniform texture2D t_emissive;
+layout(set = 1, binding = 10) uniform sampler s_emissive;
+layout(set = 1, binding = 0) uniform PbrParams {
+    vec4 u_base_color;
+    vec4 u_emissive;
+    float u_metallic;
+    float u_roughness;
+    float u_normal_scale;
+    float u_occlusion_strength;
+};
+
+const float MIN_ROUGHNESS = 0.04;
+
+// Matriz de Bayer 4x4: reparte los 16 valores de forma que cualquier umbral deja pixeles bien mezclados
+const float BAYER[16] = float[](
+    0.0, 8.0, 2.0, 10.0,
+    12.0, 4.0, 14.0, 6.0,
+    3.0, 11.0, 1.0, 9.0,
+    15.0, 7.0, 13.0, 5.0
+);
+
+void main() {
+    // Cross-fade de los LOD (LodFade en lod.rs): hasta 1 se queda con los pixeles por debajo del umbral, de 1 a 2 con
+    // los de encima de umbral - 1
+    ivec2 pixel = ivec2(mod(gl_FragCoord.xy, 4.0));
+    float dither = (BAYER[pixel.y * 4 + pixel.x] + 0.5) / 16.0;
+    if (v_fade <= 1.0 ? dither >= v_fade : dither < v_fade - 1.0) {
+        discard;
+    }
+
+    vec3 normal = normalize(v_world_normal);
+    vec3 tangent = normalize(v_world_tangent.xyz - normal * dot(normal, v_world_tangent.xyz));
+    vec3 bitangent = cross(normal, tangent) * v_world_tangent.w;
+    vec3 normal_ts = texture(sampler2D(t_normal, s_normal), v_tex_coords).xyz * 2.0 - 1.0;
+    normal_ts.xy *= u_normal_scale;
+
+    vec4 base_color = texture(sampler2D(t_base_color, s_base_color), v_tex_coords) * u_base_color;
+    vec4 metallic_roughness = texture(sampler2D(t_metallic_roughness, s_metallic_roughness), v_tex_coords);
+    float occlusion = texture(sampler2D(t_occlusion, s_occlusion), v_tex_coords).r;
+    vec3 emissive = texture(sampler2D(t_emissive, s_emissive), v_tex_coords).rgb * u_emissive.rgb;
+
+    f_albedo = vec4(base_color.rgb, 1.0);
+    f_normal = vec4(normalize(mat3(tangent, bitangent, normal) * normal_ts), 0.0);
+    f_material = vec4(
+        clamp(u_metallic * metallic_roughness.b, 0.0, 1.0),
+        clamp(u_roughness * metallic_roughness.g, MIN_ROUGHNESS, 1.0),
+        1.0 + u_occlusion_strength * (occlusion - 1.0),
+        0.0
+    );
+    f_emissive = vec4(emissive, 1.0);
+}
\ No newline at end of file
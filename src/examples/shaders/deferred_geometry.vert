@@ -0,0 +1,37 @@
+#version 450
+
+// Como normal_map.vert pero con el umbral de dithering de la instancia para el cross-fade de los LOD (LodFade en
+// lod.rs), que pasa tal cual al fragment shader
+layout(location=0) in vec3 a_position;
+layout(location=1) in vec3 a_normal;
+layout(location=2) in vec2 a_tex_coords;
+layout(location=3) in vec4 a_tangent;
+layout(location=5) in vec4 a_model_0;
+layout(location=6) in vec4 a_model_1;
+layout(location=7) in vec4 a_model_2;
+layout(location=8) in vec4 a_model_3;
+layout(location=9) in float a_fade;
+
+layout(location=0) out vec3 v_world_position;
+layout(location=1) out vec3 v_world_normal;
+layout(location=2) out vec2 v_tex_coords;
+layout(location=3) out vec4 v_world_tangent;
+layout(location=4) flat out float v_fade;
+
+layout(set = 2, binding = 0) uniform Camera {
+    mat4 u_view_proj;
+    vec4 u_view_position;
+};
+
+void main() {
+    mat4 model = mat4(a_model_0, a_model_1, a_model_2, a_model_3);
+    mat3 normal_matrix = transpose(inverse(mat3(model)));
+    v_world_normal = normal_matrix * a_normal;
+    // La tangente va sobre la superficie, asi que se transforma como las posiciones
+    v_world_tangent = vec4(mat3(model) * a_tangent.xyz, a_tangent.w);
+    vec4 world_position = model * vec4(a_position, 1.0);
+    v_world_position = world_position.xyz;
+    v_tex_coords = a_tex_coords;
+    v_fade = a_fade;
+    gl_Position = u_view_proj * world_position;
+}
\ No newline at end of file
@@ -0,0 +1,198 @@
+#version 450
+
+// Pass de luz del deferred: para cada pixel lee el G-buffer, reconstruye la posicion en el mundo a partir de la
+// profundidad y suma el sol, los focos y solo las luces puntuales de la lista de su tile
+// (deferred_light_culling.comp). Las formulas son las de pbr.frag. Usa fullscreen.vert
+layout(location=0) in vec2 v_tex_coords;
+layout(location=0) out vec4 f_color;
+
+layout(set=0, binding=0) uniform texture2D t_albedo;
+layout(set=0, binding=1) uniform texture2D t_normal;
+layout(set=0, binding=2) uniform texture2D t_material;
+layout(set=0, binding=3) uniform texture2D t_emissive;
+layout(set=0, binding=4) uniform texture2D t_depth;
+layout(set=0, binding=5) uniform sampler s_gbuffer;
+
+layout(set=1, binding=0) uniform Deferred {
+    mat4 u_view;
+    mat4 u_inv_projection;
+    mat4 u_inv_view_proj;
+    vec4 u_view_position;
+    uvec2 u_screen_size;
+    uvec2 u_num_tiles;
+    uint u_num_lights;
+    uint u_show_tiles;
+};
+
+struct PointLight {
+    vec3 position;
+    float range;
+    vec3 color;
+    float intensity;
+};
+
+layout(set=1, binding=1) readonly buffer PointLights {
+    PointLight point_lights[];
+};
+layout(set=1, binding=2) readonly buffer Tiles {
+    uint tile_lights[];
+};
+
+const uint TILE_SIZE = 16;
+const uint MAX_LIGHTS_PER_TILE = 255;
+const uint TILE_STRIDE = MAX_LIGHTS_PER_TILE + 1;
+
+const int MAX_POINT_LIGHTS = 4;
+const int MAX_DIRECTIONAL_LIGHTS = 2;
+const int MAX_SPOT_LIGHTS = 2;
+
+struct UniformPointLight {
+    vec3 position;
+    float range;
+    vec3 color;
+    float intensity;
+};
+
+struct DirectionalLight {
+    vec3 direction;
+    float _padding;
+    vec3 color;
+    float intensity;
+};
+
+struct SpotLight {
+    vec3 position;
+    float range;
+    vec3 direction;
+    float intensity;
+    vec3 color;
+    float cos_inner;
+    float cos_outer;
+};
+
+// El mismo bloque que en lit.frag. Las luces puntuales de aqui no se usan, todas van en PointLights
+layout(set=2, binding=0) uniform Lights {
+    vec3 u_ambient;
+    uint u_blinn;
+    UniformPointLight u_point_lights[MAX_POINT_LIGHTS];
+    DirectionalLight u_directional_lights[MAX_DIRECTIONAL_LIGHTS];
+    SpotLight u_spot_lights[MAX_SPOT_LIGHTS];
+    uint u_num_point_lights;
+    uint u_num_directional_lights;
+    uint u_num_spot_lights;
+};
+
+const float PI = 3.14159265359;
+// Con cuantas luces en un tile se llega al rojo en la vista de los tiles
+const float HEAT_LIGHTS = 32.0;
+
+struct Surface {
+    vec3 normal;
+    vec3 view_dir;
+    vec3 albedo;
+    float metallic;
+    float roughness;
+    vec3 f0;
+};
+
+float distribution_ggx(float n_dot_h, float roughness) {
+    float a = roughness * roughness;
+    float a2 = a * a;
+    float d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+float geometry_schlick_ggx(float n_dot_v, float k) {
+    return n_dot_v / (n_dot_v * (1.0 - k) + k);
+}
+
+float geometry_smith(float n_dot_v, float n_dot_l, float roughness) {
+    float k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+    return geometry_schlick_ggx(n_dot_v, k) * geometry_schlick_ggx(n_dot_l, k);
+}
+
+vec3 fresnel_schlick(float cos_theta, vec3 f0) {
+    return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+vec3 shade(vec3 light_dir, vec3 radiance, Surface surface) {
+    vec3 half_dir = normalize(light_dir + surface.view_dir);
+    float n_dot_l = max(dot(surface.normal, light_dir), 0.0);
+    float n_dot_v = max(dot(surface.normal, surface.view_dir), 1e-4);
+    float n_dot_h = max(dot(surface.normal, half_dir), 0.0);
+
+    vec3 fresnel = fresnel_schlick(max(dot(half_dir, surface.view_dir), 0.0), surface.f0);
+    float d = distribution_ggx(n_dot_h, surface.roughness);
+    float g = geometry_smith(n_dot_v, n_dot_l, surface.roughness);
+    vec3 specular = d * g * fresnel / (4.0 * n_dot_v * n_dot_l + 1e-4);
+    vec3 diffuse = (1.0 - fresnel) * (1.0 - surface.metallic) * surface.albedo / PI;
+    return (diffuse + specular) * radiance * n_dot_l;
+}
+
+float attenuation(float distance, float range) {
+    float falloff = clamp(1.0 - pow(distance / range, 4.0), 0.0, 1.0);
+    return falloff * falloff / (distance * distance + 1.0);
+}
+
+// De azul (ninguna luz) a verde y a rojo (HEAT_LIGHTS o mas)
+vec3 heat(uint count) {
+    float t = clamp(float(count) / HEAT_LIGHTS, 0.0, 1.0);
+    return clamp(vec3(t * 2.0 - 0.5, 1.0 - abs(t * 2.0 - 1.0), 1.5 - t * 2.0), 0.0, 1.0);
+}
+
+void main() {
+    ivec2 pixel = ivec2(gl_FragCoord.xy);
+    float depth = texelFetch(sampler2D(t_depth, s_gbuffer), pixel, 0).r;
+    // Donde no hay geometria se queda el color con el que se limpio la textura
+    if (depth >= 1.0) {
+        discard;
+    }
+    vec3 albedo = texelFetch(sampler2D(t_albedo, s_gbuffer), pixel, 0).rgb;
+    vec3 normal = texelFetch(sampler2D(t_normal, s_gbuffer), pixel, 0).xyz;
+    vec3 material = texelFetch(sampler2D(t_material, s_gbuffer), pixel, 0).rgb;
+    vec3 emissive = texelFetch(sampler2D(t_emissive, s_gbuffer), pixel, 0).rgb;
+
+    // La y de NDC crece hacia arriba y la de gl_FragCoord hacia abajo
+    vec2 ndc = gl_FragCoord.xy / vec2(u_screen_size) * 2.0 - 1.0;
+    vec4 world_position = u_inv_view_proj * vec4(ndc.x, -ndc.y, depth, 1.0);
+    vec3 position = world_position.xyz / world_position.w;
+
+    Surface surface;
+    surface.normal = normalize(normal);
+    surface.view_dir = normalize(u_view_position.xyz - position);
+    surface.albedo = albedo;
+    surface.metallic = material.r;
+    surface.roughness = material.g;
+    surface.f0 = mix(vec3(0.04), albedo, surface.metallic);
+
+    vec3 color = u_ambient * albedo * material.b + emissive;
+    for (uint i = 0; i < u_num_directional_lights; i++) {
+        DirectionalLight light = u_directional_lights[i];
+        color += shade(normalize(-light.direction), light.color * light.intensity, surface);
+    }
+    for (uint i = 0; i < u_num_spot_lights; i++) {
+        SpotLight light = u_spot_lights[i];
+        vec3 to_light = light.position - position;
+        float distance = length(to_light);
+        vec3 light_dir = to_light / distance;
+        float cone = smoothstep(light.cos_outer, light.cos_inner, dot(-light_dir, normalize(light.direction)));
+        vec3 radiance = light.color * light.intensity * attenuation(distance, light.range) * cone;
+        color += shade(light_dir, radiance, surface);
+    }
+
+    uvec2 tile = uvec2(pixel) / TILE_SIZE;
+    uint first = (tile.y * u_num_tiles.x + tile.x) * TILE_STRIDE;
+    uint count = tile_lights[first];
+    for (uint i = 0; i < count; i++) {
+        PointLight light = point_lights[tile_lights[first + 1 + i]];
+        vec3 to_light = light.position - position;
+        float distance = length(to_light);
+        vec3 radiance = light.color * light.intensity * attenuation(distance, light.range);
+        color += shade(to_light / distance, radiance, surface);
+    }
+
+    if (u_show_tiles != 0) {
+        color = mix(color, heat(count), 0.5);
+    }
+    f_color = vec4(color, 1.0);
+}
\ No newline at end of file
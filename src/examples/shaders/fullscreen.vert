@@ -0,0 +1,12 @@
+#version 450
+
+// Un solo triangulo que cubre toda la pantalla. Como en shader_1_3.vert, las posiciones salen de gl_VertexIndex
+// y no hace falta ningun vertex buffer, se dibuja con draw(0..3, 0..1)
+layout(location=0) out vec2 v_tex_coords;
+
+void main() {
+    vec2 uv = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
+    // En wgpu la coordenada v de las texturas crece hacia abajo
+    v_tex_coords = vec2(uv.x, 1.0 - uv.y);
+    gl_Position = vec4(uv * 2.0 - 1.0, 0.0, 1.0);
+}
\ No newline at end of file
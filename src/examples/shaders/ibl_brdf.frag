@@ -0,0 +1,65 @@
+#version 450
+
+// Tabla de la segunda parte del split sum: para cada n_dot_v (x) y rugosidad (y), la escala y el sesgo que se aplican
+// a F0 en la reflexion especular del cielo. No depende del cielo, asi que se calcula una vez
+layout(location=0) in vec2 v_tex_coords;
+layout(location=0) out vec2 f_brdf;
+
+const float PI = 3.14159265359;
+const uint SAMPLE_COUNT = 1024u;
+
+// Puntos de Hammersley: reparten las muestras por el cuadrado [0, 1) mejor que numeros aleatorios
+vec2 hammersley(uint i, uint count) {
+    uint bits = i;
+    bits = (bits << 16u) | (bits >> 16u);
+    bits = ((bits & 0x55555555u) << 1u) | ((bits & 0xAAAAAAAAu) >> 1u);
+    bits = ((bits & 0x33333333u) << 2u) | ((bits & 0xCCCCCCCCu) >> 2u);
+    bits = ((bits & 0x0F0F0F0Fu) << 4u) | ((bits & 0xF0F0F0F0u) >> 4u);
+    bits = ((bits & 0x00FF00FFu) << 8u) | ((bits & 0xFF00FF00u) >> 8u);
+    return vec2(float(i) / float(count), float(bits) * 2.3283064365386963e-10);
+}
+
+// Una direccion alrededor de normal repartida segun la distribucion GGX con esa rugosidad
+vec3 importance_sample_ggx(vec2 xi, vec3 normal, float roughness) {
+    float a = roughness * roughness;
+    float phi = 2.0 * PI * xi.x;
+    float cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+    float sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+    vec3 h = vec3(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+
+    vec3 up = abs(normal.z) < 0.999 ? vec3(0.0, 0.0, 1.0) : vec3(1.0, 0.0, 0.0);
+    vec3 tangent = normalize(cross(up, normal));
+    vec3 bitangent = cross(normal, tangent);
+    return normalize(tangent * h.x + bitangent * h.y + normal * h.z);
+}
+
+float geometry_schlick_ggx(float n_dot_v, float roughness) {
+    // Con IBL k es a / 2, con luces puntuales (r + 1)^2 / 8
+    float k = roughness * roughness / 2.0;
+    return n_dot_v / (n_dot_v * (1.0 - k) + k);
+}
+
+void main() {
+    float n_dot_v = max(v_tex_coords.x, 1e-3);
+    float roughness = v_tex_coords.y;
+    vec3 view = vec3(sqrt(1.0 - n_dot_v * n_dot_v), 0.0, n_dot_v);
+    vec3 normal = vec3(0.0, 0.0, 1.0);
+
+    float scale = 0.0;
+    float bias = 0.0;
+    for (uint i = 0u; i < SAMPLE_COUNT; i++) {
+        vec3 half_dir = importance_sample_ggx(hammersley(i, SAMPLE_COUNT), normal, roughness);
+        vec3 light = normalize(2.0 * dot(view, half_dir) * half_dir - view);
+        float n_dot_l = max(light.z, 0.0);
+        float n_dot_h = max(half_dir.z, 0.0);
+        float v_dot_h = max(dot(view, half_dir), 0.0);
+        if (n_dot_l > 0.0) {
+            float g = geometry_schlick_ggx(n_dot_v, roughness) * geometry_schlick_ggx(n_dot_l, roughness);
+            float g_vis = g * v_dot_h / (n_dot_h * n_dot_v);
+            float fresnel = pow(1.0 - v_dot_h, 5.0);
+            scale += (1.0 - fresnel) * g_vis;
+            bias += fresnel * g_vis;
+        }
+    }
+    f_brdf = vec2(scale, bias) / float(SAMPLE_COUNT);
+}
\ No newline at end of file
@@ -0,0 +1,51 @@
+#version 450
+
+// Mapa de irradiancia: para cada normal, la luz difusa que llega del cielo (la integral del coseno sobre el
+// hemisferio). Se dibuja una vez por cara con fullscreen.vert
+layout(location=0) in vec2 v_tex_coords;
+layout(location=0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform Face {
+    uint u_face;
+    float u_roughness;
+};
+layout(set = 0, binding = 1) uniform textureCube t_environment;
+layout(set = 0, binding = 2) uniform sampler s_environment;
+
+const float PI = 3.14159265359;
+// Paso en radianes entre muestras
+const float SAMPLE_DELTA = 0.05;
+
+// Direccion del pixel de una cara del cubemap con uv entre -1 y 1 (v hacia abajo), como cube_face_direction en
+// texture.rs
+vec3 cube_face_direction(uint face, vec2 uv) {
+    switch (face) {
+        case 0: return vec3(1.0, -uv.y, -uv.x);
+        case 1: return vec3(-1.0, -uv.y, uv.x);
+        case 2: return vec3(uv.x, 1.0, uv.y);
+        case 3: return vec3(uv.x, -1.0, -uv.y);
+        case 4: return vec3(uv.x, -uv.y, 1.0);
+        default: return vec3(-uv.x, -uv.y, -1.0);
+    }
+}
+
+void main() {
+    vec3 normal = normalize(cube_face_direction(u_face, v_tex_coords * 2.0 - 1.0));
+    vec3 up = abs(normal.y) < 0.999 ? vec3(0.0, 1.0, 0.0) : vec3(0.0, 0.0, 1.0);
+    vec3 right = normalize(cross(up, normal));
+    up = cross(normal, right);
+
+    vec3 irradiance = vec3(0.0);
+    float count = 0.0;
+    for (float phi = 0.0; phi < 2.0 * PI; phi += SAMPLE_DELTA) {
+        for (float theta = 0.0; theta < 0.5 * PI; theta += SAMPLE_DELTA) {
+            vec3 local = vec3(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta));
+            vec3 direction = local.x * right + local.y * up + local.z * normal;
+            // cos(theta) por el angulo con la normal y sin(theta) porque cerca del polo las muestras estan mas juntas
+            vec3 radiance = textureLod(samplerCube(t_environment, s_environment), direction, 0.0).rgb;
+            irradiance += radiance * cos(theta) * sin(theta);
+            count += 1.0;
+        }
+    }
+    f_color = vec4(PI * irradiance / count, 1.0);
+}
\ No newline at end of file
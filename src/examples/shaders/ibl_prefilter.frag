@@ -0,0 +1,72 @@
+#version 450
+
+// Mapa especular prefiltrado: cada mip es el cielo visto a traves de un lobulo GGX mas ancho (mas rugosidad), con
+// importance sampling. Como en el split sum de Epic se supone que la direccion de vista es la normal
+layout(location=0) in vec2 v_tex_coords;
+layout(location=0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform Face {
+    uint u_face;
+    float u_roughness;
+};
+layout(set = 0, binding = 1) uniform textureCube t_environment;
+layout(set = 0, binding = 2) uniform sampler s_environment;
+
+const float PI = 3.14159265359;
+const uint SAMPLE_COUNT = 512u;
+
+// Direccion del pixel de una cara del cubemap con uv entre -1 y 1 (v hacia abajo), como cube_face_direction en
+// texture.rs
+vec3 cube_face_direction(uint face, vec2 uv) {
+    switch (face) {
+        case 0: return vec3(1.0, -uv.y, -uv.x);
+        case 1: return vec3(-1.0, -uv.y, uv.x);
+        case 2: return vec3(uv.x, 1.0, uv.y);
+        case 3: return vec3(uv.x, -1.0, -uv.y);
+        case 4: return vec3(uv.x, -uv.y, 1.0);
+        default: return vec3(-uv.x, -uv.y, -1.0);
+    }
+}
+
+// Puntos de Hammersley: reparten las muestras por el cuadrado [0, 1) mejor que numeros aleatorios
+vec2 hammersley(uint i, uint count) {
+    uint bits = i;
+    bits = (bits << 16u) | (bits >> 16u);
+    bits = ((bits & 0x55555555u) << 1u) | ((bits & 0xAAAAAAAAu) >> 1u);
+    bits = ((bits & 0x33333333u) << 2u) | ((bits & 0xCCCCCCCCu) >> 2u);
+    bits = ((bits & 0x0F0F0F0Fu) << 4u) | ((bits & 0xF0F0F0F0u) >> 4u);
+    bits = ((bits & 0x00FF00FFu) << 8u) | ((bits & 0xFF00FF00u) >> 8u);
+    return vec2(float(i) / float(count), float(bits) * 2.3283064365386963e-10);
+}
+
+// Una direccion alrededor de normal repartida segun la distribucion GGX con esa rugosidad
+vec3 importance_sample_ggx(vec2 xi, vec3 normal, float roughness) {
+    float a = roughness * roughness;
+    float phi = 2.0 * PI * xi.x;
+    float cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+    float sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+    vec3 h = vec3(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+
+    vec3 up = abs(normal.z) < 0.999 ? vec3(0.0, 0.0, 1.0) : vec3(1.0, 0.0, 0.0);
+    vec3 tangent = normalize(cross(up, normal));
+    vec3 bitangent = cross(normal, tangent);
+    return normalize(tangent * h.x + bitangent * h.y + normal * h.z);
+}
+
+void main() {
+    vec3 normal = normalize(cube_face_direction(u_face, v_tex_coords * 2.0 - 1.0));
+    vec3 view = normal;
+
+    vec3 color = vec3(0.0);
+    float total_weight = 0.0;
+    for (uint i = 0u; i < SAMPLE_COUNT; i++) {
+        vec3 half_dir = importance_sample_ggx(hammersley(i, SAMPLE_COUNT), normal, u_roughness);
+        vec3 light = normalize(2.0 * dot(view, half_dir) * half_dir - view);
+        float n_dot_l = dot(normal, light);
+        if (n_dot_l > 0.0) {
+            color += textureLod(samplerCube(t_environment, s_environment), light, 0.0).rgb * n_dot_l;
+            total_weight += n_dot_l;
+        }
+    }
+    f_color = vec4(color / total_weight, 1.0);
+}
\ No newline at end of file
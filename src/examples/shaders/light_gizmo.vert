@@ -0,0 +1,64 @@
+#version 450
+
+layout(location=0) in vec3 a_position;
+
+layout(location=0) out vec3 v_color;
+
+layout(set = 1, binding = 0) uniform Camera {
+    mat4 u_view_proj;
+    vec4 u_view_position;
+};
+
+const int MAX_POINT_LIGHTS = 4;
+const int MAX_DIRECTIONAL_LIGHTS = 2;
+const int MAX_SPOT_LIGHTS = 2;
+
+// Tiene que coincidir con LightsData de lighting.rs
+struct PointLight {
+    vec3 position;
+    float range;
+    vec3 color;
+    float intensity;
+};
+
+struct DirectionalLight {
+    vec3 direction;
+    float _padding;
+    vec3 color;
+    float intensity;
+};
+
+struct SpotLight {
+    vec3 position;
+    float range;
+    vec3 direction;
+    float intensity;
+    vec3 color;
+    float cos_inner;
+    float cos_outer;
+};
+
+layout(set = 2, binding = 0) uniform Lights {
+    vec3 u_ambient;
+    uint u_blinn;
+    PointLight u_point_lights[MAX_POINT_LIGHTS];
+    DirectionalLight u_directional_lights[MAX_DIRECTIONAL_LIGHTS];
+    SpotLight u_spot_lights[MAX_SPOT_LIGHTS];
+    uint u_num_point_lights;
+    uint u_num_directional_lights;
+    uint u_num_spot_lights;
+};
+
+// Una instancia por luz: primero las puntuales y despues los focos
+void main() {
+    uint index = uint(gl_InstanceIndex);
+    vec3 position;
+    if (index < u_num_point_lights) {
+        position = u_point_lights[index].position;
+        v_color = u_point_lights[index].color;
+    } else {
+        position = u_spot_lights[index - u_num_point_lights].position;
+        v_color = u_spot_lights[index - u_num_point_lights].color;
+    }
+    gl_Position = u_view_proj * vec4(position + a_position, 1.0);
+}
\ No newline at end of file
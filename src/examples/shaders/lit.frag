@@ -0,0 +1,109 @@
+#version 450
+
+layout(location=0) in vec3 v_world_position;
+layout(location=1) in vec3 v_world_normal;
+layout(location=2) in vec2 v_tex_coords;
+layout(location=0) out vec4 f_color;
+
+// u_tint: color difuso, u_values.x: intensidad especular, u_values.y: shininess
+layout(set = 1, binding = 0) uniform MaterialParams {
+    vec4 u_tint;
+    vec4 u_values;
+};
+
+layout(set = 2, binding = 0) uniform Camera {
+    mat4 u_view_proj;
+    vec4 u_view_position;
+};
+
+const int MAX_POINT_LIGHTS = 4;
+const int MAX_DIRECTIONAL_LIGHTS = 2;
+const int MAX_SPOT_LIGHTS = 2;
+
+// Tiene que coincidir con LightsData de lighting.rs
+struct PointLight {
+    vec3 position;
+    float range;
+    vec3 color;
+    float intensity;
+};
+
+struct DirectionalLight {
+    vec3 direction;
+    float _padding;
+    vec3 color;
+    float intensity;
+};
+
+struct SpotLight {
+    vec3 position;
+    float range;
+    vec3 direction;
+    float intensity;
+    vec3 color;
+    float cos_inner;
+    float cos_outer;
+};
+
+layout(set = 3, binding = 0) uniform Lights {
+    vec3 u_ambient;
+    uint u_blinn;
+    PointLight u_point_lights[MAX_POINT_LIGHTS];
+    DirectionalLight u_directional_lights[MAX_DIRECTIONAL_LIGHTS];
+    SpotLight u_spot_lights[MAX_SPOT_LIGHTS];
+    uint u_num_point_lights;
+    uint u_num_directional_lights;
+    uint u_num_spot_lights;
+};
+
+// Difusa + especular de una luz que llega desde light_dir (hacia la luz) con la radiancia dada
+vec3 shade(vec3 light_dir, vec3 radiance, vec3 normal, vec3 view_dir) {
+    float diffuse = max(dot(normal, light_dir), 0.0);
+    float specular_angle;
+    if (u_blinn != 0) {
+        vec3 half_dir = normalize(light_dir + view_dir);
+        specular_angle = max(dot(normal, half_dir), 0.0);
+    } else {
+        vec3 reflect_dir = reflect(-light_dir, normal);
+        specular_angle = max(dot(view_dir, reflect_dir), 0.0);
+    }
+    // Si la luz llega por detras de la cara tampoco hay brillo
+    float specular = diffuse > 0.0 ? u_values.x * pow(specular_angle, u_values.y) : 0.0;
+    return radiance * (diffuse * u_tint.rgb + specular);
+}
+
+// Inversa del cuadrado de la distancia, llevada suavemente a 0 en range
+float attenuation(float distance, float range) {
+    float falloff = clamp(1.0 - pow(distance / range, 4.0), 0.0, 1.0);
+    return falloff * falloff / (distance * distance + 1.0);
+}
+
+void main() {
+    // La interpolacion acorta las normales, hay que volver a normalizarlas
+    vec3 normal = normalize(v_world_normal);
+    vec3 view_dir = normalize(u_view_position.xyz - v_world_position);
+
+    vec3 color = u_ambient * u_tint.rgb;
+    for (uint i = 0; i < u_num_directional_lights; i++) {
+        DirectionalLight light = u_directional_lights[i];
+        color += shade(normalize(-light.direction), light.color * light.intensity, normal, view_dir);
+    }
+    for (uint i = 0; i < u_num_point_lights; i++) {
+        PointLight light = u_point_lights[i];
+        vec3 to_light = light.position - v_world_position;
+        float distance = length(to_light);
+        vec3 radiance = light.color * light.intensity * attenuation(distance, light.range);
+        color += shade(to_light / distance, radiance, normal, view_dir);
+    }
+    for (uint i = 0; i < u_num_spot_lights; i++) {
+        SpotLight light = u_spot_lights[i];
+        vec3 to_light = light.position - v_world_position;
+        float distance = length(to_light);
+        vec3 light_dir = to_light / distance;
+        // 1 dentro del cono interior, 0 fuera del exterior
+        float cone = smoothstep(light.cos_outer, light.cos_inner, dot(-light_dir, normalize(light.direction)));
+        vec3 radiance = light.color * light.intensity * attenuation(distance, light.range) * cone;
+        color += shade(light_dir, radiance, normal, view_dir);
+    }
+    f_color = vec4(color, u_tint.a);
+}
\ No newline at end of file
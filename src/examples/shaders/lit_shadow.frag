@@ -0,0 +1,178 @@
+#version 450
+
+layout(location=0) in vec3 v_world_position;
+layout(location=1) in vec3 v_world_normal;
+layout(location=2) in vec2 v_tex_coords;
+layout(location=0) out vec4 f_color;
+
+// u_tint: color difuso, u_values.x: intensidad especular, u_values.y: shininess
+layout(set = 1, binding = 0) uniform MaterialParams {
+    vec4 u_tint;
+    vec4 u_values;
+};
+
+layout(set = 2, binding = 0) uniform Camera {
+    mat4 u_view_proj;
+    vec4 u_view_position;
+};
+
+const int MAX_POINT_LIGHTS = 4;
+const int MAX_DIRECTIONAL_LIGHTS = 2;
+const int MAX_SPOT_LIGHTS = 2;
+
+// Tiene que coincidir con LightsData de lighting.rs
+struct PointLight {
+    vec3 position;
+    float range;
+    vec3 color;
+    float intensity;
+};
+
+struct DirectionalLight {
+    vec3 direction;
+    float _padding;
+    vec3 color;
+    float intensity;
+};
+
+struct SpotLight {
+    vec3 position;
+    float range;
+    vec3 direction;
+    float intensity;
+    vec3 color;
+    float cos_inner;
+    float cos_outer;
+};
+
+layout(set = 3, binding = 0) uniform Lights {
+    vec3 u_ambient;
+    uint u_blinn;
+    PointLight u_point_lights[MAX_POINT_LIGHTS];
+    DirectionalLight u_directional_lights[MAX_DIRECTIONAL_LIGHTS];
+    SpotLight u_spot_lights[MAX_SPOT_LIGHTS];
+    uint u_num_point_lights;
+    uint u_num_directional_lights;
+    uint u_num_spot_lights;
+};
+
+const int MAX_CASCADES = 4;
+const int MAX_SHADOW_LAYERS = MAX_CASCADES + MAX_SPOT_LIGHTS;
+
+// Tiene que coincidir con ShadowsData de shadow.rs
+layout(set = 3, binding = 1) uniform texture2DArray t_shadow;
+layout(set = 3, binding = 2) uniform samplerShadow s_shadow;
+layout(set = 3, binding = 3) uniform Shadows {
+    mat4 u_light_view_proj[MAX_SHADOW_LAYERS];
+    vec4 u_cascade_splits;
+    vec4 u_camera_forward;
+    uint u_num_cascades;
+    uint u_pcf_radius;
+    uint u_show_cascades;
+    float u_shadow_texel_size;
+};
+
+// Cuanto se separa de la superficie el punto que se busca en el mapa, para que no se sombree a si misma
+const float NORMAL_OFFSET = 0.02;
+
+// Difusa + especular de una luz que llega desde light_dir (hacia la luz) con la radiancia dada
+vec3 shade(vec3 light_dir, vec3 radiance, vec3 normal, vec3 view_dir) {
+    float diffuse = max(dot(normal, light_dir), 0.0);
+    float specular_angle;
+    if (u_blinn != 0) {
+        vec3 half_dir = normalize(light_dir + view_dir);
+        specular_angle = max(dot(normal, half_dir), 0.0);
+    } else {
+        vec3 reflect_dir = reflect(-light_dir, normal);
+        specular_angle = max(dot(view_dir, reflect_dir), 0.0);
+    }
+    // Si la luz llega por detras de la cara tampoco hay brillo
+    float specular = diffuse > 0.0 ? u_values.x * pow(specular_angle, u_values.y) : 0.0;
+    return radiance * (diffuse * u_tint.rgb + specular);
+}
+
+// 1 si el punto esta iluminado, 0 si esta en sombra, o algo entre medias en el borde de la sombra
+float shadow_factor(uint layer, vec3 world_position) {
+    vec4 light_space = u_light_view_proj[layer] * vec4(world_position, 1.0);
+    vec3 ndc = light_space.xyz / light_space.w;
+    // Fuera del mapa no hay sombra
+    if (ndc.z > 1.0 || abs(ndc.x) > 1.0 || abs(ndc.y) > 1.0) {
+        return 1.0;
+    }
+    // En el clip space la y va hacia arriba y en las texturas hacia abajo
+    vec2 uv = ndc.xy * vec2(0.5, -0.5) + 0.5;
+
+    // PCF: la media de (2 * radius + 1)^2 comparaciones alrededor del punto
+    int radius = int(u_pcf_radius);
+    float lit = 0.0;
+    for (int y = -radius; y <= radius; y++) {
+        for (int x = -radius; x <= radius; x++) {
+            vec2 offset = vec2(float(x), float(y)) * u_shadow_texel_size;
+            lit += texture(sampler2DArrayShadow(t_shadow, s_shadow), vec4(uv + offset, float(layer), ndc.z));
+        }
+    }
+    float taps = float((2 * radius + 1) * (2 * radius + 1));
+    return lit / taps;
+}
+
+// La cascada en la que cae el punto segun su distancia a la camara, o u_num_cascades si esta mas lejos que la ultima
+uint cascade_index(vec3 world_position) {
+    float depth = dot(world_position - u_view_position.xyz, u_camera_forward.xyz);
+    for (uint i = 0; i < u_num_cascades; i++) {
+        if (depth < u_cascade_splits[i]) {
+            return i;
+        }
+    }
+    return u_num_cascades;
+}
+
+// Inversa del cuadrado de la distancia, llevada suavemente a 0 en range
+float attenuation(float distance, float range) {
+    float falloff = clamp(1.0 - pow(distance / range, 4.0), 0.0, 1.0);
+    return falloff * falloff / (distance * distance + 1.0);
+}
+
+void main() {
+    // La interpolacion acorta las normales, hay que volver a normalizarlas
+    vec3 normal = normalize(v_world_normal);
+    vec3 view_dir = normalize(u_view_position.xyz - v_world_position);
+
+    vec3 shadow_position = v_world_position + normal * NORMAL_OFFSET;
+
+    // Solo la primera luz direccional tiene sombras
+    uint cascade = cascade_index(v_world_position);
+    float sun_shadow = cascade < u_num_cascades ? shadow_factor(cascade, shadow_position) : 1.0;
+
+    vec3 color = u_ambient * u_tint.rgb;
+    for (uint i = 0; i < u_num_directional_lights; i++) {
+        DirectionalLight light = u_directional_lights[i];
+        float shadow = i == 0 ? sun_shadow : 1.0;
+        color += shade(normalize(-light.direction), light.color * light.intensity * shadow, normal, view_dir);
+    }
+    for (uint i = 0; i < u_num_point_lights; i++) {
+        PointLight light = u_point_lights[i];
+        vec3 to_light = light.position - v_world_position;
+        float distance = length(to_light);
+        vec3 radiance = light.color * light.intensity * attenuation(distance, light.range);
+        color += shade(to_light / distance, radiance, normal, view_dir);
+    }
+    for (uint i = 0; i < u_num_spot_lights; i++) {
+        SpotLight light = u_spot_lights[i];
+        vec3 to_light = light.position - v_world_position;
+        float distance = length(to_light);
+        vec3 light_dir = to_light / distance;
+        // 1 dentro del cono interior, 0 fuera del exterior
+        float cone = smoothstep(light.cos_outer, light.cos_inner, dot(-light_dir, normalize(light.direction)));
+        float shadow = shadow_factor(uint(MAX_CASCADES) + i, shadow_position);
+        vec3 radiance = light.color * light.intensity * attenuation(distance, light.range) * cone * shadow;
+        color += shade(light_dir, radiance, normal, view_dir);
+    }
+
+    if (u_show_cascades != 0 && cascade < u_num_cascades) {
+        const vec3 CASCADE_COLORS[4] = vec3[4](
+            vec3(1.0, 0.3, 0.3), vec3(0.3, 1.0, 0.3), vec3(0.3, 0.3, 1.0), vec3(1.0, 1.0, 0.3)
+        );
+        color *= CASCADE_COLORS[cascade];
+    }
+    f_color = vec4(color, u_tint.a);
+}
\ No newline at end of file
@@ -0,0 +1,44 @@
+#version 450
+
+// Vista de depuracion del histograma de luminancia: una barra por bin, normalizada al bin mas alto, y una linea en
+// la luminancia con la que se calcula la exposicion. Se dibuja con un viewport en una esquina de la pantalla
+layout(location=0) in vec2 v_tex_coords;
+layout(location=0) out vec4 f_color;
+
+const uint NUM_BINS = 256;
+
+layout(set=0, binding=0) uniform Params {
+    float u_min_log_luminance;
+    float u_log_luminance_range;
+    float u_adaptation_rate;
+    float u_key;
+    float u_time_delta;
+    uint u_num_pixels;
+};
+layout(set=0, binding=1) readonly buffer Histogram {
+    uint histogram[];
+};
+layout(set=0, binding=2) uniform Exposure {
+    float u_luminance;
+    float u_exposure;
+};
+
+void main() {
+    // El bin 0 (negro) suele ser enorme y aplastaria a los demas
+    uint max_count = 1;
+    for (uint i = 1; i < NUM_BINS; i++) {
+        max_count = max(max_count, histogram[i]);
+    }
+    uint bin = min(uint(v_tex_coords.x * float(NUM_BINS)), NUM_BINS - 1);
+    float height = float(histogram[bin]) / float(max_count);
+
+    f_color = vec4(0.0, 0.0, 0.0, 0.6);
+    if (1.0 - v_tex_coords.y < height) {
+        f_color = vec4(0.85, 0.85, 0.85, 0.9);
+    }
+    float position = clamp((log2(u_luminance) - u_min_log_luminance) / u_log_luminance_range, 0.0, 1.0);
+    float marker = (position * float(NUM_BINS - 2) + 1.5) / float(NUM_BINS);
+    if (abs(v_tex_coords.x - marker) < 1.0 / float(NUM_BINS)) {
+        f_color = vec4(1.0, 0.6, 0.1, 1.0);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,16 @@
+#version 450
+
+layout(location=0) in vec2 v_tex_coords;
+layout(location=0) out vec4 f_color;
+
+layout(set = 1, binding = 1) uniform texture2D t_diffuse;
+layout(set = 1, binding = 2) uniform sampler s_diffuse;
+// Los parametros del material van en el binding 0 con cualquier numero de texturas
+layout(set = 1, binding = 0) uniform MaterialParams {
+    vec4 u_tint;
+    vec4 u_values;
+};
+
+void main() {
+    f_color = texture(sampler2D(t_diffuse, s_diffuse), v_tex_coords) * u_tint;
+}
\ No newline at end of file
@@ -0,0 +1,165 @@
+#version 450
+
+// lit.frag con normal map y parallax occlusion mapping. El normal map esta en espacio tangente (x hacia donde crece u,
+// y hacia donde crece v, z saliendo de la superficie) y en el alpha tiene la altura (1 arriba, 0 abajo)
+layout(location=0) in vec3 v_world_position;
+layout(location=1) in vec3 v_world_normal;
+layout(location=2) in vec2 v_tex_coords;
+layout(location=3) in vec4 v_world_tangent;
+layout(location=0) out vec4 f_color;
+
+layout(set = 1, binding = 1) uniform texture2D t_diffuse;
+layout(set = 1, binding = 2) uniform sampler s_diffuse;
+layout(set = 1, binding = 3) uniform texture2D t_normal;
+layout(set = 1, binding = 4) uniform sampler s_normal;
+// u_tint: multiplica al color difuso, u_values.x: intensidad especular, u_values.y: shininess,
+// u_values.z: profundidad del parallax en unidades de uv, u_values.w: 0 normal del vertice, 1 normal map,
+// 2 normal map y parallax
+layout(set = 1, binding = 0) uniform MaterialParams {
+    vec4 u_tint;
+    vec4 u_values;
+};
+
+layout(set = 2, binding = 0) uniform Camera {
+    mat4 u_view_proj;
+    vec4 u_view_position;
+};
+
+const int MAX_POINT_LIGHTS = 4;
+const int MAX_DIRECTIONAL_LIGHTS = 2;
+const int MAX_SPOT_LIGHTS = 2;
+
+// Tiene que coincidir con LightsData de lighting.rs
+struct PointLight {
+    vec3 position;
+    float range;
+    vec3 color;
+    float intensity;
+};
+
+struct DirectionalLight {
+    vec3 direction;
+    float _padding;
+    vec3 color;
+    float intensity;
+};
+
+struct SpotLight {
+    vec3 position;
+    float range;
+    vec3 direction;
+    float intensity;
+    vec3 color;
+    float cos_inner;
+    float cos_outer;
+};
+
+layout(set = 3, binding = 0) uniform Lights {
+    vec3 u_ambient;
+    uint u_blinn;
+    PointLight u_point_lights[MAX_POINT_LIGHTS];
+    DirectionalLight u_directional_lights[MAX_DIRECTIONAL_LIGHTS];
+    SpotLight u_spot_lights[MAX_SPOT_LIGHTS];
+    uint u_num_point_lights;
+    uint u_num_directional_lights;
+    uint u_num_spot_lights;
+};
+
+// Difusa + especular de una luz que llega desde light_dir (hacia la luz) con la radiancia dada
+vec3 shade(vec3 light_dir, vec3 radiance, vec3 normal, vec3 view_dir, vec3 albedo) {
+    float diffuse = max(dot(normal, light_dir), 0.0);
+    float specular_angle;
+    if (u_blinn != 0) {
+        vec3 half_dir = normalize(light_dir + view_dir);
+        specular_angle = max(dot(normal, half_dir), 0.0);
+    } else {
+        vec3 reflect_dir = reflect(-light_dir, normal);
+        specular_angle = max(dot(view_dir, reflect_dir), 0.0);
+    }
+    // Si la luz llega por detras de la cara tampoco hay brillo
+    float specular = diffuse > 0.0 ? u_values.x * pow(specular_angle, u_values.y) : 0.0;
+    return radiance * (diffuse * albedo + specular);
+}
+
+// Inversa del cuadrado de la distancia, llevada suavemente a 0 en range
+float attenuation(float distance, float range) {
+    float falloff = clamp(1.0 - pow(distance / range, 4.0), 0.0, 1.0);
+    return falloff * falloff / (distance * distance + 1.0);
+}
+
+const float MIN_PARALLAX_LAYERS = 8.0;
+const float MAX_PARALLAX_LAYERS = 32.0;
+
+float depth_at(vec2 uv) {
+    // Sin mipmaps, y dentro de un bucle no se pueden usar las derivadas implicitas de texture()
+    return 1.0 - textureLod(sampler2D(t_normal, s_normal), uv, 0.0).a;
+}
+
+// Parallax occlusion mapping: avanza por el rayo de la camara dentro de la superficie en capas de profundidad hasta
+// que queda por debajo del mapa de alturas, e interpola entre las dos ultimas capas. view_ts es la direccion hacia la
+// camara en espacio tangente
+vec2 parallax_uv(vec2 uv, vec3 view_ts) {
+    // Mirando de lado hacen falta mas capas para no ver los escalones
+    float num_layers = mix(MAX_PARALLAX_LAYERS, MIN_PARALLAX_LAYERS, abs(view_ts.z));
+    float layer_depth = 1.0 / num_layers;
+    vec2 delta_uv = view_ts.xy / max(view_ts.z, 0.05) * u_values.z / num_layers;
+
+    float current_depth = 0.0;
+    float map_depth = depth_at(uv);
+    for (int i = 0; i < int(MAX_PARALLAX_LAYERS) && current_depth < map_depth; i++) {
+        uv -= delta_uv;
+        map_depth = depth_at(uv);
+        current_depth += layer_depth;
+    }
+
+    vec2 previous_uv = uv + delta_uv;
+    float after = map_depth - current_depth;
+    float before = depth_at(previous_uv) - current_depth + layer_depth;
+    float weight = after / (after - before);
+    return mix(uv, previous_uv, weight);
+}
+
+void main() {
+    vec3 normal = normalize(v_world_normal);
+    vec3 view_dir = normalize(u_view_position.xyz - v_world_position);
+
+    // TBN lleva del espacio tangente al mundo. La tangente interpolada ya no es perpendicular a la normal
+    vec3 tangent = normalize(v_world_tangent.xyz - normal * dot(normal, v_world_tangent.xyz));
+    vec3 bitangent = cross(normal, tangent) * v_world_tangent.w;
+    mat3 tbn = mat3(tangent, bitangent, normal);
+
+    vec2 uv = v_tex_coords;
+    if (u_values.w > 1.5) {
+        // La traspuesta de una matriz ortonormal es su inversa
+        uv = parallax_uv(uv, normalize(transpose(tbn) * view_dir));
+    }
+    if (u_values.w > 0.5) {
+        vec3 normal_ts = textureLod(sampler2D(t_normal, s_normal), uv, 0.0).xyz * 2.0 - 1.0;
+        normal = normalize(tbn * normal_ts);
+    }
+    vec3 albedo = texture(sampler2D(t_diffuse, s_diffuse), uv).rgb * u_tint.rgb;
+
+    vec3 color = u_ambient * albedo;
+    for (uint i = 0; i < u_num_directional_lights; i++) {
+        DirectionalLight light = u_directional_lights[i];
+        color += shade(normalize(-light.direction), light.color * light.intensity, normal, view_dir, albedo);
+    }
+    for (uint i = 0; i < u_num_point_lights; i++) {
+        PointLight light = u_point_lights[i];
+        vec3 to_light = light.position - v_world_position;
+        float distance = length(to_light);
+        vec3 radiance = light.color * light.intensity * attenuation(distance, light.range);
+        color += shade(to_light / distance, radiance, normal, view_dir, albedo);
+    }
+    for (uint i = 0; i < u_num_spot_lights; i++) {
+        SpotLight light = u_spot_lights[i];
+        vec3 to_light = light.position - v_world_position;
+        float distance = length(to_light);
+        vec3 light_dir = to_light / distance;
+        // 1 dentro del cono interior, 0 fuera del exterior
+        float cone = smoothstep(light.cos_outer, light.cos_inner, dot(-light_dir, normalize(light.direction)));
+        vec3 radiance = light.color * light.intensity * attenuation(distance, light.range) * cone;
+        color += shade(light_dir, radiance, normal, view_dir, albedo);
+    }
+    f_color = vec4(color, u_tint.a);
+}
\ No newline at end of file
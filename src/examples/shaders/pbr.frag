@@ -0,0 +1,210 @@
+#version 450
+
+// Modelo metallic-roughness (el de glTF): Cook-Torrance con distribucion GGX, Fresnel de Schlick y geometria de
+// Smith para las luces, y luz del cielo (IBL) con el mapa de irradiancia para la difusa y el split sum de Epic
+// (mapa especular prefiltrado y tabla de la BRDF) para la especular. Usa normal_map.vert
+layout(location=0) in vec3 v_world_position;
+layout(location=1) in vec3 v_world_normal;
+layout(location=2) in vec2 v_tex_coords;
+layout(location=3) in vec4 v_world_tangent;
+layout(location=0) out vec4 f_color;
+
+// Las texturas de PbrMaterial (pbr.rs). Las que faltan son de 1x1 y no cambian nada
+layout(set = 1, binding = 1) uniform texture2D t_base_color;
+layout(set = 1, binding = 2) uniform sampler s_base_color;
+// Como en glTF: rugosidad en el verde y metalicidad en el azul
+layout(set = 1, binding = 3) uniform texture2D t_metallic_roughness;
+layout(set = 1, binding = 4) uniform sampler s_metallic_roughness;
+layout(set = 1, binding = 5) uniform texture2D t_normal;
+layout(set = 1, binding = 6) uniform sampler s_normal;
+layout(set = 1, binding = 7) uniform texture2D t_occlusion;
+layout(set = 1, binding = 8) uniform sampler s_occlusion;
+layout(set = 1, binding = 9) uniform texture2D t_emissive;
+layout(set = 1, binding = 10) uniform sampler s_emissive;
+layout(set = 1, binding = 0) uniform PbrParams {
+    vec4 u_base_color;
+    vec4 u_emissive;
+    float u_metallic;
+    float u_roughness;
+    float u_normal_scale;
+    float u_occlusion_strength;
+};
+
+layout(set = 2, binding = 0) uniform Camera {
+    mat4 u_view_proj;
+    vec4 u_view_position;
+};
+
+const int MAX_POINT_LIGHTS = 4;
+const int MAX_DIRECTIONAL_LIGHTS = 2;
+const int MAX_SPOT_LIGHTS = 2;
+
+// Tiene que coincidir con LightsData de lighting.rs
+struct PointLight {
+    vec3 position;
+    float range;
+    vec3 color;
+    float intensity;
+};
+
+struct DirectionalLight {
+    vec3 direction;
+    float _padding;
+    vec3 color;
+    float intensity;
+};
+
+struct SpotLight {
+    vec3 position;
+    float range;
+    vec3 direction;
+    float intensity;
+    vec3 color;
+    float cos_inner;
+    float cos_outer;
+};
+
+// El mismo bloque que en lit.frag, pero u_ambient y u_blinn no se usan: la luz ambiente es la del cielo
+layout(set = 3, binding = 0) uniform Lights {
+    vec3 u_ambient;
+    uint u_blinn;
+    PointLight u_point_lights[MAX_POINT_LIGHTS];
+    DirectionalLight u_directional_lights[MAX_DIRECTIONAL_LIGHTS];
+    SpotLight u_spot_lights[MAX_SPOT_LIGHTS];
+    uint u_num_point_lights;
+    uint u_num_directional_lights;
+    uint u_num_spot_lights;
+};
+
+layout(set = 3, binding = 1) uniform textureCube t_irradiance;
+layout(set = 3, binding = 2) uniform textureCube t_prefiltered;
+layout(set = 3, binding = 3) uniform texture2D t_brdf_lut;
+layout(set = 3, binding = 4) uniform sampler s_environment;
+layout(set = 3, binding = 5) uniform Environment {
+    float u_intensity;
+    // Mip del mapa prefiltrado que corresponde a rugosidad 1
+    float u_max_lod;
+};
+
+const float PI = 3.14159265359;
+// Con rugosidad 0 el brillo de una luz puntual seria infinitamente pequeño
+const float MIN_ROUGHNESS = 0.04;
+
+struct Surface {
+    vec3 normal;
+    vec3 view_dir;
+    vec3 albedo;
+    float metallic;
+    float roughness;
+    // Reflectancia con la luz de frente: 4% en los dielectricos y el color base en los metales
+    vec3 f0;
+};
+
+float distribution_ggx(float n_dot_h, float roughness) {
+    float a = roughness * roughness;
+    float a2 = a * a;
+    float d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+float geometry_schlick_ggx(float n_dot_v, float k) {
+    return n_dot_v / (n_dot_v * (1.0 - k) + k);
+}
+
+// Que parte de las microfacetas no esta tapada por otras, hacia la luz y hacia la camara
+float geometry_smith(float n_dot_v, float n_dot_l, float roughness) {
+    float k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+    return geometry_schlick_ggx(n_dot_v, k) * geometry_schlick_ggx(n_dot_l, k);
+}
+
+vec3 fresnel_schlick(float cos_theta, vec3 f0) {
+    return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// Para el cielo, que llega de todas direcciones: con mucha rugosidad el Fresnel de los bordes se nota menos
+vec3 fresnel_schlick_roughness(float cos_theta, vec3 f0, float roughness) {
+    return f0 + (max(vec3(1.0 - roughness), f0) - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// La luz que sale hacia la camara de una luz que llega desde light_dir (hacia la luz) con la radiancia dada
+vec3 shade(vec3 light_dir, vec3 radiance, Surface surface) {
+    vec3 half_dir = normalize(light_dir + surface.view_dir);
+    float n_dot_l = max(dot(surface.normal, light_dir), 0.0);
+    float n_dot_v = max(dot(surface.normal, surface.view_dir), 1e-4);
+    float n_dot_h = max(dot(surface.normal, half_dir), 0.0);
+
+    vec3 fresnel = fresnel_schlick(max(dot(half_dir, surface.view_dir), 0.0), surface.f0);
+    float d = distribution_ggx(n_dot_h, surface.roughness);
+    float g = geometry_smith(n_dot_v, n_dot_l, surface.roughness);
+    vec3 specular = d * g * fresnel / (4.0 * n_dot_v * n_dot_l + 1e-4);
+    // Lo que no se refleja entra en la superficie; los metales absorben todo lo que entra
+    vec3 diffuse = (1.0 - fresnel) * (1.0 - surface.metallic) * surface.albedo / PI;
+    return (diffuse + specular) * radiance * n_dot_l;
+}
+
+// Inversa del cuadrado de la distancia, llevada suavemente a 0 en range
+float attenuation(float distance, float range) {
+    float falloff = clamp(1.0 - pow(distance / range, 4.0), 0.0, 1.0);
+    return falloff * falloff / (distance * distance + 1.0);
+}
+
+vec3 image_based_lighting(Surface surface, float occlusion) {
+    float n_dot_v = max(dot(surface.normal, surface.view_dir), 1e-4);
+    vec3 fresnel = fresnel_schlick_roughness(n_dot_v, surface.f0, surface.roughness);
+
+    vec3 irradiance = texture(samplerCube(t_irradiance, s_environment), surface.normal).rgb;
+    vec3 diffuse = (1.0 - fresnel) * (1.0 - surface.metallic) * irradiance * surface.albedo;
+
+    vec3 reflection = reflect(-surface.view_dir, surface.normal);
+    float lod = surface.roughness * u_max_lod;
+    vec3 prefiltered = textureLod(samplerCube(t_prefiltered, s_environment), reflection, lod).rgb;
+    vec2 brdf = texture(sampler2D(t_brdf_lut, s_environment), vec2(n_dot_v, surface.roughness)).rg;
+    vec3 specular = prefiltered * (fresnel * brdf.x + brdf.y);
+
+    return (diffuse + specular) * occlusion * u_intensity;
+}
+
+void main() {
+    vec3 normal = normalize(v_world_normal);
+    vec3 tangent = normalize(v_world_tangent.xyz - normal * dot(normal, v_world_tangent.xyz));
+    vec3 bitangent = cross(normal, tangent) * v_world_tangent.w;
+    vec3 normal_ts = texture(sampler2D(t_normal, s_normal), v_tex_coords).xyz * 2.0 - 1.0;
+    normal_ts.xy *= u_normal_scale;
+
+    vec4 base_color = texture(sampler2D(t_base_color, s_base_color), v_tex_coords) * u_base_color;
+    vec4 metallic_roughness = texture(sampler2D(t_metallic_roughness, s_metallic_roughness), v_tex_coords);
+    float occlusion = texture(sampler2D(t_occlusion, s_occlusion), v_tex_coords).r;
+    vec3 emissive = texture(sampler2D(t_emissive, s_emissive), v_tex_coords).rgb * u_emissive.rgb;
+
+    Surface surface;
+    surface.normal = normalize(mat3(tangent, bitangent, normal) * normal_ts);
+    surface.view_dir = normalize(u_view_position.xyz - v_world_position);
+    surface.albedo = base_color.rgb;
+    surface.metallic = clamp(u_metallic * metallic_roughness.b, 0.0, 1.0);
+    surface.roughness = clamp(u_roughness * metallic_roughness.g, MIN_ROUGHNESS, 1.0);
+    surface.f0 = mix(vec3(0.04), surface.albedo, surface.metallic);
+
+    vec3 color = image_based_lighting(surface, 1.0 + u_occlusion_strength * (occlusion - 1.0));
+    for (uint i = 0; i < u_num_directional_lights; i++) {
+        DirectionalLight light = u_directional_lights[i];
+        color += shade(normalize(-light.direction), light.color * light.intensity, surface);
+    }
+    for (uint i = 0; i < u_num_point_lights; i++) {
+        PointLight light = u_point_lights[i];
+        vec3 to_light = light.position - v_world_position;
+        float distance = length(to_light);
+        vec3 radiance = light.color * light.intensity * attenuation(distance, light.range);
+        color += shade(to_light / distance, radiance, surface);
+    }
+    for (uint i = 0; i < u_num_spot_lights; i++) {
+        SpotLight light = u_spot_lights[i];
+        vec3 to_light = light.position - v_world_position;
+        float distance = length(to_light);
+        vec3 light_dir = to_light / distance;
+        // 1 dentro del cono interior, 0 fuera del exterior
+        float cone = smoothstep(light.cos_outer, light.cos_inner, dot(-light_dir, normalize(light.direction)));
+        vec3 radiance = light.color * light.intensity * attenuation(distance, light.range) * cone;
+        color += shade(light_dir, radiance, surface);
+    }
+    f_color = vec4(color + emissive, base_color.a);
+}
\ No newline at end of file
@@ -0,0 +1,22 @@
+#version 450
+
+// Ultimo pass del bloom: la escena mas el brillo difuminado
+layout(location=0) in vec2 v_tex_coords;
+layout(location=0) out vec4 f_color;
+
+layout(set=1, binding=0) uniform texture2D t_input;
+layout(set=1, binding=1) uniform sampler s_input;
+
+// x: umbral, y: intensidad, z: knee
+layout(set=2, binding=0) uniform Effect {
+    vec4 u_params;
+};
+
+layout(set=3, binding=0) uniform texture2D t_bloom;
+layout(set=3, binding=1) uniform sampler s_bloom;
+
+void main() {
+    vec3 color = texture(sampler2D(t_input, s_input), v_tex_coords).rgb;
+    vec3 bloom = texture(sampler2D(t_bloom, s_bloom), v_tex_coords).rgb;
+    f_color = vec4(color + bloom * u_params.y, 1.0);
+}
\ No newline at end of file
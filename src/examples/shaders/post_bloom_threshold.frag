@@ -0,0 +1,25 @@
+#version 450
+
+// Primer pass del bloom: se queda solo con lo que pasa del umbral, a media resolucion. El knee suaviza el corte para
+// que no aparezcan bordes duros alrededor de las zonas brillantes
+layout(location=0) in vec2 v_tex_coords;
+layout(location=0) out vec4 f_color;
+
+layout(set=1, binding=0) uniform texture2D t_input;
+layout(set=1, binding=1) uniform sampler s_input;
+
+// x: umbral, y: intensidad, z: knee
+layout(set=2, binding=0) uniform Effect {
+    vec4 u_params;
+};
+
+void main() {
+    vec3 color = texture(sampler2D(t_input, s_input), v_tex_coords).rgb;
+    float threshold = u_params.x;
+    float knee = max(threshold * u_params.z, 1e-4);
+    float brightness = max(color.r, max(color.g, color.b));
+    float soft = clamp(brightness - threshold + knee, 0.0, 2.0 * knee);
+    soft = soft * soft / (4.0 * knee);
+    float contribution = max(soft, brightness - threshold) / max(brightness, 1e-4);
+    f_color = vec4(color * contribution, 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,27 @@
+#version 450
+
+// Blur gaussiano separable de 9 muestras: se hace una vez en horizontal y otra en vertical. Las muestras caen entre
+// dos pixels para que el filtro lineal del sampler haga la mitad del trabajo
+layout(location=0) in vec2 v_tex_coords;
+layout(location=0) out vec4 f_color;
+
+layout(set=1, binding=0) uniform texture2D t_input;
+layout(set=1, binding=1) uniform sampler s_input;
+
+// xy: direccion del blur
+layout(set=2, binding=0) uniform Effect {
+    vec4 u_params;
+};
+
+const float OFFSETS[3] = float[](0.0, 1.3846153846, 3.2307692308);
+const float WEIGHTS[3] = float[](0.2270270270, 0.3162162162, 0.0702702703);
+
+void main() {
+    vec2 texel = u_params.xy / vec2(textureSize(sampler2D(t_input, s_input), 0));
+    vec3 color = texture(sampler2D(t_input, s_input), v_tex_coords).rgb * WEIGHTS[0];
+    for (int i = 1; i < 3; i++) {
+        color += texture(sampler2D(t_input, s_input), v_tex_coords + texel * OFFSETS[i]).rgb * WEIGHTS[i];
+        color += texture(sampler2D(t_input, s_input), v_tex_coords - texel * OFFSETS[i]).rgb * WEIGHTS[i];
+    }
+    f_color = vec4(color, 1.0);
+}
\ No newline at end of file
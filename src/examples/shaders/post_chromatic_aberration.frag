@@ -0,0 +1,21 @@
+#version 450
+
+// Aberracion cromatica: el rojo y el azul se separan hacia fuera y hacia dentro, mas cuanto mas lejos del centro,
+// como en una lente barata. x: separacion en el borde (en fraccion de pantalla)
+layout(location=0) in vec2 v_tex_coords;
+layout(location=0) out vec4 f_color;
+
+layout(set=1, binding=0) uniform texture2D t_input;
+layout(set=1, binding=1) uniform sampler s_input;
+
+layout(set=2, binding=0) uniform Effect {
+    vec4 u_params;
+};
+
+void main() {
+    vec2 offset = (v_tex_coords - 0.5) * u_params.x;
+    float r = texture(sampler2D(t_input, s_input), v_tex_coords + offset).r;
+    float g = texture(sampler2D(t_input, s_input), v_tex_coords).g;
+    float b = texture(sampler2D(t_input, s_input), v_tex_coords - offset).b;
+    f_color = vec4(r, g, b, 1.0);
+}
\ No newline at end of file
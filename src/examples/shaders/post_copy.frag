@@ -0,0 +1,13 @@
+#version 450
+
+// Copia la entrada a la salida. Es el ultimo pass de PostProcess (al formato del swap chain) y el unico si no hay
+// ningun efecto activo
+layout(location=0) in vec2 v_tex_coords;
+layout(location=0) out vec4 f_color;
+
+layout(set=1, binding=0) uniform texture2D t_input;
+layout(set=1, binding=1) uniform sampler s_input;
+
+void main() {
+    f_color = vec4(texture(sampler2D(t_input, s_input), v_tex_coords).rgb, 1.0);
+}
\ No newline at end of file
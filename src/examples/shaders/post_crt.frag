@@ -0,0 +1,32 @@
+#version 450
+
+// Monitor CRT: pantalla curvada, lineas de barrido y la mascara de fosforos RGB. x: curvatura, y: intensidad de las
+// lineas de barrido
+layout(location=0) in vec2 v_tex_coords;
+layout(location=0) out vec4 f_color;
+
+layout(set=1, binding=0) uniform texture2D t_input;
+layout(set=1, binding=1) uniform sampler s_input;
+
+layout(set=2, binding=0) uniform Effect {
+    vec4 u_params;
+};
+
+void main() {
+    // Distorsion de barril: los puntos se alejan del centro mas cuanto mas lejos estan
+    vec2 centered = v_tex_coords * 2.0 - 1.0;
+    centered *= 1.0 + u_params.x * dot(centered, centered) * vec2(0.25, 0.35);
+    vec2 uv = centered * 0.5 + 0.5;
+    if (any(lessThan(uv, vec2(0.0))) || any(greaterThan(uv, vec2(1.0)))) {
+        f_color = vec4(0.0, 0.0, 0.0, 1.0);
+        return;
+    }
+
+    vec3 color = texture(sampler2D(t_input, s_input), uv).rgb;
+    float scanline = 0.5 + 0.5 * cos(gl_FragCoord.y * 3.14159265);
+    color *= 1.0 - u_params.y * scanline;
+    // Cada columna de pixels refuerza uno de los tres canales
+    int column = int(gl_FragCoord.x) % 3;
+    vec3 mask = vec3(column == 0 ? 1.0 : 0.75, column == 1 ? 1.0 : 0.75, column == 2 ? 1.0 : 0.75);
+    f_color = vec4(color * mask * 1.2, 1.0);
+}
\ No newline at end of file
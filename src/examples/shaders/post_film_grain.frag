@@ -0,0 +1,34 @@
+#version 450
+
+// Grano de pelicula: ruido que cambia cada frame, mas visible en los tonos medios. x: cantidad
+layout(location=0) in vec2 v_tex_coords;
+layout(location=0) out vec4 f_color;
+
+layout(set=0, binding=0) uniform Globals {
+    vec2 u_resolution;
+    float u_time;
+    float u_time_delta;
+    vec4 u_mouse;
+    uint u_frame;
+};
+
+layout(set=1, binding=0) uniform texture2D t_input;
+layout(set=1, binding=1) uniform sampler s_input;
+
+layout(set=2, binding=0) uniform Effect {
+    vec4 u_params;
+};
+
+float hash(vec3 p) {
+    p = fract(p * 0.1031);
+    p += dot(p, p.zyx + 31.32);
+    return fract((p.x + p.y) * p.z);
+}
+
+void main() {
+    vec3 color = texture(sampler2D(t_input, s_input), v_tex_coords).rgb;
+    float noise = hash(vec3(gl_FragCoord.xy, float(u_frame % 1024u))) - 0.5;
+    float luma = dot(color, vec3(0.2126, 0.7152, 0.0722));
+    float midtones = 1.0 - abs(luma * 2.0 - 1.0);
+    f_color = vec4(max(color + noise * u_params.x * (0.25 + midtones), 0.0), 1.0);
+}
\ No newline at end of file
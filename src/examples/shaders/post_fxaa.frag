@@ -0,0 +1,50 @@
+#version 450
+
+// FXAA simplificado (el de la demo original de Timothy Lottes): busca bordes comparando la luminancia con los
+// vecinos y difumina a lo largo del borde. Va despues del tonemapping, con colores en [0, 1]. x: longitud maxima
+// de la busqueda en pixels
+layout(location=0) in vec2 v_tex_coords;
+layout(location=0) out vec4 f_color;
+
+layout(set=1, binding=0) uniform texture2D t_input;
+layout(set=1, binding=1) uniform sampler s_input;
+
+layout(set=2, binding=0) uniform Effect {
+    vec4 u_params;
+};
+
+const float REDUCE_MIN = 1.0 / 128.0;
+const float REDUCE_MUL = 1.0 / 8.0;
+
+vec3 fetch(vec2 uv) {
+    return texture(sampler2D(t_input, s_input), uv).rgb;
+}
+
+float luma(vec3 color) {
+    // Con gamma aproximada, los bordes se ven en el espacio perceptual
+    return dot(sqrt(color), vec3(0.299, 0.587, 0.114));
+}
+
+void main() {
+    vec2 texel = 1.0 / vec2(textureSize(sampler2D(t_input, s_input), 0));
+    vec3 center = fetch(v_tex_coords);
+    float luma_nw = luma(fetch(v_tex_coords + vec2(-1.0, -1.0) * texel));
+    float luma_ne = luma(fetch(v_tex_coords + vec2(1.0, -1.0) * texel));
+    float luma_sw = luma(fetch(v_tex_coords + vec2(-1.0, 1.0) * texel));
+    float luma_se = luma(fetch(v_tex_coords + vec2(1.0, 1.0) * texel));
+    float luma_m = luma(center);
+    float luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    float luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+    // Perpendicular al gradiente de luminancia, es decir a lo largo del borde
+    vec2 dir = vec2(-((luma_nw + luma_ne) - (luma_sw + luma_se)), (luma_nw + luma_sw) - (luma_ne + luma_se));
+    float reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+    float scale = 1.0 / (min(abs(dir.x), abs(dir.y)) + reduce);
+    dir = clamp(dir * scale, vec2(-u_params.x), vec2(u_params.x)) * texel;
+
+    vec3 a = 0.5 * (fetch(v_tex_coords + dir * (1.0 / 3.0 - 0.5)) + fetch(v_tex_coords + dir * (2.0 / 3.0 - 0.5)));
+    vec3 b = a * 0.5 + 0.25 * (fetch(v_tex_coords - dir * 0.5) + fetch(v_tex_coords + dir * 0.5));
+    // Si la muestra larga se sale del rango de luminancia es que ha cruzado otro borde
+    float luma_b = luma(b);
+    f_color = vec4(luma_b < luma_min || luma_b > luma_max ? a : b, 1.0);
+}
\ No newline at end of file
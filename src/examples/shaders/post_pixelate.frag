@@ -0,0 +1,19 @@
+#version 450
+
+// Baja la resolucion a bloques de x pixels, cogiendo el color del centro de cada bloque
+layout(location=0) in vec2 v_tex_coords;
+layout(location=0) out vec4 f_color;
+
+layout(set=1, binding=0) uniform texture2D t_input;
+layout(set=1, binding=1) uniform sampler s_input;
+
+layout(set=2, binding=0) uniform Effect {
+    vec4 u_params;
+};
+
+void main() {
+    vec2 size = vec2(textureSize(sampler2D(t_input, s_input), 0));
+    vec2 block = max(u_params.x, 1.0) / size;
+    vec2 uv = (floor(v_tex_coords / block) + 0.5) * block;
+    f_color = vec4(texture(sampler2D(t_input, s_input), uv).rgb, 1.0);
+}
\ No newline at end of file
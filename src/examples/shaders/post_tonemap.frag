@@ -0,0 +1,58 @@
+#version 450
+
+// Pasa el color HDR de la escena a [0, 1]. x: exposicion (multiplica a la automatica si esta activa), y: operador
+// (0 Reinhard, 1 ACES, 2 filmico), z: exposicion automatica (0 o 1)
+layout(location=0) in vec2 v_tex_coords;
+layout(location=0) out vec4 f_color;
+
+layout(set=1, binding=0) uniform texture2D t_input;
+layout(set=1, binding=1) uniform sampler s_input;
+
+layout(set=2, binding=0) uniform Effect {
+    vec4 u_params;
+};
+
+// La calcula AutoExposure a partir del histograma de luminancia
+layout(set=3, binding=0) uniform Exposure {
+    float u_luminance;
+    float u_exposure;
+};
+
+vec3 reinhard(vec3 x) {
+    return x / (1.0 + x);
+}
+
+// Aproximacion de la curva filmica de ACES de Krzysztof Narkowicz
+vec3 aces(vec3 x) {
+    return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0);
+}
+
+// La curva de John Hable para Uncharted 2
+vec3 hable(vec3 x) {
+    const float A = 0.15;
+    const float B = 0.50;
+    const float C = 0.10;
+    const float D = 0.20;
+    const float E = 0.02;
+    const float F = 0.30;
+    return (x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F) - E / F;
+}
+
+vec3 filmic(vec3 x) {
+    // El blanco: lo que vale WHITE o mas acaba en 1.0
+    const float WHITE = 11.2;
+    return clamp(hable(x * 2.0) / hable(vec3(WHITE)), 0.0, 1.0);
+}
+
+void main() {
+    float exposure = u_params.z > 0.5 ? u_params.x * u_exposure : u_params.x;
+    vec3 color = texture(sampler2D(t_input, s_input), v_tex_coords).rgb * exposure;
+    if (u_params.y < 0.5) {
+        color = reinhard(color);
+    } else if (u_params.y < 1.5) {
+        color = aces(color);
+    } else {
+        color = filmic(color);
+    }
+    f_color = vec4(color, 1.0);
+}
\ No newline at end of file
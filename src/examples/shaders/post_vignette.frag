@@ -0,0 +1,28 @@
+#version 450
+
+// Oscurece las esquinas. x: intensidad, y: distancia al centro a la que empieza
+layout(location=0) in vec2 v_tex_coords;
+layout(location=0) out vec4 f_color;
+
+layout(set=0, binding=0) uniform Globals {
+    vec2 u_resolution;
+    float u_time;
+    float u_time_delta;
+    vec4 u_mouse;
+    uint u_frame;
+};
+
+layout(set=1, binding=0) uniform texture2D t_input;
+layout(set=1, binding=1) uniform sampler s_input;
+
+layout(set=2, binding=0) uniform Effect {
+    vec4 u_params;
+};
+
+void main() {
+    vec3 color = texture(sampler2D(t_input, s_input), v_tex_coords).rgb;
+    // Circular aunque la pantalla no sea cuadrada
+    vec2 position = (v_tex_coords - 0.5) * vec2(u_resolution.x / u_resolution.y, 1.0);
+    float vignette = smoothstep(u_params.y, u_params.y + 0.6, length(position));
+    f_color = vec4(color * (1.0 - u_params.x * vignette), 1.0);
+}
\ No newline at end of file
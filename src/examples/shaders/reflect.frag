@@ -0,0 +1,37 @@
+#version 450
+
+// Mezcla un color difuso (con una luz fija, sin uniform de luces) con el reflejo del cielo en la direccion de la
+// camara reflejada en la normal
+layout(location=0) in vec3 v_world_position;
+layout(location=1) in vec3 v_world_normal;
+layout(location=2) in vec2 v_tex_coords;
+
+layout(location=0) out vec4 f_color;
+
+layout(set=1, binding=0) uniform MaterialParams {
+    vec4 u_tint;
+    // x: reflectividad (0 solo difuso, 1 espejo)
+    vec4 u_values;
+};
+
+layout(set=2, binding=0) uniform Camera {
+    mat4 u_view_proj;
+    vec4 u_view_position;
+};
+
+layout(set=3, binding=1) uniform textureCube t_skybox;
+layout(set=3, binding=2) uniform sampler s_skybox;
+
+const vec3 LIGHT_DIRECTION = vec3(-0.4, -1.0, -0.3);
+const float AMBIENT = 0.15;
+
+void main() {
+    vec3 normal = normalize(v_world_normal);
+    float diffuse = max(dot(normal, normalize(-LIGHT_DIRECTION)), 0.0);
+    vec3 base = u_tint.rgb * (AMBIENT + diffuse);
+
+    vec3 view_dir = normalize(v_world_position - u_view_position.xyz);
+    vec3 reflection = texture(samplerCube(t_skybox, s_skybox), reflect(view_dir, normal)).rgb;
+
+    f_color = vec4(mix(base, reflection, u_values.x), u_tint.a);
+}
\ No newline at end of file
@@ -3,6 +3,17 @@
 
 layout(location=0) out vec4 f_color;
 
+// Uniforms por frame, ver examples/globals.rs
+layout(set=0, binding=0) uniform Globals {
+    vec2 u_resolution;
+    float u_time;
+    float u_time_delta;
+    vec4 u_mouse;
+    uint u_frame;
+};
+
 void main() {
-    f_color = vec4(0.3, 0.2, 0.1, 1.0);
+    // El color original "respira" con el tiempo
+    float pulse = 0.75 + 0.25 * sin(u_time * 2.0);
+    f_color = vec4(vec3(0.3, 0.2, 0.1) * pulse, 1.0);
 }
\ No newline at end of file
@@ -3,6 +3,16 @@
 layout(location=0) in vec2 v_position;
 layout(location=0) out vec4 f_color;
 
+layout(set=0, binding=0) uniform Globals {
+    vec2 u_resolution;
+    float u_time;
+    float u_time_delta;
+    vec4 u_mouse;
+    uint u_frame;
+};
+
 void main() {
-    f_color = vec4(v_position, 0.5, 1.0);
+    // El mismo efecto que el clear_color del ejemplo 1_2_1, pero calculado en el shader
+    vec2 mouse = u_mouse.xy / u_resolution;
+    f_color = vec4(mix(v_position, mouse, 0.5), 0.5, 1.0);
 }
\ No newline at end of file
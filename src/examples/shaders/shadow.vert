@@ -0,0 +1,18 @@
+#version 450
+
+// Solo hace falta la posicion, el pass de sombras no tiene fragment shader
+layout(location=0) in vec3 a_position;
+layout(location=5) in vec4 a_model_0;
+layout(location=6) in vec4 a_model_1;
+layout(location=7) in vec4 a_model_2;
+layout(location=8) in vec4 a_model_3;
+
+// La matriz de la luz de la capa del mapa de sombras que se esta dibujando
+layout(set = 0, binding = 0) uniform ShadowLayer {
+    mat4 u_light_view_proj;
+};
+
+void main() {
+    mat4 model = mat4(a_model_0, a_model_1, a_model_2, a_model_3);
+    gl_Position = u_light_view_proj * model * vec4(a_position, 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,11 @@
+#version 450
+
+layout(location=0) in vec3 v_direction;
+layout(location=0) out vec4 f_color;
+
+layout(set=0, binding=1) uniform textureCube t_skybox;
+layout(set=0, binding=2) uniform sampler s_skybox;
+
+void main() {
+    f_color = texture(samplerCube(t_skybox, s_skybox), normalize(v_direction));
+}
\ No newline at end of file
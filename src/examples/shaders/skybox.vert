@@ -0,0 +1,18 @@
+#version 450
+
+// El triangulo de fullscreen.vert pero con z = w = 1, es decir en el plano far (depth 1.0). Con LessEqual solo se
+// ve donde no se ha dibujado nada de la escena
+layout(location=0) out vec3 v_direction;
+
+layout(set=0, binding=0) uniform Skybox {
+    // Inversa de projection * view sin la traslacion: lleva un punto del clip space a una direccion del mundo
+    mat4 u_inv_view_proj;
+};
+
+void main() {
+    vec2 uv = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
+    vec4 position = vec4(uv * 2.0 - 1.0, 1.0, 1.0);
+    vec4 world = u_inv_view_proj * position;
+    v_direction = world.xyz / world.w;
+    gl_Position = position;
+}
\ No newline at end of file
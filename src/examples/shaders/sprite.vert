@@ -0,0 +1,28 @@
+#version 450
+
+layout(location=0) in vec2 a_position;
+layout(location=1) in vec2 a_tex_coords;
+// Datos por instancia (un sprite)
+layout(location=2) in vec3 i_offset;
+layout(location=3) in float i_scale;
+layout(location=4) in vec4 i_tint;
+
+layout(location=0) out vec2 v_tex_coords;
+layout(location=1) out vec4 v_tint;
+
+layout(set=0, binding=0) uniform Globals {
+    vec2 u_resolution;
+    float u_time;
+    float u_time_delta;
+    vec4 u_mouse;
+    uint u_frame;
+};
+
+void main() {
+    v_tex_coords = a_tex_coords;
+    v_tint = i_tint;
+    // Corregimos el aspect ratio para que los sprites sean cuadrados
+    float aspect = u_resolution.y / max(u_resolution.x, 1.0);
+    vec2 position = a_position * i_scale + i_offset.xy;
+    gl_Position = vec4(position.x * aspect, position.y, i_offset.z, 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,25 @@
+#version 450
+
+// Vertices ya transformados en la CPU (ver sprite_batch.rs): posicion en pixels y depth en z
+layout(location=0) in vec3 a_position;
+layout(location=1) in vec2 a_tex_coords;
+layout(location=2) in vec4 a_tint;
+
+layout(location=0) out vec2 v_tex_coords;
+layout(location=1) out vec4 v_tint;
+
+layout(set=0, binding=0) uniform Globals {
+    vec2 u_resolution;
+    float u_time;
+    float u_time_delta;
+    vec4 u_mouse;
+    uint u_frame;
+};
+
+void main() {
+    v_tex_coords = a_tex_coords;
+    v_tint = a_tint;
+    // De pixels (origen arriba a la izquierda) a clip space (origen en el centro, y hacia arriba)
+    vec2 ndc = a_position.xy / max(u_resolution, vec2(1.0)) * 2.0 - 1.0;
+    gl_Position = vec4(ndc.x, -ndc.y, a_position.z, 1.0);
+}
\ No newline at end of file
@@ -0,0 +1,14 @@
+#version 450
+
+layout(location=0) in vec2 v_tex_coords;
+layout(location=1) in vec4 v_tint;
+layout(location=0) out vec4 f_color;
+
+layout(set = 1, binding = 0) uniform texture2D t_diffuse;
+layout(set = 1, binding = 1) uniform sampler s_diffuse;
+
+// Igual que sprite.frag pero devuelve el color multiplicado por alpha, para BlendMode::PremultipliedAlpha y Multiply
+void main() {
+    vec4 color = texture(sampler2D(t_diffuse, s_diffuse), v_tex_coords) * v_tint;
+    f_color = vec4(color.rgb * color.a, color.a);
+}
\ No newline at end of file
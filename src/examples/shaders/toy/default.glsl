@@ -0,0 +1,14 @@
+// Shader por defecto del modo shadertoy. Se puede pegar casi cualquier shader de shadertoy.com,
+// solo hace falta definir mainImage.
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    vec2 uv = fragCoord / iResolution.xy;
+    vec3 col = 0.5 + 0.5 * cos(iTime + uv.xyx + vec3(0.0, 2.0, 4.0));
+
+    // Un circulo que sigue al raton mientras se mantiene pulsado
+    if (iMouse.z > 0.0) {
+        float d = length(fragCoord - iMouse.xy);
+        col = mix(vec3(1.0), col, smoothstep(20.0, 22.0, d));
+    }
+
+    fragColor = vec4(col, 1.0);
+}
@@ -0,0 +1,437 @@
+//## Modo shadertoy: dibuja un triangulo que cubre toda la pantalla (como en _1_3_pipeline.rs, sin vertex buffers) y
+//## ejecuta un fragment shader del usuario con los mismos uniforms que shadertoy.com (iTime, iResolution, iMouse, iFrame
+//## y iChannel0..3). El shader se compila en tiempo de ejecucion con shaderc y se recarga al guardar el fichero.
+//##
+//## cargo run -- toy [shader.glsl] [canal0.png] [canal1.png] [canal2.png] [canal3.png]
+
+use std::iter;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::{anyhow, Context, Result};
+use winit::{
+    event::*,
+    event_loop::{ControlFlow, EventLoop},
+    window::{Window, WindowBuilder},
+};
+
+use crate::examples::{Capture, GlobalsUniform, PipelineBuilder};
+use crate::examples::Texture as texture;
+
+const NUM_CHANNELS: usize = 4;
+
+// Se antepone al codigo del usuario. Los nombres de shadertoy se definen sobre el bloque Globals (ver globals.rs),
+// y cada iChannel es una textura y un sampler separados porque wgpu no acepta combined image samplers.
+const PRELUDE: &str = r#"#version 450
+
+layout(location=0) in vec2 v_tex_coords;
+layout(location=0) out vec4 f_color;
+
+layout(set=0, binding=0) uniform Globals {
+    vec2 u_resolution;
+    float u_time;
+    float u_time_delta;
+    vec4 u_mouse;
+    uint u_frame;
+};
+
+layout(set=1, binding=0) uniform texture2D t_channel0;
+layout(set=1, binding=1) uniform sampler s_channel0;
+layout(set=1, binding=2) uniform texture2D t_channel1;
+layout(set=1, binding=3) uniform sampler s_channel1;
+layout(set=1, binding=4) uniform texture2D t_channel2;
+layout(set=1, binding=5) uniform sampler s_channel2;
+layout(set=1, binding=6) uniform texture2D t_channel3;
+layout(set=1, binding=7) uniform sampler s_channel3;
+
+#define iChannel0 sampler2D(t_channel0, s_channel0)
+#define iChannel1 sampler2D(t_channel1, s_channel1)
+#define iChannel2 sampler2D(t_channel2, s_channel2)
+#define iChannel3 sampler2D(t_channel3, s_channel3)
+
+// Shadertoy tiene el origen abajo a la izquierda, wgpu arriba a la izquierda
+#define iResolution vec3(u_resolution, 1.0)
+#define iTime u_time
+#define iTimeDelta u_time_delta
+#define iFrame int(u_frame)
+#define iMouse vec4(u_mouse.x, u_resolution.y - u_mouse.y, u_mouse.z, sign(u_mouse.w) * (u_resolution.y - abs(u_mouse.w)))
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord);
+"#;
+
+const EPILOGUE: &str = r#"
+void main() {
+    mainImage(f_color, vec2(gl_FragCoord.x, u_resolution.y - gl_FragCoord.y));
+}
+"#;
+
+// Compila el shader del usuario envuelto en PRELUDE/EPILOGUE. Con #line los errores apuntan a las lineas del fichero del usuario
+fn compile_toy_shader(compiler: &mut shaderc::Compiler, path: &Path) -> Result<Vec<u8>> {
+    let user_src = std::fs::read_to_string(path)
+        .with_context(|| format!("Unable to read {}", path.display()))?;
+    let src = format!("{}\n#line 1\n{}\n{}", PRELUDE, user_src, EPILOGUE);
+
+    let compiled = compiler.compile_into_spirv(
+        &src,
+        shaderc::ShaderKind::Fragment,
+        &path.to_string_lossy(),
+        "main",
+        None,
+    )?;
+    Ok(compiled.as_binary_u8().to_vec())
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+struct State {
+    surface: wgpu::Surface,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    sc_desc: wgpu::SwapChainDescriptor,
+    swap_chain: wgpu::SwapChain,
+    size: winit::dpi::PhysicalSize<u32>,
+    globals: GlobalsUniform,
+    #[allow(dead_code)]
+    channels: Vec<texture>,
+    channels_bind_group: wgpu::BindGroup,
+    channels_bind_group_layout: wgpu::BindGroupLayout,
+    vs_module: wgpu::ShaderModule,
+    render_pipeline: wgpu::RenderPipeline,
+    compiler: shaderc::Compiler,
+    shader_path: PathBuf,
+    shader_modified: Option<SystemTime>,
+    capture: Capture,
+}
+
+impl State {
+    async fn new(window: &Window, shader_path: PathBuf, channel_paths: &[String]) -> Result<Self> {
+        let size = window.inner_size();
+
+        // The instance is a handle to our GPU
+        // BackendBit::PRIMARY => Vulkan + Metal + DX12 + Browser WebGPU
+        let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+        let surface = unsafe { instance.create_surface(window) };
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::Default,
+                compatible_surface: Some(&surface),
+            })
+            .await
+            .context("No suitable adapter found")?;
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    features: wgpu::Features::empty(),
+                    limits: wgpu::Limits::default(),
+                    shader_validation: true,
+                },
+                None, // Trace path
+            )
+            .await?;
+
+        let sc_desc = wgpu::SwapChainDescriptor {
+            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT,
+            format: wgpu::TextureFormat::Bgra8UnormSrgb,
+            width: size.width,
+            height: size.height,
+            present_mode: wgpu::PresentMode::Fifo,
+        };
+        let swap_chain = device.create_swap_chain(&surface, &sc_desc);
+        let capture = Capture::new(&device, &sc_desc);
+
+        let globals = GlobalsUniform::new(&device, size);
+
+        // Los canales que no se pasan por linea de comandos se quedan con una textura negra de 1x1
+        let mut channels = Vec::with_capacity(NUM_CHANNELS);
+        for i in 0..NUM_CHANNELS {
+            let channel = match channel_paths.get(i) {
+                Some(path) => texture::load(&device, &queue, path)?,
+                None => texture::from_image(
+                    &device,
+                    &queue,
+                    &image::DynamicImage::new_rgba8(1, 1),
+                    Some("empty channel"),
+                )?,
+            };
+            channels.push(channel);
+        }
+
+        let channel_layout_entries = (0..NUM_CHANNELS as u32)
+            .flat_map(|i| {
+                vec![
+                    wgpu::BindGroupLayoutEntry {
+                        binding: i * 2,
+                        visibility: wgpu::ShaderStage::FRAGMENT,
+                        ty: wgpu::BindingType::SampledTexture {
+                            multisampled: false,
+                            dimension: wgpu::TextureViewDimension::D2,
+                            component_type: wgpu::TextureComponentType::Float,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: i * 2 + 1,
+                        visibility: wgpu::ShaderStage::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler { comparison: false },
+                        count: None,
+                    },
+                ]
+            })
+            .collect::<Vec<_>>();
+        let channels_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &channel_layout_entries,
+                label: Some("channels_bind_group_layout"),
+            });
+
+        let channel_entries = channels
+            .iter()
+            .enumerate()
+            .flat_map(|(i, channel)| {
+                vec![
+                    wgpu::BindGroupEntry {
+                        binding: i as u32 * 2,
+                        resource: wgpu::BindingResource::TextureView(&channel.view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: i as u32 * 2 + 1,
+                        resource: wgpu::BindingResource::Sampler(&channel.sampler),
+                    },
+                ]
+            })
+            .collect::<Vec<_>>();
+        let channels_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &channels_bind_group_layout,
+            entries: &channel_entries,
+            label: Some("channels_bind_group"),
+        });
+
+        let vs_module = device.create_shader_module(wgpu::include_spirv!("shaders/fullscreen.vert.spv"));
+
+        // Esta vez el fragment shader se compila en tiempo de ejecucion (ver el comentario de _1_3_pipeline.rs)
+        let mut compiler = shaderc::Compiler::new().context("Unable to create shader compiler")?;
+        let fs_spirv = compile_toy_shader(&mut compiler, &shader_path)?;
+        let render_pipeline = Self::create_pipeline(
+            &device,
+            &[&globals.bind_group_layout, &channels_bind_group_layout],
+            &vs_module,
+            &fs_spirv,
+            sc_desc.format,
+        )?;
+        let shader_modified = modified_time(&shader_path);
+
+        Ok(Self {
+            surface,
+            device,
+            queue,
+            sc_desc,
+            swap_chain,
+            size,
+            globals,
+            channels,
+            channels_bind_group,
+            channels_bind_group_layout,
+            vs_module,
+            render_pipeline,
+            compiler,
+            shader_path,
+            shader_modified,
+            capture,
+        })
+    }
+
+    // wgpu 0.6 no tiene error scopes: si el shader compila pero el pipeline no pasa la validacion (un binding que no
+    // esta en el layout, una salida de otro tipo...) hace panic. Lo capturamos para devolverlo como un error mas
+    fn create_pipeline(
+        device: &wgpu::Device,
+        bind_group_layouts: &[&wgpu::BindGroupLayout],
+        vs_module: &wgpu::ShaderModule,
+        fs_spirv: &[u8],
+        format: wgpu::TextureFormat,
+    ) -> Result<wgpu::RenderPipeline> {
+        let create = || {
+            let fs_module = device.create_shader_module(wgpu::util::make_spirv(fs_spirv));
+
+            PipelineBuilder::new(vs_module, format)
+                .label("Shadertoy Pipeline")
+                .fragment_shader(&fs_module)
+                .bind_group_layouts(bind_group_layouts)
+                .cull_mode(wgpu::CullMode::None)
+                .build(device)
+        };
+
+        // Sin el hook por defecto, que imprimiria el panic ademas de nuestro error
+        let hook = panic::take_hook();
+        panic::set_hook(Box::new(|_| {}));
+        let result = panic::catch_unwind(AssertUnwindSafe(create));
+        panic::set_hook(hook);
+
+        result.map_err(|payload| {
+            let message = payload
+                .downcast_ref::<String>()
+                .map(String::as_str)
+                .or_else(|| payload.downcast_ref::<&str>().copied())
+                .unwrap_or("unknown error");
+            anyhow!("Invalid pipeline: {}", message)
+        })
+    }
+
+    // Hot reload: si el fichero ha cambiado lo recompilamos. Si tiene errores seguimos con el pipeline anterior
+    fn reload_if_changed(&mut self) {
+        let modified = modified_time(&self.shader_path);
+        if modified == self.shader_modified {
+            return;
+        }
+        self.shader_modified = modified;
+
+        let pipeline = compile_toy_shader(&mut self.compiler, &self.shader_path).and_then(|fs_spirv| {
+            Self::create_pipeline(
+                &self.device,
+                &[&self.globals.bind_group_layout, &self.channels_bind_group_layout],
+                &self.vs_module,
+                &fs_spirv,
+                self.sc_desc.format,
+            )
+        });
+        match pipeline {
+            Ok(pipeline) => {
+                self.render_pipeline = pipeline;
+                log::info!("Reloaded {}", self.shader_path.display());
+            }
+            Err(e) => log::error!("{:#}", e),
+        }
+    }
+
+    fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.size = new_size;
+        self.sc_desc.width = new_size.width;
+        self.sc_desc.height = new_size.height;
+        self.swap_chain = self.device.create_swap_chain(&self.surface, &self.sc_desc);
+        self.capture.resize(&self.device, &self.sc_desc);
+        self.globals.resize(new_size);
+    }
+
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        self.globals.input(event);
+        self.capture.input(event)
+    }
+
+    fn update(&mut self) {
+        self.reload_if_changed();
+        self.globals.set_fixed_time_step(self.capture.time_step());
+        self.globals.update(&self.queue);
+    }
+
+    fn render(&mut self) {
+        let frame = self
+            .swap_chain
+            .get_current_frame()
+            .expect("Timeout getting texture")
+            .output;
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        self.draw(&mut encoder, &frame.view);
+        // Si hay una captura pendiente volvemos a dibujar el frame en la textura de captura
+        if let Some(view) = self.capture.target() {
+            self.draw(&mut encoder, view);
+        }
+
+        self.queue.submit(iter::once(encoder.finish()));
+        self.capture.finish_frame(&self.device, &self.queue);
+    }
+
+    fn draw(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
+                attachment: view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        });
+
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, &self.globals.bind_group, &[]);
+        render_pass.set_bind_group(1, &self.channels_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+pub fn main_shadertoy(args: &[String]) {
+    env_logger::init();
+
+    let shader_path = match args.first() {
+        Some(path) => PathBuf::from(path),
+        None => PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/src/examples/shaders/toy/default.glsl")),
+    };
+    let channel_paths = args.get(1..).unwrap_or(&[]);
+
+    let event_loop = EventLoop::new();
+    let window = WindowBuilder::new()
+        .with_title(format!("shadertoy - {}", shader_path.display()))
+        .build(&event_loop)
+        .unwrap();
+
+    use futures::executor::block_on;
+
+    // Since main can't be async, we're going to need to block
+    let mut state = match block_on(State::new(&window, shader_path, channel_paths)) {
+        Ok(state) => state,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            std::process::exit(1);
+        }
+    };
+
+    event_loop.run(move |event, _, control_flow| {
+        match event {
+            Event::WindowEvent {
+                ref event,
+                window_id,
+            } if window_id == window.id() => {
+                if !state.input(event) {
+                    match event {
+                        WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+                        WindowEvent::KeyboardInput { input, .. } => match input {
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Escape),
+                                ..
+                            } => *control_flow = ControlFlow::Exit,
+                            _ => {}
+                        },
+                        WindowEvent::Resized(physical_size) => {
+                            state.resize(*physical_size);
+                        }
+                        WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                            // new_inner_size is &mut so w have to dereference it twice
+                            state.resize(**new_inner_size);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Event::RedrawRequested(_) => {
+                state.update();
+                state.render();
+            }
+            Event::MainEventsCleared => {
+                // RedrawRequested will only trigger once, unless we manually
+                // request it.
+                window.request_redraw();
+            }
+            _ => {}
+        }
+    });
+}
@@ -0,0 +1,407 @@
+//## Mapas de sombras: antes del render pass normal se dibuja la escena desde el punto de vista de cada luz en un pass
+//## que solo escribe profundidad (sin fragment shader ni color). Al dibujar la escena, el fragment shader pasa cada
+//## punto al espacio de la luz y compara su profundidad con la del mapa: si hay algo mas cerca de la luz, esta en
+//## sombra.
+//##
+//## Todos los mapas son capas de una misma textura Depth32Float 2D array:
+//##   - La primera luz direccional usa las capas 0..num_cascades. Con una sola cascada un mapa cubre toda la distancia
+//##     de sombras; con varias (cascaded shadow maps) el frustum de la camara se parte en tramos y cada uno tiene su
+//##     mapa, asi que cerca de la camara hay mucha mas resolucion.
+//##   - El foco i usa la capa MAX_CASCADES + i, con una proyeccion en perspectiva que cubre su cono.
+//##
+//## La comparacion la hace el sampler (comparison sampler), que con filtrado lineal ya promedia 2x2 texels. Ademas el
+//## shader hace PCF (percentage closer filtering): promedia varias comparaciones alrededor del punto para suavizar el
+//## borde de la sombra.
+//##
+//## En el set de las luces van tambien las sombras:
+//##
+//## layout(set=N, binding=0) uniform Lights { ... };   // ver lighting.rs
+//## layout(set=N, binding=1) uniform texture2DArray t_shadow;
+//## layout(set=N, binding=2) uniform samplerShadow s_shadow;
+//## layout(set=N, binding=3) uniform Shadows {
+//##     mat4 u_light_view_proj[MAX_SHADOW_LAYERS];
+//##     vec4 u_cascade_splits;      // Distancia a la camara a la que acaba cada cascada
+//##     vec4 u_camera_forward;
+//##     uint u_num_cascades;
+//##     uint u_pcf_radius;          // 0: una sola muestra, 1: 3x3, 2: 5x5...
+//##     uint u_show_cascades;       // Tiñe cada cascada de un color
+//##     float u_shadow_texel_size;
+//## };
+
+use std::num::NonZeroU32;
+
+use cgmath::{EuclideanSpace, InnerSpace, Matrix4, Point3, Rad, Vector3, Vector4};
+use wgpu::util::DeviceExt;
+
+use crate::examples::{
+    Camera, GpuMesh, InstanceData, Lights, LightsUniform, MeshVertex, PipelineBuilder, MAX_SPOT_LIGHTS,
+    OPENGL_TO_WGPU_MATRIX,
+};
+
+pub const SHADOW_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+pub const SHADOW_MAP_SIZE: u32 = 2048;
+pub const MAX_CASCADES: usize = 4;
+const MAX_SHADOW_LAYERS: usize = MAX_CASCADES + MAX_SPOT_LIGHTS;
+
+// Entre 0 (tramos iguales) y 1 (tramos en progresion geometrica, mas cortos cerca de la camara)
+const CASCADE_SPLIT_LAMBDA: f32 = 0.75;
+// Distancia extra hacia la luz para que proyecten sombra los objetos que estan fuera del frustum de la cascada
+const CASTER_MARGIN: f32 = 20.0;
+const SPOT_SHADOW_NEAR: f32 = 0.1;
+
+// Tiene que coincidir con el bloque Shadows de los shaders (std140)
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+struct ShadowsData {
+    light_view_proj: [[[f32; 4]; 4]; MAX_SHADOW_LAYERS],
+    cascade_splits: [f32; 4],
+    camera_forward: [f32; 4],
+    num_cascades: u32,
+    pcf_radius: u32,
+    show_cascades: u32,
+    texel_size: f32,
+}
+
+unsafe impl bytemuck::Pod for ShadowsData {}
+unsafe impl bytemuck::Zeroable for ShadowsData {}
+
+// Una capa del mapa de sombras: su view para dibujar en ella y la matriz de la luz para el vertex shader del pass
+struct ShadowLayer {
+    view: wgpu::TextureView,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+// Una malla con sus instancias, que proyecta sombra
+pub struct ShadowCaster<'a> {
+    pub mesh: &'a GpuMesh,
+    pub instance_buffer: &'a wgpu::Buffer,
+    pub num_instances: u32,
+}
+
+// Distancia a la camara a la que acaba cada cascada, entre near y far
+fn cascade_splits(near: f32, far: f32, num_cascades: usize) -> [f32; MAX_CASCADES] {
+    let mut splits = [far; MAX_CASCADES];
+    for (i, split) in splits.iter_mut().enumerate().take(num_cascades) {
+        let p = (i + 1) as f32 / num_cascades as f32;
+        let uniform = near + (far - near) * p;
+        let logarithmic = near * (far / near).powf(p);
+        *split = uniform + (logarithmic - uniform) * CASCADE_SPLIT_LAMBDA;
+    }
+    splits
+}
+
+fn look_at_direction(eye: Point3<f32>, direction: Vector3<f32>) -> Matrix4<f32> {
+    // look_at no funciona si la direccion es paralela al vector up
+    let up = if direction.y.abs() > 0.99 {
+        Vector3::unit_z()
+    } else {
+        Vector3::unit_y()
+    };
+    Matrix4::look_at_dir(eye, direction, up)
+}
+
+// Proyeccion ortografica de la luz direccional que cubre el tramo del frustum de la camara entre near y far
+fn cascade_matrix(camera: &Camera, direction: Vector3<f32>, near: f32, far: f32) -> Matrix4<f32> {
+    let forward = (camera.target - camera.eye).normalize();
+    let right = forward.cross(camera.up).normalize();
+    let up = right.cross(forward);
+    let tan_half_fovy = (camera.fovy.to_radians() * 0.5).tan();
+
+    let mut corners = Vec::with_capacity(8);
+    for &distance in &[near, far] {
+        let half_height = distance * tan_half_fovy;
+        let half_width = half_height * camera.aspect;
+        let center = camera.eye + forward * distance;
+        for &(x, y) in &[(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+            corners.push(center + right * (x * half_width) + up * (y * half_height));
+        }
+    }
+
+    // Una esfera alrededor del tramo en vez de una caja: su tamaño no cambia al girar la camara, asi que los texels
+    // del mapa tampoco y el borde de las sombras no parpadea
+    let center = corners
+        .iter()
+        .fold(Vector3::new(0.0, 0.0, 0.0), |sum, corner| sum + corner.to_vec())
+        / corners.len() as f32;
+    let center = Point3::from_vec(center);
+    let radius = corners
+        .iter()
+        .map(|corner| (corner - center).magnitude())
+        .fold(0.0, f32::max);
+    let radius = (radius * 16.0).ceil() / 16.0;
+
+    let view = look_at_direction(center - direction * (radius + CASTER_MARGIN), direction);
+    let mut projection = cgmath::ortho(-radius, radius, -radius, radius, 0.0, radius * 2.0 + CASTER_MARGIN);
+
+    // Al moverse la camara la proyeccion se mueve solo en pasos de un texel, por el mismo motivo
+    let texels = SHADOW_MAP_SIZE as f32 * 0.5;
+    let origin = projection * view * Vector4::new(0.0, 0.0, 0.0, 1.0) * texels;
+    projection.w.x += (origin.x.round() - origin.x) / texels;
+    projection.w.y += (origin.y.round() - origin.y) / texels;
+
+    OPENGL_TO_WGPU_MATRIX * projection * view
+}
+
+pub struct ShadowMap {
+    // Entre 1 y MAX_CASCADES
+    pub num_cascades: u32,
+    // Hasta donde hay sombras de la luz direccional
+    pub shadow_distance: f32,
+    pub pcf_radius: u32,
+    pub show_cascades: bool,
+    layers: Vec<ShadowLayer>,
+    // Las capas que se dibujan este frame
+    active_layers: Vec<usize>,
+    pipeline: wgpu::RenderPipeline,
+    shadows_buffer: wgpu::Buffer,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl ShadowMap {
+    // El bind group incluye el buffer de las luces, asi que sustituye al de LightsUniform en el pipeline de la escena
+    pub fn new(device: &wgpu::Device, lights: &LightsUniform) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Shadow Map"),
+            size: wgpu::Extent3d {
+                width: SHADOW_MAP_SIZE,
+                height: SHADOW_MAP_SIZE,
+                depth: MAX_SHADOW_LAYERS as u32,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: SHADOW_FORMAT,
+            usage: wgpu::TextureUsage::OUTPUT_ATTACHMENT | wgpu::TextureUsage::SAMPLED,
+        });
+        let array_view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("Shadow Map Array"),
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        // Fuera del mapa no hay sombra, pero eso ya lo comprueba el shader
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Shadow Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
+
+        let layer_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStage::VERTEX,
+                ty: wgpu::BindingType::UniformBuffer {
+                    dynamic: false,
+                    min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<[[f32; 4]; 4]>() as _),
+                },
+                count: None,
+            }],
+            label: Some("shadow_layer_bind_group_layout"),
+        });
+        let layers = (0..MAX_SHADOW_LAYERS as u32)
+            .map(|layer| {
+                let view = texture.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("Shadow Map Layer"),
+                    dimension: Some(wgpu::TextureViewDimension::D2),
+                    base_array_layer: layer,
+                    array_layer_count: NonZeroU32::new(1),
+                    ..Default::default()
+                });
+                let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Shadow Layer Buffer"),
+                    size: std::mem::size_of::<[[f32; 4]; 4]>() as wgpu::BufferAddress,
+                    usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+                    mapped_at_creation: false,
+                });
+                let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    layout: &layer_layout,
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::Buffer(buffer.slice(..)),
+                    }],
+                    label: Some("shadow_layer_bind_group"),
+                });
+                ShadowLayer {
+                    view,
+                    buffer,
+                    bind_group,
+                }
+            })
+            .collect();
+
+        // Solo depth: sin fragment shader ni color. El pass de sombras no usa los Globals, el set 0 es la capa
+        let vs_module = device.create_shader_module(wgpu::include_spirv!("shaders/shadow.vert.spv"));
+        let pipeline = PipelineBuilder::new(&vs_module, SHADOW_FORMAT)
+            .label("Shadow Pipeline")
+            .color_format(None)
+            .bind_group_layouts(&[&layer_layout])
+            .vertex_buffer(MeshVertex::desc())
+            .vertex_buffer(InstanceData::desc())
+            .depth(SHADOW_FORMAT, wgpu::CompareFunction::LessEqual)
+            .depth_bias(2, 2.0)
+            .build(device);
+
+        let shadows_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Shadows Buffer"),
+            contents: bytemuck::cast_slice(&[ShadowsData {
+                light_view_proj: [[[0.0; 4]; 4]; MAX_SHADOW_LAYERS],
+                cascade_splits: [0.0; 4],
+                camera_forward: [0.0; 4],
+                num_cascades: 0,
+                pcf_radius: 0,
+                show_cascades: 0,
+                texel_size: 1.0 / SHADOW_MAP_SIZE as f32,
+            }]),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::VERTEX | wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::UniformBuffer {
+                        dynamic: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::SampledTexture {
+                        multisampled: false,
+                        dimension: wgpu::TextureViewDimension::D2Array,
+                        component_type: wgpu::TextureComponentType::Float,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler { comparison: true },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::UniformBuffer {
+                        dynamic: false,
+                        min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<ShadowsData>() as _),
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("shadows_bind_group_layout"),
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(lights.buffer.slice(..)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&array_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::Buffer(shadows_buffer.slice(..)),
+                },
+            ],
+            label: Some("shadows_bind_group"),
+        });
+
+        Self {
+            num_cascades: MAX_CASCADES as u32,
+            shadow_distance: 30.0,
+            pcf_radius: 1,
+            show_cascades: false,
+            layers,
+            active_layers: Vec::new(),
+            pipeline,
+            shadows_buffer,
+            bind_group_layout,
+            bind_group,
+        }
+    }
+
+    // Calcula las matrices de las luces que proyectan sombra y las sube. Una vez por frame, antes de render()
+    pub fn update(&mut self, queue: &wgpu::Queue, camera: &Camera, lights: &Lights) {
+        let mut data = ShadowsData {
+            light_view_proj: [[[0.0; 4]; 4]; MAX_SHADOW_LAYERS],
+            cascade_splits: [0.0; 4],
+            camera_forward: (camera.target - camera.eye).normalize().extend(0.0).into(),
+            num_cascades: 0,
+            pcf_radius: self.pcf_radius,
+            show_cascades: self.show_cascades as u32,
+            texel_size: 1.0 / SHADOW_MAP_SIZE as f32,
+        };
+        self.active_layers.clear();
+
+        if let Some(light) = lights.directional_lights.first() {
+            let num_cascades = (self.num_cascades as usize).clamp(1, MAX_CASCADES);
+            let direction = Vector3::from(light.direction).normalize();
+            let splits = cascade_splits(camera.znear, self.shadow_distance, num_cascades);
+            let mut near = camera.znear;
+            for (layer, &far) in splits.iter().enumerate().take(num_cascades) {
+                data.light_view_proj[layer] = cascade_matrix(camera, direction, near, far).into();
+                self.active_layers.push(layer);
+                near = far;
+            }
+            data.cascade_splits = splits;
+            data.num_cascades = num_cascades as u32;
+        }
+
+        for (i, light) in lights.spot_lights.iter().take(MAX_SPOT_LIGHTS).enumerate() {
+            let layer = MAX_CASCADES + i;
+            let view = look_at_direction(Point3::from(light.position), Vector3::from(light.direction).normalize());
+            let projection = cgmath::perspective(Rad(light.outer_angle * 2.0), 1.0, SPOT_SHADOW_NEAR, light.range);
+            data.light_view_proj[layer] = (OPENGL_TO_WGPU_MATRIX * projection * view).into();
+            self.active_layers.push(layer);
+        }
+
+        for &layer in &self.active_layers {
+            let matrix = data.light_view_proj[layer];
+            queue.write_buffer(&self.layers[layer].buffer, 0, bytemuck::cast_slice(&[matrix]));
+        }
+        queue.write_buffer(&self.shadows_buffer, 0, bytemuck::cast_slice(&[data]));
+    }
+
+    // Un pass de solo depth por cada capa en uso, antes del pass de la escena
+    pub fn render(&self, encoder: &mut wgpu::CommandEncoder, casters: &[ShadowCaster]) {
+        for &layer in &self.active_layers {
+            let layer = &self.layers[layer];
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                color_attachments: &[],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachmentDescriptor {
+                    attachment: &layer.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_bind_group(0, &layer.bind_group, &[]);
+            for caster in casters {
+                caster
+                    .mesh
+                    .draw_instanced(&mut render_pass, caster.instance_buffer, 0..caster.num_instances);
+            }
+        }
+    }
+}
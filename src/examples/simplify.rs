@@ -0,0 +1,475 @@
+//## Simplificacion de mallas para los niveles de detalle (LOD): quita triangulos colapsando aristas, empezando
+//## siempre por la que menos cambia la forma segun la quadric error metric de Garland y Heckbert. Cada vertice acumula
+//## las ecuaciones de los planos de sus triangulos en una matriz 4x4 (la quadric), y el error de llevar el vertice a
+//## un punto es la suma de los cuadrados de las distancias del punto a esos planos.
+//##
+//## Los colapsos son de media arista: un vertice se junta con un vecino y desaparece, asi no hay que inventar normales
+//## ni coordenadas de textura nuevas. No se mueven los vertices del borde, y eso incluye las costuras: en la costura y
+//## los polos de sphere_mesh los vertices estan repetidos, asi que para el index buffer la malla esta abierta ahi. Asi
+//## no se abren grietas. Tambien se descarta cualquier colapso que deje la malla no manifold o que le de la vuelta a
+//## algun triangulo.
+//##
+//## Es todo CPU y se hace una sola vez, al cargar la malla.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::ops::AddAssign;
+
+use cgmath::{InnerSpace, Vector3};
+
+use crate::examples::MeshVertex;
+
+// Matriz simetrica 4x4; solo se guarda la mitad de arriba
+#[derive(Copy, Clone, Debug, Default)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    // El plano a * x + b * y + c * z + d = 0, con (a, b, c) unitario
+    fn from_plane(normal: Vector3<f64>, d: f64, weight: f64) -> Self {
+        let (a, b, c) = (normal.x, normal.y, normal.z);
+        let mut q = [a * a, a * b, a * c, a * d, b * b, b * c, b * d, c * c, c * d, d * d];
+        for value in &mut q {
+            *value *= weight;
+        }
+        Quadric(q)
+    }
+
+    fn error(&self, p: Vector3<f64>) -> f64 {
+        let q = &self.0;
+        let (x, y, z) = (p.x, p.y, p.z);
+        q[0] * x * x
+            + q[4] * y * y
+            + q[7] * z * z
+            + q[9]
+            + 2.0 * (q[1] * x * y + q[2] * x * z + q[5] * y * z + q[3] * x + q[6] * y + q[8] * z)
+    }
+}
+
+impl AddAssign for Quadric {
+    fn add_assign(&mut self, other: Self) {
+        for (a, b) in self.0.iter_mut().zip(other.0.iter()) {
+            *a += b;
+        }
+    }
+}
+
+// Juntar from con to. Los versions son los de los dos vertices al calcular el coste: si alguno ha cambiado desde
+// entonces el coste ya no vale y se descarta
+struct Collapse {
+    cost: f64,
+    from: u32,
+    to: u32,
+    versions: (u32, u32),
+}
+
+// BinaryHeap saca el mayor, asi que se ordena al reves para sacar el de menor coste
+impl Ord for Collapse {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.partial_cmp(&self.cost).unwrap_or(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Collapse {}
+
+struct Simplifier {
+    positions: Vec<Vector3<f64>>,
+    quadrics: Vec<Quadric>,
+    locked: Vec<bool>,
+    versions: Vec<u32>,
+    triangles: Vec<[u32; 3]>,
+    alive: Vec<bool>,
+    num_alive: usize,
+    // Los triangulos de cada vertice. Puede haber muertos, se filtran con alive
+    vertex_triangles: Vec<Vec<usize>>,
+}
+
+impl Simplifier {
+    fn new(vertices: &[MeshVertex], indices: &[u16]) -> Self {
+        let positions: Vec<Vector3<f64>> = vertices
+            .iter()
+            .map(|v| Vector3::new(v.position[0] as f64, v.position[1] as f64, v.position[2] as f64))
+            .collect();
+        let triangles: Vec<[u32; 3]> = indices
+            .chunks(3)
+            .map(|t| [t[0] as u32, t[1] as u32, t[2] as u32])
+            .collect();
+
+        let mut vertex_triangles = vec![Vec::new(); vertices.len()];
+        let mut quadrics = vec![Quadric::default(); vertices.len()];
+        let mut edges = HashMap::new();
+        for (i, triangle) in triangles.iter().enumerate() {
+            for k in 0..3 {
+                let (a, b) = (triangle[k], triangle[(k + 1) % 3]);
+                vertex_triangles[a as usize].push(i);
+                *edges.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+            }
+            // Pesado por el area para que los triangulos pequeños cuenten menos
+            let [p0, p1, p2] = [0, 1, 2].map(|k| positions[triangle[k] as usize]);
+            let normal = (p1 - p0).cross(p2 - p0);
+            let length = normal.magnitude();
+            if length > 0.0 {
+                let normal = normal / length;
+                let quadric = Quadric::from_plane(normal, -normal.dot(p0), length * 0.5);
+                for &v in triangle {
+                    quadrics[v as usize] += quadric;
+                }
+            }
+        }
+
+        // Borde (y costuras): aristas con un solo triangulo, o con mas de dos, que tampoco se pueden tocar
+        let mut locked = vec![false; vertices.len()];
+        for (&(a, b), &count) in &edges {
+            if count != 2 {
+                locked[a as usize] = true;
+                locked[b as usize] = true;
+            }
+        }
+
+        Self {
+            positions,
+            quadrics,
+            locked,
+            versions: vec![0; vertices.len()],
+            alive: vec![true; triangles.len()],
+            num_alive: triangles.len(),
+            triangles,
+            vertex_triangles,
+        }
+    }
+
+    fn triangles_of(&self, v: u32) -> impl Iterator<Item = usize> + '_ {
+        self.vertex_triangles[v as usize]
+            .iter()
+            .copied()
+            .filter(move |&t| self.alive[t])
+    }
+
+    fn neighbors(&self, v: u32) -> Vec<u32> {
+        let mut neighbors: Vec<u32> = self
+            .triangles_of(v)
+            .flat_map(|t| self.triangles[t].iter().copied())
+            .filter(|&n| n != v)
+            .collect();
+        neighbors.sort_unstable();
+        neighbors.dedup();
+        neighbors
+    }
+
+    fn push_edges(&self, v: u32, heap: &mut BinaryHeap<Collapse>) {
+        for n in self.neighbors(v) {
+            for &(from, to) in &[(v, n), (n, v)] {
+                if self.locked[from as usize] {
+                    continue;
+                }
+                let mut quadric = self.quadrics[from as usize];
+                quadric += self.quadrics[to as usize];
+                heap.push(Collapse {
+                    cost: quadric.error(self.positions[to as usize]),
+                    from,
+                    to,
+                    versions: (self.versions[from as usize], self.versions[to as usize]),
+                });
+            }
+        }
+    }
+
+    fn normal(&self, triangle: [u32; 3], moved: u32, to: u32) -> Vector3<f64> {
+        let [p0, p1, p2] = triangle.map(|v| self.positions[if v == moved { to } else { v } as usize]);
+        (p1 - p0).cross(p2 - p0)
+    }
+
+    fn can_collapse(&self, from: u32, to: u32) -> bool {
+        // La arista tiene que tener un triangulo a cada lado
+        let shared: Vec<usize> = self
+            .triangles_of(from)
+            .filter(|&t| self.triangles[t].contains(&to))
+            .collect();
+        if shared.len() != 2 {
+            return false;
+        }
+        let mut opposite: Vec<u32> = shared
+            .iter()
+            .map(|&t| *self.triangles[t].iter().find(|&&v| v != from && v != to).unwrap())
+            .collect();
+        opposite.sort_unstable();
+        if opposite[0] == opposite[1] {
+            return false;
+        }
+
+        // Link condition: los unicos vecinos comunes son los dos vertices opuestos. Si hubiera otro, al colapsar
+        // quedaria una arista con mas de dos triangulos
+        let to_neighbors = self.neighbors(to);
+        let common: Vec<u32> = self
+            .neighbors(from)
+            .into_iter()
+            .filter(|n| to_neighbors.binary_search(n).is_ok())
+            .collect();
+        if common != opposite {
+            return false;
+        }
+        // Cada vertice opuesto pierde un vecino; con tres (un tetraedro) quedarian dos triangulos iguales
+        if opposite.iter().any(|&v| self.neighbors(v).len() <= 3) {
+            return false;
+        }
+
+        // Ningun triangulo de los que quedan puede darse la vuelta ni quedarse sin area
+        self.triangles_of(from).filter(|t| !shared.contains(t)).all(|t| {
+            let triangle = self.triangles[t];
+            let before = self.normal(triangle, from, from);
+            let after = self.normal(triangle, from, to);
+            after.dot(before) > 0.0 && after.magnitude2() > before.magnitude2() * 1e-6
+        })
+    }
+
+    fn collapse(&mut self, from: u32, to: u32) {
+        for t in self.vertex_triangles[from as usize].clone() {
+            if !self.alive[t] {
+                continue;
+            }
+            let triangle = &mut self.triangles[t];
+            if triangle.contains(&to) {
+                self.alive[t] = false;
+                self.num_alive -= 1;
+            } else {
+                for v in triangle.iter_mut().filter(|v| **v == from) {
+                    *v = to;
+                }
+                self.vertex_triangles[to as usize].push(t);
+            }
+        }
+        let quadric = self.quadrics[from as usize];
+        self.quadrics[to as usize] += quadric;
+        self.versions[from as usize] += 1;
+        self.versions[to as usize] += 1;
+    }
+
+    // Una pasada con todas las aristas. Los colapsos que no se pueden hacer se tiran, pero pueden volverse posibles
+    // despues de otros, por eso se repiten las pasadas mientras se quite algo
+    fn pass(&mut self, target_triangles: usize) -> bool {
+        let mut heap = BinaryHeap::new();
+        for v in 0..self.positions.len() as u32 {
+            if !self.locked[v as usize] {
+                self.push_edges(v, &mut heap);
+            }
+        }
+
+        let mut collapsed = false;
+        while self.num_alive > target_triangles {
+            let collapse = match heap.pop() {
+                Some(collapse) => collapse,
+                None => break,
+            };
+            let (from, to) = (collapse.from, collapse.to);
+            if collapse.versions != (self.versions[from as usize], self.versions[to as usize]) {
+                continue;
+            }
+            if self.can_collapse(from, to) {
+                self.collapse(from, to);
+                self.push_edges(to, &mut heap);
+                collapsed = true;
+            }
+        }
+        collapsed
+    }
+
+    // Los vertices que siguen en uso, en el orden original
+    fn output(&self, vertices: &[MeshVertex]) -> (Vec<MeshVertex>, Vec<u16>) {
+        let alive_triangles = || {
+            (0..self.triangles.len())
+                .filter(|&t| self.alive[t])
+                .map(|t| self.triangles[t])
+        };
+        let mut used = vec![false; vertices.len()];
+        for triangle in alive_triangles() {
+            for &v in &triangle {
+                used[v as usize] = true;
+            }
+        }
+
+        let mut remap = vec![0; vertices.len()];
+        let mut new_vertices = Vec::new();
+        for (i, vertex) in vertices.iter().enumerate() {
+            if used[i] {
+                remap[i] = new_vertices.len() as u16;
+                new_vertices.push(*vertex);
+            }
+        }
+        let indices = alive_triangles()
+            .flat_map(|triangle| triangle.iter().map(|&v| remap[v as usize]).collect::<Vec<_>>())
+            .collect();
+        (new_vertices, indices)
+    }
+}
+
+// Quita triangulos hasta dejar como mucho target_triangles, o menos si no se puede colapsar ninguna arista mas. Cada
+// colapso quita dos triangulos, asi que puede quedarse uno por debajo
+pub fn simplify_mesh(vertices: &[MeshVertex], indices: &[u16], target_triangles: usize) -> (Vec<MeshVertex>, Vec<u16>) {
+    let mut simplifier = Simplifier::new(vertices, indices);
+    while simplifier.num_alive > target_triangles && simplifier.pass(target_triangles) {}
+    simplifier.output(vertices)
+}
+
+// levels mallas: la 0 es la original y cada una intenta tener la mitad de triangulos que la anterior. Todas salen de
+// la original para no ir acumulando errores
+pub fn generate_lods(vertices: &[MeshVertex], indices: &[u16], levels: usize) -> Vec<(Vec<MeshVertex>, Vec<u16>)> {
+    let triangles = indices.len() / 3;
+    (0..levels)
+        .map(|level| {
+            if level == 0 {
+                (vertices.to_vec(), indices.to_vec())
+            } else {
+                simplify_mesh(vertices, indices, triangles >> level)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::{cube_mesh, sphere_mesh};
+
+    // Junta los vertices que estan en la misma posicion, para ver la malla como superficie sin las costuras. Con algo
+    // de margen: en sphere_mesh sin(2 * PI) no da exactamente 0
+    fn weld(vertices: &[MeshVertex], indices: &[u16]) -> (Vec<MeshVertex>, Vec<u16>) {
+        let mut welded: Vec<MeshVertex> = Vec::new();
+        let mut by_position = HashMap::new();
+        let remap: Vec<u16> = vertices
+            .iter()
+            .map(|vertex| {
+                let key = vertex.position.map(|x| (x * 1e4).round() as i32);
+                *by_position.entry(key).or_insert_with(|| {
+                    welded.push(*vertex);
+                    welded.len() as u16 - 1
+                })
+            })
+            .collect();
+        (welded, indices.iter().map(|&i| remap[i as usize]).collect())
+    }
+
+    // Cerrada y manifold: cada arista esta en exactamente dos triangulos, una vez en cada sentido (orientacion
+    // consistente), y ningun triangulo repite vertice
+    fn is_closed_manifold(indices: &[u16]) -> bool {
+        let mut edges = HashMap::new();
+        for t in indices.chunks(3) {
+            if t[0] == t[1] || t[1] == t[2] || t[0] == t[2] {
+                return false;
+            }
+            for k in 0..3 {
+                *edges.entry((t[k], t[(k + 1) % 3])).or_insert(0) += 1;
+            }
+        }
+        edges
+            .iter()
+            .all(|(&(a, b), &count)| count == 1 && edges.get(&(b, a)) == Some(&1))
+    }
+
+    // Rejilla plana de n x n cuadrados en el plano y = 0, mirando hacia arriba
+    fn grid(n: u16) -> (Vec<MeshVertex>, Vec<u16>) {
+        let mut vertices = Vec::new();
+        for z in 0..=n {
+            for x in 0..=n {
+                vertices.push(MeshVertex {
+                    position: [x as f32, 0.0, z as f32],
+                    normal: [0.0, 1.0, 0.0],
+                    tex_coords: [x as f32 / n as f32, z as f32 / n as f32],
+                });
+            }
+        }
+        let mut indices = Vec::new();
+        for z in 0..n {
+            for x in 0..n {
+                let k = z * (n + 1) + x;
+                indices.extend_from_slice(&[k, k + n + 1, k + 1, k + 1, k + n + 1, k + n + 2]);
+            }
+        }
+        (vertices, indices)
+    }
+
+    fn triangle_count(indices: &[u16]) -> usize {
+        indices.len() / 3
+    }
+
+    #[test]
+    fn reaches_target_and_stays_manifold() {
+        let (vertices, indices) = sphere_mesh(1.0, 32, 16);
+        let (vertices, indices) = weld(&vertices, &indices);
+        assert!(is_closed_manifold(&indices));
+
+        for &target in &[400, 200, 100, 50] {
+            let (simplified_vertices, simplified) = simplify_mesh(&vertices, &indices, target);
+            let count = triangle_count(&simplified);
+            assert!(
+                count <= target && count + 2 > target,
+                "{} triangles for {}",
+                count,
+                target
+            );
+            assert!(is_closed_manifold(&simplified));
+            // Euler de una esfera: V - E + F = 2, con E = 3F / 2
+            assert_eq!(simplified_vertices.len() + count - count * 3 / 2, 2);
+        }
+    }
+
+    #[test]
+    fn seams_do_not_open() {
+        let (vertices, indices) = sphere_mesh(1.0, 32, 16);
+        let (simplified_vertices, simplified) = simplify_mesh(&vertices, &indices, triangle_count(&indices) / 4);
+        assert!(triangle_count(&simplified) <= triangle_count(&indices) / 4);
+        // Con las costuras soldadas sigue siendo una esfera cerrada, asi que no ha salido ninguna grieta
+        let (_, welded) = weld(&simplified_vertices, &simplified);
+        assert!(is_closed_manifold(&welded));
+    }
+
+    #[test]
+    fn flat_grid_keeps_border_and_orientation() {
+        let (vertices, indices) = grid(8);
+        let (simplified_vertices, simplified) = simplify_mesh(&vertices, &indices, 0);
+        // Quedan los 32 vertices del borde y casi ninguno de dentro
+        let on_border = |v: &MeshVertex| v.position[0] % 8.0 == 0.0 || v.position[2] % 8.0 == 0.0;
+        assert_eq!(simplified_vertices.iter().filter(|v| on_border(v)).count(), 32);
+        assert!(triangle_count(&simplified) < triangle_count(&indices) / 3);
+        // Todos siguen mirando hacia arriba y cubren el cuadrado entero, sin huecos ni solapes
+        let mut area = 0.0;
+        for t in simplified.chunks(3) {
+            let [p0, p1, p2] = [t[0], t[1], t[2]].map(|i| Vector3::from(simplified_vertices[i as usize].position));
+            let normal = (p1 - p0).cross(p2 - p0);
+            assert!(normal.y > 0.0);
+            area += normal.y * 0.5;
+        }
+        assert!((area - 64.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn cube_is_left_alone() {
+        // Todas las esquinas son costuras (cada cara tiene sus vertices), asi que no se puede colapsar nada
+        let (vertices, indices) = cube_mesh(1.0);
+        let (simplified_vertices, simplified) = simplify_mesh(&vertices, &indices, 0);
+        assert_eq!(simplified_vertices.len(), vertices.len());
+        assert_eq!(simplified, indices);
+    }
+
+    #[test]
+    fn lods_halve_triangles() {
+        let (vertices, indices) = sphere_mesh(1.0, 48, 24);
+        let lods = generate_lods(&vertices, &indices, 4);
+        assert_eq!(lods.len(), 4);
+        assert_eq!(lods[0].1, indices);
+        for (level, (_, lod_indices)) in lods.iter().enumerate().skip(1) {
+            assert!(triangle_count(lod_indices) <= triangle_count(&indices) >> level);
+            assert!(triangle_count(lod_indices) < triangle_count(&lods[level - 1].1));
+        }
+    }
+}
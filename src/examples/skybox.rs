@@ -0,0 +1,192 @@
+//## Skybox: un cubemap que se dibuja detras de toda la escena. No hace falta ningun cubo, basta un triangulo que
+//## cubre la pantalla: el vertex shader deshace projection * view (sin la traslacion de la camara, el cielo esta
+//## infinitamente lejos) para sacar la direccion del mundo que se ve en cada pixel, y el fragment shader lee el cubemap
+//## en esa direccion.
+//##
+//## El triangulo esta en el plano far (depth 1.0) y el pipeline usa LessEqual sin escribir profundidad, asi que se
+//## dibuja despues de los objetos opacos y solo rellena los pixeles en los que no hay nada. Necesita un depth buffer.
+//##
+//## layout(set=0, binding=0) uniform Skybox { mat4 u_inv_view_proj; };
+//## layout(set=0, binding=1) uniform textureCube t_skybox;
+//## layout(set=0, binding=2) uniform sampler s_skybox;
+//##
+//## Los objetos que reflejan el cielo pueden usar el mismo bind group (bindings 1 y 2) en su pipeline.
+//## procedural_sky() genera un cielo para los ejemplos que no cargan uno de disco.
+
+use std::f32::consts::PI;
+
+use cgmath::{Matrix4, SquareMatrix, Vector3, Vector4};
+use wgpu::util::DeviceExt;
+
+use crate::examples::{Camera, PipelineBuilder, Texture, DEPTH_FORMAT};
+
+pub struct Skybox {
+    pipeline: wgpu::RenderPipeline,
+    buffer: wgpu::Buffer,
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl Skybox {
+    pub fn new(
+        device: &wgpu::Device,
+        cubemap: &Texture,
+        color_format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Self {
+        let inv_view_proj: [[f32; 4]; 4] = Matrix4::identity().into();
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Skybox Buffer"),
+            contents: bytemuck::cast_slice(&[inv_view_proj]),
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStage::VERTEX,
+                    ty: wgpu::BindingType::UniformBuffer {
+                        dynamic: false,
+                        min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<[[f32; 4]; 4]>() as _),
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::SampledTexture {
+                        multisampled: false,
+                        dimension: wgpu::TextureViewDimension::Cube,
+                        component_type: wgpu::TextureComponentType::Float,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStage::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler { comparison: false },
+                    count: None,
+                },
+            ],
+            label: Some("skybox_bind_group_layout"),
+        });
+        let bind_group = Self::create_bind_group(device, &bind_group_layout, &buffer, cubemap);
+
+        let vs_module = device.create_shader_module(wgpu::include_spirv!("shaders/skybox.vert.spv"));
+        let fs_module = device.create_shader_module(wgpu::include_spirv!("shaders/skybox.frag.spv"));
+        let pipeline = PipelineBuilder::new(&vs_module, color_format)
+            .label("Skybox Pipeline")
+            .fragment_shader(&fs_module)
+            .bind_group_layouts(&[&bind_group_layout])
+            .depth_stencil(Some(wgpu::DepthStencilStateDescriptor {
+                format: DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilStateDescriptor::default(),
+            }))
+            .sample_count(sample_count)
+            .build(device);
+
+        Self {
+            pipeline,
+            buffer,
+            bind_group_layout,
+            bind_group,
+        }
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        buffer: &wgpu::Buffer,
+        cubemap: &Texture,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Buffer(buffer.slice(..)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&cubemap.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&cubemap.sampler),
+                },
+            ],
+            label: Some("skybox_bind_group"),
+        })
+    }
+
+    // Cambia el cielo. Los pipelines que usan bind_group_layout siguen valiendo, pero hay que volver a coger bind_group
+    pub fn set_cubemap(&mut self, device: &wgpu::Device, cubemap: &Texture) {
+        self.bind_group = Self::create_bind_group(device, &self.bind_group_layout, &self.buffer, cubemap);
+    }
+
+    pub fn update(&self, queue: &wgpu::Queue, camera: &Camera) {
+        // Sin la traslacion solo queda la rotacion de la camara
+        let mut view = camera.view();
+        view.w = Vector4::new(0.0, 0.0, 0.0, 1.0);
+        // Con la ventana minimizada el aspect no es valido y la matriz no tiene inversa
+        if let Some(inv_view_proj) = (camera.projection() * view).invert() {
+            let inv_view_proj: [[f32; 4]; 4] = inv_view_proj.into();
+            queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[inv_view_proj]));
+        }
+    }
+
+    // Despues de los objetos opacos, en el mismo render pass (con el depth buffer de la escena)
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+// De lineal a sRGB (aproximado) y a bytes
+fn to_srgb8(color: [f32; 3]) -> image::Rgba<u8> {
+    let channel = |c: f32| (c.clamp(0.0, 1.0).powf(1.0 / 2.2) * 255.0).round() as u8;
+    image::Rgba([channel(color[0]), channel(color[1]), channel(color[2]), 255])
+}
+
+fn mix(a: [f32; 3], b: [f32; 3], t: f32) -> [f32; 3] {
+    [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t, a[2] + (b[2] - a[2]) * t]
+}
+
+// Un cielo con degradado, un sol y montañas en el horizonte, en formato equirectangular: la x es la longitud (una
+// vuelta completa) y la y la latitud, de +90º arriba a -90º abajo
+pub fn procedural_sky(width: u32, height: u32) -> image::RgbaImage {
+    let sun = Vector3::new(0.6f32, 0.35, -0.72);
+    let sun = sun / (sun.x * sun.x + sun.y * sun.y + sun.z * sun.z).sqrt();
+    image::RgbaImage::from_fn(width, height, |x, y| {
+        let longitude = ((x as f32 + 0.5) / width as f32 - 0.5) * 2.0 * PI;
+        let latitude = (0.5 - (y as f32 + 0.5) / height as f32) * PI;
+        let direction = Vector3::new(
+            latitude.cos() * longitude.cos(),
+            latitude.sin(),
+            latitude.cos() * longitude.sin(),
+        );
+
+        let mountains = 0.06
+            + 0.04 * (3.0 * longitude).sin()
+            + 0.025 * (7.0 * longitude + 1.0).sin()
+            + 0.015 * (13.0 * longitude + 2.0).sin();
+        let color = if latitude < 0.0 {
+            mix([0.25, 0.22, 0.16], [0.08, 0.07, 0.05], (-latitude / (PI * 0.5)).sqrt())
+        } else if latitude < mountains {
+            mix([0.12, 0.2, 0.22], [0.3, 0.4, 0.45], latitude / mountains)
+        } else {
+            let sky = mix([0.75, 0.85, 0.95], [0.15, 0.35, 0.8], (latitude / (PI * 0.5)).sqrt());
+            let cos_sun = direction.x * sun.x + direction.y * sun.y + direction.z * sun.z;
+            if cos_sun > 0.9995 {
+                [1.0, 1.0, 0.9]
+            } else {
+                mix(sky, [1.0, 0.9, 0.7], cos_sun.max(0.0).powf(64.0))
+            }
+        };
+        to_srgb8(color)
+    })
+}
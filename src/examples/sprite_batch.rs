@@ -0,0 +1,251 @@
+//## SpriteBatch: dibuja miles de sprites 2D con un solo draw. Todos los sprites usan imagenes del mismo TextureAtlas,
+//## asi que comparten bind group, y sus vertices (ya girados y escalados en la CPU) se escriben cada frame en un unico
+//## vertex buffer dinamico. El index buffer es fijo (dos triangulos por sprite) y solo se rehace si el buffer crece.
+//##
+//## Las posiciones van en pixels con el origen arriba a la izquierda, como las coordenadas del raton en los Globals.
+//## Sin depth buffer el orden de dibujado es el del buffer, asi que los sprites se ordenan por depth de atras hacia
+//## delante antes de escribirlos (mayor depth = mas al fondo).
+
+use std::mem;
+
+use wgpu::util::DeviceExt;
+
+use crate::examples::{
+    sampler_entry, sort_back_to_front, texture_entry, AtlasRegion, BlendMode, PipelineBuilder, TextureAtlas,
+};
+
+#[derive(Copy, Clone, Debug)]
+pub struct Sprite {
+    // Centro del sprite en pixels
+    pub position: [f32; 2],
+    // Indice de la imagen en TextureAtlas::regions
+    pub region: usize,
+    // En x y en y, [1.0, 1.0] = tamaño original de la imagen
+    pub scale: [f32; 2],
+    // En radianes, en el sentido de las agujas del reloj (la y crece hacia abajo)
+    pub rotation: f32,
+    // Entre 0 (delante) y 1 (detras)
+    pub depth: f32,
+    pub tint: [f32; 4],
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+struct SpriteVertex {
+    position: [f32; 3],
+    tex_coords: [f32; 2],
+    tint: [f32; 4],
+}
+
+unsafe impl bytemuck::Pod for SpriteVertex {}
+unsafe impl bytemuck::Zeroable for SpriteVertex {}
+
+impl SpriteVertex {
+    fn desc<'a>() -> wgpu::VertexBufferDescriptor<'a> {
+        wgpu::VertexBufferDescriptor {
+            stride: mem::size_of::<SpriteVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::InputStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttributeDescriptor {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float3,
+                },
+                wgpu::VertexAttributeDescriptor {
+                    offset: mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float2,
+                },
+                wgpu::VertexAttributeDescriptor {
+                    offset: mem::size_of::<[f32; 5]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float4,
+                },
+            ],
+        }
+    }
+}
+
+// Los 4 vertices del sprite girado: arriba-izquierda, arriba-derecha, abajo-derecha, abajo-izquierda
+fn sprite_vertices(sprite: &Sprite, region: &AtlasRegion) -> [SpriteVertex; 4] {
+    let half_width = region.size[0] * sprite.scale[0] * 0.5;
+    let half_height = region.size[1] * sprite.scale[1] * 0.5;
+    let (sin, cos) = sprite.rotation.sin_cos();
+    let corner = |x: f32, y: f32, u: f32, v: f32| SpriteVertex {
+        position: [
+            sprite.position[0] + x * cos - y * sin,
+            sprite.position[1] + x * sin + y * cos,
+            sprite.depth.clamp(0.0, 1.0),
+        ],
+        tex_coords: [u, v],
+        tint: sprite.tint,
+    };
+    let [u0, v0] = region.uv_min;
+    let [u1, v1] = region.uv_max;
+    [
+        corner(-half_width, -half_height, u0, v0),
+        corner(half_width, -half_height, u1, v0),
+        corner(half_width, half_height, u1, v1),
+        corner(-half_width, half_height, u0, v1),
+    ]
+}
+
+fn quad_indices(num_sprites: usize) -> Vec<u32> {
+    (0..num_sprites as u32)
+        .flat_map(|i| {
+            let base = i * 4;
+            vec![base, base + 1, base + 2, base, base + 2, base + 3]
+        })
+        .collect()
+}
+
+const INITIAL_CAPACITY: usize = 1024;
+
+fn create_atlas_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    atlas: &TextureAtlas,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&atlas.texture.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&atlas.texture.sampler),
+            },
+        ],
+        label: Some("atlas_bind_group"),
+    })
+}
+
+pub struct SpriteBatch {
+    pipeline: wgpu::RenderPipeline,
+    atlas: TextureAtlas,
+    atlas_layout: wgpu::BindGroupLayout,
+    atlas_bind_group: wgpu::BindGroup,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    // Cuantos sprites caben en los buffers
+    capacity: usize,
+    // Se reutilizan de un frame a otro para no reservar memoria cada vez
+    vertices: Vec<SpriteVertex>,
+    order: Vec<usize>,
+    num_sprites: u32,
+}
+
+impl SpriteBatch {
+    pub fn new(
+        device: &wgpu::Device,
+        atlas: TextureAtlas,
+        globals_layout: &wgpu::BindGroupLayout,
+        color_format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Self {
+        let atlas_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[texture_entry(0), sampler_entry(1)],
+            label: Some("atlas_bind_group_layout"),
+        });
+        let atlas_bind_group = create_atlas_bind_group(device, &atlas_layout, &atlas);
+
+        let vs_module = device.create_shader_module(wgpu::include_spirv!("shaders/sprite_batch.vert.spv"));
+        let fs_module = device.create_shader_module(wgpu::include_spirv!("shaders/sprite.frag.spv"));
+        // Sin culling: con la y hacia abajo y escalas negativas el orden de los vertices puede quedar al reves
+        let pipeline = PipelineBuilder::new(&vs_module, color_format)
+            .label("Sprite Batch Pipeline")
+            .fragment_shader(&fs_module)
+            .bind_group_layouts(&[globals_layout, &atlas_layout])
+            .vertex_buffer(SpriteVertex::desc())
+            .index_format(wgpu::IndexFormat::Uint32)
+            .cull_mode(wgpu::CullMode::None)
+            .blend(BlendMode::Alpha)
+            .sample_count(sample_count)
+            .build(device);
+
+        let (vertex_buffer, index_buffer) = Self::create_buffers(device, INITIAL_CAPACITY);
+
+        Self {
+            pipeline,
+            atlas,
+            atlas_layout,
+            atlas_bind_group,
+            vertex_buffer,
+            index_buffer,
+            capacity: INITIAL_CAPACITY,
+            vertices: Vec::new(),
+            order: Vec::new(),
+            num_sprites: 0,
+        }
+    }
+
+    // u16 solo da para 16384 sprites, por eso los indices son u32
+    fn create_buffers(device: &wgpu::Device, capacity: usize) -> (wgpu::Buffer, wgpu::Buffer) {
+        let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Sprite Vertex Buffer"),
+            size: (capacity * 4 * mem::size_of::<SpriteVertex>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Sprite Index Buffer"),
+            contents: bytemuck::cast_slice(&quad_indices(capacity)),
+            usage: wgpu::BufferUsage::INDEX,
+        });
+        (vertex_buffer, index_buffer)
+    }
+
+    pub fn regions(&self) -> &[AtlasRegion] {
+        &self.atlas.regions
+    }
+
+    // Para añadir imagenes al atlas (TextureAtlas::add): la textura es la misma, asi que el bind group sigue valiendo
+    pub fn atlas_mut(&mut self) -> &mut TextureAtlas {
+        &mut self.atlas
+    }
+
+    // Cambia el atlas (por ejemplo porque se han añadido imagenes). Los sprites tienen que usar los indices del nuevo
+    pub fn set_atlas(&mut self, device: &wgpu::Device, atlas: TextureAtlas) {
+        self.atlas_bind_group = create_atlas_bind_group(device, &self.atlas_layout, &atlas);
+        self.atlas = atlas;
+    }
+
+    // Ordena los sprites y escribe sus vertices en el vertex buffer. Se llama una vez por frame antes de draw()
+    pub fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, sprites: &[Sprite]) {
+        if sprites.len() > self.capacity {
+            self.capacity = sprites.len().next_power_of_two();
+            let (vertex_buffer, index_buffer) = Self::create_buffers(device, self.capacity);
+            self.vertex_buffer = vertex_buffer;
+            self.index_buffer = index_buffer;
+        }
+
+        self.order.clear();
+        self.order.extend(0..sprites.len());
+        sort_back_to_front(&mut self.order, |&i| sprites[i].depth);
+
+        self.vertices.clear();
+        for &i in &self.order {
+            let sprite = &sprites[i];
+            self.vertices
+                .extend_from_slice(&sprite_vertices(sprite, &self.atlas.regions[sprite.region]));
+        }
+        if !self.vertices.is_empty() {
+            queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&self.vertices));
+        }
+        self.num_sprites = sprites.len() as u32;
+    }
+
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, globals_bind_group: &'a wgpu::BindGroup) {
+        if self.num_sprites == 0 {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, globals_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.atlas_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..));
+        render_pass.draw_indexed(0..self.num_sprites * 6, 0, 0..1);
+    }
+}
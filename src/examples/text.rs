@@ -0,0 +1,430 @@
+//## Texto: fuentes TTF rasterizadas con ab_glyph en un atlas de glifos y dibujadas como sprites con SpriteBatch.
+//##
+//## - layout_text() coloca los caracteres de un &str (UTF-8) en lineas: aplica el kerning de la fuente entre cada par
+//##   de letras, salta de linea con '\n' y, si se le da un ancho maximo, parte las lineas por los espacios (o por la
+//##   mitad de la palabra si una sola palabra no cabe).
+//## - GlyphCache rasteriza cada caracter la primera vez que se usa, a su tamaño exacto para que se vea nitido.
+//## - TextRenderer junta todo: queue_text() durante el frame, prepare() antes de dibujar y draw() al final del render
+//##   pass para que el texto quede encima del resto. Las posiciones van en pixels desde arriba a la izquierda.
+
+use std::collections::HashMap;
+
+use ab_glyph::{point, Font, FontArc, ScaleFont};
+use anyhow::{Context, Result};
+
+use crate::examples::{Sprite, SpriteBatch, TextureAtlas};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LayoutGlyph {
+    pub character: char,
+    // Posicion del lapiz: x al principio del caracter, y en la linea base
+    pub position: [f32; 2],
+    pub advance: f32,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TextLayout {
+    // Sin los espacios, que no se dibujan
+    pub glyphs: Vec<LayoutGlyph>,
+    pub width: f32,
+    pub height: f32,
+}
+
+pub fn layout_text(font: &FontArc, text: &str, size: f32, max_width: Option<f32>) -> TextLayout {
+    let font = font.as_scaled(size);
+    let line_height = font.height() + font.line_gap();
+
+    // Todos los caracteres con su linea, los espacios tambien porque marcan donde se puede partir la linea
+    let mut glyphs: Vec<(LayoutGlyph, usize)> = Vec::with_capacity(text.len());
+    let mut line = 0;
+    for paragraph in text.split('\n') {
+        let mut x = 0.0;
+        let mut previous = None;
+        // Indice del primer caracter de la linea y del primer caracter despues del ultimo espacio
+        let mut line_start = glyphs.len();
+        let mut word_start = None;
+        for character in paragraph.chars() {
+            let id = font.glyph_id(character);
+            if let Some(previous) = previous {
+                x += font.kern(previous, id);
+            }
+            let advance = font.h_advance(id);
+            previous = Some(id);
+
+            if character.is_whitespace() {
+                word_start = Some(glyphs.len() + 1);
+            } else if matches!(max_width, Some(max_width) if x + advance > max_width) && glyphs.len() > line_start {
+                line += 1;
+                match word_start.filter(|&start| start < glyphs.len()) {
+                    // Bajamos la palabra entera a la linea siguiente
+                    Some(start) => {
+                        let offset = glyphs[start].0.position[0];
+                        for (glyph, glyph_line) in &mut glyphs[start..] {
+                            glyph.position[0] -= offset;
+                            *glyph_line = line;
+                        }
+                        x -= offset;
+                        line_start = start;
+                    }
+                    // La palabra no cabe en una linea, la partimos aqui
+                    None => {
+                        x = 0.0;
+                        line_start = glyphs.len();
+                    }
+                }
+                word_start = None;
+            }
+
+            glyphs.push((
+                LayoutGlyph {
+                    character,
+                    position: [x, 0.0],
+                    advance,
+                },
+                line,
+            ));
+            x += advance;
+        }
+        line += 1;
+    }
+
+    let mut layout = TextLayout {
+        height: line as f32 * line_height,
+        ..Default::default()
+    };
+    for (mut glyph, line) in glyphs {
+        if glyph.character.is_whitespace() {
+            continue;
+        }
+        glyph.position[1] = font.ascent() + line as f32 * line_height;
+        layout.width = layout.width.max(glyph.position[0] + glyph.advance);
+        layout.glyphs.push(glyph);
+    }
+    layout
+}
+
+// Un glifo rasterizado: su imagen en el atlas y donde va respecto a la posicion del lapiz
+#[derive(Copy, Clone, Debug)]
+pub struct CachedGlyph {
+    // Indice en GlyphCache::images (y en las regiones del atlas, que tiene las imagenes en el mismo orden)
+    pub index: usize,
+    pub offset: [f32; 2],
+    pub size: [f32; 2],
+}
+
+pub struct GlyphCache {
+    pub font: FontArc,
+    // None para los caracteres sin nada que dibujar (espacios...)
+    glyphs: HashMap<(char, u32), Option<CachedGlyph>>,
+    // Blancas con la cobertura en el alpha, el color lo pone el tinte del sprite
+    pub images: Vec<image::RgbaImage>,
+    // Hay glifos nuevos que todavia no estan en el atlas (los del final de images)
+    pub dirty: bool,
+}
+
+impl GlyphCache {
+    pub fn new(font: FontArc) -> Self {
+        Self {
+            font,
+            glyphs: HashMap::new(),
+            images: Vec::new(),
+            dirty: false,
+        }
+    }
+
+    // El tamaño se redondea a pixels enteros para no rasterizar el mismo glifo a 16.0, 16.01...
+    pub fn glyph(&mut self, character: char, size: f32) -> Option<CachedGlyph> {
+        let key = (character, size.round() as u32);
+        if let Some(&glyph) = self.glyphs.get(&key) {
+            return glyph;
+        }
+
+        let glyph = self
+            .font
+            .glyph_id(character)
+            .with_scale_and_position(key.1 as f32, point(0.0, 0.0));
+        let cached = self.font.outline_glyph(glyph).map(|outline| {
+            let bounds = outline.px_bounds();
+            let width = bounds.width() as u32;
+            let height = bounds.height() as u32;
+            let mut image = image::RgbaImage::new(width, height);
+            outline.draw(|x, y, coverage| {
+                if x < width && y < height {
+                    let alpha = (coverage.min(1.0) * 255.0).round() as u8;
+                    image.put_pixel(x, y, image::Rgba([255, 255, 255, alpha]));
+                }
+            });
+            self.images.push(image);
+            self.dirty = true;
+            CachedGlyph {
+                index: self.images.len() - 1,
+                offset: [bounds.min.x, bounds.min.y],
+                size: [width as f32, height as f32],
+            }
+        });
+        self.glyphs.insert(key, cached);
+        cached
+    }
+}
+
+// Media de los FPS en intervalos de medio segundo, para que el numero no baile cada frame
+#[derive(Default)]
+pub struct FpsCounter {
+    frames: u32,
+    elapsed: f32,
+    pub fps: f32,
+}
+
+impl FpsCounter {
+    pub fn update(&mut self, time_delta: f32) {
+        self.frames += 1;
+        self.elapsed += time_delta;
+        if self.elapsed >= 0.5 {
+            self.fps = self.frames as f32 / self.elapsed;
+            self.frames = 0;
+            self.elapsed = 0.0;
+        }
+    }
+}
+
+pub struct TextRenderer {
+    cache: GlyphCache,
+    sprite_batch: SpriteBatch,
+    // Los glifos de los queue_text() de este frame
+    sprites: Vec<Sprite>,
+}
+
+impl TextRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        font_data: &'static [u8],
+        globals_layout: &wgpu::BindGroupLayout,
+        color_format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Result<Self> {
+        let font = FontArc::try_from_slice(font_data).context("Invalid font")?;
+        let mut cache = GlyphCache::new(font);
+        // El atlas no puede estar vacio, empezamos con un glifo cualquiera
+        cache.glyph('?', 16.0);
+        let atlas = TextureAtlas::new(device, queue, &cache.images, "Glyph Atlas")?;
+        cache.dirty = false;
+        let sprite_batch = SpriteBatch::new(device, atlas, globals_layout, color_format, sample_count);
+        Ok(Self {
+            cache,
+            sprite_batch,
+            sprites: Vec::new(),
+        })
+    }
+
+    // Añade un texto para dibujar este frame. position es la esquina de arriba a la izquierda del texto.
+    // Devuelve el ancho y el alto que ocupa, por si hay que colocar algo a continuacion
+    pub fn queue_text(
+        &mut self,
+        text: &str,
+        position: [f32; 2],
+        size: f32,
+        color: [f32; 4],
+        max_width: Option<f32>,
+    ) -> [f32; 2] {
+        let layout = layout_text(&self.cache.font, text, size, max_width);
+        for glyph in &layout.glyphs {
+            let cached = match self.cache.glyph(glyph.character, size) {
+                Some(cached) => cached,
+                None => continue,
+            };
+            // Redondeamos a pixels enteros para que el glifo no se emborrone con el filtrado
+            let x = (position[0] + glyph.position[0]).round() + cached.offset[0];
+            let y = (position[1] + glyph.position[1]).round() + cached.offset[1];
+            self.sprites.push(Sprite {
+                position: [x + cached.size[0] * 0.5, y + cached.size[1] * 0.5],
+                region: cached.index,
+                scale: [1.0, 1.0],
+                rotation: 0.0,
+                depth: 0.0,
+                tint: color,
+            });
+        }
+        [layout.width, layout.height]
+    }
+
+    // Sube los glifos nuevos al atlas y los vertices de los textos de este frame
+    pub fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if self.cache.dirty {
+            // Los glifos nuevos van al espacio libre del atlas. Solo si ya no caben se rehace, con el doble de lado
+            let atlas = self.sprite_batch.atlas_mut();
+            let first_new = atlas.regions.len();
+            if !atlas.add(queue, &self.cache.images[first_new..]) {
+                let size = atlas.size() * 2;
+                match TextureAtlas::with_min_size(device, queue, &self.cache.images, "Glyph Atlas", size) {
+                    Ok(atlas) => self.sprite_batch.set_atlas(device, atlas),
+                    Err(e) => eprintln!("{:#}", e),
+                }
+            }
+            self.cache.dirty = false;
+        }
+        self.sprite_batch.prepare(device, queue, &self.sprites);
+        self.sprites.clear();
+    }
+
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, globals_bind_group: &'a wgpu::BindGroup) {
+        self.sprite_batch.draw(render_pass, globals_bind_group);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::{pack_into, AtlasPacker, AtlasRect};
+
+    fn font() -> FontArc {
+        FontArc::try_from_slice(include_bytes!("fonts/DejaVuSans.ttf")).unwrap()
+    }
+
+    fn line_height(size: f32) -> f32 {
+        let font = font();
+        let scaled = font.as_scaled(size);
+        scaled.height() + scaled.line_gap()
+    }
+
+    fn num_lines(layout: &TextLayout, size: f32) -> usize {
+        (layout.height / line_height(size)).round() as usize
+    }
+
+    // Todos dentro del atlas en los dos ejes y sin solaparse
+    fn assert_disjoint_inside(rects: &[AtlasRect], size: u32) {
+        for (i, a) in rects.iter().enumerate() {
+            assert!(a.right() <= size && a.bottom() <= size, "{:?} is outside the atlas", a);
+            for b in &rects[i + 1..] {
+                let overlap = a.x < b.right() && b.x < a.right() && a.y < b.bottom() && b.y < a.bottom();
+                assert!(!overlap, "{:?} and {:?} overlap", a, b);
+            }
+        }
+    }
+
+    fn line_of(glyph: &LayoutGlyph, size: f32) -> usize {
+        let first_baseline = font().as_scaled(size).ascent();
+        ((glyph.position[1] - first_baseline) / line_height(size)).round() as usize
+    }
+
+    #[test]
+    fn single_line_skips_spaces() {
+        let layout = layout_text(&font(), "Hola mundo", 20.0, None);
+        let characters = layout.glyphs.iter().map(|glyph| glyph.character).collect::<String>();
+        assert_eq!(characters, "Holamundo");
+        assert_eq!(num_lines(&layout, 20.0), 1);
+        // Los caracteres van de izquierda a derecha sin solaparse
+        for pair in layout.glyphs.windows(2) {
+            assert!(pair[1].position[0] >= pair[0].position[0] + pair[0].advance - 2.0);
+        }
+    }
+
+    #[test]
+    fn applies_kerning() {
+        let font = font();
+        let scaled = font.as_scaled(40.0);
+        let a = scaled.glyph_id('A');
+        let v = scaled.glyph_id('V');
+        // DejaVu Sans acerca la V a la A
+        assert!(scaled.kern(a, v) < 0.0);
+        let layout = layout_text(&font, "AV", 40.0, None);
+        let expected = scaled.h_advance(a) + scaled.kern(a, v);
+        assert!((layout.glyphs[1].position[0] - expected).abs() < 1e-3);
+    }
+
+    #[test]
+    fn utf8_characters() {
+        let layout = layout_text(&font(), "¿Año?", 20.0, None);
+        let characters = layout.glyphs.iter().map(|glyph| glyph.character).collect::<String>();
+        assert_eq!(characters, "¿Año?");
+    }
+
+    #[test]
+    fn newlines_break_lines() {
+        let layout = layout_text(&font(), "a\nb\n\nc", 20.0, None);
+        assert_eq!(num_lines(&layout, 20.0), 4);
+        let lines = layout.glyphs.iter().map(|glyph| line_of(glyph, 20.0)).collect::<Vec<_>>();
+        assert_eq!(lines, vec![0, 1, 3]);
+        assert!(layout.glyphs.iter().all(|glyph| glyph.position[0] == 0.0));
+    }
+
+    #[test]
+    fn wraps_at_spaces() {
+        let font = font();
+        let hello = layout_text(&font, "hello", 20.0, None).width;
+        let layout = layout_text(&font, "hello big cat", 20.0, Some(hello + 5.0));
+        assert_eq!(num_lines(&layout, 20.0), 3);
+        let lines = layout.glyphs.iter().map(|glyph| line_of(glyph, 20.0)).collect::<Vec<_>>();
+        assert_eq!(lines, vec![0, 0, 0, 0, 0, 1, 1, 1, 2, 2, 2]);
+        // Las palabras que bajan de linea empiezan a la izquierda
+        for character in &['b', 'c'] {
+            let glyph = layout.glyphs.iter().find(|glyph| glyph.character == *character).unwrap();
+            assert_eq!(glyph.position[0], 0.0);
+        }
+        assert!(layout.width <= hello + 5.0);
+    }
+
+    #[test]
+    fn breaks_long_words() {
+        let layout = layout_text(&font(), "aaaaaaaaaaaaaaaaaaaa", 20.0, Some(50.0));
+        assert!(num_lines(&layout, 20.0) > 1);
+        assert!(layout.width <= 50.0);
+        assert!(layout.glyphs.iter().all(|glyph| glyph.position[0] + glyph.advance <= 50.0));
+    }
+
+    #[test]
+    fn glyph_cache_reuses_glyphs() {
+        let mut cache = GlyphCache::new(font());
+        let a = cache.glyph('a', 16.0).unwrap();
+        let b = cache.glyph('b', 16.0).unwrap();
+        assert_ne!(a.index, b.index);
+        assert_eq!(cache.glyph('a', 16.2).unwrap().index, a.index);
+        // Otro tamaño es otro glifo
+        assert_ne!(cache.glyph('a', 32.0).unwrap().index, a.index);
+        assert!(cache.glyph(' ', 16.0).is_none());
+        assert_eq!(cache.images.len(), 3);
+        let image = &cache.images[a.index];
+        assert_eq!([image.width() as f32, image.height() as f32], a.size);
+        // El glifo tiene algo dibujado
+        assert!(image.pixels().any(|pixel| pixel[3] > 0));
+    }
+
+    #[test]
+    fn glyph_atlas_packing() {
+        let mut cache = GlyphCache::new(font());
+        for character in "The quick brown fox jumps over the lazy dog 0123456789".chars() {
+            cache.glyph(character, 24.0);
+        }
+        let sizes = cache.images.iter().map(|image| image.dimensions()).collect::<Vec<_>>();
+        let rects = pack_into(&mut AtlasPacker::new(256, 256, 1), &sizes).unwrap();
+        for (rect, &(width, height)) in rects.iter().zip(&sizes) {
+            assert_eq!((rect.width, rect.height), (width, height));
+        }
+        assert_disjoint_inside(&rects, 256);
+    }
+
+    #[test]
+    fn new_glyphs_go_in_the_free_space() {
+        let mut cache = GlyphCache::new(font());
+        for character in "abcdefghij".chars() {
+            cache.glyph(character, 24.0);
+        }
+        let sizes = cache.images.iter().map(|image| image.dimensions()).collect::<Vec<_>>();
+        let mut packer = AtlasPacker::new(256, 256, 1);
+        let mut rects = pack_into(&mut packer, &sizes).unwrap();
+
+        // Los glifos que aparecen despues se colocan sin mover los que ya estaban
+        for character in "klmnopqrstuvwxyz".chars() {
+            cache.glyph(character, 24.0);
+        }
+        let new_sizes = cache.images[sizes.len()..]
+            .iter()
+            .map(|image| image.dimensions())
+            .collect::<Vec<_>>();
+        for &(width, height) in &new_sizes {
+            rects.push(packer.insert(width, height).unwrap());
+        }
+        assert_eq!(rects.len(), cache.images.len());
+        assert_disjoint_inside(&rects, 256);
+    }
+}
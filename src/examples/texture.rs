@@ -18,13 +18,46 @@ impl Texture {
         Self::from_image(device, queue, &img, Some(label))
     }
 
+    // Carga una imagen desde disco en tiempo de ejecucion (en vez de include_bytes!)
+    pub fn load<P: AsRef<std::path::Path>>(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: P,
+    ) -> Result<Self> {
+        let path = path.as_ref();
+        let img = image::open(path).with_context(|| format!("Unable to load {}", path.display()))?;
+        Self::from_image(device, queue, &img, path.to_str())
+    }
+
     pub fn from_image(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         img: &image::DynamicImage,
         label: Option<&str>,
     ) -> Result<Self> {
-        let rgba = img.as_rgba8().unwrap();
+        Self::from_image_with_format(device, queue, img, label, wgpu::TextureFormat::Rgba8UnormSrgb)
+    }
+
+    // Para texturas que no son colores (normal maps, alturas...). Con Rgba8UnormSrgb el sampler pasaria los valores
+    // de sRGB a lineal y las normales saldrian torcidas
+    pub fn from_image_linear(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        img: &image::DynamicImage,
+        label: Option<&str>,
+    ) -> Result<Self> {
+        Self::from_image_with_format(device, queue, img, label, wgpu::TextureFormat::Rgba8Unorm)
+    }
+
+    fn from_image_with_format(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        img: &image::DynamicImage,
+        label: Option<&str>,
+        format: wgpu::TextureFormat,
+    ) -> Result<Self> {
+        // to_rgba8 convierte si hace falta, as_rgba8 solo funciona con imagenes que ya son RGBA (no con JPG por ejemplo)
+        let rgba = img.to_rgba8();
         let dimensions = img.dimensions();
 
         let size = wgpu::Extent3d {
@@ -40,7 +73,7 @@ impl Texture {
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            format,
             // SAMPLED tells wgpu that we want to use this texture in shaders
             // COPY_DST means that we want to copy data to this texture
             usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
@@ -54,7 +87,7 @@ impl Texture {
                 origin: wgpu::Origin3d::ZERO,
             },
             // The actual pixel data
-            rgba,
+            &rgba,
             // The layout of the texture
             wgpu::TextureDataLayout {
                 offset: 0,
@@ -100,4 +133,250 @@ impl Texture {
             sampler,
         })
     }
+
+    // Cubemap a partir de 6 imagenes en el orden de las capas del cubo: +X, -X, +Y, -Y, +Z, -Z
+    pub fn load_cube_faces<P: AsRef<std::path::Path>>(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        paths: &[P],
+    ) -> Result<Self> {
+        let faces = paths
+            .iter()
+            .map(|path| {
+                let path = path.as_ref();
+                let img = image::open(path).with_context(|| format!("Unable to load {}", path.display()))?;
+                Ok(img.to_rgba8())
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Self::cube_from_faces(device, queue, &faces, Some("Cubemap"))
+    }
+
+    // Cubemap a partir de un panorama equirectangular (longitud en x, latitud en y, normalmente 2:1)
+    pub fn load_equirectangular<P: AsRef<std::path::Path>>(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: P,
+        face_size: u32,
+    ) -> Result<Self> {
+        let path = path.as_ref();
+        let img = image::open(path).with_context(|| format!("Unable to load {}", path.display()))?;
+        Self::cube_from_equirectangular(device, queue, &img, face_size, path.to_str())
+    }
+
+    pub fn cube_from_equirectangular(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        img: &image::DynamicImage,
+        face_size: u32,
+        label: Option<&str>,
+    ) -> Result<Self> {
+        let faces = equirectangular_to_cube_faces(&img.to_rgba8(), face_size);
+        Self::cube_from_faces(device, queue, &faces, label)
+    }
+
+    // Una textura 2D con 6 capas (depth 6), una por cara, y una vista con dimension Cube. En el shader se usa como
+    // textureCube y se muestrea con una direccion en vez de con coordenadas uv
+    pub fn cube_from_faces(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        faces: &[image::RgbaImage],
+        label: Option<&str>,
+    ) -> Result<Self> {
+        if faces.len() != 6 {
+            bail!("A cubemap needs 6 faces, got {}", faces.len());
+        }
+        let face_size = faces[0].width();
+        if faces.iter().any(|face| face.dimensions() != (face_size, face_size)) {
+            bail!("Cubemap faces must be square and all the same size");
+        }
+
+        let size = wgpu::Extent3d {
+            width: face_size,
+            height: face_size,
+            depth: 6,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsage::SAMPLED | wgpu::TextureUsage::COPY_DST,
+        });
+
+        // Las 6 caras seguidas, y rows_per_image dice donde empieza cada capa
+        let data: Vec<u8> = faces.iter().flat_map(|face| face.as_raw().iter().copied()).collect();
+        queue.write_texture(
+            wgpu::TextureCopyView {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+            },
+            &data,
+            wgpu::TextureDataLayout {
+                offset: 0,
+                bytes_per_row: 4 * face_size,
+                rows_per_image: face_size,
+            },
+            size,
+        );
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label,
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+        // Linear para que no se vean los pixeles de las caras al mirar el cielo de cerca
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        Ok(Self {
+            texture,
+            view,
+            sampler,
+        })
+    }
+}
+
+// Direccion (sin normalizar) del centro del pixel (u, v) de una cara, con u y v entre -1 y 1 y v hacia abajo.
+// Es la convencion de los cubemaps de Vulkan/D3D, que es la que usa wgpu
+fn cube_face_direction(face: usize, u: f32, v: f32) -> [f32; 3] {
+    match face {
+        0 => [1.0, -v, -u],
+        1 => [-1.0, -v, u],
+        2 => [u, 1.0, v],
+        3 => [u, -1.0, -v],
+        4 => [u, -v, 1.0],
+        _ => [-u, -v, -1.0],
+    }
+}
+
+// Para cada pixel de cada cara calcula su direccion, la pasa a longitud y latitud y lee el panorama ahi (con
+// filtrado bilineal). En x el panorama da la vuelta, asi que se repite; en y se limita a los bordes
+pub fn equirectangular_to_cube_faces(panorama: &image::RgbaImage, face_size: u32) -> Vec<image::RgbaImage> {
+    let (width, height) = panorama.dimensions();
+    let texel = |x: i64, y: i64| {
+        let x = x.rem_euclid(width as i64) as u32;
+        let y = y.clamp(0, height as i64 - 1) as u32;
+        panorama.get_pixel(x, y).0
+    };
+
+    (0..6)
+        .map(|face| {
+            image::RgbaImage::from_fn(face_size, face_size, |x, y| {
+                let u = 2.0 * (x as f32 + 0.5) / face_size as f32 - 1.0;
+                let v = 2.0 * (y as f32 + 0.5) / face_size as f32 - 1.0;
+                let [dx, dy, dz] = cube_face_direction(face, u, v);
+                let length = (dx * dx + dy * dy + dz * dz).sqrt();
+                let longitude = dz.atan2(dx);
+                let latitude = (dy / length).asin();
+
+                let px = (0.5 + longitude / (2.0 * std::f32::consts::PI)) * width as f32 - 0.5;
+                let py = (0.5 - latitude / std::f32::consts::PI) * height as f32 - 0.5;
+                let (x0, y0) = (px.floor(), py.floor());
+                let (fx, fy) = (px - x0, py - y0);
+                let (x0, y0) = (x0 as i64, y0 as i64);
+                let (a, b, c, d) = (texel(x0, y0), texel(x0 + 1, y0), texel(x0, y0 + 1), texel(x0 + 1, y0 + 1));
+
+                let mut pixel = [0; 4];
+                for (i, channel) in pixel.iter_mut().enumerate() {
+                    let top = a[i] as f32 * (1.0 - fx) + b[i] as f32 * fx;
+                    let bottom = c[i] as f32 * (1.0 - fx) + d[i] as f32 * fx;
+                    *channel = (top * (1.0 - fy) + bottom * fy).round() as u8;
+                }
+                image::Rgba(pixel)
+            })
+        })
+        .collect()
+}
+
+// Normal map a partir de un mapa de alturas (blanco alto, negro bajo) con diferencias centrales, repitiendo en los
+// bordes para que se pueda usar en texturas que se repiten. Las normales estan en espacio tangente: x (rojo) hacia
+// donde crece u, y (verde) hacia donde crece v (hacia abajo en la imagen) y z (azul) saliendo de la superficie.
+// strength es cuanto sube la altura por pixel con la altura maxima. La altura se guarda en el alpha para el parallax
+pub fn normal_map_from_height(height: &image::GrayImage, strength: f32) -> image::RgbaImage {
+    let (width, rows) = height.dimensions();
+    let sample = |x: i64, y: i64| {
+        let x = x.rem_euclid(width as i64) as u32;
+        let y = y.rem_euclid(rows as i64) as u32;
+        height.get_pixel(x, y).0[0] as f32 / 255.0
+    };
+    image::RgbaImage::from_fn(width, rows, |x, y| {
+        let (x, y) = (x as i64, y as i64);
+        let dx = (sample(x + 1, y) - sample(x - 1, y)) * 0.5 * strength;
+        let dy = (sample(x, y + 1) - sample(x, y - 1)) * 0.5 * strength;
+        let length = (dx * dx + dy * dy + 1.0).sqrt();
+        let encode = |n: f32| ((n / length * 0.5 + 0.5) * 255.0).round() as u8;
+        image::Rgba([encode(-dx), encode(-dy), encode(1.0), (sample(x, y) * 255.0).round() as u8])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flat_height_map_points_straight_up() {
+        let height = image::GrayImage::from_pixel(8, 8, image::Luma([100]));
+        let normals = normal_map_from_height(&height, 4.0);
+        for pixel in normals.pixels() {
+            assert_eq!(pixel.0, [128, 128, 255, 100]);
+        }
+    }
+
+    #[test]
+    fn normals_lean_away_from_higher_ground() {
+        // Sube hacia la derecha (u) y es constante en v: la normal se inclina hacia -u
+        let height = image::GrayImage::from_fn(16, 16, |x, _| image::Luma([(x * 8) as u8]));
+        let normals = normal_map_from_height(&height, 4.0);
+        let pixel = normals.get_pixel(8, 8).0;
+        assert!(pixel[0] < 128, "{:?}", pixel);
+        assert_eq!(pixel[1], 128);
+        assert!(pixel[2] > 128 && pixel[2] < 255, "{:?}", pixel);
+    }
+
+    #[test]
+    fn cube_faces_look_along_their_axis() {
+        // Cada cuarto del panorama, centrado en una de las longitudes de los lados, tiene su propio rojo. La fila de
+        // arriba es verde y la de abajo azul
+        let (width, height) = (64, 32);
+        let panorama = image::RgbaImage::from_fn(width, height, |x, y| {
+            let quarter = ((x + 8) / 16) % 4;
+            let green = if y == 0 { 255 } else { 0 };
+            let blue = if y == height - 1 { 255 } else { 0 };
+            image::Rgba([10 + 10 * quarter as u8, green, blue, 255])
+        });
+        // Con caras de 1x1 el unico pixel mira justo hacia el centro de la cara
+        let faces = equirectangular_to_cube_faces(&panorama, 1);
+        let pixel = |face: usize| faces[face].get_pixel(0, 0).0;
+        assert_eq!(pixel(0), [30, 0, 0, 255], "+X");
+        assert_eq!(pixel(1), [10, 0, 0, 255], "-X");
+        assert_eq!(pixel(2)[1..3], [255, 0], "+Y");
+        assert_eq!(pixel(3)[1..3], [0, 255], "-Y");
+        assert_eq!(pixel(4), [40, 0, 0, 255], "+Z");
+        assert_eq!(pixel(5), [20, 0, 0, 255], "-Z");
+    }
+
+    #[test]
+    fn panorama_wraps_at_the_seam() {
+        // -X mira justo a la costura entre la ultima columna y la primera, asi que mezcla las dos a partes iguales
+        let panorama = image::RgbaImage::from_fn(64, 32, |x, _| {
+            if x == 0 {
+                image::Rgba([200, 0, 0, 255])
+            } else {
+                image::Rgba([0, 0, 0, 255])
+            }
+        });
+        let faces = equirectangular_to_cube_faces(&panorama, 1);
+        assert_eq!(faces[1].get_pixel(0, 0).0, [100, 0, 0, 255]);
+        assert_eq!(faces[0].get_pixel(0, 0).0, [0, 0, 0, 255]);
+    }
 }
\ No newline at end of file
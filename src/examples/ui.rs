@@ -0,0 +1,348 @@
+//## UI de depuracion en modo inmediato: cada frame, en update(), el ejemplo llama a los widgets con sus parametros
+//## y los widgets los modifican directamente si el usuario los toca:
+//##
+//##     self.ui.label("Pentagono");
+//##     if self.ui.slider_u32("Vertices", &mut self.num_vertices, 3..=64) {
+//##         // El valor ha cambiado
+//##     }
+//##     self.ui.color_edit("Clear color", &mut self.clear_color);
+//##     self.ui.prepare(&self.device, &self.queue);
+//##
+//## No hay estado de los widgets que guardar aparte, solo el de la interaccion (que slider se esta arrastrando).
+//## En el bucle de eventos la UI recibe los eventos antes que State::input, que solo recibe los que la UI no usa:
+//## los clicks dentro del panel y el raton mientras se arrastra un slider. F1 muestra u oculta el panel.
+//##
+//## Se dibuja con dos SpriteBatch: los rectangulos (un pixel blanco estirado y teñido) y despues el texto encima.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::ops::RangeInclusive;
+
+use winit::event::{ElementState, KeyboardInput, MouseButton, VirtualKeyCode, WindowEvent};
+
+use crate::examples::{Sprite, SpriteBatch, TextRenderer, TextureAtlas};
+
+const PANEL_POSITION: [f32; 2] = [10.0, 10.0];
+const PANEL_WIDTH: f32 = 300.0;
+const PADDING: f32 = 8.0;
+const ROW_HEIGHT: f32 = 22.0;
+const LABEL_WIDTH: f32 = 110.0;
+const FONT_SIZE: f32 = 15.0;
+
+const PANEL_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+const WIDGET_COLOR: [f32; 4] = [0.25, 0.25, 0.25, 1.0];
+const ACTIVE_COLOR: [f32; 4] = [0.3, 0.5, 0.9, 1.0];
+const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+#[derive(Copy, Clone, Debug, Default)]
+struct Rect {
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+}
+
+impl Rect {
+    fn contains(&self, point: [f32; 2]) -> bool {
+        point[0] >= self.x && point[0] < self.x + self.width && point[1] >= self.y && point[1] < self.y + self.height
+    }
+}
+
+fn widget_id(label: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    label.hash(&mut hasher);
+    hasher.finish()
+}
+
+pub struct Ui {
+    visible: bool,
+    rect_batch: SpriteBatch,
+    rects: Vec<Sprite>,
+    text: TextRenderer,
+    // Estado del raton, en pixels como las posiciones de los sprites
+    cursor: [f32; 2],
+    mouse_down: bool,
+    // Se ha pulsado el boton desde el frame anterior
+    clicked: bool,
+    // El widget que se esta arrastrando
+    active: Option<u64>,
+    // Donde va la siguiente fila de widgets
+    next_y: f32,
+    // El panel del frame anterior, para saber en input() si un click es de la UI
+    panel: Rect,
+}
+
+impl Ui {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        globals_layout: &wgpu::BindGroupLayout,
+        color_format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Self {
+        let white = image::RgbaImage::from_pixel(1, 1, image::Rgba([255, 255, 255, 255]));
+        let atlas = TextureAtlas::new(device, queue, &[white], "UI Atlas").unwrap();
+        let rect_batch = SpriteBatch::new(device, atlas, globals_layout, color_format, sample_count);
+        let text = TextRenderer::new(
+            device,
+            queue,
+            include_bytes!("fonts/DejaVuSans.ttf"),
+            globals_layout,
+            color_format,
+            sample_count,
+        )
+        .unwrap();
+        Self {
+            visible: true,
+            rect_batch,
+            rects: Vec::new(),
+            text,
+            cursor: [0.0; 2],
+            mouse_down: false,
+            clicked: false,
+            active: None,
+            next_y: PANEL_POSITION[1] + PADDING,
+            panel: Rect::default(),
+        }
+    }
+
+    // Devuelve true si la UI se queda con el evento
+    pub fn input(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::F1),
+                        ..
+                    },
+                ..
+            } => {
+                self.visible = !self.visible;
+                true
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor = [position.x as f32, position.y as f32];
+                self.active.is_some()
+            }
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } if self.visible && self.panel.contains(self.cursor) => {
+                self.mouse_down = true;
+                self.clicked = true;
+                true
+            }
+            WindowEvent::MouseInput {
+                state: ElementState::Released,
+                button: MouseButton::Left,
+                ..
+            } if self.mouse_down => {
+                self.mouse_down = false;
+                self.active = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn row(&mut self) -> Rect {
+        let rect = Rect {
+            x: PANEL_POSITION[0] + PADDING,
+            y: self.next_y,
+            width: PANEL_WIDTH - PADDING * 2.0,
+            height: ROW_HEIGHT,
+        };
+        self.next_y += ROW_HEIGHT + 4.0;
+        rect
+    }
+
+    fn fill(&mut self, rect: Rect, color: [f32; 4]) {
+        self.rects.push(Sprite {
+            position: [rect.x + rect.width * 0.5, rect.y + rect.height * 0.5],
+            region: 0,
+            scale: [rect.width, rect.height],
+            rotation: 0.0,
+            depth: 0.0,
+            tint: color,
+        });
+    }
+
+    fn text_in(&mut self, text: &str, rect: Rect) {
+        // Centrado en vertical en la fila
+        let y = rect.y + (rect.height - FONT_SIZE * 1.2) * 0.5;
+        self.text.queue_text(text, [rect.x + 2.0, y], FONT_SIZE, TEXT_COLOR, Some(rect.width));
+    }
+
+    pub fn label(&mut self, text: &str) {
+        if !self.visible {
+            return;
+        }
+        let rect = self.row();
+        self.text_in(text, rect);
+    }
+
+    // Devuelve true si el valor ha cambiado
+    pub fn checkbox(&mut self, label: &str, value: &mut bool) -> bool {
+        if !self.visible {
+            return false;
+        }
+        let rect = self.row();
+        let clicked = self.clicked && rect.contains(self.cursor);
+        if clicked {
+            *value = !*value;
+            self.clicked = false;
+        }
+
+        let size = ROW_HEIGHT - 6.0;
+        let check = Rect {
+            x: rect.x,
+            y: rect.y + 3.0,
+            width: size,
+            height: size,
+        };
+        self.fill(check, WIDGET_COLOR);
+        if *value {
+            let inner = Rect {
+                x: check.x + 3.0,
+                y: check.y + 3.0,
+                width: size - 6.0,
+                height: size - 6.0,
+            };
+            self.fill(inner, ACTIVE_COLOR);
+        }
+        self.text_in(
+            label,
+            Rect {
+                x: rect.x + size + 6.0,
+                width: rect.width - size - 6.0,
+                ..rect
+            },
+        );
+        clicked
+    }
+
+    // El slider que se esta arrastrando sigue al raton aunque se salga de la fila
+    fn slider_value(&mut self, label: &str, id: u64, t: f32, text: &str) -> Option<f32> {
+        let rect = self.row();
+        if self.clicked && rect.contains(self.cursor) {
+            self.active = Some(id);
+            self.clicked = false;
+        }
+
+        let track = Rect {
+            x: rect.x + LABEL_WIDTH,
+            width: rect.width - LABEL_WIDTH,
+            ..rect
+        };
+        let dragging = self.mouse_down && self.active == Some(id);
+        let t = if dragging {
+            ((self.cursor[0] - track.x) / track.width).clamp(0.0, 1.0)
+        } else {
+            t
+        };
+
+        self.text_in(label, Rect { width: LABEL_WIDTH, ..rect });
+        self.fill(track, WIDGET_COLOR);
+        self.fill(Rect { width: track.width * t, ..track }, ACTIVE_COLOR);
+        self.text_in(text, track);
+
+        if dragging {
+            Some(t)
+        } else {
+            None
+        }
+    }
+
+    pub fn slider(&mut self, label: &str, value: &mut f32, range: RangeInclusive<f32>) -> bool {
+        if !self.visible {
+            return false;
+        }
+        self.slider_with_id(label, widget_id(label), value, range)
+    }
+
+    fn slider_with_id(&mut self, label: &str, id: u64, value: &mut f32, range: RangeInclusive<f32>) -> bool {
+        let (min, max) = (*range.start(), *range.end());
+        let t = (*value - min) / (max - min);
+        match self.slider_value(label, id, t, &format!("{:.2}", value)) {
+            Some(t) => {
+                let new_value = min + (max - min) * t;
+                let changed = new_value != *value;
+                *value = new_value;
+                changed
+            }
+            None => false,
+        }
+    }
+
+    pub fn slider_u32(&mut self, label: &str, value: &mut u32, range: RangeInclusive<u32>) -> bool {
+        if !self.visible {
+            return false;
+        }
+        let (min, max) = (*range.start() as f32, *range.end() as f32);
+        let t = (*value as f32 - min) / (max - min);
+        match self.slider_value(label, widget_id(label), t, &value.to_string()) {
+            Some(t) => {
+                let new_value = (min + (max - min) * t).round() as u32;
+                let changed = new_value != *value;
+                *value = new_value;
+                changed
+            }
+            None => false,
+        }
+    }
+
+    // Una muestra del color y un slider por componente
+    pub fn color_edit(&mut self, label: &str, color: &mut [f32; 3]) -> bool {
+        if !self.visible {
+            return false;
+        }
+        let rect = self.row();
+        self.text_in(label, Rect { width: LABEL_WIDTH, ..rect });
+        let swatch = Rect {
+            x: rect.x + LABEL_WIDTH,
+            width: rect.width - LABEL_WIDTH,
+            ..rect
+        };
+        self.fill(swatch, [color[0], color[1], color[2], 1.0]);
+
+        let mut changed = false;
+        for (channel, name) in color.iter_mut().zip(&["R", "G", "B"]) {
+            // Se muestra solo la componente, pero el id lleva el nombre del color para no chocar con otros
+            let id = widget_id(&format!("{} {}", label, name));
+            changed |= self.slider_with_id(&format!("    {}", name), id, channel, 0.0..=1.0);
+        }
+        changed
+    }
+
+    // Al final del frame, despues de todos los widgets
+    pub fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.panel = if self.visible {
+            Rect {
+                x: PANEL_POSITION[0],
+                y: PANEL_POSITION[1],
+                width: PANEL_WIDTH,
+                height: self.next_y - PANEL_POSITION[1] + PADDING - 4.0,
+            }
+        } else {
+            Rect::default()
+        };
+        if self.visible {
+            // El fondo del panel tiene que ir debajo de los widgets, al principio de la lista
+            self.fill(self.panel, PANEL_COLOR);
+            self.rects.rotate_right(1);
+        }
+
+        self.rect_batch.prepare(device, queue, &self.rects);
+        self.text.prepare(device, queue);
+        self.rects.clear();
+        self.clicked = false;
+        self.next_y = PANEL_POSITION[1] + PADDING;
+    }
+
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, globals_bind_group: &'a wgpu::BindGroup) {
+        self.rect_batch.draw(render_pass, globals_bind_group);
+        self.text.draw(render_pass, globals_bind_group);
+    }
+}
@@ -3,9 +3,13 @@ mod examples;
 use examples::*;
 
 fn main() {
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() != 2 {
-        println!("Call with the number of the tutorial, e.g. `1_1_2`");
+    let mut args: Vec<String> = std::env::args().collect();
+    // --msaa puede ir en cualquier sitio, se quita antes de que 1_8 y toy lean sus rutas
+    let sample_count = take_msaa_arg(&mut args).unwrap_or(DEFAULT_SAMPLE_COUNT);
+    if args.len() < 2 {
+        println!("Call with the number of the tutorial, e.g. `1_1_2`, or `toy [shader.glsl] [channels...]`");
+        println!("Example 1_8 accepts a sky: `1_8 panorama.png` or `1_8 px nx py ny pz nz` (6 cubemap faces)");
+        println!("Examples 1_3 to 1_11, 2_2 and 2_3 accept `--msaa N` (1, 2, 4 or 8, default {})", DEFAULT_SAMPLE_COUNT);
         std::process::exit(1);
     }
     let tutorial_id = &args[1];
@@ -14,12 +18,23 @@ fn main() {
         "1_1" => main_1_1(),
         "1_2" => main_1_2(),
         "1_2_1" => main_1_2_1(),
-        "1_3" => main_1_3(),
-        "1_3_1" => main_1_3_1(),
-        "1_4" => main_1_4(),
-        "1_4_1" => main_1_4_1(),
-        "1_5" => main_1_5(),
-        "1_5_1" => main_1_5_1(),
+        "1_3" => main_1_3(sample_count),
+        "1_3_1" => main_1_3_1(sample_count),
+        "1_4" => main_1_4(sample_count),
+        "1_4_1" => main_1_4_1(sample_count),
+        "1_5" => main_1_5(sample_count),
+        "1_5_1" => main_1_5_1(sample_count),
+        "1_6" => main_1_6(sample_count),
+        "1_7" => main_1_7(sample_count),
+        "1_8" => main_1_8(&args[2..], sample_count),
+        "1_9" => main_1_9(sample_count),
+        "1_10" => main_1_10(sample_count),
+        "1_11" => main_1_11(sample_count),
+        "1_12" => main_1_12(),
+        "2_1" => main_2_1(),
+        "2_2" => main_2_2(sample_count),
+        "2_3" => main_2_3(sample_count),
+        "toy" => main_shadertoy(&args[2..]),
         _     => println!("Unknown tutorial id")
     }
 }